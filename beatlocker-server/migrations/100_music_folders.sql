@@ -0,0 +1,4 @@
+-- Every folder belongs to one of the configured music folders, which are the roots of the folder
+-- tree. Until now there was only a single root.
+ALTER TABLE folders ADD COLUMN music_folder_id text;
+UPDATE folders SET music_folder_id = (SELECT folder_id FROM folders WHERE parent_id IS NULL);
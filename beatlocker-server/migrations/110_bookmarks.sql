@@ -0,0 +1,33 @@
+-- Positions in a song a user wants to return to, at most one per song. Positions are in
+-- milliseconds.
+CREATE TABLE bookmarks
+(
+    username text not null,
+    folder_child_id text not null,
+    position integer not null,
+    comment text,
+    created datetime not null,
+    changed datetime not null,
+    foreign key (folder_child_id) references folder_children(folder_child_id) on delete cascade,
+    primary key (username, folder_child_id)
+);
+
+-- The play queue a user saved last, so they can continue it on another device
+CREATE TABLE play_queues
+(
+    username text primary key not null,
+    current text,
+    position integer not null,
+    changed datetime not null,
+    changed_by text not null
+);
+
+CREATE TABLE play_queue_entries
+(
+    username text not null,
+    position integer not null,
+    folder_child_id text not null,
+    foreign key (username) references play_queues(username) on delete cascade,
+    foreign key (folder_child_id) references folder_children(folder_child_id) on delete cascade,
+    primary key (username, position)
+);
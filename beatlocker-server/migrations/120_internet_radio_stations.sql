@@ -0,0 +1,10 @@
+-- Stations are either added through the API, or imported from a station list in the library, in
+-- which case path is the station list.
+CREATE TABLE internet_radio_stations
+(
+    station_id text primary key not null,
+    name text not null,
+    stream_url text not null,
+    home_page_url text,
+    path text
+);
@@ -0,0 +1,34 @@
+-- Channels are 'new' until their feed was first read, then 'completed', or 'error' if the feed
+-- couldn't be read.
+CREATE TABLE podcast_channels
+(
+    channel_id text primary key not null,
+    url text not null,
+    title text,
+    description text,
+    image_url text,
+    cover_art_id text,
+    status text not null,
+    error_message text,
+    created datetime not null
+);
+
+-- Episodes are 'skipped' until they are downloaded, after which they're 'completed'. Downloaded
+-- episodes that are deleted again stay 'deleted', so they can be told apart from new episodes.
+CREATE TABLE podcast_episodes
+(
+    episode_id text primary key not null,
+    channel_id text not null,
+    title text not null,
+    description text,
+    publish_date datetime,
+    url text not null,
+    status text not null,
+    path text,
+    content_type text,
+    suffix text,
+    size integer,
+    bit_rate integer,
+    duration integer,
+    foreign key (channel_id) references podcast_channels(channel_id) on delete cascade
+);
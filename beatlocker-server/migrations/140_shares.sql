@@ -0,0 +1,22 @@
+-- Songs a user shared through a public link, which anyone with the link can listen to until the
+-- share expires.
+CREATE TABLE shares
+(
+    share_id text primary key not null,
+    username text not null,
+    description text,
+    created datetime not null,
+    expires datetime,
+    last_visited datetime,
+    visit_count integer not null default 0
+);
+
+CREATE TABLE share_entries
+(
+    share_id text not null,
+    position integer not null,
+    folder_child_id text not null,
+    foreign key (share_id) references shares(share_id) on delete cascade,
+    foreign key (folder_child_id) references folder_children(folder_child_id) on delete cascade,
+    primary key (share_id, position)
+);
@@ -0,0 +1,21 @@
+-- Lyrics of songs, from their tags or an .lrc file next to them. A song has at most one set of
+-- unsynced lyrics and one set of synced lyrics, whose lines start at a time in milliseconds.
+CREATE TABLE lyrics
+(
+    song_id text not null,
+    synced boolean not null,
+    lang text not null,
+    foreign key (song_id) references songs(song_id) on delete cascade,
+    primary key (song_id, synced)
+);
+
+CREATE TABLE lyrics_lines
+(
+    song_id text not null,
+    synced boolean not null,
+    position integer not null,
+    start integer,
+    value text not null,
+    foreign key (song_id, synced) references lyrics(song_id, synced) on delete cascade,
+    primary key (song_id, synced, position)
+);
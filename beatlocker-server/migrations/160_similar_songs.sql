@@ -0,0 +1,19 @@
+-- Tags MusicBrainz gives artists, which together with genres tell which artists are alike when
+-- last.fm can't be asked.
+CREATE TABLE artist_tags
+(
+    artist_id text not null,
+    tag text not null,
+    foreign key (artist_id) references artists(artist_id) on delete cascade,
+    primary key (artist_id, tag)
+);
+
+-- Responses of last.fm about an artist, so they don't have to be requested again for a while.
+CREATE TABLE lastfm_responses
+(
+    method text not null,
+    artist text not null,
+    response text not null,
+    fetched datetime not null,
+    primary key (method, artist)
+);
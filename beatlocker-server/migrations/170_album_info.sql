@@ -0,0 +1,14 @@
+-- What last.fm and MusicBrainz know about an album, kept so album pages don't have to ask them
+-- every time. A row without any information means they were asked, but didn't know the album.
+CREATE TABLE album_info
+(
+    album_id text primary key not null,
+    notes text,
+    musicbrainz_id text,
+    lastfm_url text,
+    small_image_url text,
+    medium_image_url text,
+    large_image_url text,
+    fetched datetime not null,
+    foreign key (album_id) references albums(album_id) on delete cascade
+);
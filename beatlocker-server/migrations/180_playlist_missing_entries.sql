@@ -0,0 +1,3 @@
+-- How many entries of a playlist file didn't match a song in the library, so users can tell that
+-- an imported playlist is incomplete
+ALTER TABLE playlists ADD COLUMN missing_entries integer not null default 0;
@@ -0,0 +1,5 @@
+-- Names of artists without case, accents and punctuation, so artists that other services know can
+-- be found without comparing against every artist. Like sort keys, they are derived on startup and
+-- import.
+ALTER TABLE artists ADD COLUMN match_name text;
+CREATE INDEX artists_match_name ON artists (match_name);
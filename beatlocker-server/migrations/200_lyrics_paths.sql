@@ -0,0 +1,3 @@
+-- The .lrc file lyrics were read from, so they are removed along with it. Lyrics from the tags of a
+-- song have none.
+ALTER TABLE lyrics ADD COLUMN path text;
@@ -0,0 +1,36 @@
+-- Song titles, album titles and artist names, normalized with unidecode
+CREATE TABLE search_items
+(
+    search_id integer primary key not null,
+    item_id text not null unique,
+    item_type text not null,
+    name text not null
+);
+
+CREATE VIRTUAL TABLE search_index USING fts5
+(
+    item_id unindexed,
+    item_type unindexed,
+    name,
+    content = 'search_items',
+    content_rowid = 'search_id',
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+CREATE TRIGGER search_items_insert AFTER INSERT ON search_items
+BEGIN
+    INSERT INTO search_index (rowid, item_id, item_type, name)
+    VALUES (new.search_id, new.item_id, new.item_type, new.name);
+END;
+
+CREATE TRIGGER search_items_delete AFTER DELETE ON search_items
+BEGIN
+    INSERT INTO search_index (search_index, rowid, item_id, item_type, name)
+    VALUES ('delete', old.search_id, old.item_id, old.item_type, old.name);
+END;
+
+-- Existing items can't be normalized here, but diacritics are still removed by the tokenizer
+INSERT INTO search_items (item_id, item_type, name)
+SELECT song_id, 'song', title FROM songs
+UNION ALL SELECT album_id, 'album', title FROM albums
+UNION ALL SELECT artist_id, 'artist', name FROM artists;
@@ -0,0 +1,4 @@
+-- Folder names, so search2 can find the top level folders that getIndexes lists as artists.
+-- Existing folders can't be normalized here, but diacritics are still removed by the tokenizer.
+INSERT OR IGNORE INTO search_items (item_id, item_type, name)
+SELECT folder_id, 'folder', name FROM folders;
@@ -0,0 +1,20 @@
+CREATE TABLE playlists
+(
+    playlist_id text primary key not null,
+    name text not null,
+    comment text,
+    owner text not null,
+    public boolean not null,
+    created datetime not null,
+    changed datetime not null
+);
+
+CREATE TABLE playlist_entries
+(
+    playlist_id text not null,
+    position integer not null,
+    folder_child_id text not null,
+    foreign key (playlist_id) references playlists(playlist_id) on delete cascade,
+    foreign key (folder_child_id) references folder_children(folder_child_id) on delete cascade,
+    primary key (playlist_id, position)
+);
@@ -0,0 +1,3 @@
+-- Playlists imported from M3U and PLS files are read-only, and are refreshed when the file changes
+ALTER TABLE playlists ADD COLUMN path text;
+ALTER TABLE playlists ADD COLUMN file_modified integer;
@@ -0,0 +1,21 @@
+-- Every time a song was played, as submitted through scrobble
+CREATE TABLE plays
+(
+    play_id integer primary key,
+    song_id text not null,
+    username text not null,
+    client text not null,
+    played datetime not null,
+    foreign key (song_id) references songs(song_id) on delete cascade
+);
+
+-- What every client is currently playing, as reported through scrobble without submission
+CREATE TABLE now_playing
+(
+    username text not null,
+    client text not null,
+    folder_child_id text not null,
+    started datetime not null,
+    foreign key (folder_child_id) references folder_children(folder_child_id) on delete cascade,
+    primary key (username, client)
+);
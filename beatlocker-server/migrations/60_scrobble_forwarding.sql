@@ -0,0 +1,26 @@
+-- Last.fm and ListenBrainz accounts that a user's plays are forwarded to. The token is a last.fm
+-- session key or a ListenBrainz user token.
+CREATE TABLE scrobble_accounts
+(
+    username text not null,
+    service text not null,
+    token text not null,
+    primary key (username, service)
+);
+
+-- Plays that haven't been accepted by a service yet. They are kept until they are, so plays made
+-- while the server is offline are submitted later.
+CREATE TABLE scrobble_outbox
+(
+    outbox_id integer primary key,
+    username text not null,
+    service text not null,
+    artist text not null,
+    title text not null,
+    album text,
+    duration integer,
+    played datetime not null,
+    attempts integer not null default 0,
+    next_attempt datetime not null,
+    foreign key (username, service) references scrobble_accounts(username, service) on delete cascade
+);
@@ -0,0 +1,15 @@
+-- Users that can log in, with the Subsonic roles they have. Passwords are stored as-is, since
+-- Subsonic token authentication needs them to check the token. Users without a password can't log
+-- in, like the default user that owns everything when authentication is disabled.
+CREATE TABLE users
+(
+    username text primary key not null,
+    password text,
+    email text,
+    admin_role boolean not null default false,
+    settings_role boolean not null default true,
+    stream_role boolean not null default true,
+    download_role boolean not null default false,
+    playlist_role boolean not null default false,
+    share_role boolean not null default false
+);
@@ -0,0 +1,26 @@
+-- Stars belong to the user that starred them. Nobody exists yet while migrating, so stars from
+-- before there were users are left without one, and go to the admin when it is seeded.
+CREATE TABLE starred_by_user
+(
+    starred_id text not null,
+    username text not null,
+    created datetime not null,
+    primary key (starred_id, username)
+);
+
+INSERT INTO starred_by_user (starred_id, username, created)
+SELECT starred_id, '', created
+FROM starred;
+
+DROP TABLE starred;
+ALTER TABLE starred_by_user RENAME TO starred;
+
+-- Ratings from 1 to 5 that a user gave a song, album, folder or artist. Ratings of songs are always
+-- stored by song_id, even when they were rated through a folder child.
+CREATE TABLE ratings
+(
+    rated_id text not null,
+    username text not null,
+    rating integer not null,
+    primary key (rated_id, username)
+);
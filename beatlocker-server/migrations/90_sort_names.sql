@@ -0,0 +1,8 @@
+-- Sort names come from the ARTISTSORT, ALBUMARTISTSORT and ALBUMSORT tags. The sort keys are what
+-- lists are actually ordered by, and are derived from the sort name or name on startup and import,
+-- since they depend on the configured ignored articles.
+ALTER TABLE artists ADD COLUMN sort_name text;
+ALTER TABLE artists ADD COLUMN sort_key text;
+ALTER TABLE albums ADD COLUMN sort_name text;
+ALTER TABLE albums ADD COLUMN sort_key text;
+ALTER TABLE folders ADD COLUMN sort_key text;
@@ -1,14 +1,19 @@
-use crate::SubsonicAuth;
-use crate::SubsonicAuth::UsernamePassword;
+use crate::db::DbUser;
+use crate::{SharedState, SubsonicAuth};
 use axum::extract::{FromRequestParts, Query};
 use axum::http::request::Parts;
+use axum::http::StatusCode;
 use axum::{async_trait, RequestPartsExt, TypedHeader};
 use headers::authorization::Basic;
 use serde::Deserialize;
+use std::convert::Infallible;
 use std::time::Duration;
 use tokio::sync::Mutex;
 use tokio::time::sleep;
+use tracing::error;
 
+/// Resolves the user making the request, which handlers can then extract as [`CurrentUser`] or
+/// [`AuthenticatedUser`].
 pub struct RequireAuth;
 
 #[derive(Debug, Deserialize)]
@@ -26,15 +31,15 @@ pub fn auth_mutex() -> &'static Mutex<()> {
 }
 
 #[async_trait]
-impl FromRequestParts<SubsonicAuth> for RequireAuth {
-    type Rejection = axum::http::StatusCode;
+impl FromRequestParts<SharedState> for RequireAuth {
+    type Rejection = StatusCode;
 
     async fn from_request_parts(
         parts: &mut Parts,
-        state: &SubsonicAuth,
+        state: &SharedState,
     ) -> Result<Self, Self::Rejection> {
-        match &state {
-            UsernamePassword { username, password } => {
+        let user = match &state.options.subsonic_auth {
+            SubsonicAuth::UsernamePassword { .. } => {
                 // Don't allow concurrent login attempts
                 let _ = auth_mutex().lock().await;
 
@@ -55,32 +60,95 @@ impl FromRequestParts<SubsonicAuth> for RequireAuth {
                         .map(|q| q.0)
                 };
 
-                let is_valid = {
-                    match auth_query {
-                        Some(query) => match (&query.u, &query.p, &query.t, &query.s) {
-                            (Some(u), _, Some(t), Some(s)) => {
-                                check_user(username, password, u, t, s)
-                            }
-                            (Some(u), Some(p), _, _) => check_legacy_user(username, password, u, p),
-                            _ => false,
-                        },
-                        None => false,
-                    }
+                let user = match auth_query.as_ref().and_then(|query| query.u.as_ref()) {
+                    Some(u) => find_user(state, u).await?,
+                    None => None,
                 };
 
-                if is_valid {
-                    Ok(Self)
-                } else {
-                    // Wait a bit, to prevent login attempts being spammed
-                    sleep(Duration::from_millis(800)).await;
-                    Err(axum::http::StatusCode::UNAUTHORIZED)
+                let is_valid = match (&user, auth_query) {
+                    (
+                        Some(DbUser {
+                            username,
+                            password: Some(password),
+                            ..
+                        }),
+                        Some(query),
+                    ) => match (&query.u, &query.p, &query.t, &query.s) {
+                        (Some(u), _, Some(t), Some(s)) => check_user(username, password, u, t, s),
+                        (Some(u), Some(p), _, _) => check_legacy_user(username, password, u, p),
+                        _ => false,
+                    },
+                    _ => false,
+                };
+
+                match user {
+                    Some(user) if is_valid => user,
+                    _ => {
+                        // Wait a bit, to prevent login attempts being spammed
+                        sleep(Duration::from_millis(800)).await;
+                        return Err(StatusCode::UNAUTHORIZED);
+                    }
                 }
             }
-            SubsonicAuth::None => Ok(Self),
+            SubsonicAuth::None => match find_user(state, DEFAULT_USERNAME).await? {
+                Some(user) => user,
+                None => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+            },
+        };
+
+        parts.extensions.insert(AuthenticatedUser(user));
+        Ok(Self)
+    }
+}
+
+async fn find_user(state: &SharedState, username: &str) -> Result<Option<DbUser>, StatusCode> {
+    state.db.find_user(username).await.map_err(|e| {
+        error!(?e, "Could not find user");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+/// Owner of everything that is created without authentication.
+pub const DEFAULT_USERNAME: &str = "admin";
+
+/// The username of the user making the request.
+pub struct CurrentUser(pub String);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for CurrentUser
+where
+    S: Send + Sync,
+{
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        match parts.extensions.get::<AuthenticatedUser>() {
+            Some(AuthenticatedUser(user)) => Ok(CurrentUser(user.username.clone())),
+            None => Ok(CurrentUser(DEFAULT_USERNAME.to_string())),
         }
     }
 }
 
+/// The user making the request, including the roles they have.
+#[derive(Clone, Debug)]
+pub struct AuthenticatedUser(pub DbUser);
+
+#[async_trait]
+impl<S> FromRequestParts<S> for AuthenticatedUser
+where
+    S: Send + Sync,
+{
+    type Rejection = StatusCode;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        parts
+            .extensions
+            .get::<AuthenticatedUser>()
+            .cloned()
+            .ok_or(StatusCode::UNAUTHORIZED)
+    }
+}
+
 fn check_user(username: &str, password: &str, u: &str, t: &str, s: &str) -> bool {
     let digest = md5::compute(format!("{password}{s}"));
     let expected_token = format!("{:02X?}", digest);
@@ -88,7 +156,12 @@ fn check_user(username: &str, password: &str, u: &str, t: &str, s: &str) -> bool
 }
 
 fn check_legacy_user(username: &str, password: &str, u: &str, p: &str) -> bool {
-    let p = if p.starts_with("enc:") {
+    username == u && password == decode_password(p)
+}
+
+/// Decodes a password that may be hex encoded, as in `enc:666f6f`.
+pub(crate) fn decode_password(p: &str) -> String {
+    if p.starts_with("enc:") {
         if let Some((_, encoded)) = p.split_once(':') {
             String::from_utf8_lossy(&hex::decode(encoded).unwrap_or_default()).to_string()
         } else {
@@ -96,8 +169,7 @@ fn check_legacy_user(username: &str, password: &str, u: &str, p: &str) -> bool {
         }
     } else {
         p.to_string()
-    };
-    username == u && password == p
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,42 @@
+use crate::api::auth::decode_password;
+use crate::api::format::SubsonicFormat;
+use crate::{AppResult, AuthenticatedUser, Deserialize, SharedState};
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use std::ops::DerefMut;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangePasswordParams {
+    username: String,
+    password: String,
+}
+
+/// Users with the settings role can change their own password, admins can change anyone's.
+pub async fn change_password(
+    format: SubsonicFormat,
+    AuthenticatedUser(current_user): AuthenticatedUser,
+    Query(params): Query<ChangePasswordParams>,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    let is_self = current_user.username == params.username && current_user.settings_role;
+    if !current_user.admin_role && !is_self {
+        return Ok((StatusCode::FORBIDDEN, ()).into_response());
+    }
+    if params.password.is_empty() {
+        return Ok((StatusCode::BAD_REQUEST, ()).into_response());
+    }
+
+    let updated = sqlx::query("UPDATE users SET password = ? WHERE username = ?")
+        .bind(decode_password(&params.password))
+        .bind(&params.username)
+        .execute(state.db.conn().await?.deref_mut())
+        .await?
+        .rows_affected();
+
+    if updated == 0 {
+        return Ok((StatusCode::NOT_FOUND, ()).into_response());
+    }
+    Ok(format.render::<()>(None))
+}
@@ -0,0 +1,54 @@
+use crate::api::format::SubsonicFormat;
+use crate::api::update_playlist::resolve_song_ids;
+use crate::{AppResult, CurrentUser, Deserialize, SharedState};
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use std::ops::DerefMut;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateBookmarkParams {
+    id: String,
+    /// Position in the song, in milliseconds.
+    position: u64,
+    comment: Option<String>,
+}
+
+/// Creates a bookmark in a song, or moves the user's existing bookmark in it.
+pub async fn create_bookmark(
+    format: SubsonicFormat,
+    CurrentUser(username): CurrentUser,
+    Query(params): Query<CreateBookmarkParams>,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    let mut conn = state.db.conn().await?;
+    let now = (state.options.now_provider)();
+
+    let folder_child_id = match resolve_song_ids(conn.deref_mut(), &[params.id])
+        .await?
+        .into_iter()
+        .next()
+    {
+        Some(id) => id,
+        None => return Ok((StatusCode::NOT_FOUND, ()).into_response()),
+    };
+
+    sqlx::query(
+        r#"
+        INSERT INTO bookmarks (username, folder_child_id, position, comment, created, changed)
+        VALUES (?, ?, ?, ?, ?, ?)
+        ON CONFLICT (username, folder_child_id) DO UPDATE SET position = excluded.position, comment = excluded.comment, changed = excluded.changed
+        "#,
+    )
+    .bind(&username)
+    .bind(folder_child_id)
+    .bind(params.position as i64)
+    .bind(&params.comment)
+    .bind(now)
+    .bind(now)
+    .execute(conn.deref_mut())
+    .await?;
+
+    Ok(format.render::<()>(None))
+}
@@ -0,0 +1,38 @@
+use crate::api::format::SubsonicFormat;
+use crate::{AppResult, AuthenticatedUser, Deserialize, SharedState};
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use std::ops::DerefMut;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateInternetRadioStationParams {
+    stream_url: String,
+    name: String,
+    homepage_url: Option<String>,
+}
+
+pub async fn create_internet_radio_station(
+    format: SubsonicFormat,
+    AuthenticatedUser(current_user): AuthenticatedUser,
+    Query(params): Query<CreateInternetRadioStationParams>,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    if !current_user.admin_role {
+        return Ok((StatusCode::FORBIDDEN, ()).into_response());
+    }
+
+    sqlx::query(
+        "INSERT INTO internet_radio_stations (station_id, name, stream_url, home_page_url) VALUES (?, ?, ?, ?)",
+    )
+    .bind(Uuid::new_v4())
+    .bind(&params.name)
+    .bind(&params.stream_url)
+    .bind(&params.homepage_url)
+    .execute(state.db.conn().await?.deref_mut())
+    .await?;
+
+    Ok(format.render::<()>(None))
+}
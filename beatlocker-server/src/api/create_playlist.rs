@@ -0,0 +1,78 @@
+use crate::api::format::SubsonicFormat;
+use crate::api::get_playlist::{get_playlist_impl, GetPlaylistResponse};
+use crate::api::update_playlist::{check_playlist_editable, resolve_song_ids};
+use crate::{AppResult, AuthenticatedUser, Deserialize, SharedState};
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use std::ops::DerefMut;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatePlaylistParams {
+    playlist_id: Option<Uuid>,
+    name: Option<String>,
+    #[serde(default = "Vec::new")]
+    song_id: Vec<String>,
+}
+
+/// Creates a playlist, or replaces all songs of an existing one if `playlistId` is given.
+pub async fn create_playlist(
+    format: SubsonicFormat,
+    AuthenticatedUser(current_user): AuthenticatedUser,
+    params: axum_extra::extract::Query<CreatePlaylistParams>,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    if !current_user.playlist_role {
+        return Ok((StatusCode::FORBIDDEN, ()).into_response());
+    }
+    let username = current_user.username;
+
+    let mut conn = state.db.conn().await?;
+    let now = (state.options.now_provider)();
+
+    let playlist_id = match (params.playlist_id, &params.name) {
+        (Some(playlist_id), _) => {
+            if let Some(status) =
+                check_playlist_editable(conn.deref_mut(), playlist_id, &username).await?
+            {
+                return Ok((status, ()).into_response());
+            }
+
+            sqlx::query(
+                "UPDATE playlists SET name = COALESCE(?, name), changed = ? WHERE playlist_id = ?",
+            )
+            .bind(&params.name)
+            .bind(now)
+            .bind(playlist_id)
+            .execute(conn.deref_mut())
+            .await?;
+            playlist_id
+        }
+        (None, Some(name)) => {
+            let playlist_id = Uuid::new_v4();
+            sqlx::query(
+                r#"
+                INSERT INTO playlists (playlist_id, name, owner, public, created, changed)
+                VALUES (?, ?, ?, false, ?, ?)
+                "#,
+            )
+            .bind(playlist_id)
+            .bind(name)
+            .bind(&username)
+            .bind(now)
+            .bind(now)
+            .execute(conn.deref_mut())
+            .await?;
+            playlist_id
+        }
+        (None, None) => return Ok((StatusCode::BAD_REQUEST, ()).into_response()),
+    };
+
+    let entries = resolve_song_ids(conn.deref_mut(), &params.song_id).await?;
+    state.db.set_playlist_entries(playlist_id, &entries).await?;
+
+    let playlist = get_playlist_impl(conn.deref_mut(), playlist_id, &username).await?;
+    Ok(format.render::<GetPlaylistResponse>(playlist))
+}
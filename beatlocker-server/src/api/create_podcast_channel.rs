@@ -0,0 +1,54 @@
+use crate::api::format::SubsonicFormat;
+use crate::{
+    refresh_podcast_feed, AppResult, AuthenticatedUser, Deserialize, SharedState, TaskState,
+};
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use std::ops::DerefMut;
+use std::sync::Arc;
+use tracing::error;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreatePodcastChannelParams {
+    url: String,
+}
+
+/// Adds a podcast channel. Its feed is read in the background, after which its episodes are
+/// listed.
+pub async fn create_podcast_channel(
+    format: SubsonicFormat,
+    AuthenticatedUser(current_user): AuthenticatedUser,
+    Query(params): Query<CreatePodcastChannelParams>,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    if !current_user.admin_role {
+        return Ok((StatusCode::FORBIDDEN, ()).into_response());
+    }
+
+    let channel_id = Uuid::new_v4();
+    sqlx::query(
+        "INSERT INTO podcast_channels (channel_id, url, status, created) VALUES (?, ?, 'new', ?)",
+    )
+    .bind(channel_id)
+    .bind(&params.url)
+    .bind((state.options.now_provider)())
+    .execute(state.db.conn().await?.deref_mut())
+    .await?;
+
+    let task_state = Arc::new(TaskState {
+        options: state.options.clone(),
+        db: state.db.clone(),
+    });
+    tokio::spawn(async move {
+        refresh_podcast_feed(task_state, channel_id)
+            .await
+            .unwrap_or_else(|e| {
+                error!(?e, "Error when refreshing podcast");
+            });
+    });
+
+    Ok(format.render::<()>(None))
+}
@@ -0,0 +1,125 @@
+use crate::api::format::SubsonicFormat;
+use crate::api::get_shares::{find_shares, share_base_url, GetSharesResponse};
+use crate::api::update_playlist::resolve_song_ids;
+use crate::{AppResult, AuthenticatedUser, Deserialize, SharedState};
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, TimeZone, Utc};
+use sqlx::sqlite::SqliteRow;
+use sqlx::{Row, SqliteConnection};
+use std::ops::DerefMut;
+use std::str::FromStr;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CreateShareParams {
+    #[serde(default = "Vec::new")]
+    id: Vec<String>,
+    description: Option<String>,
+    /// Milliseconds since the epoch. Shares without an expiry date never expire.
+    expires: Option<i64>,
+}
+
+/// Shares songs, folders or albums through a public link.
+pub async fn create_share(
+    format: SubsonicFormat,
+    AuthenticatedUser(current_user): AuthenticatedUser,
+    axum_extra::extract::Query(params): axum_extra::extract::Query<CreateShareParams>,
+    headers: HeaderMap,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    if !current_user.share_role {
+        return Ok((StatusCode::FORBIDDEN, ()).into_response());
+    }
+
+    let mut conn = state.db.conn().await?;
+    let entries = resolve_share_entries(conn.deref_mut(), &params.id).await?;
+    if entries.is_empty() {
+        return Ok((StatusCode::NOT_FOUND, ()).into_response());
+    }
+
+    let share_id = Uuid::new_v4();
+    sqlx::query(
+        "INSERT INTO shares (share_id, username, description, created, expires) VALUES (?, ?, ?, ?, ?)",
+    )
+    .bind(share_id)
+    .bind(&current_user.username)
+    .bind(&params.description)
+    .bind((state.options.now_provider)())
+    .bind(params.expires.and_then(expiry_date))
+    .execute(conn.deref_mut())
+    .await?;
+
+    for (position, folder_child_id) in entries.iter().enumerate() {
+        sqlx::query(
+            "INSERT INTO share_entries (share_id, position, folder_child_id) VALUES (?, ?, ?)",
+        )
+        .bind(share_id)
+        .bind(position as u32)
+        .bind(folder_child_id)
+        .execute(conn.deref_mut())
+        .await?;
+    }
+
+    let base_url = share_base_url(&state.options, &headers);
+    let shares = find_shares(
+        conn.deref_mut(),
+        &current_user.username,
+        Some(share_id),
+        &base_url,
+    )
+    .await?;
+
+    Ok(format.render(GetSharesResponse::new(shares)))
+}
+
+/// Converts an expiry date in milliseconds since the epoch. Zero means the share doesn't expire.
+pub(crate) fn expiry_date(millis: i64) -> Option<DateTime<Utc>> {
+    match millis {
+        0 => None,
+        millis => Utc.timestamp_millis_opt(millis).single(),
+    }
+}
+
+/// Songs are shared by themselves, while folders and albums share all of their songs. Unknown ids
+/// are skipped.
+async fn resolve_share_entries(
+    conn: &mut SqliteConnection,
+    ids: &[String],
+) -> AppResult<Vec<Uuid>> {
+    let mut folder_child_ids = vec![];
+    for id in ids {
+        let songs = resolve_song_ids(&mut *conn, &[id.clone()]).await?;
+        if !songs.is_empty() {
+            folder_child_ids.extend(songs);
+            continue;
+        }
+
+        let id = match Uuid::from_str(id) {
+            Ok(id) => id,
+            Err(_) => continue,
+        };
+        let songs = sqlx::query(
+            r#"
+            SELECT fc.folder_child_id
+            FROM folder_children fc
+            JOIN songs s ON s.song_id = fc.song_id
+            WHERE fc.folder_id = ? OR s.album_id = ?
+            ORDER BY s.disc_number, s.track_number, fc.name
+            "#,
+        )
+        .bind(id)
+        .bind(id)
+        .map(|row: SqliteRow| {
+            let folder_child_id: Uuid = row.get("folder_child_id");
+            folder_child_id
+        })
+        .fetch_all(&mut *conn)
+        .await?;
+        folder_child_ids.extend(songs);
+    }
+
+    Ok(folder_child_ids)
+}
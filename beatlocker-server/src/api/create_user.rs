@@ -0,0 +1,61 @@
+use crate::api::auth::decode_password;
+use crate::api::format::SubsonicFormat;
+use crate::{AppResult, AuthenticatedUser, Deserialize, SharedState};
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use std::ops::DerefMut;
+
+/// Parameters of both `createUser` and `updateUser`. Roles that aren't given keep their default
+/// or current value.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UserParams {
+    pub(crate) username: String,
+    pub(crate) password: Option<String>,
+    pub(crate) email: Option<String>,
+    pub(crate) admin_role: Option<bool>,
+    pub(crate) settings_role: Option<bool>,
+    pub(crate) stream_role: Option<bool>,
+    pub(crate) download_role: Option<bool>,
+    pub(crate) playlist_role: Option<bool>,
+    pub(crate) share_role: Option<bool>,
+}
+
+pub async fn create_user(
+    format: SubsonicFormat,
+    AuthenticatedUser(current_user): AuthenticatedUser,
+    Query(params): Query<UserParams>,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    if !current_user.admin_role {
+        return Ok((StatusCode::FORBIDDEN, ()).into_response());
+    }
+    let password = match &params.password {
+        Some(password) if !params.username.is_empty() => decode_password(password),
+        _ => return Ok((StatusCode::BAD_REQUEST, ()).into_response()),
+    };
+    if state.db.find_user(&params.username).await?.is_some() {
+        return Ok((StatusCode::CONFLICT, ()).into_response());
+    }
+
+    sqlx::query(
+        r#"
+        INSERT INTO users (username, password, email, admin_role, settings_role, stream_role, download_role, playlist_role, share_role)
+        VALUES (?, ?, ?, COALESCE(?, false), COALESCE(?, true), COALESCE(?, true), COALESCE(?, false), COALESCE(?, false), COALESCE(?, false))
+        "#,
+    )
+    .bind(&params.username)
+    .bind(password)
+    .bind(&params.email)
+    .bind(params.admin_role)
+    .bind(params.settings_role)
+    .bind(params.stream_role)
+    .bind(params.download_role)
+    .bind(params.playlist_role)
+    .bind(params.share_role)
+    .execute(state.db.conn().await?.deref_mut())
+    .await?;
+
+    Ok(format.render::<()>(None))
+}
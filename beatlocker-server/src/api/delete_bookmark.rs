@@ -0,0 +1,36 @@
+use crate::api::format::SubsonicFormat;
+use crate::{AppResult, CurrentUser, Deserialize, SharedState};
+use axum::extract::{Query, State};
+use axum::response::Response;
+use std::ops::DerefMut;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteBookmarkParams {
+    id: Uuid,
+}
+
+pub async fn delete_bookmark(
+    format: SubsonicFormat,
+    CurrentUser(username): CurrentUser,
+    Query(params): Query<DeleteBookmarkParams>,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    let mut conn = state.db.conn().await?;
+
+    // Bookmarks can be deleted by the song id as well as by the folder child id they're listed with
+    sqlx::query(
+        r#"
+        DELETE FROM bookmarks
+        WHERE username = ? AND folder_child_id IN (SELECT folder_child_id FROM folder_children WHERE folder_child_id = ? OR song_id = ?)
+        "#,
+    )
+    .bind(&username)
+    .bind(params.id)
+    .bind(params.id)
+    .execute(conn.deref_mut())
+    .await?;
+
+    Ok(format.render::<()>(None))
+}
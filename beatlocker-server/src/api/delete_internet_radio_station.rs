@@ -0,0 +1,37 @@
+use crate::api::format::SubsonicFormat;
+use crate::api::update_internet_radio_station::check_station_editable;
+use crate::{AppResult, AuthenticatedUser, Deserialize, SharedState};
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use std::ops::DerefMut;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteInternetRadioStationParams {
+    id: Uuid,
+}
+
+pub async fn delete_internet_radio_station(
+    format: SubsonicFormat,
+    AuthenticatedUser(current_user): AuthenticatedUser,
+    Query(params): Query<DeleteInternetRadioStationParams>,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    if !current_user.admin_role {
+        return Ok((StatusCode::FORBIDDEN, ()).into_response());
+    }
+
+    let mut conn = state.db.conn().await?;
+    if let Some(status) = check_station_editable(conn.deref_mut(), params.id).await? {
+        return Ok((status, ()).into_response());
+    }
+
+    sqlx::query("DELETE FROM internet_radio_stations WHERE station_id = ?")
+        .bind(params.id)
+        .execute(conn.deref_mut())
+        .await?;
+
+    Ok(format.render::<()>(None))
+}
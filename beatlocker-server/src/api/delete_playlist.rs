@@ -0,0 +1,40 @@
+use crate::api::format::SubsonicFormat;
+use crate::api::update_playlist::check_playlist_editable;
+use crate::{AppResult, AuthenticatedUser, Deserialize, SharedState};
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use std::ops::DerefMut;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeletePlaylistParams {
+    id: Uuid,
+}
+
+pub async fn delete_playlist(
+    format: SubsonicFormat,
+    AuthenticatedUser(current_user): AuthenticatedUser,
+    Query(params): Query<DeletePlaylistParams>,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    if !current_user.playlist_role {
+        return Ok((StatusCode::FORBIDDEN, ()).into_response());
+    }
+    let username = current_user.username;
+
+    let mut conn = state.db.conn().await?;
+
+    if let Some(status) = check_playlist_editable(conn.deref_mut(), params.id, &username).await? {
+        return Ok((status, ()).into_response());
+    }
+
+    // Entries are removed through the foreign key
+    sqlx::query("DELETE FROM playlists WHERE playlist_id = ?")
+        .bind(params.id)
+        .execute(conn.deref_mut())
+        .await?;
+
+    Ok(format.render::<()>(None))
+}
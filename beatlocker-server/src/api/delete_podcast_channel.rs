@@ -0,0 +1,51 @@
+use crate::api::format::SubsonicFormat;
+use crate::{AppResult, AuthenticatedUser, Deserialize, SharedState};
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use sqlx::sqlite::SqliteRow;
+use sqlx::Row;
+use std::ops::DerefMut;
+use tracing::warn;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeletePodcastChannelParams {
+    id: Uuid,
+}
+
+/// Deletes a podcast channel together with its episodes, including any downloaded files.
+pub async fn delete_podcast_channel(
+    format: SubsonicFormat,
+    AuthenticatedUser(current_user): AuthenticatedUser,
+    Query(params): Query<DeletePodcastChannelParams>,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    if !current_user.admin_role {
+        return Ok((StatusCode::FORBIDDEN, ()).into_response());
+    }
+
+    let mut conn = state.db.conn().await?;
+    let paths =
+        sqlx::query("SELECT path FROM podcast_episodes WHERE channel_id = ? AND path IS NOT NULL")
+            .bind(params.id)
+            .map(|row: SqliteRow| {
+                let path: String = row.get("path");
+                path
+            })
+            .fetch_all(conn.deref_mut())
+            .await?;
+    for path in paths {
+        if let Err(e) = tokio::fs::remove_file(&path).await {
+            warn!(?path, ?e, "Could not remove podcast episode");
+        }
+    }
+
+    sqlx::query("DELETE FROM podcast_channels WHERE channel_id = ?")
+        .bind(params.id)
+        .execute(conn.deref_mut())
+        .await?;
+
+    Ok(format.render::<()>(None))
+}
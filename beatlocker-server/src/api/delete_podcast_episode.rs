@@ -0,0 +1,55 @@
+use crate::api::format::SubsonicFormat;
+use crate::{AppResult, AuthenticatedUser, Deserialize, SharedState};
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use sqlx::sqlite::SqliteRow;
+use sqlx::Row;
+use std::ops::DerefMut;
+use tracing::warn;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeletePodcastEpisodeParams {
+    id: Uuid,
+}
+
+/// Removes the downloaded file of an episode. The episode itself is kept as `deleted`, so it isn't
+/// added again on the next refresh of its feed.
+pub async fn delete_podcast_episode(
+    format: SubsonicFormat,
+    AuthenticatedUser(current_user): AuthenticatedUser,
+    Query(params): Query<DeletePodcastEpisodeParams>,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    if !current_user.admin_role {
+        return Ok((StatusCode::FORBIDDEN, ()).into_response());
+    }
+
+    let mut conn = state.db.conn().await?;
+    let path = sqlx::query("SELECT path FROM podcast_episodes WHERE episode_id = ?")
+        .bind(params.id)
+        .map(|row: SqliteRow| {
+            let path: Option<String> = row.get("path");
+            path
+        })
+        .fetch_optional(conn.deref_mut())
+        .await?;
+    let path = match path {
+        Some(path) => path,
+        None => return Ok((StatusCode::NOT_FOUND, ()).into_response()),
+    };
+    if let Some(path) = path {
+        if let Err(e) = tokio::fs::remove_file(&path).await {
+            warn!(?path, ?e, "Could not remove podcast episode");
+        }
+    }
+
+    sqlx::query("UPDATE podcast_episodes SET status = 'deleted', path = NULL WHERE episode_id = ?")
+        .bind(params.id)
+        .execute(conn.deref_mut())
+        .await?;
+
+    Ok(format.render::<()>(None))
+}
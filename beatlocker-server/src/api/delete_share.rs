@@ -0,0 +1,33 @@
+use crate::api::format::SubsonicFormat;
+use crate::api::update_share::check_share_editable;
+use crate::{AppResult, AuthenticatedUser, Deserialize, SharedState};
+use axum::extract::{Query, State};
+use axum::response::{IntoResponse, Response};
+use std::ops::DerefMut;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteShareParams {
+    id: Uuid,
+}
+
+pub async fn delete_share(
+    format: SubsonicFormat,
+    AuthenticatedUser(current_user): AuthenticatedUser,
+    Query(params): Query<DeleteShareParams>,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    let mut conn = state.db.conn().await?;
+    if let Some(status) = check_share_editable(conn.deref_mut(), params.id, &current_user).await? {
+        return Ok((status, ()).into_response());
+    }
+
+    // Entries are removed through the foreign key
+    sqlx::query("DELETE FROM shares WHERE share_id = ?")
+        .bind(params.id)
+        .execute(conn.deref_mut())
+        .await?;
+
+    Ok(format.render::<()>(None))
+}
@@ -0,0 +1,60 @@
+use crate::api::format::SubsonicFormat;
+use crate::{AppResult, AuthenticatedUser, Deserialize, SharedState};
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use sqlx::Connection;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DeleteUserParams {
+    username: String,
+}
+
+/// Deletes a user along with their playlists, play history, stars, ratings, bookmarks, play queue
+/// and linked scrobble accounts.
+pub async fn delete_user(
+    format: SubsonicFormat,
+    AuthenticatedUser(current_user): AuthenticatedUser,
+    Query(params): Query<DeleteUserParams>,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    if !current_user.admin_role {
+        return Ok((StatusCode::FORBIDDEN, ()).into_response());
+    }
+    if current_user.username == params.username {
+        return Ok((StatusCode::BAD_REQUEST, ()).into_response());
+    }
+
+    let mut conn = state.db.conn().await?;
+    // Everything of the user is removed together, or not at all
+    let mut tx = conn.begin().await?;
+    let deleted = sqlx::query("DELETE FROM users WHERE username = ?")
+        .bind(&params.username)
+        .execute(&mut tx)
+        .await?
+        .rows_affected();
+    if deleted == 0 {
+        return Ok((StatusCode::NOT_FOUND, ()).into_response());
+    }
+
+    for query in [
+        "DELETE FROM playlists WHERE owner = ?",
+        "DELETE FROM plays WHERE username = ?",
+        "DELETE FROM now_playing WHERE username = ?",
+        "DELETE FROM scrobble_accounts WHERE username = ?",
+        "DELETE FROM starred WHERE username = ?",
+        "DELETE FROM ratings WHERE username = ?",
+        "DELETE FROM bookmarks WHERE username = ?",
+        "DELETE FROM play_queues WHERE username = ?",
+        "DELETE FROM shares WHERE username = ?",
+    ] {
+        sqlx::query(query)
+            .bind(&params.username)
+            .execute(&mut tx)
+            .await?;
+    }
+    tx.commit().await?;
+
+    Ok(format.render::<()>(None))
+}
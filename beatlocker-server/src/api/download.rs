@@ -0,0 +1,326 @@
+use crate::api::range::file_response;
+use crate::api::zip::{write_zip, ZipEntry};
+use crate::{AppResult, AuthenticatedUser, SharedState};
+use std::collections::HashSet;
+use std::ops::DerefMut;
+use std::path::{Path, PathBuf};
+
+use axum::extract::{Query, State};
+use axum::http::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum_extra::body::AsyncReadBody;
+use serde::Deserialize;
+use sqlx::sqlite::SqliteRow;
+use sqlx::{Row, SqliteConnection};
+use tracing::warn;
+
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadParams {
+    id: Uuid,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Download {
+    File {
+        name: String,
+        path: PathBuf,
+        content_type: String,
+    },
+    Archive {
+        name: String,
+        entries: Vec<(String, PathBuf)>,
+    },
+}
+
+pub async fn download(
+    AuthenticatedUser(current_user): AuthenticatedUser,
+    Query(params): Query<DownloadParams>,
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+) -> AppResult<Response> {
+    if !current_user.download_role {
+        return Ok((StatusCode::FORBIDDEN, ()).into_response());
+    }
+
+    let mut conn = state.db.conn().await?;
+
+    match download_impl(conn.deref_mut(), params.id).await? {
+        Some(Download::File {
+            name,
+            path,
+            content_type,
+        }) => {
+            let mut response = file_response(&path, &content_type, &headers).await?;
+            response
+                .headers_mut()
+                .insert(CONTENT_DISPOSITION, content_disposition(&name)?);
+            Ok(response)
+        }
+        Some(Download::Archive { name, entries }) => {
+            let entries = entries
+                .into_iter()
+                .map(|(name, path)| ZipEntry { name, path })
+                .collect();
+            let (writer, reader) = tokio::io::duplex(64 * 1024);
+            tokio::spawn(async move {
+                if let Err(e) = write_zip(entries, writer).await {
+                    warn!(?e, "Could not write zip archive");
+                }
+            });
+
+            let mut headers = HeaderMap::new();
+            headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/zip"));
+            headers.insert(
+                CONTENT_DISPOSITION,
+                content_disposition(&format!("{name}.zip"))?,
+            );
+            Ok((headers, AsyncReadBody::new(reader)).into_response())
+        }
+        None => Ok((StatusCode::NOT_FOUND, ()).into_response()),
+    }
+}
+
+/// Determines what to send for an id, which may be a song, a folder or an album.
+pub async fn download_impl(conn: &mut SqliteConnection, id: Uuid) -> AppResult<Option<Download>> {
+    let song = sqlx::query(
+        "SELECT path, s.content_type FROM folder_children fc LEFT JOIN songs s ON s.song_id = fc.song_id WHERE folder_child_id = ?",
+    )
+    .bind(id)
+    .map(|row: SqliteRow| {
+        let path: String = row.get("path");
+        let content_type: Option<String> = row.get("content_type");
+        Download::File {
+            name: file_name(&path),
+            path: PathBuf::from(path),
+            content_type: content_type.unwrap_or_else(|| "application/octet-stream".to_string()),
+        }
+    })
+    .fetch_optional(&mut *conn)
+    .await?;
+    if song.is_some() {
+        return Ok(song);
+    }
+
+    let folder_name: Option<String> = sqlx::query("SELECT name FROM folders WHERE folder_id = ?")
+        .bind(id)
+        .map(|row: SqliteRow| row.get("name"))
+        .fetch_optional(&mut *conn)
+        .await?;
+    if let Some(name) = folder_name {
+        // Everything below the folder, named relative to it
+        let entries = sqlx::query(
+            r#"WITH RECURSIVE subfolders(folder_id, relative_path) AS (
+                SELECT ?, ''
+                UNION ALL
+                SELECT f.folder_id, sf.relative_path || f.name || '/'
+                FROM folders f
+                JOIN subfolders sf ON f.parent_id = sf.folder_id
+            )
+            SELECT sf.relative_path, fc.path
+            FROM folder_children fc
+            JOIN subfolders sf ON sf.folder_id = fc.folder_id
+            ORDER BY sf.relative_path, fc.path"#,
+        )
+        .bind(id)
+        .map(|row: SqliteRow| {
+            let relative_path: String = row.get("relative_path");
+            let path: String = row.get("path");
+            (
+                format!("{}{}", relative_path, file_name(&path)),
+                PathBuf::from(path),
+            )
+        })
+        .fetch_all(&mut *conn)
+        .await?;
+
+        return Ok(Some(Download::Archive { name, entries }));
+    }
+
+    let album_title: Option<String> = sqlx::query("SELECT title FROM albums WHERE album_id = ?")
+        .bind(id)
+        .map(|row: SqliteRow| row.get("title"))
+        .fetch_optional(&mut *conn)
+        .await?;
+    if let Some(name) = album_title {
+        let paths: Vec<String> = sqlx::query(
+            r#"SELECT fc.path
+            FROM folder_children fc
+            JOIN songs s ON s.song_id = fc.song_id
+            WHERE s.album_id = ?
+            ORDER BY s.disc_number, s.track_number, fc.path"#,
+        )
+        .bind(id)
+        .map(|row: SqliteRow| row.get("path"))
+        .fetch_all(&mut *conn)
+        .await?;
+
+        // An album can span folders, so file names aren't necessarily unique
+        let mut names = HashSet::new();
+        let entries = paths
+            .into_iter()
+            .map(|path| {
+                let name = unique_name(&mut names, &file_name(&path));
+                (name, PathBuf::from(path))
+            })
+            .collect();
+
+        return Ok(Some(Download::Archive { name, entries }));
+    }
+
+    Ok(None)
+}
+
+fn file_name(path: &str) -> String {
+    Path::new(path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.to_string())
+}
+
+fn unique_name(names: &mut HashSet<String>, name: &str) -> String {
+    let mut candidate = name.to_string();
+    let path = Path::new(name);
+    let mut n = 2;
+    while !names.insert(candidate.clone()) {
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        candidate = match path.extension() {
+            Some(extension) => format!("{stem} ({n}).{}", extension.to_string_lossy()),
+            None => format!("{stem} ({n})"),
+        };
+        n += 1;
+    }
+    candidate
+}
+
+/// `Content-Disposition` with both an ASCII fallback and the UTF-8 file name.
+pub(crate) fn content_disposition(name: &str) -> AppResult<HeaderValue> {
+    let fallback: String = name
+        .chars()
+        .map(|c| match c {
+            ' '..='~' if c != '"' && c != '\\' => c,
+            _ => '_',
+        })
+        .collect();
+    let encoded: String = name
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'.' | b'-' | b'_' => (b as char).to_string(),
+            _ => format!("%{b:02X}"),
+        })
+        .collect();
+
+    Ok(HeaderValue::from_str(&format!(
+        "attachment; filename=\"{fallback}\"; filename*=UTF-8''{encoded}"
+    ))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{str_to_uuid, TestState};
+
+    async fn entry_names(state: &TestState, id: Uuid) -> (String, Vec<String>) {
+        let mut conn = state.db().await.conn().await.unwrap();
+        match download_impl(conn.deref_mut(), id).await.unwrap() {
+            Some(Download::Archive { name, entries }) => {
+                (name, entries.into_iter().map(|(name, _)| name).collect())
+            }
+            _ => panic!("Expected an archive"),
+        }
+    }
+
+    #[tokio::test]
+    async fn can_download_song() {
+        let state = TestState::new().await.unwrap();
+        let path = state
+            .tempdir
+            .as_ref()
+            .unwrap()
+            .path()
+            .join("folder1/artist1-a.mp3");
+
+        let mut conn = state.db().await.conn().await.unwrap();
+        let download = download_impl(conn.deref_mut(), str_to_uuid(path.to_str().unwrap()))
+            .await
+            .unwrap();
+        assert_eq!(
+            download,
+            Some(Download::File {
+                name: "artist1-a.mp3".to_string(),
+                path,
+                content_type: "audio/mp3".to_string()
+            })
+        );
+
+        assert_eq!(
+            download_impl(conn.deref_mut(), Uuid::new_v4())
+                .await
+                .unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn can_download_folder() {
+        let state = TestState::new().await.unwrap();
+        let path = state.tempdir.as_ref().unwrap().path();
+
+        assert_eq!(
+            entry_names(&state, str_to_uuid(path.join("folder1").to_str().unwrap())).await,
+            (
+                "folder1".to_string(),
+                vec![
+                    "artist1-a.mp3".to_string(),
+                    "artist1-b.mp3".to_string(),
+                    "artist2-c.mp3".to_string()
+                ]
+            )
+        );
+
+        // The root folder includes all subfolders
+        let (_, names) = entry_names(&state, Uuid::nil()).await;
+        assert_eq!(names.len(), 8);
+        assert_eq!(names[0], "folder1/artist1-a.mp3");
+        assert_eq!(names[7], "folder3/artist2-h.mp3");
+    }
+
+    #[tokio::test]
+    async fn can_download_album() {
+        let state = TestState::new().await.unwrap();
+
+        assert_eq!(
+            entry_names(&state, str_to_uuid("Artist1_Album1Artist1")).await,
+            (
+                "Artist1_Album1".to_string(),
+                vec![
+                    "artist1-a.mp3".to_string(),
+                    "artist1-b.mp3".to_string(),
+                    "artist1-d.mp3".to_string(),
+                    "artist1-e.mp3".to_string()
+                ]
+            )
+        );
+    }
+
+    #[test]
+    fn can_make_unique_names() {
+        let mut names = HashSet::new();
+        assert_eq!(unique_name(&mut names, "a.mp3"), "a.mp3");
+        assert_eq!(unique_name(&mut names, "a.mp3"), "a (2).mp3");
+        assert_eq!(unique_name(&mut names, "a.mp3"), "a (3).mp3");
+        assert_eq!(unique_name(&mut names, "b"), "b");
+        assert_eq!(unique_name(&mut names, "b"), "b (2)");
+    }
+
+    #[test]
+    fn can_make_content_disposition() {
+        assert_eq!(
+            content_disposition("Déjà \"vu\".zip").unwrap(),
+            "attachment; filename=\"D_j_ _vu_.zip\"; filename*=UTF-8''D%C3%A9j%C3%A0%20%22vu%22.zip"
+        );
+    }
+}
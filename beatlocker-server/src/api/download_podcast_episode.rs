@@ -0,0 +1,53 @@
+use crate::api::format::SubsonicFormat;
+use crate::{
+    fetch_podcast_episode, AppResult, AuthenticatedUser, Deserialize, SharedState, TaskState,
+};
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use std::ops::DerefMut;
+use std::sync::Arc;
+use tracing::error;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadPodcastEpisodeParams {
+    id: Uuid,
+}
+
+/// Downloads an episode onto the server in the background. Once it's done, the episode is
+/// `completed` and can be streamed.
+pub async fn download_podcast_episode(
+    format: SubsonicFormat,
+    AuthenticatedUser(current_user): AuthenticatedUser,
+    Query(params): Query<DownloadPodcastEpisodeParams>,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    if !current_user.admin_role {
+        return Ok((StatusCode::FORBIDDEN, ()).into_response());
+    }
+
+    let found = sqlx::query("SELECT 1 FROM podcast_episodes WHERE episode_id = ?")
+        .bind(params.id)
+        .fetch_optional(state.db.conn().await?.deref_mut())
+        .await?
+        .is_some();
+    if !found {
+        return Ok((StatusCode::NOT_FOUND, ()).into_response());
+    }
+
+    let task_state = Arc::new(TaskState {
+        options: state.options.clone(),
+        db: state.db.clone(),
+    });
+    tokio::spawn(async move {
+        fetch_podcast_episode(task_state, params.id)
+            .await
+            .unwrap_or_else(|e| {
+                error!(?e, "Error when downloading podcast episode");
+            });
+    });
+
+    Ok(format.render::<()>(None))
+}
@@ -0,0 +1,427 @@
+use crate::api::download::content_disposition;
+use crate::api::get_shares::share_base_url;
+use crate::{AppResult, CurrentUser, Deserialize, ServerOptions, SharedState};
+use anyhow::anyhow;
+use axum::extract::{Query, State};
+use axum::http::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::TypedHeader;
+use headers::authorization::Basic;
+use headers::Authorization;
+use quick_xml::escape::escape;
+use reqwest::Url;
+use sqlx::sqlite::SqliteRow;
+use sqlx::{Row, SqliteConnection};
+use std::ops::DerefMut;
+use std::path::Path;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportPlaylistParams {
+    /// A playlist or a folder. Starred songs are exported when there is no id.
+    id: Option<Uuid>,
+    #[serde(default)]
+    format: ExportFormat,
+    #[serde(default)]
+    entries: ExportEntries,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    #[default]
+    M3u8,
+    Xspf,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportEntries {
+    /// Paths of the files on the server, for players that can access the library directly.
+    Path,
+    /// `stream` URLs, authenticated with the credentials of the export request.
+    #[default]
+    Stream,
+}
+
+/// The credentials of the export request, which are copied into exported `stream` URLs.
+#[derive(Debug, Deserialize)]
+pub struct ExportAuthParams {
+    u: Option<String>,
+    p: Option<String>,
+    t: Option<String>,
+    s: Option<String>,
+    v: Option<String>,
+    c: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ExportEntry {
+    id: Uuid,
+    path: String,
+    title: Option<String>,
+    artist: Option<String>,
+    album: Option<String>,
+    duration: Option<u32>,
+}
+
+const SELECT_EXPORT_ENTRIES: &str = r#"
+    SELECT fc.folder_child_id, fc.path, s.title, ar.name as artist, al.title as album, s.duration
+    FROM folder_children fc
+    JOIN songs s ON s.song_id = fc.song_id
+    LEFT JOIN artists ar ON ar.artist_id = s.artist_id
+    LEFT JOIN albums al ON al.album_id = s.album_id
+"#;
+
+pub async fn export_playlist(
+    CurrentUser(username): CurrentUser,
+    Query(params): Query<ExportPlaylistParams>,
+    Query(auth): Query<ExportAuthParams>,
+    basic_auth: Option<TypedHeader<Authorization<Basic>>>,
+    headers: HeaderMap,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    let mut conn = state.db.conn().await?;
+
+    let (name, entries) = match params.id {
+        Some(id) => match find_export_entries(conn.deref_mut(), id, &username).await? {
+            Some(found) => found,
+            None => return Ok((StatusCode::NOT_FOUND, ()).into_response()),
+        },
+        None => (
+            "Starred".to_string(),
+            sqlx::query(&format!(
+                "{SELECT_EXPORT_ENTRIES} JOIN starred st ON (st.starred_id = s.song_id OR st.starred_id = fc.folder_child_id) AND st.username = ? ORDER BY s.title"
+            ))
+            .bind(&username)
+            .map(export_entry)
+            .fetch_all(conn.deref_mut())
+            .await?,
+        ),
+    };
+
+    let locations = match params.entries {
+        ExportEntries::Path => entries
+            .iter()
+            .map(|entry| match params.format {
+                ExportFormat::M3u8 => Ok(entry.path.clone()),
+                ExportFormat::Xspf => file_url(&entry.path),
+            })
+            .collect::<AppResult<Vec<_>>>()?,
+        ExportEntries::Stream => {
+            let base_url = stream_base_url(
+                &state.options,
+                &headers,
+                &auth,
+                basic_auth.as_ref().map(|h| &h.0),
+            )?;
+            entries
+                .iter()
+                .map(|entry| {
+                    let mut url = base_url.clone();
+                    url.query_pairs_mut()
+                        .append_pair("id", &entry.id.to_string());
+                    url.to_string()
+                })
+                .collect()
+        }
+    };
+
+    let (body, content_type, extension) = match params.format {
+        ExportFormat::M3u8 => (
+            render_m3u8(&name, &entries, &locations),
+            "audio/x-mpegurl",
+            "m3u8",
+        ),
+        ExportFormat::Xspf => (
+            render_xspf(&name, &entries, &locations),
+            "application/xspf+xml",
+            "xspf",
+        ),
+    };
+
+    let mut headers = HeaderMap::new();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_static(content_type));
+    headers.insert(
+        CONTENT_DISPOSITION,
+        content_disposition(&format!("{name}.{extension}"))?,
+    );
+    Ok((headers, body).into_response())
+}
+
+/// Finds the songs of a playlist the user can see, or of a folder and all of its subfolders.
+async fn find_export_entries(
+    conn: &mut SqliteConnection,
+    id: Uuid,
+    username: &str,
+) -> AppResult<Option<(String, Vec<ExportEntry>)>> {
+    let playlist_name: Option<String> =
+        sqlx::query("SELECT name FROM playlists WHERE playlist_id = ? AND (owner = ? OR public)")
+            .bind(id)
+            .bind(username)
+            .map(|row: SqliteRow| row.get("name"))
+            .fetch_optional(&mut *conn)
+            .await?;
+    if let Some(name) = playlist_name {
+        let entries = sqlx::query(&format!(
+            "{SELECT_EXPORT_ENTRIES} JOIN playlist_entries pe ON pe.folder_child_id = fc.folder_child_id WHERE pe.playlist_id = ? ORDER BY pe.position"
+        ))
+        .bind(id)
+        .map(export_entry)
+        .fetch_all(&mut *conn)
+        .await?;
+        return Ok(Some((name, entries)));
+    }
+
+    let folder_name: Option<String> = sqlx::query("SELECT name FROM folders WHERE folder_id = ?")
+        .bind(id)
+        .map(|row: SqliteRow| row.get("name"))
+        .fetch_optional(&mut *conn)
+        .await?;
+    if let Some(name) = folder_name {
+        let entries = sqlx::query(&format!(
+            r#"WITH RECURSIVE subfolders(folder_id) AS (
+                SELECT ?
+                UNION ALL
+                SELECT f.folder_id FROM folders f JOIN subfolders sf ON f.parent_id = sf.folder_id
+            )
+            {SELECT_EXPORT_ENTRIES}
+            JOIN subfolders sf ON sf.folder_id = fc.folder_id
+            ORDER BY fc.path"#
+        ))
+        .bind(id)
+        .map(export_entry)
+        .fetch_all(&mut *conn)
+        .await?;
+        return Ok(Some((name, entries)));
+    }
+
+    Ok(None)
+}
+
+fn export_entry(row: SqliteRow) -> ExportEntry {
+    ExportEntry {
+        id: row.get("folder_child_id"),
+        path: row.get("path"),
+        title: row.get("title"),
+        artist: row.get("artist"),
+        album: row.get("album"),
+        duration: row.get("duration"),
+    }
+}
+
+/// The `stream` URL of this server, with the client's credentials. Like share links, it starts with
+/// the external URL of the server, or otherwise the URL the client used.
+pub(crate) fn stream_base_url(
+    options: &ServerOptions,
+    headers: &HeaderMap,
+    auth: &ExportAuthParams,
+    basic_auth: Option<&Authorization<Basic>>,
+) -> AppResult<Url> {
+    let mut url = Url::parse(&format!("{}/rest/stream", share_base_url(options, headers)))?;
+
+    {
+        let mut query = url.query_pairs_mut();
+        match (auth, basic_auth) {
+            (
+                ExportAuthParams {
+                    u: Some(u),
+                    t: Some(t),
+                    s: Some(s),
+                    ..
+                },
+                _,
+            ) => {
+                query
+                    .append_pair("u", u)
+                    .append_pair("t", t)
+                    .append_pair("s", s);
+            }
+            (
+                ExportAuthParams {
+                    u: Some(u),
+                    p: Some(p),
+                    ..
+                },
+                _,
+            ) => {
+                query.append_pair("u", u).append_pair("p", p);
+            }
+            (_, Some(basic)) => {
+                let p = format!("enc:{}", hex::encode(basic.password()));
+                query
+                    .append_pair("u", basic.username())
+                    .append_pair("p", &p);
+            }
+            _ => {}
+        }
+        query
+            .append_pair("v", auth.v.as_deref().unwrap_or("1.16.1"))
+            .append_pair("c", auth.c.as_deref().unwrap_or("beatlocker"));
+    }
+
+    Ok(url)
+}
+
+fn file_url(path: &str) -> AppResult<String> {
+    let path = Path::new(path);
+    let path = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        std::env::current_dir()?.join(path)
+    };
+    let url = Url::from_file_path(&path).map_err(|_| anyhow!("Not a valid file path"))?;
+    Ok(url.to_string())
+}
+
+fn render_m3u8(name: &str, entries: &[ExportEntry], locations: &[String]) -> String {
+    let mut m3u8 = format!("#EXTM3U\n#PLAYLIST:{name}\n");
+    for (entry, location) in entries.iter().zip(locations) {
+        let duration = entry.duration.map(|d| d as i64).unwrap_or(-1);
+        let title = match (&entry.artist, &entry.title) {
+            (Some(artist), Some(title)) => format!("{artist} - {title}"),
+            (None, Some(title)) => title.clone(),
+            _ => String::new(),
+        };
+        m3u8.push_str(&format!("#EXTINF:{duration},{title}\n{location}\n"));
+    }
+    m3u8
+}
+
+fn render_xspf(name: &str, entries: &[ExportEntry], locations: &[String]) -> String {
+    let mut xspf = format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<playlist version=\"1\" xmlns=\"http://xspf.org/ns/0/\">\n  <title>{}</title>\n  <trackList>\n",
+        escape(name)
+    );
+    for (entry, location) in entries.iter().zip(locations) {
+        xspf.push_str("    <track>\n");
+        xspf.push_str(&format!(
+            "      <location>{}</location>\n",
+            escape(location)
+        ));
+        let elements = [
+            ("title", &entry.title),
+            ("creator", &entry.artist),
+            ("album", &entry.album),
+        ];
+        for (element, value) in elements {
+            if let Some(value) = value {
+                xspf.push_str(&format!("      <{element}>{}</{element}>\n", escape(value)));
+            }
+        }
+        // XSPF durations are in milliseconds
+        if let Some(duration) = entry.duration {
+            xspf.push_str(&format!(
+                "      <duration>{}</duration>\n",
+                duration as u64 * 1000
+            ));
+        }
+        xspf.push_str("    </track>\n");
+    }
+    xspf.push_str("  </trackList>\n</playlist>\n");
+    xspf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::header::HOST;
+
+    fn entries() -> Vec<ExportEntry> {
+        vec![
+            ExportEntry {
+                id: Uuid::nil(),
+                path: "music/a.mp3".to_string(),
+                title: Some("A & B".to_string()),
+                artist: Some("Artist".to_string()),
+                album: None,
+                duration: Some(61),
+            },
+            ExportEntry {
+                id: Uuid::nil(),
+                path: "music/b.mp3".to_string(),
+                title: None,
+                artist: None,
+                album: None,
+                duration: None,
+            },
+        ]
+    }
+
+    #[test]
+    fn can_render_m3u8() {
+        let locations = vec!["music/a.mp3".to_string(), "music/b.mp3".to_string()];
+        assert_eq!(
+            render_m3u8("Mix", &entries(), &locations),
+            "#EXTM3U\n#PLAYLIST:Mix\n#EXTINF:61,Artist - A & B\nmusic/a.mp3\n#EXTINF:-1,\nmusic/b.mp3\n"
+        );
+    }
+
+    #[test]
+    fn can_render_xspf() {
+        let locations = vec![
+            "http://host/rest/stream?id=1&u=admin".to_string(),
+            "file:///music/b.mp3".to_string(),
+        ];
+        let xspf = render_xspf("<Mix>", &entries(), &locations);
+        assert!(xspf.contains("<title>&lt;Mix&gt;</title>"));
+        assert!(xspf.contains(
+            "<location>http://host/rest/stream?id=1&amp;u=admin</location>\n      <title>A &amp; B</title>\n      <creator>Artist</creator>\n      <duration>61000</duration>"
+        ));
+        assert!(
+            xspf.contains("<track>\n      <location>file:///music/b.mp3</location>\n    </track>")
+        );
+    }
+
+    #[test]
+    fn can_build_stream_url() {
+        let mut headers = HeaderMap::new();
+        headers.insert(HOST, HeaderValue::from_static("music.local:2222"));
+        let auth = ExportAuthParams {
+            u: Some("joe".to_string()),
+            p: None,
+            t: Some("token".to_string()),
+            s: Some("salt".to_string()),
+            v: None,
+            c: Some("player".to_string()),
+        };
+        assert_eq!(
+            stream_base_url(&ServerOptions::default(), &headers, &auth, None)
+                .unwrap()
+                .as_str(),
+            "http://music.local:2222/rest/stream?u=joe&t=token&s=salt&v=1.16.1&c=player"
+        );
+
+        // Basic authentication is turned into a hex encoded password
+        headers.insert("x-forwarded-proto", HeaderValue::from_static("https"));
+        let auth = ExportAuthParams {
+            u: None,
+            p: None,
+            t: None,
+            s: None,
+            v: None,
+            c: None,
+        };
+        let basic = Authorization::basic("joe", "foo");
+        assert_eq!(
+            stream_base_url(&ServerOptions::default(), &headers, &auth, Some(&basic))
+                .unwrap()
+                .as_str(),
+            "https://music.local:2222/rest/stream?u=joe&p=enc%3A666f6f&v=1.16.1&c=beatlocker"
+        );
+
+        // The external URL wins from the URL the client used
+        let options = ServerOptions {
+            external_url: Some("https://music.example.com/".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            stream_base_url(&options, &headers, &auth, Some(&basic))
+                .unwrap()
+                .as_str(),
+            "https://music.example.com/rest/stream?u=joe&p=enc%3A666f6f&v=1.16.1&c=beatlocker"
+        );
+    }
+}
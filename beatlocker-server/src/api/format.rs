@@ -33,6 +33,7 @@ impl SubsonicFormat {
                     version: SUBSONIC_API_VERSION.to_owned(),
                     ty: "beatlocker".into(),
                     server_version: self.server_version,
+                    open_subsonic: true,
                     data,
                 },
             })
@@ -43,6 +44,7 @@ impl SubsonicFormat {
                     version: SUBSONIC_API_VERSION.to_owned(),
                     ty: "beatlocker".into(),
                     server_version: self.server_version,
+                    open_subsonic: true,
                     data: data.map(|d| d.into_xml()),
                 };
 
@@ -116,6 +118,7 @@ pub struct SubsonicResponse<T: Clone + Debug + Serialize> {
     #[serde(rename = "type")]
     ty: String,
     server_version: String,
+    open_subsonic: bool,
     #[serde(flatten)]
     data: Option<T>,
 }
@@ -128,6 +131,7 @@ pub struct XmlSubsonicResponse<T: Clone + Debug + Serialize> {
     #[serde(rename = "type")]
     ty: String,
     server_version: String,
+    open_subsonic: bool,
     #[serde(rename = "$value")]
     data: Option<T>,
 }
@@ -154,6 +158,7 @@ impl<T: Clone + Debug + Serialize + ToXml> ToXml for SubsonicResponse<T> {
             version: self.version,
             ty: self.ty,
             server_version: self.server_version,
+            open_subsonic: self.open_subsonic,
             data: self.data,
         }
     }
@@ -3,10 +3,11 @@ use crate::api::model::{SubsonicAlbum, SubsonicSong};
 use crate::api::queries::{
     get_subsonic_albums_by_id3, get_subsonic_songs, GetSubsonicAlbumsQuery, GetSubsonicSongsQuery,
 };
-use crate::{AppResult, Db, Deserialize, Serialize, SharedState};
+use crate::{AppResult, CurrentUser, Db, Deserialize, Serialize, SharedState};
 use axum::extract::{Query, State};
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, Utc};
 use std::ops::DerefMut;
 
 use uuid::Uuid;
@@ -19,19 +20,25 @@ pub struct GetAlbumParams {
 
 pub async fn get_album(
     format: SubsonicFormat,
+    CurrentUser(username): CurrentUser,
     Query(params): Query<GetAlbumParams>,
     State(state): State<SharedState>,
 ) -> AppResult<Response> {
-    match get_album_impl(&state.db, params).await? {
+    match get_album_impl(&state.db, &username, params).await? {
         Some(response) => Ok(format.render(response)),
         None => Ok((StatusCode::NOT_FOUND, ()).into_response()),
     }
 }
 
-async fn get_album_impl(db: &Db, params: GetAlbumParams) -> AppResult<Option<AlbumResponse>> {
+async fn get_album_impl(
+    db: &Db,
+    username: &str,
+    params: GetAlbumParams,
+) -> AppResult<Option<AlbumResponse>> {
     match get_subsonic_albums_by_id3(
         db.conn().await?.deref_mut(),
         GetSubsonicAlbumsQuery {
+            username: username.to_string(),
             album_id: Some(params.id),
             ..Default::default()
         },
@@ -43,6 +50,7 @@ async fn get_album_impl(db: &Db, params: GetAlbumParams) -> AppResult<Option<Alb
             let songs = get_subsonic_songs(
                 db.conn().await?.deref_mut(),
                 GetSubsonicSongsQuery {
+                    username: username.to_string(),
                     album_id: Some(params.id),
                     ..Default::default()
                 },
@@ -74,6 +82,8 @@ pub enum XmlAlbumResponse {
         id: Uuid,
         name: String,
         title: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        sort_name: Option<String>,
         song_count: u32,
         duration: u32,
         #[serde(skip_serializing_if = "Option::is_none")]
@@ -82,6 +92,14 @@ pub enum XmlAlbumResponse {
         artist_id: Option<Uuid>,
         #[serde(skip_serializing_if = "Option::is_none")]
         cover_art: Option<Uuid>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        user_rating: Option<u32>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        average_rating: Option<f64>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        play_count: Option<u32>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        played: Option<DateTime<Utc>>,
         #[serde(skip_serializing_if = "Vec::is_empty")]
         song: Vec<SubsonicSong>,
     },
@@ -95,11 +113,16 @@ impl ToXml for AlbumResponse {
             id: self.album.id,
             name: self.album.name,
             title: self.album.title,
+            sort_name: self.album.sort_name,
             song_count: self.album.song_count,
             duration: self.album.duration,
             artist: self.album.artist,
             artist_id: self.album.artist_id,
             cover_art: self.album.cover_art,
+            user_rating: self.album.user_rating,
+            average_rating: self.album.average_rating,
+            play_count: self.album.play_count,
+            played: self.album.played,
             song: self.album.song,
         }
     }
@@ -0,0 +1,270 @@
+use crate::api::format::{SubsonicFormat, ToXml};
+use crate::api::model::XmlStringWrapper;
+use crate::db::DbAlbum;
+use crate::{
+    get_lastfm, get_musicbrainz, AppResult, AppState, Deserialize, LastFmAlbumResponse,
+    MusicbrainzReleasesResponse, Serialize, SharedState,
+};
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use sqlx::sqlite::SqliteRow;
+use sqlx::Row;
+use std::ops::DerefMut;
+use tracing::warn;
+use uuid::Uuid;
+
+/// How long stored album info is used before asking again.
+const ALBUM_INFO_DAYS: i64 = 30;
+/// MusicBrainz finds releases with only part of the title or artist in common, so lower scores
+/// are likely another album.
+const MIN_MUSICBRAINZ_SCORE: u32 = 90;
+
+#[derive(Default, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetAlbumInfoParams {
+    id: Uuid,
+}
+
+pub async fn get_album_info(
+    format: SubsonicFormat,
+    Query(params): Query<GetAlbumInfoParams>,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    match get_album_info_impl(&state, params.id, false).await? {
+        Some(album_info) => Ok(format.render(AlbumInfoResponse { album_info })),
+        None => Ok((StatusCode::NOT_FOUND, ()).into_response()),
+    }
+}
+
+pub async fn get_album_info2(
+    format: SubsonicFormat,
+    Query(params): Query<GetAlbumInfoParams>,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    match get_album_info_impl(&state, params.id, true).await? {
+        Some(album_info) => Ok(format.render(AlbumInfoResponse { album_info })),
+        None => Ok((StatusCode::NOT_FOUND, ()).into_response()),
+    }
+}
+
+/// Finds the info of an album, through the id of the album, or of one of its songs or folders.
+/// Image URLs are only those of last.fm; clients get the album's own cover art through its
+/// `coverArt` id, which needs their credentials.
+async fn get_album_info_impl(
+    state: &AppState,
+    id: Uuid,
+    only_check_album_id: bool,
+) -> AppResult<Option<AlbumInfo>> {
+    let album_id = sqlx::query(
+        r#"
+        SELECT album_id FROM albums WHERE album_id = ?
+        UNION ALL
+        SELECT s.album_id FROM folder_children fc JOIN songs s ON s.song_id = fc.song_id
+        WHERE ? = 0 AND (fc.folder_child_id = ? OR fc.song_id = ? OR fc.folder_id = ?) AND s.album_id IS NOT NULL
+        LIMIT 1
+        "#,
+    )
+    .bind(id)
+    .bind(only_check_album_id)
+    .bind(id)
+    .bind(id)
+    .bind(id)
+    .map(|row: SqliteRow| {
+        let album_id: Uuid = row.get("album_id");
+        album_id
+    })
+    .fetch_optional(state.db.conn().await?.deref_mut())
+    .await?;
+    let album = match album_id {
+        Some(album_id) => state.db.find_album_by_id(album_id).await?,
+        None => None,
+    };
+    let album = match album {
+        Some(album) => album,
+        None => return Ok(None),
+    };
+
+    let since = (state.options.now_provider)() - chrono::Duration::days(ALBUM_INFO_DAYS);
+    let stored = sqlx::query("SELECT * FROM album_info WHERE album_id = ? AND fetched > ?")
+        .bind(album.album_id)
+        .bind(since)
+        .map(|row: SqliteRow| AlbumInfo {
+            notes: row.get("notes"),
+            music_brainz_id: row.get("musicbrainz_id"),
+            last_fm_url: row.get("lastfm_url"),
+            small_image_url: row.get("small_image_url"),
+            medium_image_url: row.get("medium_image_url"),
+            large_image_url: row.get("large_image_url"),
+        })
+        .fetch_optional(state.db.conn().await?.deref_mut())
+        .await?;
+    let result = match stored {
+        Some(result) => result,
+        None => fetch_album_info(state, &album).await?,
+    };
+
+    Ok(Some(result))
+}
+
+/// Asks MusicBrainz and last.fm about an album, and stores what they know. MusicBrainz is only
+/// asked when external metadata is imported, and last.fm only when there is an API key. Nothing is
+/// stored when either couldn't be reached, so they're asked again next time.
+async fn fetch_album_info(state: &AppState, album: &DbAlbum) -> AppResult<AlbumInfo> {
+    let artist = sqlx::query(
+        r#"
+        SELECT ar.name FROM album_artists aa
+        JOIN artists ar ON ar.artist_id = aa.artist_id
+        WHERE aa.album_id = ?
+        ORDER BY ar.name
+        LIMIT 1
+        "#,
+    )
+    .bind(album.album_id)
+    .map(|row: SqliteRow| {
+        let name: String = row.get("name");
+        name
+    })
+    .fetch_optional(state.db.conn().await?.deref_mut())
+    .await?;
+
+    let mut result = AlbumInfo::default();
+    let mut asked = false;
+    let mut complete = true;
+
+    if state.options.import_external_metadata {
+        asked = true;
+        // Quotes would end the phrases of the query
+        let mut query = format!("release:\"{}\"", album.title.replace('"', ""));
+        if let Some(artist) = &artist {
+            query += &format!(" AND artist:\"{}\"", artist.replace('"', ""));
+        }
+        let query = &[("fmt", "json"), ("query", &query)];
+
+        match get_musicbrainz::<MusicbrainzReleasesResponse, _>("release", query).await {
+            Ok(response) => {
+                result.music_brainz_id = response
+                    .and_then(|response| {
+                        response.releases.into_iter().find(|release| {
+                            release.score.unwrap_or_default() >= MIN_MUSICBRAINZ_SCORE
+                        })
+                    })
+                    .map(|release| release.id);
+            }
+            Err(e) => {
+                warn!(album.title, ?e, "Could not get album info from MusicBrainz");
+                complete = false;
+            }
+        }
+    }
+
+    if let Some(api_key) = &state.options.lastfm_api_key {
+        asked = true;
+        let mut query = vec![
+            ("api_key", api_key.as_str()),
+            ("format", "json"),
+            ("method", "album.getinfo"),
+            ("album", &album.title),
+        ];
+        if let Some(artist) = &artist {
+            query.push(("artist", artist));
+        }
+
+        match get_lastfm::<LastFmAlbumResponse, _>(&state.options.lastfm_url, &query).await {
+            Ok(response) => {
+                if let Some(lastfm_album) = response.and_then(|response| response.album) {
+                    result.last_fm_url = lastfm_album.url.clone();
+                    result.small_image_url = lastfm_album.image("small");
+                    result.medium_image_url = lastfm_album.image("medium");
+                    result.large_image_url = lastfm_album.image("large");
+                    result.notes = lastfm_album.wiki.map(|wiki| wiki.summary);
+                    if result.music_brainz_id.is_none() {
+                        result.music_brainz_id = lastfm_album.mbid.filter(|mbid| !mbid.is_empty());
+                    }
+                }
+            }
+            Err(e) => {
+                warn!(album.title, ?e, "Could not get album info from last.fm");
+                complete = false;
+            }
+        }
+    }
+
+    if asked && complete {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO album_info (album_id, notes, musicbrainz_id, lastfm_url, small_image_url, medium_image_url, large_image_url, fetched)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(album.album_id)
+        .bind(&result.notes)
+        .bind(&result.music_brainz_id)
+        .bind(&result.last_fm_url)
+        .bind(&result.small_image_url)
+        .bind(&result.medium_image_url)
+        .bind(&result.large_image_url)
+        .bind((state.options.now_provider)())
+        .execute(state.db.conn().await?.deref_mut())
+        .await?;
+    }
+
+    Ok(result)
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlbumInfoResponse {
+    album_info: AlbumInfo,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AlbumInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    notes: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    music_brainz_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_fm_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    small_image_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    medium_image_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    large_image_url: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum XmlAlbumInfoResponse {
+    AlbumInfo {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        notes: Option<XmlStringWrapper>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        music_brainz_id: Option<XmlStringWrapper>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        last_fm_url: Option<XmlStringWrapper>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        small_image_url: Option<XmlStringWrapper>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        medium_image_url: Option<XmlStringWrapper>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        large_image_url: Option<XmlStringWrapper>,
+    },
+}
+
+impl ToXml for AlbumInfoResponse {
+    type Output = XmlAlbumInfoResponse;
+
+    fn into_xml(self) -> Self::Output {
+        XmlAlbumInfoResponse::AlbumInfo {
+            notes: self.album_info.notes.map(XmlStringWrapper),
+            music_brainz_id: self.album_info.music_brainz_id.map(XmlStringWrapper),
+            last_fm_url: self.album_info.last_fm_url.map(XmlStringWrapper),
+            small_image_url: self.album_info.small_image_url.map(XmlStringWrapper),
+            medium_image_url: self.album_info.medium_image_url.map(XmlStringWrapper),
+            large_image_url: self.album_info.large_image_url.map(XmlStringWrapper),
+        }
+    }
+}
@@ -5,9 +5,13 @@ use crate::api::queries::{
     get_subsonic_albums, get_subsonic_albums_by_id3, GetSubsonicAlbumsListType,
     GetSubsonicAlbumsQuery,
 };
-use crate::{AlbumList2Response, AppResult, AppState, Db, GetAlbumList2Params, SharedState};
+use crate::{
+    AlbumList2Response, AppResult, AppState, AuthenticatedUser, Db, GetAlbumList2Params,
+    SharedState,
+};
 use axum::extract::{Query, State};
-use axum::response::Response;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
 
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -15,22 +19,33 @@ use uuid::Uuid;
 #[derive(Default, Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GetAlbumListParams {
-    //music_folder_id: Option<Uuid>,
+    music_folder_id: Option<Uuid>,
     size: Option<u32>,
     offset: Option<u32>,
+    /// Only counts the plays of this user, so `recent` and `frequent` follow their listening. Only
+    /// admins can ask about the plays of others.
+    played_by: Option<String>,
 }
 
 pub async fn get_album_list(
     format: SubsonicFormat,
+    AuthenticatedUser(current_user): AuthenticatedUser,
     ty: GetSubsonicAlbumsListType,
     Query(params): Query<GetAlbumListParams>,
     State(state): State<SharedState>,
 ) -> AppResult<Response> {
-    Ok(format.render(get_album_list_impl(&state.db, params, ty).await?))
+    if let Some(played_by) = &params.played_by {
+        if !current_user.admin_role && *played_by != current_user.username {
+            return Ok((StatusCode::FORBIDDEN, ()).into_response());
+        }
+    }
+
+    Ok(format.render(get_album_list_impl(&state.db, &current_user.username, params, ty).await?))
 }
 
 async fn get_album_list_impl(
     db: &Db,
+    username: &str,
     params: GetAlbumListParams,
     ty: GetSubsonicAlbumsListType,
 ) -> AppResult<AlbumListResponse> {
@@ -39,10 +54,12 @@ async fn get_album_list_impl(
     let results = get_subsonic_albums(
         &mut conn,
         GetSubsonicAlbumsQuery {
-            //music_folder_id: params.music_folder_id,
+            username: username.to_string(),
+            music_folder_id: params.music_folder_id,
             offset: params.offset.unwrap_or_default(),
             size: params.size.unwrap_or(10),
             ty,
+            played_by: params.played_by,
             ..Default::default()
         },
     )
@@ -86,6 +103,7 @@ impl ToXml for AlbumListResponse {
                     is_dir: a.is_dir,
                     name: a.title.clone(),
                     title: a.title,
+                    sort_name: a.sort_name,
                     song_count: a.song_count,
                     duration: a.duration,
                     artist: a.artist,
@@ -93,6 +111,10 @@ impl ToXml for AlbumListResponse {
                     cover_art: a.cover_art,
                     song: vec![],
                     starred: a.starred,
+                    user_rating: a.user_rating,
+                    average_rating: a.average_rating,
+                    play_count: a.play_count,
+                    played: a.played,
                 })
                 .collect(),
         )
@@ -102,7 +124,7 @@ impl ToXml for AlbumListResponse {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::TestState;
+    use crate::{TestState, DEFAULT_USERNAME};
     use itertools::Itertools;
     use std::sync::Arc;
 
@@ -165,7 +187,7 @@ mod tests {
     }
 
     async fn get(db: Arc<Db>, ty: GetSubsonicAlbumsListType) -> Vec<String> {
-        let results = get_album_list_impl(&db, Default::default(), ty)
+        let results = get_album_list_impl(&db, DEFAULT_USERNAME, Default::default(), ty)
             .await
             .unwrap();
 
@@ -2,32 +2,45 @@ use crate::api::format::{SubsonicFormat, ToXml};
 use crate::api::queries::{
     get_subsonic_albums_by_id3, GetSubsonicAlbumsListType, GetSubsonicAlbumsQuery,
 };
-use crate::{AppResult, Db, SharedState};
+use crate::{AppResult, AuthenticatedUser, Db, SharedState};
 use axum::extract::{Query, State};
-use axum::response::Response;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
 
 use crate::api::model::SubsonicAlbum;
 use serde::{Deserialize, Serialize};
+use uuid::Uuid;
 
 #[derive(Debug, Default, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GetAlbumList2Params {
-    //music_folder_id: Option<Uuid>,
+    music_folder_id: Option<Uuid>,
     size: Option<u32>,
     offset: Option<u32>,
+    /// Only counts the plays of this user, so `recent` and `frequent` follow their listening. Only
+    /// admins can ask about the plays of others.
+    played_by: Option<String>,
 }
 
 pub async fn get_album_list2(
     format: SubsonicFormat,
+    AuthenticatedUser(current_user): AuthenticatedUser,
     ty: GetSubsonicAlbumsListType,
     Query(params): Query<GetAlbumList2Params>,
     State(state): State<SharedState>,
 ) -> AppResult<Response> {
-    Ok(format.render(get_album_list2_impl(&state.db, params, ty).await?))
+    if let Some(played_by) = &params.played_by {
+        if !current_user.admin_role && *played_by != current_user.username {
+            return Ok((StatusCode::FORBIDDEN, ()).into_response());
+        }
+    }
+
+    Ok(format.render(get_album_list2_impl(&state.db, &current_user.username, params, ty).await?))
 }
 
 async fn get_album_list2_impl(
     db: &Db,
+    username: &str,
     params: GetAlbumList2Params,
     ty: GetSubsonicAlbumsListType,
 ) -> AppResult<AlbumList2Response> {
@@ -36,10 +49,12 @@ async fn get_album_list2_impl(
     let results = get_subsonic_albums_by_id3(
         &mut conn,
         GetSubsonicAlbumsQuery {
-            //music_folder_id: params.music_folder_id,
+            username: username.to_string(),
+            music_folder_id: params.music_folder_id,
             offset: params.offset.unwrap_or_default(),
             size: params.size.unwrap_or(10),
             ty,
+            played_by: params.played_by,
             ..Default::default()
         },
     )
@@ -81,11 +96,16 @@ impl ToXml for AlbumList2Response {
                     id: a.id,
                     name: a.title.clone(),
                     title: a.title,
+                    sort_name: a.sort_name,
                     song_count: a.song_count,
                     duration: a.duration,
                     artist: a.artist,
                     artist_id: a.artist_id,
                     cover_art: a.cover_art,
+                    user_rating: a.user_rating,
+                    average_rating: a.average_rating,
+                    play_count: a.play_count,
+                    played: a.played,
                     ..Default::default()
                 })
                 .collect(),
@@ -96,8 +116,10 @@ impl ToXml for AlbumList2Response {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::TestState;
+    use crate::{TestState, DEFAULT_USERNAME};
+    use chrono::{DateTime, Utc};
     use itertools::Itertools;
+    use std::ops::DerefMut;
     use std::sync::Arc;
 
     #[tokio::test]
@@ -173,8 +195,107 @@ mod tests {
         );
     }
 
+    #[tokio::test]
+    async fn query_highest() {
+        let state = TestState::new().await.unwrap();
+        let db = state.db().await;
+        for (title, username, rating) in [
+            ("Artist2_Album1", "admin", 5),
+            ("Artist2_Album1", "joe", 3),
+            ("SharedAlbum", "admin", 5),
+        ] {
+            sqlx::query(
+                "INSERT INTO ratings (rated_id, username, rating) SELECT album_id, ?, ? FROM albums WHERE title = ?",
+            )
+            .bind(username)
+            .bind(rating)
+            .bind(title)
+            .execute(db.conn().await.unwrap().deref_mut())
+            .await
+            .unwrap();
+        }
+
+        // Only rated albums are returned, by their average rating
+        assert_eq!(
+            get(db, GetSubsonicAlbumsListType::Highest).await,
+            &["SharedAlbum", "Artist2_Album1"]
+        );
+    }
+
+    #[tokio::test]
+    async fn query_by_plays() {
+        let state = TestState::new().await.unwrap();
+        let db = state.db().await;
+        for (title, username, played) in [
+            ("Artist1_Album1", "admin", "2020-01-01T00:00:00Z"),
+            ("Artist1_Album1", "admin", "2020-01-02T00:00:00Z"),
+            ("SharedAlbum", "admin", "2020-01-03T00:00:00Z"),
+            ("Artist2_Album1", "joe", "2020-01-04T00:00:00Z"),
+        ] {
+            let played: DateTime<Utc> = played.parse().unwrap();
+            sqlx::query(
+                "INSERT INTO plays (song_id, username, client, played) SELECT song_id, ?, 'test', ? FROM songs s JOIN albums al ON al.album_id = s.album_id WHERE al.title = ? LIMIT 1",
+            )
+            .bind(username)
+            .bind(played)
+            .bind(title)
+            .execute(db.conn().await.unwrap().deref_mut())
+            .await
+            .unwrap();
+        }
+
+        assert_eq!(
+            get(db.clone(), GetSubsonicAlbumsListType::Recent).await,
+            &["Artist2_Album1", "SharedAlbum", "Artist1_Album1"]
+        );
+        assert_eq!(
+            get(db.clone(), GetSubsonicAlbumsListType::Frequent).await,
+            &["Artist1_Album1", "Artist2_Album1", "SharedAlbum"]
+        );
+
+        // Only the plays of a single user
+        let results = get_album_list2_impl(
+            &db,
+            DEFAULT_USERNAME,
+            GetAlbumList2Params {
+                played_by: Some("joe".to_string()),
+                ..Default::default()
+            },
+            GetSubsonicAlbumsListType::Recent,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            results
+                .album_list2
+                .album
+                .iter()
+                .map(|t| t.title.clone())
+                .collect_vec(),
+            &["Artist2_Album1"]
+        );
+
+        // Plays aren't counted once for every artist of an album
+        sqlx::query(
+            "INSERT INTO album_artists (album_id, artist_id) SELECT al.album_id, ar.artist_id FROM albums al, artists ar WHERE al.title = 'Artist1_Album1' AND ar.name = 'Artist2'",
+        )
+        .execute(db.conn().await.unwrap().deref_mut())
+        .await
+        .unwrap();
+        let results = get_album_list2_impl(
+            &db,
+            DEFAULT_USERNAME,
+            Default::default(),
+            GetSubsonicAlbumsListType::Frequent,
+        )
+        .await
+        .unwrap();
+        assert_eq!(results.album_list2.album[0].title, "Artist1_Album1");
+        assert_eq!(results.album_list2.album[0].play_count, Some(2));
+    }
+
     async fn get(db: Arc<Db>, ty: GetSubsonicAlbumsListType) -> Vec<String> {
-        let results = get_album_list2_impl(&db, Default::default(), ty)
+        let results = get_album_list2_impl(&db, DEFAULT_USERNAME, Default::default(), ty)
             .await
             .unwrap();
 
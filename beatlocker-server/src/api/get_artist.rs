@@ -4,7 +4,7 @@ use crate::api::queries::{
     get_subsonic_albums_by_id3, get_subsonic_artists, get_subsonic_songs, GetSubsonicAlbumsQuery,
     GetSubsonicArtistsQuery, GetSubsonicSongsQuery,
 };
-use crate::{AppResult, Db, Deserialize, Serialize, SharedState};
+use crate::{AppResult, CurrentUser, Db, Deserialize, Serialize, SharedState};
 use axum::extract::{Query, State};
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
@@ -19,19 +19,25 @@ pub struct GetArtistParams {
 
 pub async fn get_artist(
     format: SubsonicFormat,
+    CurrentUser(username): CurrentUser,
     Query(params): Query<GetArtistParams>,
     State(state): State<SharedState>,
 ) -> AppResult<Response> {
-    match get_artist_impl(&state.db, params).await? {
+    match get_artist_impl(&state.db, &username, params).await? {
         Some(response) => Ok(format.render(response)),
         None => Ok((StatusCode::NOT_FOUND, ()).into_response()),
     }
 }
 
-async fn get_artist_impl(db: &Db, params: GetArtistParams) -> AppResult<Option<ArtistResponse>> {
+async fn get_artist_impl(
+    db: &Db,
+    username: &str,
+    params: GetArtistParams,
+) -> AppResult<Option<ArtistResponse>> {
     match get_subsonic_artists(
         db.conn().await?.deref_mut(),
         GetSubsonicArtistsQuery {
+            username: username.to_string(),
             artist_id: Some(params.id),
             ..Default::default()
         },
@@ -43,6 +49,7 @@ async fn get_artist_impl(db: &Db, params: GetArtistParams) -> AppResult<Option<A
             let albums = get_subsonic_albums_by_id3(
                 db.conn().await?.deref_mut(),
                 GetSubsonicAlbumsQuery {
+                    username: username.to_string(),
                     artist_id: Some(params.id),
                     ..Default::default()
                 },
@@ -52,6 +59,7 @@ async fn get_artist_impl(db: &Db, params: GetArtistParams) -> AppResult<Option<A
             let songs = get_subsonic_songs(
                 db.conn().await?.deref_mut(),
                 GetSubsonicSongsQuery {
+                    username: username.to_string(),
                     artist_id: Some(params.id),
                     ..Default::default()
                 },
@@ -83,9 +91,15 @@ pub enum XmlArtistResponse {
     Artist {
         id: Uuid,
         name: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        sort_name: Option<String>,
         album_count: u32,
         #[serde(skip_serializing_if = "Option::is_none")]
         cover_art: Option<Uuid>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        user_rating: Option<u32>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        average_rating: Option<f64>,
         #[serde(skip_serializing_if = "Vec::is_empty")]
         album: Vec<SubsonicAlbum>,
         #[serde(skip_serializing_if = "Vec::is_empty")]
@@ -100,8 +114,11 @@ impl ToXml for ArtistResponse {
         XmlArtistResponse::Artist {
             id: self.artist.id,
             name: self.artist.name,
+            sort_name: self.artist.sort_name,
             album_count: self.artist.album_count,
             cover_art: self.artist.cover_art,
+            user_rating: self.artist.user_rating,
+            average_rating: self.artist.average_rating,
             album: self.artist.album,
             song: self.artist.song,
         }
@@ -25,6 +25,7 @@ pub async fn get_artist_info(
         &state.db,
         params,
         state.options.lastfm_api_key.clone(),
+        &state.options.lastfm_url,
         false,
     )
     .await?
@@ -43,6 +44,7 @@ pub async fn get_artist_info2(
         &state.db,
         params,
         state.options.lastfm_api_key.clone(),
+        &state.options.lastfm_url,
         true,
     )
     .await?
@@ -58,6 +60,7 @@ async fn get_artist_info_impl(
     db: &Db,
     params: GetArtistInfoParams,
     lastfm_api_key: Option<String>,
+    lastfm_url: &str,
     only_check_artist_id: bool,
 ) -> AppResult<Option<ArtistInfoResponse>> {
     let mut artist = db.find_artist_by_id(params.id).await?;
@@ -96,7 +99,7 @@ async fn get_artist_info_impl(
                     query.push(("mbid", arid));
                 }
 
-                let resp: Option<LastFmArtistResponse> = get_lastfm(&query).await?;
+                let resp: Option<LastFmArtistResponse> = get_lastfm(lastfm_url, &query).await?;
                 if let Some(resp) = resp {
                     if let Some(artist) = resp.artist {
                         result.last_fm_url = artist.url.clone();
@@ -1,6 +1,6 @@
 use crate::api::format::{SubsonicFormat, ToXml};
 use crate::api::queries::{get_subsonic_artists, GetSubsonicArtistsQuery};
-use crate::{AppResult, Db, Deserialize, Serialize, SharedState};
+use crate::{index_name, sort_key, AppResult, Db, Deserialize, Serialize, SharedState};
 use axum::extract::{Query, State};
 use axum::response::Response;
 use itertools::Itertools;
@@ -10,7 +10,7 @@ use uuid::Uuid;
 #[derive(Default, Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GetArtistsParams {
-    //music_folder_id: Option<Uuid>,
+    music_folder_id: Option<Uuid>,
 }
 
 pub async fn get_artists(
@@ -18,22 +18,35 @@ pub async fn get_artists(
     Query(params): Query<GetArtistsParams>,
     State(state): State<SharedState>,
 ) -> AppResult<Response> {
-    Ok(format.render(get_artists_impl(&state.db, params).await?))
+    Ok(format.render(get_artists_impl(&state.db, &state.options.ignored_articles, params).await?))
 }
 
-async fn get_artists_impl(db: &Db, _params: GetArtistsParams) -> AppResult<ArtistsResponse> {
+async fn get_artists_impl(
+    db: &Db,
+    ignored_articles: &[String],
+    params: GetArtistsParams,
+) -> AppResult<ArtistsResponse> {
     let artists = get_subsonic_artists(
         db.conn().await?.deref_mut(),
-        GetSubsonicArtistsQuery::default(),
+        GetSubsonicArtistsQuery {
+            artist_count: u32::MAX,
+            music_folder_id: params.music_folder_id,
+            ..Default::default()
+        },
     )
     .await?;
 
     let index = artists
         .into_iter()
-        .group_by(|ia| ia.name.chars().next().unwrap_or_default())
+        .group_by(|a| {
+            index_name(&sort_key(
+                a.sort_name.as_deref().unwrap_or(&a.name),
+                ignored_articles,
+            ))
+        })
         .into_iter()
         .map(|(index, artist)| Index {
-            name: index.to_string(),
+            name: index,
             artist: artist
                 .map(|a| IndexArtist {
                     id: a.id,
@@ -47,7 +60,7 @@ async fn get_artists_impl(db: &Db, _params: GetArtistsParams) -> AppResult<Artis
 
     Ok(ArtistsResponse {
         artists: Indexes { index },
-        ignored_articles: "The El La Los Las Le Les Os As O A".to_owned(),
+        ignored_articles: ignored_articles.join(" "),
     })
 }
 
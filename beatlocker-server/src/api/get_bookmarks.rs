@@ -0,0 +1,105 @@
+use crate::api::format::{SubsonicFormat, ToXml};
+use crate::api::model::SubsonicSong;
+use crate::api::queries::{get_subsonic_songs, GetSubsonicSongsQuery};
+use crate::{AppResult, CurrentUser, Deserialize, Serialize, SharedState};
+use axum::extract::State;
+use axum::response::Response;
+use chrono::{DateTime, Utc};
+use sqlx::sqlite::SqliteRow;
+use sqlx::Row;
+use std::ops::DerefMut;
+use uuid::Uuid;
+
+pub async fn get_bookmarks(
+    format: SubsonicFormat,
+    CurrentUser(username): CurrentUser,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    let mut conn = state.db.conn().await?;
+
+    let bookmarks = sqlx::query(
+        "SELECT folder_child_id, position, comment, created, changed FROM bookmarks WHERE username = ? ORDER BY changed DESC",
+    )
+    .bind(&username)
+    .map(|row: SqliteRow| {
+        let folder_child_id: Uuid = row.get("folder_child_id");
+        let position: i64 = row.get("position");
+        let comment: Option<String> = row.get("comment");
+        let created: DateTime<Utc> = row.get("created");
+        let changed: DateTime<Utc> = row.get("changed");
+        (folder_child_id, position, comment, created, changed)
+    })
+    .fetch_all(conn.deref_mut())
+    .await?;
+
+    let mut results = vec![];
+    for (folder_child_id, position, comment, created, changed) in bookmarks {
+        let song = get_subsonic_songs(
+            conn.deref_mut(),
+            GetSubsonicSongsQuery {
+                username: username.clone(),
+                folder_child_id: Some(folder_child_id),
+                ..Default::default()
+            },
+        )
+        .await?
+        .into_iter()
+        .next();
+
+        if let Some(entry) = song {
+            results.push(Bookmark {
+                position: position as u64,
+                username: username.clone(),
+                comment,
+                created,
+                changed,
+                entry,
+            });
+        }
+    }
+
+    Ok(format.render(GetBookmarksResponse {
+        bookmarks: Bookmarks { bookmark: results },
+    }))
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetBookmarksResponse {
+    bookmarks: Bookmarks,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Bookmarks {
+    bookmark: Vec<Bookmark>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Bookmark {
+    position: u64,
+    username: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comment: Option<String>,
+    created: DateTime<Utc>,
+    changed: DateTime<Utc>,
+    entry: SubsonicSong,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum XmlGetBookmarksResponse {
+    #[serde(rename_all = "camelCase")]
+    Bookmarks { bookmark: Vec<Bookmark> },
+}
+
+impl ToXml for GetBookmarksResponse {
+    type Output = XmlGetBookmarksResponse;
+
+    fn into_xml(self) -> Self::Output {
+        XmlGetBookmarksResponse::Bookmarks {
+            bookmark: self.bookmarks.bookmark,
+        }
+    }
+}
@@ -1,3 +1,4 @@
+use crate::thumbnails::{make_thumbnail, MAX_THUMBNAIL_SIZE};
 use crate::{AppResult, SharedState};
 use axum::extract::{Query, State};
 use axum::http::header::{CONTENT_LENGTH, CONTENT_TYPE};
@@ -7,12 +8,14 @@ use std::ops::DerefMut;
 use serde::Deserialize;
 use sqlx::sqlite::SqliteRow;
 use sqlx::Row;
+use tracing::warn;
 use uuid::Uuid;
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct GetCoverArtParams {
     id: Uuid,
+    size: Option<u32>,
 }
 
 pub async fn get_cover_art(
@@ -29,8 +32,45 @@ pub async fn get_cover_art(
         })
         .fetch_optional(conn.deref_mut())
         .await?;
+    drop(conn);
 
-    let data = data.unwrap_or_else(|| include_bytes!("fallback_cover.jpg").to_vec());
+    let (cache_id, data) = match data {
+        Some(data) => (params.id.to_string(), data),
+        None => (
+            "fallback".to_string(),
+            include_bytes!("fallback_cover.jpg").to_vec(),
+        ),
+    };
+
+    let size = params.size.map(|s| s.clamp(1, MAX_THUMBNAIL_SIZE));
+    let cache_key = match size {
+        Some(size) => format!("{cache_id}-{size}.jpg"),
+        None => format!("{cache_id}.jpg"),
+    };
+    let data = match state.thumbnails.get(&cache_key).await {
+        Some(thumbnail) => thumbnail,
+        None => {
+            let (data, thumbnail) =
+                tokio::task::spawn_blocking(move || match make_thumbnail(&data, size) {
+                    Ok(thumbnail) => (data, thumbnail),
+                    Err(e) => {
+                        warn!(?e, "Could not resize cover art, sending the original");
+                        (data, None)
+                    }
+                })
+                .await?;
+
+            match thumbnail {
+                Some(thumbnail) => {
+                    if let Err(e) = state.thumbnails.insert(&cache_key, &thumbnail).await {
+                        warn!(?e, "Could not cache thumbnail");
+                    }
+                    thumbnail
+                }
+                None => data,
+            }
+        }
+    };
 
     let content_type = infer::get(&data)
         .map(|ty| ty.mime_type())
@@ -1,5 +1,5 @@
 use crate::api::format::{SubsonicFormat, ToXml};
-use crate::{AppResult, SharedState};
+use crate::{index_name, AppResult, SharedState};
 use axum::extract::{Query, State};
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
@@ -27,17 +27,24 @@ pub async fn get_indexes(
     let mut builder = QueryBuilder::new("SELECT * FROM folders");
     match params.music_folder_id {
         Some(id) => builder.push(" WHERE parent_id = ").push_bind(id),
-        None => builder.push(" WHERE parent_id IS NOT NULL"),
+        // The top level folders of every music folder
+        None => builder
+            .push(" WHERE parent_id IN (SELECT folder_id FROM folders WHERE parent_id IS NULL)"),
     };
+    builder.push(" ORDER BY sort_key");
     let folders = builder
         .build()
         .map(|row: SqliteRow| {
             let id: Uuid = row.get("folder_id");
-            IndexArtist {
-                id: id.to_string(),
-                name: row.get("name"),
-                album_count: 1,
-            }
+            let sort_key: String = row.get("sort_key");
+            (
+                index_name(&sort_key),
+                IndexArtist {
+                    id: id.to_string(),
+                    name: row.get("name"),
+                    album_count: 1,
+                },
+            )
         })
         .fetch_all(conn.deref_mut())
         .await?;
@@ -48,18 +55,18 @@ pub async fn get_indexes(
 
     let index = folders
         .into_iter()
-        .group_by(|ia| ia.name.chars().next().unwrap_or_default())
+        .group_by(|(index, _)| index.clone())
         .into_iter()
         .map(|(index, artist)| Index {
-            name: index.to_string(),
-            artist: artist.collect_vec(),
+            name: index,
+            artist: artist.map(|(_, ia)| ia).collect_vec(),
         })
         .sorted_by_key(|index| index.name.clone())
         .collect();
 
     Ok(format.render(GetIndexesResponse {
         indexes: Indexes { index },
-        ignored_articles: "The El La Los Las Le Les Os As O A".to_owned(),
+        ignored_articles: state.options.ignored_articles.join(" "),
     }))
 }
 
@@ -0,0 +1,104 @@
+use crate::api::export_playlist::{stream_base_url, ExportAuthParams};
+use crate::api::format::{SubsonicFormat, ToXml};
+use crate::{AppResult, Deserialize, Serialize, SharedState};
+use axum::extract::{Query, State};
+use axum::http::HeaderMap;
+use axum::response::Response;
+use axum::TypedHeader;
+use headers::authorization::Basic;
+use headers::Authorization;
+use sqlx::sqlite::SqliteRow;
+use sqlx::Row;
+use std::ops::DerefMut;
+use uuid::Uuid;
+
+/// Lists the internet radio stations. When stations are relayed, their stream URL is the `stream`
+/// URL of this server, with the credentials of the request, so clients that can't reach a
+/// station themselves play it through the server.
+pub async fn get_internet_radio_stations(
+    format: SubsonicFormat,
+    Query(auth): Query<ExportAuthParams>,
+    basic_auth: Option<TypedHeader<Authorization<Basic>>>,
+    headers: HeaderMap,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    let relay_url = if state.options.relay_internet_radio {
+        Some(stream_base_url(
+            &state.options,
+            &headers,
+            &auth,
+            basic_auth.as_ref().map(|h| &h.0),
+        )?)
+    } else {
+        None
+    };
+    let mut conn = state.db.conn().await?;
+
+    let results = sqlx::query("SELECT * FROM internet_radio_stations ORDER BY name")
+        .map(|row: SqliteRow| {
+            let id: Uuid = row.get("station_id");
+            let stream_url = match &relay_url {
+                Some(relay_url) => {
+                    let mut url = relay_url.clone();
+                    url.query_pairs_mut().append_pair("id", &id.to_string());
+                    url.to_string()
+                }
+                None => row.get("stream_url"),
+            };
+            InternetRadioStation {
+                id,
+                name: row.get("name"),
+                stream_url,
+                home_page_url: row.get("home_page_url"),
+            }
+        })
+        .fetch_all(conn.deref_mut())
+        .await?;
+
+    Ok(format.render(GetInternetRadioStationsResponse {
+        internet_radio_stations: InternetRadioStations {
+            internet_radio_station: results,
+        },
+    }))
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetInternetRadioStationsResponse {
+    internet_radio_stations: InternetRadioStations,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InternetRadioStations {
+    internet_radio_station: Vec<InternetRadioStation>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InternetRadioStation {
+    id: Uuid,
+    name: String,
+    stream_url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    home_page_url: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum XmlGetInternetRadioStationsResponse {
+    #[serde(rename_all = "camelCase")]
+    InternetRadioStations {
+        internet_radio_station: Vec<InternetRadioStation>,
+    },
+}
+
+impl ToXml for GetInternetRadioStationsResponse {
+    type Output = XmlGetInternetRadioStationsResponse;
+
+    fn into_xml(self) -> Self::Output {
+        XmlGetInternetRadioStationsResponse::InternetRadioStations {
+            internet_radio_station: self.internet_radio_stations.internet_radio_station,
+        }
+    }
+}
@@ -0,0 +1,125 @@
+use crate::api::format::{SubsonicFormat, ToXml};
+use crate::{AppResult, Deserialize, Serialize, SharedState};
+use axum::extract::{Query, State};
+use axum::response::Response;
+use sqlx::sqlite::SqliteRow;
+use sqlx::Row;
+use std::ops::DerefMut;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetLyricsParams {
+    artist: Option<String>,
+    title: Option<String>,
+}
+
+pub async fn get_lyrics(
+    format: SubsonicFormat,
+    Query(params): Query<GetLyricsParams>,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    // Without an artist or title, any song would do
+    let is_empty = |param: &Option<String>| param.as_deref().map_or(true, str::is_empty);
+    if is_empty(&params.artist) && is_empty(&params.title) {
+        return Ok(format.render(GetLyricsResponse {
+            lyrics: Lyrics::default(),
+        }));
+    }
+
+    let mut conn = state.db.conn().await?;
+
+    // Unsynced lyrics are preferred, as their text is usually more complete
+    let song = sqlx::query(
+        r#"
+        SELECT s.song_id, s.title, ar.name AS artist, l.synced
+        FROM songs s
+        JOIN lyrics l ON l.song_id = s.song_id
+        LEFT JOIN artists ar ON ar.artist_id = s.artist_id
+        WHERE (? IS NULL OR ar.name = ? COLLATE NOCASE) AND (? IS NULL OR s.title = ? COLLATE NOCASE)
+        ORDER BY l.synced, s.title
+        LIMIT 1
+        "#,
+    )
+    .bind(&params.artist)
+    .bind(&params.artist)
+    .bind(&params.title)
+    .bind(&params.title)
+    .map(|row: SqliteRow| {
+        let song_id: Uuid = row.get("song_id");
+        let title: String = row.get("title");
+        let artist: Option<String> = row.get("artist");
+        let synced: bool = row.get("synced");
+        (song_id, title, artist, synced)
+    })
+    .fetch_optional(conn.deref_mut())
+    .await?;
+
+    let lyrics = match song {
+        Some((song_id, title, artist, synced)) => {
+            let lines = sqlx::query(
+                "SELECT value FROM lyrics_lines WHERE song_id = ? AND synced = ? ORDER BY position",
+            )
+            .bind(song_id)
+            .bind(synced)
+            .map(|row: SqliteRow| {
+                let value: String = row.get("value");
+                value
+            })
+            .fetch_all(conn.deref_mut())
+            .await?;
+
+            Lyrics {
+                artist,
+                title: Some(title),
+                value: Some(lines.join("\n")),
+            }
+        }
+        None => Lyrics::default(),
+    };
+
+    Ok(format.render(GetLyricsResponse { lyrics }))
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetLyricsResponse {
+    lyrics: Lyrics,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Lyrics {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    artist: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    value: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum XmlGetLyricsResponse {
+    #[serde(rename_all = "camelCase")]
+    Lyrics {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        artist: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+        #[serde(rename = "$value", skip_serializing_if = "Option::is_none")]
+        value: Option<String>,
+    },
+}
+
+impl ToXml for GetLyricsResponse {
+    type Output = XmlGetLyricsResponse;
+
+    fn into_xml(self) -> Self::Output {
+        XmlGetLyricsResponse::Lyrics {
+            artist: self.lyrics.artist,
+            title: self.lyrics.title,
+            value: self.lyrics.value,
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use crate::api::format::{SubsonicFormat, ToXml};
+use crate::{AppResult, Deserialize, Serialize, SharedState};
+use axum::extract::{Query, State};
+use axum::response::Response;
+use sqlx::sqlite::SqliteRow;
+use sqlx::Row;
+use std::ops::DerefMut;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetLyricsBySongIdParams {
+    id: Uuid,
+}
+
+pub async fn get_lyrics_by_song_id(
+    format: SubsonicFormat,
+    Query(params): Query<GetLyricsBySongIdParams>,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    let mut conn = state.db.conn().await?;
+
+    // Synced lyrics come first, as clients show the first lyrics they understand
+    let mut structured_lyrics = sqlx::query(
+        r#"
+        SELECT l.song_id, l.synced, l.lang, s.title, ar.name AS artist
+        FROM lyrics l
+        JOIN songs s ON s.song_id = l.song_id
+        LEFT JOIN artists ar ON ar.artist_id = s.artist_id
+        WHERE l.song_id IN (SELECT song_id FROM folder_children WHERE folder_child_id = ? OR song_id = ?)
+        ORDER BY l.synced DESC
+        "#,
+    )
+    .bind(params.id)
+    .bind(params.id)
+    .map(|row: SqliteRow| {
+        let song_id: Uuid = row.get("song_id");
+        (
+            song_id,
+            StructuredLyrics {
+                display_artist: row.get("artist"),
+                display_title: row.get("title"),
+                lang: row.get("lang"),
+                offset: 0,
+                synced: row.get("synced"),
+                line: vec![],
+            },
+        )
+    })
+    .fetch_all(conn.deref_mut())
+    .await?;
+
+    for (song_id, lyrics) in &mut structured_lyrics {
+        lyrics.line = sqlx::query(
+            "SELECT start, value FROM lyrics_lines WHERE song_id = ? AND synced = ? ORDER BY position",
+        )
+        .bind(*song_id)
+        .bind(lyrics.synced)
+        .map(|row: SqliteRow| LyricsLine {
+            start: row.get("start"),
+            value: row.get("value"),
+        })
+        .fetch_all(conn.deref_mut())
+        .await?;
+    }
+
+    Ok(format.render(GetLyricsBySongIdResponse {
+        lyrics_list: LyricsList {
+            structured_lyrics: structured_lyrics
+                .into_iter()
+                .map(|(_, lyrics)| lyrics)
+                .collect(),
+        },
+    }))
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetLyricsBySongIdResponse {
+    lyrics_list: LyricsList,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LyricsList {
+    structured_lyrics: Vec<StructuredLyrics>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct StructuredLyrics {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    display_artist: Option<String>,
+    display_title: String,
+    lang: String,
+    /// Milliseconds to shift the lines by. Offsets of `.lrc` files are applied when importing.
+    offset: i32,
+    synced: bool,
+    line: Vec<LyricsLine>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LyricsLine {
+    /// Start of the line in milliseconds, for synced lyrics.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start: Option<u32>,
+    value: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum XmlGetLyricsBySongIdResponse {
+    #[serde(rename_all = "camelCase")]
+    LyricsList {
+        structured_lyrics: Vec<XmlStructuredLyrics>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct XmlStructuredLyrics {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    display_artist: Option<String>,
+    display_title: String,
+    lang: String,
+    offset: i32,
+    synced: bool,
+    line: Vec<XmlLyricsLine>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename = "line", rename_all = "camelCase")]
+pub struct XmlLyricsLine {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    start: Option<u32>,
+    #[serde(rename = "$value")]
+    value: String,
+}
+
+impl ToXml for GetLyricsBySongIdResponse {
+    type Output = XmlGetLyricsBySongIdResponse;
+
+    fn into_xml(self) -> Self::Output {
+        XmlGetLyricsBySongIdResponse::LyricsList {
+            structured_lyrics: self
+                .lyrics_list
+                .structured_lyrics
+                .into_iter()
+                .map(|lyrics| XmlStructuredLyrics {
+                    display_artist: lyrics.display_artist,
+                    display_title: lyrics.display_title,
+                    lang: lyrics.lang,
+                    offset: lyrics.offset,
+                    synced: lyrics.synced,
+                    line: lyrics
+                        .line
+                        .into_iter()
+                        .map(|line| XmlLyricsLine {
+                            start: line.start,
+                            value: line.value,
+                        })
+                        .collect(),
+                })
+                .collect(),
+        }
+    }
+}
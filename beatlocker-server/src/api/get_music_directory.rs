@@ -1,7 +1,7 @@
 use crate::api::format::{SubsonicFormat, ToXml};
 use crate::api::model::{SubsonicChild, SubsonicChildDirectory};
 use crate::api::queries::{get_subsonic_songs, GetSubsonicSongsQuery};
-use crate::{AppResult, SharedState};
+use crate::{AppResult, CurrentUser, SharedState};
 use axum::extract::{Query, State};
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
@@ -20,6 +20,7 @@ pub struct GetMusicDirectoryParams {
 
 pub async fn get_music_directory(
     format: SubsonicFormat,
+    CurrentUser(username): CurrentUser,
     Query(params): Query<GetMusicDirectoryParams>,
     State(state): State<SharedState>,
 ) -> AppResult<Response> {
@@ -47,6 +48,7 @@ pub async fn get_music_directory(
                         title: row.get("name"),
                         name: row.get("name"),
                         created: row.get("created"),
+                        cover_art: row.get("cover_art_id"),
                         ..Default::default()
                     })
                 })
@@ -56,6 +58,7 @@ pub async fn get_music_directory(
             let children = get_subsonic_songs(
                 conn.deref_mut(),
                 GetSubsonicSongsQuery {
+                    username,
                     folder_id: Some(params.id),
                     ..Default::default()
                 },
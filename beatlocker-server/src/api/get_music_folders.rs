@@ -1,18 +1,27 @@
 use crate::api::format::{SubsonicFormat, ToXml};
-use crate::AppResult;
+use crate::{AppResult, SharedState};
 
+use axum::extract::State;
 use axum::response::Response;
 use serde::{Deserialize, Serialize};
 
 use uuid::Uuid;
 
-pub async fn get_music_folders(format: SubsonicFormat) -> AppResult<Response> {
+pub async fn get_music_folders(
+    format: SubsonicFormat,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
     Ok(format.render(MusicFoldersResponse {
         music_folders: MusicFolders {
-            music_folder: vec![MusicFolder {
-                id: Uuid::nil(),
-                name: "Music".to_owned(),
-            }],
+            music_folder: state
+                .options
+                .music_folders
+                .iter()
+                .map(|music_folder| MusicFolder {
+                    id: music_folder.id,
+                    name: music_folder.name.clone(),
+                })
+                .collect(),
         },
     }))
 }
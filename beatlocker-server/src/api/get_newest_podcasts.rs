@@ -0,0 +1,55 @@
+use crate::api::format::{SubsonicFormat, ToXml};
+use crate::api::get_podcasts::{get_podcast_episodes, PodcastEpisode};
+use crate::{AppResult, Deserialize, Serialize, SharedState};
+use axum::extract::{Query, State};
+use axum::response::Response;
+use std::ops::DerefMut;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetNewestPodcastsParams {
+    count: Option<u32>,
+}
+
+pub async fn get_newest_podcasts(
+    format: SubsonicFormat,
+    Query(params): Query<GetNewestPodcastsParams>,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    let mut conn = state.db.conn().await?;
+
+    let episodes = get_podcast_episodes(conn.deref_mut(), None, params.count.unwrap_or(20)).await?;
+
+    Ok(format.render(GetNewestPodcastsResponse {
+        newest_podcasts: NewestPodcasts { episode: episodes },
+    }))
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetNewestPodcastsResponse {
+    newest_podcasts: NewestPodcasts,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewestPodcasts {
+    episode: Vec<PodcastEpisode>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum XmlGetNewestPodcastsResponse {
+    #[serde(rename_all = "camelCase")]
+    NewestPodcasts { episode: Vec<PodcastEpisode> },
+}
+
+impl ToXml for GetNewestPodcastsResponse {
+    type Output = XmlGetNewestPodcastsResponse;
+
+    fn into_xml(self) -> Self::Output {
+        XmlGetNewestPodcastsResponse::NewestPodcasts {
+            episode: self.newest_podcasts.episode,
+        }
+    }
+}
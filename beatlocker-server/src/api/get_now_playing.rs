@@ -0,0 +1,150 @@
+use crate::api::format::{SubsonicFormat, ToXml};
+use crate::api::queries::{get_subsonic_songs, GetSubsonicSongsQuery};
+use crate::{AppResult, Deserialize, Serialize, SharedState};
+use axum::extract::State;
+use axum::response::Response;
+use chrono::{DateTime, Utc};
+use sqlx::sqlite::SqliteRow;
+use sqlx::Row;
+use std::ops::DerefMut;
+use uuid::Uuid;
+
+/// Clients don't report when they stop playing, so songs are no longer listed once they would
+/// have finished, allowing for some time spent paused.
+const NOW_PLAYING_GRACE_SECONDS: i64 = 5 * 60;
+
+pub async fn get_now_playing(
+    format: SubsonicFormat,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    let mut conn = state.db.conn().await?;
+    let now = (state.options.now_provider)();
+
+    let playing = sqlx::query(
+        "SELECT username, client, folder_child_id, started FROM now_playing ORDER BY started DESC",
+    )
+    .map(|row: SqliteRow| {
+        let username: String = row.get("username");
+        let client: String = row.get("client");
+        let folder_child_id: Uuid = row.get("folder_child_id");
+        let started: DateTime<Utc> = row.get("started");
+        (username, client, folder_child_id, started)
+    })
+    .fetch_all(conn.deref_mut())
+    .await?;
+
+    let mut entries = vec![];
+    for (username, client, folder_child_id, started) in playing {
+        let song = get_subsonic_songs(
+            conn.deref_mut(),
+            GetSubsonicSongsQuery {
+                folder_child_id: Some(folder_child_id),
+                ..Default::default()
+            },
+        )
+        .await?
+        .into_iter()
+        .next();
+        let song = match song {
+            Some(song) => song,
+            None => continue,
+        };
+
+        let elapsed = (now - started).num_seconds().max(0);
+        if elapsed > song.duration.unwrap_or(0) as i64 + NOW_PLAYING_GRACE_SECONDS {
+            continue;
+        }
+
+        entries.push(NowPlayingEntry {
+            id: song.id,
+            parent: song.parent,
+            is_dir: song.is_dir,
+            title: song.title,
+            album: song.album,
+            artist: song.artist,
+            track: song.track,
+            year: song.year,
+            cover_art: song.cover_art,
+            size: song.size,
+            content_type: song.content_type,
+            suffix: song.suffix,
+            duration: song.duration,
+            bit_rate: song.bit_rate,
+            album_id: song.album_id,
+            artist_id: song.artist_id,
+            is_video: song.is_video,
+            username,
+            minutes_ago: (elapsed / 60) as u32,
+            player_name: client,
+        });
+    }
+
+    Ok(format.render(NowPlayingResponse {
+        now_playing: NowPlaying { entry: entries },
+    }))
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NowPlayingResponse {
+    now_playing: NowPlaying,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NowPlaying {
+    entry: Vec<NowPlayingEntry>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NowPlayingEntry {
+    id: Uuid,
+    parent: Uuid,
+    is_dir: bool,
+    title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    album: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    artist: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    track: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    year: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cover_art: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    suffix: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bit_rate: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    album_id: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    artist_id: Option<Uuid>,
+    is_video: bool,
+    username: String,
+    minutes_ago: u32,
+    player_name: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum XmlNowPlayingResponse {
+    NowPlaying { entry: Vec<NowPlayingEntry> },
+}
+
+impl ToXml for NowPlayingResponse {
+    type Output = XmlNowPlayingResponse;
+
+    fn into_xml(self) -> Self::Output {
+        XmlNowPlayingResponse::NowPlaying {
+            entry: self.now_playing.entry,
+        }
+    }
+}
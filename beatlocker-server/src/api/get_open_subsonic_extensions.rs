@@ -0,0 +1,36 @@
+use crate::api::format::{SubsonicFormat, ToXml};
+use crate::AppResult;
+
+use axum::response::Response;
+use serde::{Deserialize, Serialize};
+
+/// Lists the OpenSubsonic extensions the server supports, which clients check before using them.
+pub async fn get_open_subsonic_extensions(format: SubsonicFormat) -> AppResult<Response> {
+    Ok(format.render(OpenSubsonicExtensionsResponse {
+        open_subsonic_extensions: vec![OpenSubsonicExtension {
+            name: "songLyrics".to_string(),
+            versions: vec![1],
+        }],
+    }))
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpenSubsonicExtensionsResponse {
+    open_subsonic_extensions: Vec<OpenSubsonicExtension>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename = "openSubsonicExtensions", rename_all = "camelCase")]
+pub struct OpenSubsonicExtension {
+    name: String,
+    versions: Vec<u32>,
+}
+
+impl ToXml for OpenSubsonicExtensionsResponse {
+    type Output = Vec<OpenSubsonicExtension>;
+
+    fn into_xml(self) -> Self::Output {
+        self.open_subsonic_extensions
+    }
+}
@@ -0,0 +1,104 @@
+use crate::api::format::{SubsonicFormat, ToXml};
+use crate::api::model::SubsonicSong;
+use crate::api::queries::{get_subsonic_songs, GetSubsonicSongsQuery, ALL_SONGS};
+use crate::{AppResult, CurrentUser, Deserialize, Serialize, SharedState};
+use axum::extract::State;
+use axum::response::Response;
+use chrono::{DateTime, Utc};
+use sqlx::sqlite::SqliteRow;
+use sqlx::Row;
+use std::ops::DerefMut;
+use uuid::Uuid;
+
+/// Returns the play queue the user saved last, or an empty response if they haven't saved one.
+pub async fn get_play_queue(
+    format: SubsonicFormat,
+    CurrentUser(username): CurrentUser,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    let mut conn = state.db.conn().await?;
+
+    let play_queue = sqlx::query(
+        "SELECT current, position, changed, changed_by FROM play_queues WHERE username = ?",
+    )
+    .bind(&username)
+    .map(|row: SqliteRow| {
+        let position: i64 = row.get("position");
+        PlayQueue {
+            current: row.get("current"),
+            position: position as u64,
+            username: username.clone(),
+            changed: row.get("changed"),
+            changed_by: row.get("changed_by"),
+            entry: vec![],
+        }
+    })
+    .fetch_optional(conn.deref_mut())
+    .await?;
+
+    match play_queue {
+        Some(mut play_queue) => {
+            play_queue.entry = get_subsonic_songs(
+                conn.deref_mut(),
+                GetSubsonicSongsQuery {
+                    username: username.clone(),
+                    play_queue_username: Some(username),
+                    song_count: ALL_SONGS,
+                    ..Default::default()
+                },
+            )
+            .await?;
+
+            Ok(format.render(GetPlayQueueResponse { play_queue }))
+        }
+        None => Ok(format.render::<()>(None)),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetPlayQueueResponse {
+    play_queue: PlayQueue,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlayQueue {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    current: Option<Uuid>,
+    position: u64,
+    username: String,
+    changed: DateTime<Utc>,
+    changed_by: String,
+    entry: Vec<SubsonicSong>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum XmlGetPlayQueueResponse {
+    #[serde(rename_all = "camelCase")]
+    PlayQueue {
+        #[serde(skip_serializing_if = "Option::is_none")]
+        current: Option<Uuid>,
+        position: u64,
+        username: String,
+        changed: DateTime<Utc>,
+        changed_by: String,
+        entry: Vec<SubsonicSong>,
+    },
+}
+
+impl ToXml for GetPlayQueueResponse {
+    type Output = XmlGetPlayQueueResponse;
+
+    fn into_xml(self) -> Self::Output {
+        XmlGetPlayQueueResponse::PlayQueue {
+            current: self.play_queue.current,
+            position: self.play_queue.position,
+            username: self.play_queue.username,
+            changed: self.play_queue.changed,
+            changed_by: self.play_queue.changed_by,
+            entry: self.play_queue.entry,
+        }
+    }
+}
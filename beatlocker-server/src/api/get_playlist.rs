@@ -1,13 +1,14 @@
 use crate::api::format::{SubsonicFormat, ToXml};
+use crate::api::get_playlists::SELECT_PLAYLISTS;
 use crate::api::model::SubsonicSong;
-use crate::api::queries::{get_subsonic_songs, GetSubsonicSongsQuery};
-use crate::{AppResult, Deserialize, Serialize, SharedState};
+use crate::api::queries::{get_subsonic_songs, GetSubsonicSongsQuery, ALL_SONGS};
+use crate::{AppResult, CurrentUser, Deserialize, Serialize, SharedState};
 use axum::extract::{Query, State};
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 use chrono::{DateTime, Utc};
 use sqlx::sqlite::SqliteRow;
-use sqlx::Row;
+use sqlx::{Row, SqliteConnection};
 use std::ops::DerefMut;
 use uuid::Uuid;
 
@@ -19,53 +20,66 @@ pub struct GetPlaylistParams {
 
 pub async fn get_playlist(
     format: SubsonicFormat,
+    CurrentUser(username): CurrentUser,
     Query(params): Query<GetPlaylistParams>,
     State(state): State<SharedState>,
 ) -> AppResult<Response> {
     let mut conn = state.db.conn().await?;
 
-    let playlist = sqlx::query(
-        r#"
-        SELECT f.*, COUNT(fc.song_id) AS song_count, SUM(s.duration) AS duration
-        FROM folders f
-        LEFT JOIN folder_children fc on f.folder_id = fc.folder_id
-        LEFT JOIN songs s on fc.song_id = s.song_id
-        WHERE f.folder_id = ?
-        GROUP BY 1
-    "#,
-    )
-    .bind(params.id)
+    match get_playlist_impl(conn.deref_mut(), params.id, &username).await? {
+        Some(playlist) => Ok(format.render(playlist)),
+        None => Ok((StatusCode::NOT_FOUND, ()).into_response()),
+    }
+}
+
+/// Finds a playlist with all of its songs, if it is public or owned by the user.
+pub(crate) async fn get_playlist_impl(
+    conn: &mut SqliteConnection,
+    id: Uuid,
+    username: &str,
+) -> AppResult<Option<GetPlaylistResponse>> {
+    let playlist = sqlx::query(&format!(
+        "{SELECT_PLAYLISTS} WHERE p.playlist_id = ? AND (p.owner = ? OR p.public) GROUP BY 1"
+    ))
+    .bind(id)
+    .bind(username)
     .map(|row: SqliteRow| {
-        let id: Uuid = row.get("folder_id");
+        let id: Uuid = row.get("playlist_id");
         Playlist {
             id,
             name: row.get("name"),
-            created: row.get("created"),
-            public: true,
+            comment: row.get("comment"),
+            owner: row.get("owner"),
+            public: row.get("public"),
             song_count: row.get("song_count"),
             duration: row.get("duration"),
+            created: row.get("created"),
+            changed: row.get("changed"),
             cover_art: row.get("cover_art_id"),
+            missing_entry_count: Some(row.get::<u32, _>("missing_entries"))
+                .filter(|count| *count > 0),
             entry: vec![],
         }
     })
-    .fetch_optional(conn.deref_mut())
+    .fetch_optional(&mut *conn)
     .await?;
 
     match playlist {
         Some(mut playlist) => {
             playlist.entry = get_subsonic_songs(
-                &mut conn,
+                conn,
                 GetSubsonicSongsQuery {
-                    folder_id: Some(params.id),
-                    song_count: 50000,
+                    username: username.to_string(),
+                    playlist_id: Some(id),
+                    song_count: ALL_SONGS,
                     ..Default::default()
                 },
             )
             .await?;
 
-            Ok(format.render(GetPlaylistResponse { playlist }))
+            Ok(Some(GetPlaylistResponse { playlist }))
         }
-        None => Ok((StatusCode::NOT_FOUND, ()).into_response()),
+        None => Ok(None),
     }
 }
 
@@ -80,27 +94,41 @@ pub struct GetPlaylistResponse {
 pub struct Playlist {
     id: Uuid,
     name: String,
-    created: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comment: Option<String>,
+    owner: String,
     public: bool,
     song_count: u32,
     duration: u32,
+    created: DateTime<Utc>,
+    changed: DateTime<Utc>,
     #[serde(skip_serializing_if = "Option::is_none")]
     cover_art: Option<Uuid>,
+    /// Entries of a playlist file that aren't in the library.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    missing_entry_count: Option<u32>,
     entry: Vec<SubsonicSong>,
 }
 
 #[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub enum XmlGetPlaylistResponse {
+    #[serde(rename_all = "camelCase")]
     Playlist {
         id: Uuid,
         name: String,
-        created: DateTime<Utc>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        comment: Option<String>,
+        owner: String,
         public: bool,
         song_count: u32,
         duration: u32,
+        created: DateTime<Utc>,
+        changed: DateTime<Utc>,
         #[serde(skip_serializing_if = "Option::is_none")]
         cover_art: Option<Uuid>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        missing_entry_count: Option<u32>,
         entry: Vec<SubsonicSong>,
     },
 }
@@ -112,11 +140,15 @@ impl ToXml for GetPlaylistResponse {
         XmlGetPlaylistResponse::Playlist {
             id: self.playlist.id,
             name: self.playlist.name,
-            created: self.playlist.created,
+            comment: self.playlist.comment,
+            owner: self.playlist.owner,
             public: self.playlist.public,
             song_count: self.playlist.song_count,
             duration: self.playlist.duration,
+            created: self.playlist.created,
+            changed: self.playlist.changed,
             cover_art: self.playlist.cover_art,
+            missing_entry_count: self.playlist.missing_entry_count,
             entry: self.playlist.entry,
         }
     }
@@ -1,5 +1,5 @@
 use crate::api::format::{SubsonicFormat, ToXml};
-use crate::{AppResult, Deserialize, Serialize, SharedState};
+use crate::{AppResult, CurrentUser, Deserialize, Serialize, SharedState};
 use axum::extract::State;
 use axum::response::Response;
 use chrono::{DateTime, Utc};
@@ -8,33 +8,48 @@ use sqlx::Row;
 use std::ops::DerefMut;
 use uuid::Uuid;
 
+/// Selects playlists with their song count, duration and the cover art of their first song.
+/// Callers add the WHERE clause and group by playlist.
+pub(crate) const SELECT_PLAYLISTS: &str = r#"
+        SELECT p.*, COUNT(pe.folder_child_id) AS song_count, COALESCE(SUM(s.duration), 0) AS duration,
+            (SELECT s2.cover_art_id
+            FROM playlist_entries pe2
+            JOIN folder_children fc2 ON fc2.folder_child_id = pe2.folder_child_id
+            JOIN songs s2 ON s2.song_id = fc2.song_id
+            WHERE pe2.playlist_id = p.playlist_id
+            ORDER BY pe2.position LIMIT 1) AS cover_art_id
+        FROM playlists p
+        LEFT JOIN playlist_entries pe ON pe.playlist_id = p.playlist_id
+        LEFT JOIN folder_children fc ON fc.folder_child_id = pe.folder_child_id
+        LEFT JOIN songs s ON s.song_id = fc.song_id
+        "#;
+
 pub async fn get_playlists(
     format: SubsonicFormat,
+    CurrentUser(username): CurrentUser,
     State(state): State<SharedState>,
 ) -> AppResult<Response> {
     let mut conn = state.db.conn().await?;
 
-    let results = sqlx::query(
-        r#"
-        SELECT f.*, COUNT(fc.song_id) AS song_count, SUM(s.duration) AS duration
-        FROM folders f
-        LEFT JOIN folder_children fc on f.folder_id = fc.folder_id
-        LEFT JOIN songs s on fc.song_id = s.song_id
-        WHERE parent_id IS NOT NULL
-        GROUP BY 1
-        ORDER BY f.name
-    "#,
-    )
+    let results = sqlx::query(&format!(
+        "{SELECT_PLAYLISTS} WHERE p.owner = ? OR p.public GROUP BY 1 ORDER BY p.name"
+    ))
+    .bind(username)
     .map(|row: SqliteRow| {
-        let id: Uuid = row.get("folder_id");
+        let id: Uuid = row.get("playlist_id");
         Playlist {
             id,
             name: row.get("name"),
-            created: row.get("created"),
-            public: true,
+            comment: row.get("comment"),
+            owner: row.get("owner"),
+            public: row.get("public"),
             song_count: row.get("song_count"),
             duration: row.get("duration"),
+            created: row.get("created"),
+            changed: row.get("changed"),
             cover_art: row.get("cover_art_id"),
+            missing_entry_count: Some(row.get::<u32, _>("missing_entries"))
+                .filter(|count| *count > 0),
         }
     })
     .fetch_all(conn.deref_mut())
@@ -62,12 +77,19 @@ pub struct Playlists {
 pub struct Playlist {
     id: Uuid,
     name: String,
-    created: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comment: Option<String>,
+    owner: String,
     public: bool,
     song_count: u32,
     duration: u32,
+    created: DateTime<Utc>,
+    changed: DateTime<Utc>,
     #[serde(skip_serializing_if = "Option::is_none")]
     cover_art: Option<Uuid>,
+    /// Entries of a playlist file that aren't in the library.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    missing_entry_count: Option<u32>,
 }
 
 #[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
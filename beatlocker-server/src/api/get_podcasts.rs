@@ -0,0 +1,183 @@
+use crate::api::format::{SubsonicFormat, ToXml};
+use crate::{AppResult, Deserialize, Serialize, SharedState};
+use axum::extract::{Query, State};
+use axum::response::Response;
+use chrono::{DateTime, Utc};
+use sqlx::sqlite::SqliteRow;
+use sqlx::{Row, SqliteConnection};
+use std::ops::DerefMut;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetPodcastsParams {
+    include_episodes: Option<bool>,
+    id: Option<Uuid>,
+}
+
+pub async fn get_podcasts(
+    format: SubsonicFormat,
+    Query(params): Query<GetPodcastsParams>,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    let mut conn = state.db.conn().await?;
+
+    let mut channels = sqlx::query(
+        "SELECT * FROM podcast_channels WHERE channel_id = COALESCE(?, channel_id) ORDER BY title, created",
+    )
+    .bind(params.id)
+    .map(|row: SqliteRow| {
+        let id: Uuid = row.get("channel_id");
+        PodcastChannel {
+            id,
+            url: row.get("url"),
+            title: row.get("title"),
+            description: row.get("description"),
+            cover_art: row.get("cover_art_id"),
+            original_image_url: row.get("image_url"),
+            status: row.get("status"),
+            error_message: row.get("error_message"),
+            episode: vec![],
+        }
+    })
+    .fetch_all(conn.deref_mut())
+    .await?;
+
+    if params.include_episodes.unwrap_or(true) {
+        for channel in &mut channels {
+            channel.episode =
+                get_podcast_episodes(conn.deref_mut(), Some(channel.id), u32::MAX).await?;
+        }
+    }
+
+    Ok(format.render(GetPodcastsResponse {
+        podcasts: Podcasts { channel: channels },
+    }))
+}
+
+/// Finds the newest episodes of a channel, or of all channels.
+pub(crate) async fn get_podcast_episodes(
+    conn: &mut SqliteConnection,
+    channel_id: Option<Uuid>,
+    count: u32,
+) -> AppResult<Vec<PodcastEpisode>> {
+    Ok(sqlx::query(
+        r#"
+        SELECT e.*, c.cover_art_id
+        FROM podcast_episodes e
+        JOIN podcast_channels c ON c.channel_id = e.channel_id
+        WHERE e.channel_id = COALESCE(?, e.channel_id)
+        ORDER BY e.publish_date DESC, e.title
+        LIMIT ?
+        "#,
+    )
+    .bind(channel_id)
+    .bind(count)
+    .map(|row: SqliteRow| {
+        let id: Uuid = row.get("episode_id");
+        let channel_id: Uuid = row.get("channel_id");
+        let status: String = row.get("status");
+        PodcastEpisode {
+            id,
+            // Downloaded episodes are streamed by their own id
+            stream_id: (status == "completed").then_some(id),
+            channel_id,
+            parent: channel_id,
+            is_dir: false,
+            title: row.get("title"),
+            description: row.get("description"),
+            publish_date: row.get("publish_date"),
+            status,
+            cover_art: row.get("cover_art_id"),
+            size: row.get("size"),
+            content_type: row.get("content_type"),
+            suffix: row.get("suffix"),
+            duration: row.get("duration"),
+            bit_rate: row.get("bit_rate"),
+            is_video: false,
+            ty: "podcast".to_string(),
+        }
+    })
+    .fetch_all(conn)
+    .await?)
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetPodcastsResponse {
+    podcasts: Podcasts,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Podcasts {
+    channel: Vec<PodcastChannel>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PodcastChannel {
+    id: Uuid,
+    url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cover_art: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    original_image_url: Option<String>,
+    status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error_message: Option<String>,
+    episode: Vec<PodcastEpisode>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PodcastEpisode {
+    id: Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stream_id: Option<Uuid>,
+    channel_id: Uuid,
+    parent: Uuid,
+    is_dir: bool,
+    title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    publish_date: Option<DateTime<Utc>>,
+    status: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cover_art: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    size: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    suffix: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    bit_rate: Option<u32>,
+    is_video: bool,
+    #[serde(rename = "type")]
+    ty: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum XmlGetPodcastsResponse {
+    #[serde(rename_all = "camelCase")]
+    Podcasts { channel: Vec<PodcastChannel> },
+}
+
+impl ToXml for GetPodcastsResponse {
+    type Output = XmlGetPodcastsResponse;
+
+    fn into_xml(self) -> Self::Output {
+        XmlGetPodcastsResponse::Podcasts {
+            channel: self.podcasts.channel,
+        }
+    }
+}
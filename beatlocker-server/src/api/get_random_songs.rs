@@ -6,7 +6,8 @@ use axum::response::Response;
 use crate::api::format::{SubsonicFormat, ToXml};
 use crate::api::model::SubsonicSong;
 use crate::api::queries::{get_subsonic_songs, GetSubsonicSongsQuery};
-use crate::{AppResult, Db, Deserialize, Serialize, SharedState};
+use crate::{AppResult, CurrentUser, Db, Deserialize, Serialize, SharedState};
+use uuid::Uuid;
 
 #[derive(Default, Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -15,29 +16,33 @@ pub struct GetRandomSongsParams {
     size: Option<u32>,
     from_year: Option<u32>,
     to_year: Option<u32>,
-    //music_folder_id: Option<Uuid>,
+    music_folder_id: Option<Uuid>,
 }
 
 pub async fn get_random_songs(
     format: SubsonicFormat,
+    CurrentUser(username): CurrentUser,
     Query(params): Query<GetRandomSongsParams>,
     State(state): State<SharedState>,
 ) -> AppResult<Response> {
-    Ok(format.render(get_random_songs_impl(&state.db, params).await?))
+    Ok(format.render(get_random_songs_impl(&state.db, &username, params).await?))
 }
 
 async fn get_random_songs_impl(
     db: &Db,
+    username: &str,
     params: GetRandomSongsParams,
 ) -> AppResult<RandomSongsResponse> {
     let songs = get_subsonic_songs(
         db.conn().await?.deref_mut(),
         GetSubsonicSongsQuery {
+            username: username.to_string(),
             genre: params.genre,
             song_count: params.size.unwrap_or(10),
             from_year: params.from_year,
             to_year: params.to_year,
             random: true,
+            music_folder_id: params.music_folder_id,
             ..Default::default()
         },
     )
@@ -0,0 +1,146 @@
+use crate::api::format::{SubsonicFormat, ToXml};
+use crate::api::model::SubsonicSong;
+use crate::api::queries::{get_subsonic_songs, GetSubsonicSongsQuery, ALL_SONGS};
+use crate::{AppResult, CurrentUser, Deserialize, Serialize, ServerOptions, SharedState};
+use axum::extract::State;
+use axum::http::header::HOST;
+use axum::http::HeaderMap;
+use axum::response::Response;
+use chrono::{DateTime, Utc};
+use sqlx::sqlite::SqliteRow;
+use sqlx::{Row, SqliteConnection};
+use std::ops::DerefMut;
+use uuid::Uuid;
+
+/// Returns the shares of the user.
+pub async fn get_shares(
+    format: SubsonicFormat,
+    CurrentUser(username): CurrentUser,
+    headers: HeaderMap,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    let base_url = share_base_url(&state.options, &headers);
+    let shares = find_shares(
+        state.db.conn().await?.deref_mut(),
+        &username,
+        None,
+        &base_url,
+    )
+    .await?;
+
+    Ok(format.render(GetSharesResponse::new(shares)))
+}
+
+/// Finds the shares of a user, or only a single one of them.
+pub(crate) async fn find_shares(
+    conn: &mut SqliteConnection,
+    username: &str,
+    share_id: Option<Uuid>,
+    base_url: &str,
+) -> AppResult<Vec<Share>> {
+    let mut shares = sqlx::query(
+        "SELECT * FROM shares WHERE username = ? AND share_id = COALESCE(?, share_id) ORDER BY created",
+    )
+    .bind(username)
+    .bind(share_id)
+    .map(|row: SqliteRow| {
+        let id: Uuid = row.get("share_id");
+        Share {
+            id,
+            url: format!("{base_url}/share/{id}"),
+            description: row.get("description"),
+            username: row.get("username"),
+            created: row.get("created"),
+            expires: row.get("expires"),
+            last_visited: row.get("last_visited"),
+            visit_count: row.get("visit_count"),
+            entry: vec![],
+        }
+    })
+    .fetch_all(&mut *conn)
+    .await?;
+
+    for share in &mut shares {
+        share.entry = get_subsonic_songs(
+            &mut *conn,
+            GetSubsonicSongsQuery {
+                username: username.to_string(),
+                share_id: Some(share.id),
+                song_count: ALL_SONGS,
+                ..Default::default()
+            },
+        )
+        .await?;
+    }
+
+    Ok(shares)
+}
+
+/// The URL that share links start with. Unless the server was told its external URL, this is the
+/// URL the client used to reach it.
+pub(crate) fn share_base_url(options: &ServerOptions, headers: &HeaderMap) -> String {
+    if let Some(external_url) = &options.external_url {
+        return external_url.trim_end_matches('/').to_string();
+    }
+
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    let scheme = header("x-forwarded-proto").unwrap_or("http");
+    let host = header("x-forwarded-host")
+        .or_else(|| header(HOST.as_str()))
+        .unwrap_or("localhost");
+    format!("{scheme}://{host}")
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetSharesResponse {
+    shares: Shares,
+}
+
+impl GetSharesResponse {
+    pub(crate) fn new(share: Vec<Share>) -> Self {
+        Self {
+            shares: Shares { share },
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Shares {
+    share: Vec<Share>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Share {
+    id: Uuid,
+    url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    description: Option<String>,
+    username: String,
+    created: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expires: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_visited: Option<DateTime<Utc>>,
+    visit_count: u32,
+    entry: Vec<SubsonicSong>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum XmlGetSharesResponse {
+    #[serde(rename_all = "camelCase")]
+    Shares { share: Vec<Share> },
+}
+
+impl ToXml for GetSharesResponse {
+    type Output = XmlGetSharesResponse;
+
+    fn into_xml(self) -> Self::Output {
+        XmlGetSharesResponse::Shares {
+            share: self.shares.share,
+        }
+    }
+}
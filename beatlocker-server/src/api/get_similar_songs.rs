@@ -0,0 +1,358 @@
+use crate::api::format::{SubsonicFormat, ToXml};
+use crate::api::model::SubsonicSong;
+use crate::api::queries::{get_subsonic_songs, GetSubsonicSongsQuery};
+use crate::db::DbArtist;
+use crate::{
+    get_lastfm, match_name, wrap_err, AppResult, AppState, CurrentUser, Deserialize,
+    LastFmSimilarArtistsResponse, Serialize, SharedState,
+};
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use serde::de::DeserializeOwned;
+use sqlx::sqlite::SqliteRow;
+use sqlx::{QueryBuilder, Row};
+use std::ops::DerefMut;
+use tracing::debug;
+use uuid::Uuid;
+
+/// How many similar artists songs are picked from, besides the artist itself.
+const SIMILAR_ARTIST_COUNT: u32 = 20;
+
+/// How long responses of last.fm are used before asking again.
+const LASTFM_CACHE_DAYS: i64 = 7;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetSimilarSongsParams {
+    id: Uuid,
+    count: Option<u32>,
+}
+
+pub async fn get_similar_songs(
+    format: SubsonicFormat,
+    CurrentUser(username): CurrentUser,
+    Query(params): Query<GetSimilarSongsParams>,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    match get_similar_songs_impl(&state, &username, params, false).await? {
+        Some(song) => Ok(format.render(SimilarSongsResponse {
+            similar_songs: SimilarSongs { song },
+        })),
+        None => Ok((StatusCode::NOT_FOUND, ()).into_response()),
+    }
+}
+
+pub async fn get_similar_songs2(
+    format: SubsonicFormat,
+    CurrentUser(username): CurrentUser,
+    Query(params): Query<GetSimilarSongsParams>,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    match get_similar_songs_impl(&state, &username, params, true).await? {
+        Some(song) => Ok(format.render(SimilarSongs2Response {
+            similar_songs2: SimilarSongs { song },
+        })),
+        None => Ok((StatusCode::NOT_FOUND, ()).into_response()),
+    }
+}
+
+/// Picks random songs of an artist and the artists most like them. The artist is found through
+/// the id of the artist, or of one of their songs or albums.
+async fn get_similar_songs_impl(
+    state: &AppState,
+    username: &str,
+    params: GetSimilarSongsParams,
+    only_check_artist_id: bool,
+) -> AppResult<Option<Vec<SubsonicSong>>> {
+    let artist_id = sqlx::query(
+        r#"
+        SELECT artist_id FROM artists WHERE artist_id = ?
+        UNION ALL
+        SELECT s.artist_id FROM folder_children fc JOIN songs s ON s.song_id = fc.song_id
+        WHERE ? = 0 AND (fc.folder_child_id = ? OR fc.song_id = ?) AND s.artist_id IS NOT NULL
+        UNION ALL
+        SELECT artist_id FROM album_artists WHERE ? = 0 AND album_id = ?
+        LIMIT 1
+        "#,
+    )
+    .bind(params.id)
+    .bind(only_check_artist_id)
+    .bind(params.id)
+    .bind(params.id)
+    .bind(only_check_artist_id)
+    .bind(params.id)
+    .map(|row: SqliteRow| {
+        let artist_id: Uuid = row.get("artist_id");
+        artist_id
+    })
+    .fetch_optional(state.db.conn().await?.deref_mut())
+    .await?;
+    let artist = match artist_id {
+        Some(artist_id) => state.db.find_artist_by_id(artist_id).await?,
+        None => None,
+    };
+    let artist = match artist {
+        Some(artist) => artist,
+        None => return Ok(None),
+    };
+
+    let mut artist_ids = vec![artist.artist_id];
+    artist_ids.extend(find_similar_artists(state, &artist).await?);
+
+    let songs = get_subsonic_songs(
+        state.db.conn().await?.deref_mut(),
+        GetSubsonicSongsQuery {
+            username: username.to_string(),
+            artist_ids: Some(artist_ids),
+            song_count: params.count.unwrap_or(50),
+            random: true,
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    Ok(Some(songs))
+}
+
+/// Finds the artists in the library that are most like an artist, most similar first. last.fm
+/// knows best, but when it doesn't know the artist, or none of the artists it knows are in the
+/// library, artists are alike when they share genres or MusicBrainz tags.
+async fn find_similar_artists(state: &AppState, artist: &DbArtist) -> AppResult<Vec<Uuid>> {
+    let response: Option<LastFmSimilarArtistsResponse> = wrap_err(
+        get_lastfm_cached(state, "artist.getsimilar", &artist.name),
+        || None,
+    )
+    .await;
+    if let Some(response) = response {
+        let similar = find_lastfm_artists(state, artist, response).await?;
+        if !similar.is_empty() {
+            return Ok(similar);
+        }
+    }
+
+    Ok(sqlx::query(
+        r#"
+        WITH artist_labels AS (
+            SELECT DISTINCT artist_id, lower(genre) AS label FROM songs WHERE artist_id IS NOT NULL AND genre IS NOT NULL
+            UNION
+            SELECT artist_id, tag AS label FROM artist_tags
+        )
+        SELECT other.artist_id, COUNT(*) AS shared
+        FROM artist_labels seed
+        JOIN artist_labels other ON other.label = seed.label AND other.artist_id != seed.artist_id
+        WHERE seed.artist_id = ?
+        GROUP BY other.artist_id
+        ORDER BY shared DESC, other.artist_id
+        LIMIT ?
+        "#,
+    )
+    .bind(artist.artist_id)
+    .bind(SIMILAR_ARTIST_COUNT)
+    .map(|row: SqliteRow| {
+        let artist_id: Uuid = row.get("artist_id");
+        artist_id
+    })
+    .fetch_all(state.db.conn().await?.deref_mut())
+    .await?)
+}
+
+/// Finds the artists last.fm considers similar in the library, in the order of last.fm. They are
+/// found by their MusicBrainz id, or otherwise by their name.
+async fn find_lastfm_artists(
+    state: &AppState,
+    artist: &DbArtist,
+    response: LastFmSimilarArtistsResponse,
+) -> AppResult<Vec<Uuid>> {
+    let similar_artists = response.similar_artists.artist;
+    if similar_artists.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let names: Vec<String> = similar_artists
+        .iter()
+        .map(|similar_artist| match_name(&similar_artist.name))
+        .collect();
+    let mbids: Vec<&str> = similar_artists
+        .iter()
+        .filter_map(|similar_artist| similar_artist.mbid.as_deref())
+        .filter(|mbid| !mbid.is_empty())
+        .collect();
+
+    let mut builder = QueryBuilder::new(
+        "SELECT artist_id, musicbrainz_id, match_name FROM artists WHERE match_name IN (",
+    );
+    let mut separated = builder.separated(", ");
+    for name in names.iter().filter(|name| !name.is_empty()) {
+        separated.push_bind(name.clone());
+    }
+    separated.push_unseparated(")");
+    if !mbids.is_empty() {
+        builder.push(" OR musicbrainz_id IN (");
+        let mut separated = builder.separated(", ");
+        for mbid in &mbids {
+            separated.push_bind(mbid.to_string());
+        }
+        separated.push_unseparated(")");
+    }
+    let artists = builder
+        .build()
+        .map(|row: SqliteRow| {
+            let artist_id: Uuid = row.get("artist_id");
+            let musicbrainz_id: Option<String> = row.get("musicbrainz_id");
+            let name: Option<String> = row.get("match_name");
+            (artist_id, musicbrainz_id, name)
+        })
+        .fetch_all(state.db.conn().await?.deref_mut())
+        .await?;
+
+    let mut similar: Vec<Uuid> = vec![];
+    for (similar_artist, similar_name) in similar_artists.iter().zip(&names) {
+        let found = artists.iter().find(|(_, musicbrainz_id, name)| {
+            let same_mbid = musicbrainz_id.is_some()
+                && musicbrainz_id.as_deref() == similar_artist.mbid.as_deref();
+            same_mbid || name.as_ref() == Some(similar_name)
+        });
+        if let Some((artist_id, _, _)) = found {
+            if *artist_id != artist.artist_id && !similar.contains(artist_id) {
+                similar.push(*artist_id);
+            }
+        }
+    }
+    similar.truncate(SIMILAR_ARTIST_COUNT as usize);
+
+    Ok(similar)
+}
+
+/// Asks last.fm about an artist, unless it was asked recently. Returns nothing if no last.fm API
+/// key is configured, or last.fm doesn't know the artist.
+pub(crate) async fn get_lastfm_cached<T: Serialize + DeserializeOwned>(
+    state: &AppState,
+    method: &str,
+    artist: &str,
+) -> AppResult<Option<T>> {
+    let api_key = match &state.options.lastfm_api_key {
+        Some(api_key) => api_key,
+        None => return Ok(None),
+    };
+
+    let now = (state.options.now_provider)();
+    let cached = sqlx::query(
+        "SELECT response FROM lastfm_responses WHERE method = ? AND artist = ? AND fetched > ?",
+    )
+    .bind(method)
+    .bind(artist)
+    .bind(now - chrono::Duration::days(LASTFM_CACHE_DAYS))
+    .map(|row: SqliteRow| {
+        let response: String = row.get("response");
+        response
+    })
+    .fetch_optional(state.db.conn().await?.deref_mut())
+    .await?;
+    if let Some(response) = cached.and_then(|cached| serde_json::from_str(&cached).ok()) {
+        debug!(method, artist, "Using cached last.fm response");
+        return Ok(Some(response));
+    }
+
+    let query = [
+        ("api_key", api_key.as_str()),
+        ("format", "json"),
+        ("method", method),
+        ("artist", artist),
+        ("autocorrect", "1"),
+        ("limit", "100"),
+    ];
+    let response: Option<T> = get_lastfm(&state.options.lastfm_url, &query).await?;
+    if let Some(response) = &response {
+        sqlx::query(
+            r#"
+            INSERT INTO lastfm_responses (method, artist, response, fetched) VALUES (?, ?, ?, ?)
+            ON CONFLICT (method, artist) DO UPDATE SET response = excluded.response, fetched = excluded.fetched
+            "#,
+        )
+        .bind(method)
+        .bind(artist)
+        .bind(serde_json::to_string(response)?)
+        .bind(now)
+        .execute(state.db.conn().await?.deref_mut())
+        .await?;
+    }
+
+    Ok(response)
+}
+
+/// Whether two names of an artist or song are the same, ignoring case, accents, punctuation and
+/// small differences like typos.
+pub(crate) fn is_same_name(a: &str, b: &str) -> bool {
+    let a = match_name(a);
+    let b = match_name(b);
+    let max_distance = a.chars().count().min(b.chars().count()) / 8;
+
+    !a.is_empty() && (a == b || distance::levenshtein(&a, &b) <= max_distance)
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimilarSongsResponse {
+    similar_songs: SimilarSongs,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimilarSongs2Response {
+    similar_songs2: SimilarSongs,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SimilarSongs {
+    song: Vec<SubsonicSong>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum XmlSimilarSongsResponse {
+    SimilarSongs { song: Vec<SubsonicSong> },
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum XmlSimilarSongs2Response {
+    SimilarSongs2 { song: Vec<SubsonicSong> },
+}
+
+impl ToXml for SimilarSongsResponse {
+    type Output = XmlSimilarSongsResponse;
+
+    fn into_xml(self) -> Self::Output {
+        XmlSimilarSongsResponse::SimilarSongs {
+            song: self.similar_songs.song,
+        }
+    }
+}
+
+impl ToXml for SimilarSongs2Response {
+    type Output = XmlSimilarSongs2Response;
+
+    fn into_xml(self) -> Self::Output {
+        XmlSimilarSongs2Response::SimilarSongs2 {
+            song: self.similar_songs2.song,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_match_names() {
+        assert!(is_same_name("Radar Unit", "radar unit"));
+        assert!(is_same_name("Beyoncé", "Beyonce"));
+        assert!(is_same_name("AC/DC", "AC-DC"));
+        assert!(is_same_name("Diamond Dealers", "Diamond Dealer"));
+        assert!(!is_same_name("Yes", "Yel"));
+        assert!(!is_same_name("Ba Senge", "Akwa Samba Yaya"));
+        assert!(!is_same_name("", ""));
+    }
+}
@@ -6,7 +6,8 @@ use axum::response::Response;
 use crate::api::format::{SubsonicFormat, ToXml};
 use crate::api::model::SubsonicSong;
 use crate::api::queries::{get_subsonic_songs, GetSubsonicSongsQuery};
-use crate::{AppResult, Db, Deserialize, Serialize, SharedState};
+use crate::{AppResult, CurrentUser, Db, Deserialize, Serialize, SharedState};
+use uuid::Uuid;
 
 #[derive(Default, Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -14,25 +15,28 @@ pub struct GetSongsByGenreParams {
     genre: String,
     count: Option<u32>,
     offset: Option<u32>,
-    //music_folder_id: Option<Uuid>,
+    music_folder_id: Option<Uuid>,
 }
 
 pub async fn get_songs_by_genre(
     format: SubsonicFormat,
+    CurrentUser(username): CurrentUser,
     Query(params): Query<GetSongsByGenreParams>,
     State(state): State<SharedState>,
 ) -> AppResult<Response> {
-    Ok(format.render(get_songs_by_genre_impl(&state.db, params).await?))
+    Ok(format.render(get_songs_by_genre_impl(&state.db, &username, params).await?))
 }
 
 async fn get_songs_by_genre_impl(
     db: &Db,
+    username: &str,
     params: GetSongsByGenreParams,
 ) -> AppResult<SongsByGenreResponse> {
     let songs = get_subsonic_songs(
         db.conn().await?.deref_mut(),
         GetSubsonicSongsQuery {
-            //folder_id: params.music_folder_id,
+            username: username.to_string(),
+            music_folder_id: params.music_folder_id,
             album_id: None,
             genre: Some(params.genre),
             song_offset: params.offset.unwrap_or_default(),
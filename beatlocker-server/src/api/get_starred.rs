@@ -1,6 +1,6 @@
 use std::ops::DerefMut;
 
-use axum::extract::State;
+use axum::extract::{Query, State};
 use axum::response::Response;
 use itertools::Itertools;
 
@@ -10,10 +10,19 @@ use crate::api::queries::{
     get_subsonic_albums, get_subsonic_artists, get_subsonic_songs, GetSubsonicAlbumsQuery,
     GetSubsonicArtistsQuery, GetSubsonicSongsQuery,
 };
-use crate::{AppResult, Deserialize, Serialize, SharedState};
+use crate::{AppResult, CurrentUser, Deserialize, Serialize, SharedState};
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetStarredParams {
+    music_folder_id: Option<Uuid>,
+}
 
 pub async fn get_starred(
     format: SubsonicFormat,
+    CurrentUser(username): CurrentUser,
+    Query(params): Query<GetStarredParams>,
     State(state): State<SharedState>,
 ) -> AppResult<Response> {
     let mut conn = state.db.conn().await?;
@@ -21,7 +30,9 @@ pub async fn get_starred(
     let songs = get_subsonic_songs(
         &mut conn,
         GetSubsonicSongsQuery {
+            username: username.clone(),
             starred: true,
+            music_folder_id: params.music_folder_id,
             song_count: 1000,
             ..Default::default()
         },
@@ -31,7 +42,9 @@ pub async fn get_starred(
     let artists = get_subsonic_artists(
         conn.deref_mut(),
         GetSubsonicArtistsQuery {
+            username: username.clone(),
             starred: true,
+            music_folder_id: params.music_folder_id,
             artist_count: 1000,
             ..Default::default()
         },
@@ -41,7 +54,9 @@ pub async fn get_starred(
     let albums = get_subsonic_albums(
         conn.deref_mut(),
         GetSubsonicAlbumsQuery {
+            username,
             starred: true,
+            music_folder_id: params.music_folder_id,
             size: 1000,
             ..Default::default()
         },
@@ -0,0 +1,147 @@
+use crate::api::format::{SubsonicFormat, ToXml};
+use crate::api::get_similar_songs::{get_lastfm_cached, is_same_name};
+use crate::api::model::SubsonicSong;
+use crate::api::queries::{get_subsonic_songs, GetSubsonicSongsQuery, ALL_SONGS};
+use crate::{
+    match_name, wrap_err, AppResult, AppState, CurrentUser, Deserialize, LastFmTopTracksResponse,
+    Serialize, SharedState,
+};
+use axum::extract::{Query, State};
+use axum::response::Response;
+use sqlx::sqlite::SqliteRow;
+use sqlx::Row;
+use std::cmp::Reverse;
+use std::ops::DerefMut;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetTopSongsParams {
+    artist: String,
+    count: Option<u32>,
+}
+
+pub async fn get_top_songs(
+    format: SubsonicFormat,
+    CurrentUser(username): CurrentUser,
+    Query(params): Query<GetTopSongsParams>,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    Ok(format.render(TopSongsResponse {
+        top_songs: TopSongs {
+            song: get_top_songs_impl(&state, &username, params).await?,
+        },
+    }))
+}
+
+/// The most popular songs of an artist according to last.fm, or otherwise their most played
+/// songs.
+async fn get_top_songs_impl(
+    state: &AppState,
+    username: &str,
+    params: GetTopSongsParams,
+) -> AppResult<Vec<SubsonicSong>> {
+    let (artist_id, name) = match find_artist(state, &params.artist).await? {
+        Some(artist) => artist,
+        None => return Ok(vec![]),
+    };
+
+    let mut songs = get_subsonic_songs(
+        state.db.conn().await?.deref_mut(),
+        GetSubsonicSongsQuery {
+            username: username.to_string(),
+            artist_id: Some(artist_id),
+            song_count: ALL_SONGS,
+            ..Default::default()
+        },
+    )
+    .await?;
+    let count = params.count.unwrap_or(50) as usize;
+
+    let response: Option<LastFmTopTracksResponse> = wrap_err(
+        get_lastfm_cached(state, "artist.gettoptracks", &name),
+        || None,
+    )
+    .await;
+    let mut top_songs = vec![];
+    if let Some(response) = response {
+        for track in response.top_tracks.track {
+            if let Some(index) = songs
+                .iter()
+                .position(|song| is_same_name(&song.title, &track.name))
+            {
+                top_songs.push(songs.remove(index));
+            }
+        }
+    }
+    // Without last.fm, or when none of its tracks are in the library, the most played songs are
+    // the top songs
+    if top_songs.is_empty() {
+        songs.sort_by_key(|song| Reverse(song.play_count.unwrap_or_default()));
+        top_songs = songs;
+    }
+    top_songs.truncate(count);
+
+    Ok(top_songs)
+}
+
+/// Finds an artist by name, ignoring case, accents and punctuation. Only when no artist has that
+/// name are all artists compared with it, to find one with a slightly different spelling.
+async fn find_artist(state: &AppState, artist: &str) -> AppResult<Option<(Uuid, String)>> {
+    let mut conn = state.db.conn().await?;
+    let map_artist = |row: SqliteRow| {
+        let artist_id: Uuid = row.get("artist_id");
+        let name: String = row.get("name");
+        (artist_id, name)
+    };
+
+    let name = match_name(artist);
+    if name.is_empty() {
+        return Ok(None);
+    }
+    let found = sqlx::query(
+        "SELECT artist_id, name FROM artists WHERE match_name = ? ORDER BY sort_key LIMIT 1",
+    )
+    .bind(&name)
+    .map(map_artist)
+    .fetch_optional(conn.deref_mut())
+    .await?;
+    if found.is_some() {
+        return Ok(found);
+    }
+
+    Ok(sqlx::query("SELECT artist_id, name FROM artists")
+        .map(map_artist)
+        .fetch_all(conn.deref_mut())
+        .await?
+        .into_iter()
+        .find(|(_, name)| is_same_name(name, artist)))
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TopSongsResponse {
+    top_songs: TopSongs,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TopSongs {
+    song: Vec<SubsonicSong>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum XmlTopSongsResponse {
+    TopSongs { song: Vec<SubsonicSong> },
+}
+
+impl ToXml for TopSongsResponse {
+    type Output = XmlTopSongsResponse;
+
+    fn into_xml(self) -> Self::Output {
+        XmlTopSongsResponse::TopSongs {
+            song: self.top_songs.song,
+        }
+    }
+}
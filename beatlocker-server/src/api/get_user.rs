@@ -0,0 +1,103 @@
+use crate::api::format::{SubsonicFormat, ToXml};
+use crate::db::DbUser;
+use crate::{
+    AppResult, AuthenticatedUser, Deserialize, MusicFolderOptions, Serialize, SharedState,
+};
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetUserParams {
+    username: String,
+}
+
+/// Users can get themselves, admins can get anyone.
+pub async fn get_user(
+    format: SubsonicFormat,
+    AuthenticatedUser(current_user): AuthenticatedUser,
+    Query(params): Query<GetUserParams>,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    if !current_user.admin_role && current_user.username != params.username {
+        return Ok((StatusCode::FORBIDDEN, ()).into_response());
+    }
+
+    match state.db.find_user(&params.username).await? {
+        Some(user) => Ok(format.render(GetUserResponse {
+            user: User::new(user, &state.options.music_folders),
+        })),
+        None => Ok((StatusCode::NOT_FOUND, ()).into_response()),
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetUserResponse {
+    user: User,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct User {
+    username: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    email: Option<String>,
+    scrobbling_enabled: bool,
+    admin_role: bool,
+    settings_role: bool,
+    download_role: bool,
+    upload_role: bool,
+    playlist_role: bool,
+    cover_art_role: bool,
+    comment_role: bool,
+    podcast_role: bool,
+    stream_role: bool,
+    jukebox_role: bool,
+    share_role: bool,
+    video_conversion_role: bool,
+    folder: Vec<Uuid>,
+}
+
+impl User {
+    /// Every user can access all music folders.
+    pub fn new(user: DbUser, music_folders: &[MusicFolderOptions]) -> Self {
+        // Roles for features the server doesn't have are never granted
+        User {
+            username: user.username,
+            email: user.email,
+            scrobbling_enabled: true,
+            admin_role: user.admin_role,
+            settings_role: user.settings_role,
+            download_role: user.download_role,
+            upload_role: false,
+            playlist_role: user.playlist_role,
+            cover_art_role: false,
+            comment_role: false,
+            // Managing podcasts is limited to admins
+            podcast_role: user.admin_role,
+            stream_role: user.stream_role,
+            jukebox_role: false,
+            share_role: user.share_role,
+            video_conversion_role: false,
+            folder: music_folders.iter().map(|folder| folder.id).collect(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum XmlGetUserResponse {
+    #[serde(rename_all = "camelCase")]
+    User(User),
+}
+
+impl ToXml for GetUserResponse {
+    type Output = XmlGetUserResponse;
+
+    fn into_xml(self) -> Self::Output {
+        XmlGetUserResponse::User(self.user)
+    }
+}
@@ -0,0 +1,55 @@
+use crate::api::format::{SubsonicFormat, ToXml};
+use crate::api::get_user::User;
+use crate::{AppResult, AuthenticatedUser, Deserialize, Serialize, SharedState};
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+
+pub async fn get_users(
+    format: SubsonicFormat,
+    AuthenticatedUser(current_user): AuthenticatedUser,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    if !current_user.admin_role {
+        return Ok((StatusCode::FORBIDDEN, ()).into_response());
+    }
+
+    let users = state.db.find_users().await?;
+    Ok(format.render(GetUsersResponse {
+        users: Users {
+            user: users
+                .into_iter()
+                .map(|user| User::new(user, &state.options.music_folders))
+                .collect(),
+        },
+    }))
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GetUsersResponse {
+    users: Users,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Users {
+    user: Vec<User>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum XmlGetUsersResponse {
+    #[serde(rename_all = "camelCase")]
+    Users { user: Vec<User> },
+}
+
+impl ToXml for GetUsersResponse {
+    type Output = XmlGetUsersResponse;
+
+    fn into_xml(self) -> Self::Output {
+        XmlGetUsersResponse::Users {
+            user: self.users.user,
+        }
+    }
+}
@@ -1,50 +1,140 @@
 mod auth;
+mod change_password;
+mod create_bookmark;
+mod create_internet_radio_station;
+mod create_playlist;
+mod create_podcast_channel;
+mod create_share;
+mod create_user;
+mod delete_bookmark;
+mod delete_internet_radio_station;
+mod delete_playlist;
+mod delete_podcast_channel;
+mod delete_podcast_episode;
+mod delete_share;
+mod delete_user;
+mod download;
+mod download_podcast_episode;
+mod export_playlist;
 mod format;
 mod get_album;
+mod get_album_info;
 mod get_album_list;
 mod get_album_list2;
 mod get_artist;
 mod get_artist_info;
 mod get_artists;
+mod get_bookmarks;
 mod get_cover_art;
 mod get_genres;
 mod get_indexes;
+mod get_internet_radio_stations;
 mod get_license;
+mod get_lyrics;
+mod get_lyrics_by_song_id;
 mod get_music_directory;
 mod get_music_folders;
+mod get_newest_podcasts;
+mod get_now_playing;
+mod get_open_subsonic_extensions;
+mod get_play_queue;
 mod get_playlist;
 mod get_playlists;
+mod get_podcasts;
 mod get_random_songs;
+mod get_shares;
+mod get_similar_songs;
 mod get_songs_by_genre;
 mod get_starred;
 mod get_starred2;
+mod get_top_songs;
+mod get_user;
+mod get_users;
 mod model;
 mod ping;
+mod public_share;
 mod queries;
+mod range;
+mod refresh_podcasts;
+mod save_play_queue;
+mod scrobble;
+mod scrobble_accounts;
+mod search;
+mod search2;
 mod search3;
+mod set_rating;
 mod star;
 mod stream;
+mod update_internet_radio_station;
+mod update_playlist;
+mod update_share;
+mod update_user;
+mod zip;
 
-pub use auth::RequireAuth;
+pub use auth::{AuthenticatedUser, CurrentUser, RequireAuth, DEFAULT_USERNAME};
+pub use change_password::*;
+pub use create_bookmark::*;
+pub use create_internet_radio_station::*;
+pub use create_playlist::*;
+pub use create_podcast_channel::*;
+pub use create_share::*;
+pub use create_user::*;
+pub use delete_bookmark::*;
+pub use delete_internet_radio_station::*;
+pub use delete_playlist::*;
+pub use delete_podcast_channel::*;
+pub use delete_podcast_episode::*;
+pub use delete_share::*;
+pub use delete_user::*;
+pub use download::*;
+pub use download_podcast_episode::*;
+pub use export_playlist::*;
 pub use get_album::*;
+pub use get_album_info::*;
 pub use get_album_list::*;
 pub use get_album_list2::*;
 pub use get_artist::*;
 pub use get_artist_info::*;
 pub use get_artists::*;
+pub use get_bookmarks::*;
 pub use get_cover_art::*;
 pub use get_genres::*;
 pub use get_indexes::*;
+pub use get_internet_radio_stations::*;
 pub use get_license::*;
+pub use get_lyrics::*;
+pub use get_lyrics_by_song_id::*;
 pub use get_music_directory::*;
 pub use get_music_folders::*;
+pub use get_newest_podcasts::*;
+pub use get_now_playing::*;
+pub use get_open_subsonic_extensions::*;
+pub use get_play_queue::*;
 pub use get_playlist::*;
 pub use get_playlists::*;
+pub use get_podcasts::*;
 pub use get_random_songs::*;
+pub use get_shares::*;
+pub use get_similar_songs::*;
 pub use get_songs_by_genre::*;
 pub use get_starred::*;
 pub use get_starred2::*;
+pub use get_top_songs::*;
+pub use get_user::*;
+pub use get_users::*;
 pub use ping::*;
+pub use public_share::*;
+pub use refresh_podcasts::*;
+pub use save_play_queue::*;
+pub use scrobble::*;
+pub use scrobble_accounts::*;
+pub use search::*;
+pub use search2::*;
 pub use search3::*;
+pub use set_rating::*;
 pub use star::*;
 pub use stream::*;
+pub use update_internet_radio_station::*;
+pub use update_playlist::*;
+pub use update_share::*;
+pub use update_user::*;
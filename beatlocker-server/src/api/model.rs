@@ -4,7 +4,7 @@ use uuid::Uuid;
 
 pub const UNKNOWN_GENRE: &str = "[Unknown genre]";
 
-#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
 #[serde(rename = "song", rename_all = "camelCase")]
 pub struct SubsonicSong {
     pub id: Uuid,
@@ -47,14 +47,24 @@ pub struct SubsonicSong {
     pub genre: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub starred: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_rating: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub average_rating: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub play_count: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub played: Option<DateTime<Utc>>,
 }
 
-#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Clone, Debug, Default, PartialEq, Deserialize, Serialize)]
 #[serde(rename = "artist", rename_all = "camelCase")]
 pub struct SubsonicArtist {
     pub id: Uuid,
     pub name: String,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub cover_art: Option<Uuid>,
     pub album_count: u32,
     #[serde(skip_serializing_if = "Vec::is_empty")]
@@ -63,9 +73,13 @@ pub struct SubsonicArtist {
     pub song: Vec<SubsonicSong>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub starred: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_rating: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub average_rating: Option<f64>,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 #[serde(rename = "album", rename_all = "camelCase")]
 pub struct SubsonicAlbum {
     pub id: Uuid,
@@ -75,6 +89,8 @@ pub struct SubsonicAlbum {
     pub is_dir: Option<bool>,
     pub name: String,
     pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort_name: Option<String>,
     pub song_count: u32,
     pub duration: u32,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -87,6 +103,14 @@ pub struct SubsonicAlbum {
     pub song: Vec<SubsonicSong>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub starred: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_rating: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub average_rating: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub play_count: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub played: Option<DateTime<Utc>>,
 }
 
 impl Default for SubsonicAlbum {
@@ -97,6 +121,7 @@ impl Default for SubsonicAlbum {
             is_dir: None,
             name: "".to_string(),
             title: "".to_string(),
+            sort_name: None,
             song_count: 0,
             duration: 0,
             artist: None,
@@ -104,6 +129,10 @@ impl Default for SubsonicAlbum {
             cover_art: None,
             song: vec![],
             starred: None,
+            user_rating: None,
+            average_rating: None,
+            play_count: None,
+            played: None,
         }
     }
 }
@@ -129,6 +158,8 @@ pub struct SubsonicChildDirectory {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub artist_id: Option<Uuid>,
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub cover_art: Option<Uuid>,
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub year: Option<u32>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub duration: Option<u32>,
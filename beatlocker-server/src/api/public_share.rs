@@ -0,0 +1,139 @@
+use crate::api::queries::{get_subsonic_songs, GetSubsonicSongsQuery, ALL_SONGS};
+use crate::api::range::file_response;
+use crate::{AppResult, SharedState};
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::{Html, IntoResponse, Response};
+use chrono::{DateTime, Utc};
+use quick_xml::escape::escape;
+use sqlx::sqlite::SqliteRow;
+use sqlx::{Row, SqliteConnection};
+use std::fmt::Write;
+use std::ops::DerefMut;
+use uuid::Uuid;
+
+/// Public page of a share, which lets anyone with the link listen to the shared songs. Every visit
+/// is counted.
+pub async fn public_share(
+    Path(share_id): Path<Uuid>,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    let mut conn = state.db.conn().await?;
+    let now = (state.options.now_provider)();
+
+    let (username, description) = match find_valid_share(conn.deref_mut(), share_id, now).await? {
+        Ok(share) => share,
+        Err(status) => return Ok((status, ()).into_response()),
+    };
+
+    sqlx::query(
+        "UPDATE shares SET visit_count = visit_count + 1, last_visited = ? WHERE share_id = ?",
+    )
+    .bind(now)
+    .bind(share_id)
+    .execute(conn.deref_mut())
+    .await?;
+
+    let songs = get_subsonic_songs(
+        conn.deref_mut(),
+        GetSubsonicSongsQuery {
+            username: username.clone(),
+            share_id: Some(share_id),
+            song_count: ALL_SONGS,
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    let title = description.unwrap_or_else(|| format!("Shared by {username}"));
+    let mut html = format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>{title}</title>
+</head>
+<body>
+<h1>{title}</h1>
+<ol>
+"#,
+        title = escape(&title)
+    );
+    for song in songs {
+        let artist = song.artist.unwrap_or_default();
+        writeln!(
+            html,
+            r#"<li><p>{} - {}</p><audio controls preload="none" src="/share/{share_id}/stream/{}"></audio></li>"#,
+            escape(&artist),
+            escape(&song.title),
+            song.id
+        )?;
+    }
+    html.push_str("</ol>\n</body>\n</html>\n");
+
+    Ok(Html(html).into_response())
+}
+
+/// Streams a song of a share, as-is.
+pub async fn public_share_stream(
+    Path((share_id, id)): Path<(Uuid, Uuid)>,
+    State(state): State<SharedState>,
+    headers: HeaderMap,
+) -> AppResult<Response> {
+    let mut conn = state.db.conn().await?;
+    let now = (state.options.now_provider)();
+
+    if let Err(status) = find_valid_share(conn.deref_mut(), share_id, now).await? {
+        return Ok((status, ()).into_response());
+    }
+
+    let source = sqlx::query(
+        r#"
+        SELECT fc.path, s.content_type
+        FROM share_entries se
+        JOIN folder_children fc ON fc.folder_child_id = se.folder_child_id
+        JOIN songs s ON s.song_id = fc.song_id
+        WHERE se.share_id = ? AND se.folder_child_id = ?
+        "#,
+    )
+    .bind(share_id)
+    .bind(id)
+    .map(|row: SqliteRow| {
+        let path: String = row.get("path");
+        let content_type: String = row.get("content_type");
+        (path, content_type)
+    })
+    .fetch_optional(conn.deref_mut())
+    .await?;
+
+    match source {
+        Some((path, content_type)) => file_response(&path, &content_type, &headers).await,
+        None => Ok((StatusCode::NOT_FOUND, ()).into_response()),
+    }
+}
+
+/// Finds the owner and description of a share, or the status to respond with if the share doesn't
+/// exist or has expired.
+async fn find_valid_share(
+    conn: &mut SqliteConnection,
+    share_id: Uuid,
+    now: DateTime<Utc>,
+) -> AppResult<Result<(String, Option<String>), StatusCode>> {
+    let share = sqlx::query("SELECT username, description, expires FROM shares WHERE share_id = ?")
+        .bind(share_id)
+        .map(|row: SqliteRow| {
+            let username: String = row.get("username");
+            let description: Option<String> = row.get("description");
+            let expires: Option<DateTime<Utc>> = row.get("expires");
+            (username, description, expires)
+        })
+        .fetch_optional(conn)
+        .await?;
+
+    Ok(match share {
+        Some((_, _, Some(expires))) if expires <= now => Err(StatusCode::GONE),
+        Some((username, description, _)) => Ok((username, description)),
+        None => Err(StatusCode::NOT_FOUND),
+    })
+}
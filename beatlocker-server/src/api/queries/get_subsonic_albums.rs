@@ -1,5 +1,8 @@
 use crate::api::model::SubsonicAlbum;
-use crate::{AppResult, Deserialize};
+use crate::api::queries::{
+    push_music_folder_songs, push_plays_join, push_rating_joins, push_search_join,
+};
+use crate::{AppResult, Deserialize, DEFAULT_USERNAME};
 use axum::async_trait;
 use axum::extract::{FromRequestParts, Query};
 use axum::http::request::Parts;
@@ -11,14 +14,19 @@ use sqlx::{QueryBuilder, Row, SqliteConnection};
 use uuid::Uuid;
 
 pub struct GetSubsonicAlbumsQuery {
+    /// The user whose stars and ratings are returned.
+    pub username: String,
     pub album_id: Option<Uuid>,
     pub artist_id: Option<Uuid>,
     pub folder_id: Option<Uuid>,
-    //pub music_folder_id: Option<Uuid>,
+    pub music_folder_id: Option<Uuid>,
     pub offset: u32,
     pub size: u32,
     pub ty: GetSubsonicAlbumsListType,
     pub starred: bool,
+    pub search: Option<String>,
+    /// Only counts the plays of this user, instead of those of everyone.
+    pub played_by: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -26,7 +34,9 @@ pub enum GetSubsonicAlbumsListType {
     Random,
     Newest,
     Recent,
+    Frequent,
     Starred,
+    Highest,
     AlphabeticalByName,
     AlphabeticalByArtist,
     ByYear { from_year: usize, to_year: usize },
@@ -36,14 +46,17 @@ pub enum GetSubsonicAlbumsListType {
 impl Default for GetSubsonicAlbumsQuery {
     fn default() -> Self {
         Self {
+            username: DEFAULT_USERNAME.to_string(),
             album_id: None,
             artist_id: None,
             folder_id: None,
-            //music_folder_id: None,
+            music_folder_id: None,
             size: 20,
             offset: 0,
             ty: GetSubsonicAlbumsListType::AlphabeticalByName,
             starred: false,
+            search: None,
+            played_by: None,
         }
     }
 }
@@ -53,18 +66,36 @@ pub async fn get_subsonic_albums(
     query: GetSubsonicAlbumsQuery,
 ) -> AppResult<Vec<SubsonicAlbum>> {
     let mut builder = QueryBuilder::new(
-        r#"SELECT f.*, MIN(s.date) AS song_date, COUNT(fc.song_id) AS song_count, SUM(s.duration) AS duration, st.created as starred_date
+        r#"SELECT f.*, MIN(s.date) AS song_date, COUNT(fc.song_id) AS song_count, SUM(s.duration) AS duration, st.created as starred_date, ur.rating AS user_rating, ra.average_rating, SUM(pl.play_count) AS play_count, MAX(pl.last_played) AS last_played
         FROM folders f
         LEFT JOIN folder_children fc on f.folder_id = fc.folder_id
         LEFT JOIN songs s on fc.song_id = s.song_id
-        LEFT JOIN starred st ON st.starred_id = fc.folder_id
         "#,
     );
+    builder
+        .push(" LEFT JOIN starred st ON st.starred_id = f.folder_id AND st.username = ")
+        .push_bind(query.username.clone());
+    push_rating_joins(&mut builder, &query.username, "f.folder_id");
+    push_plays_join(
+        &mut builder,
+        query.played_by.as_deref(),
+        "song_id",
+        "s.song_id",
+    );
+
+    // Folders are found through the albums of their songs
+    let searching = push_search_join(
+        &mut builder,
+        query.search.as_deref(),
+        "album",
+        "si",
+        "s.album_id",
+    );
 
     builder.push(" WHERE f.parent_id IS NOT NULL");
-    /*if let Some(id) = query.music_folder_id {
-        builder.push(" AND f.folder_id = ").push_bind(id);
-    };*/
+    if let Some(id) = query.music_folder_id {
+        builder.push(" AND f.music_folder_id = ").push_bind(id);
+    };
     if let Some(id) = query.album_id {
         builder.push(" AND s.album_id = ").push_bind(id);
     };
@@ -74,6 +105,9 @@ pub async fn get_subsonic_albums(
     if query.starred {
         builder.push(" AND starred_date IS NOT NULL");
     }
+    if let GetSubsonicAlbumsListType::Highest = query.ty {
+        builder.push(" AND ra.average_rating IS NOT NULL");
+    }
 
     if let GetSubsonicAlbumsListType::ByYear { from_year, to_year } = query.ty {
         let mut from_year: DateTime<Utc> = DateTime::default().with_year(from_year as i32).unwrap();
@@ -93,28 +127,39 @@ pub async fn get_subsonic_albums(
     }
 
     builder.push(" GROUP BY 1");
+    if let GetSubsonicAlbumsListType::Recent | GetSubsonicAlbumsListType::Frequent = query.ty {
+        builder.push(" HAVING MAX(pl.last_played) IS NOT NULL");
+    }
 
     match query.ty {
+        _ if searching => {
+            builder.push(" ORDER BY si.rank, f.sort_key");
+        }
         GetSubsonicAlbumsListType::Random => (),
         GetSubsonicAlbumsListType::Newest => {
-            builder.push(" ORDER BY folders.created DESC");
+            builder.push(" ORDER BY f.created DESC");
         }
         GetSubsonicAlbumsListType::Recent => {
-            builder.push(" ORDER BY folders.created DESC");
+            builder.push(" ORDER BY last_played DESC");
+        }
+        GetSubsonicAlbumsListType::Frequent => {
+            builder.push(" ORDER BY play_count DESC, last_played DESC");
         }
         GetSubsonicAlbumsListType::Starred => (),
-        GetSubsonicAlbumsListType::AlphabeticalByName => {
-            builder.push(" ORDER BY title");
+        GetSubsonicAlbumsListType::Highest => {
+            builder.push(" ORDER BY ra.average_rating DESC, f.sort_key");
         }
-        GetSubsonicAlbumsListType::AlphabeticalByArtist => {
-            builder.push(" ORDER BY title");
+        // Folders don't have an artist of their own
+        GetSubsonicAlbumsListType::AlphabeticalByName
+        | GetSubsonicAlbumsListType::AlphabeticalByArtist => {
+            builder.push(" ORDER BY f.sort_key");
         }
         GetSubsonicAlbumsListType::ByYear { from_year, to_year } => {
             builder.push(" ORDER BY song_date");
             if from_year > to_year {
                 builder.push(" DESC");
             }
-            builder.push(", title");
+            builder.push(", f.sort_key");
         }
         GetSubsonicAlbumsListType::ByGenre { .. } => (),
     };
@@ -131,13 +176,18 @@ pub async fn get_subsonic_albums(
             let id: Uuid = row.get("folder_id");
             SubsonicAlbum {
                 id,
-                parent: Some(Uuid::nil()),
+                parent: row.get("parent_id"),
                 is_dir: Some(true),
                 name: row.get("name"),
                 title: row.get("name"),
                 song_count: row.get("song_count"),
                 duration: row.get("duration"),
+                cover_art: row.get("cover_art_id"),
                 starred: row.get("starred_date"),
+                user_rating: row.get("user_rating"),
+                average_rating: row.get("average_rating"),
+                play_count: row.get("play_count"),
+                played: row.get("last_played"),
                 ..Default::default()
             }
         })
@@ -154,19 +204,39 @@ pub async fn get_subsonic_albums_by_id3(
 ) -> AppResult<Vec<SubsonicAlbum>> {
     let mut builder = QueryBuilder::new(
         r#"
-        SELECT albums.*, ar.name AS artist_name, ar.artist_id AS artist_id, MIN(s.date) AS song_date, COUNT(s.song_id) AS song_count, SUM(s.duration) AS duration, st.created as starred_date
+        SELECT albums.*, ar.name AS artist_name, ar.artist_id AS artist_id, MIN(s.date) AS song_date, COUNT(s.song_id) AS song_count, SUM(s.duration) AS duration, st.created as starred_date, ur.rating AS user_rating, ra.average_rating, pl.play_count, pl.last_played
         FROM albums
         LEFT JOIN album_artists aa on albums.album_id = aa.album_id
         LEFT JOIN artists ar on aa.artist_id = ar.artist_id
         LEFT JOIN songs s on s.album_id = albums.album_id
-        LEFT JOIN starred st ON st.starred_id = albums.album_id
         "#,
     );
+    builder
+        .push(" LEFT JOIN starred st ON st.starred_id = albums.album_id AND st.username = ")
+        .push_bind(query.username.clone());
+    push_rating_joins(&mut builder, &query.username, "albums.album_id");
+    push_plays_join(
+        &mut builder,
+        query.played_by.as_deref(),
+        "album_id",
+        "albums.album_id",
+    );
 
+    let searching = push_search_join(
+        &mut builder,
+        query.search.as_deref(),
+        "album",
+        "si",
+        "albums.album_id",
+    );
     builder.push(" WHERE 1=1");
     if let Some(id) = query.folder_id {
         builder.push(" AND folder_id = ").push_bind(id);
     };
+    if let Some(id) = query.music_folder_id {
+        builder.push(" AND s.song_id IN");
+        push_music_folder_songs(&mut builder, id);
+    };
     if let Some(id) = query.album_id {
         builder.push(" AND albums.album_id = ").push_bind(id);
     };
@@ -176,6 +246,9 @@ pub async fn get_subsonic_albums_by_id3(
     if query.starred {
         builder.push(" AND starred_date IS NOT NULL");
     }
+    if let GetSubsonicAlbumsListType::Highest = query.ty {
+        builder.push(" AND ra.average_rating IS NOT NULL");
+    }
 
     if let GetSubsonicAlbumsListType::ByYear { from_year, to_year } = query.ty {
         let mut from_year: DateTime<Utc> = DateTime::default().with_year(from_year as i32).unwrap();
@@ -194,29 +267,42 @@ pub async fn get_subsonic_albums_by_id3(
         builder.push(" AND s.genre = ").push_bind(genre);
     }
 
+    if let GetSubsonicAlbumsListType::Recent | GetSubsonicAlbumsListType::Frequent = query.ty {
+        builder.push(" AND pl.last_played IS NOT NULL");
+    }
+
     builder.push(" GROUP BY 1");
 
     match query.ty {
+        _ if searching => {
+            builder.push(" ORDER BY si.rank, albums.sort_key");
+        }
         GetSubsonicAlbumsListType::Random => (),
         GetSubsonicAlbumsListType::Newest => {
             builder.push(" ORDER BY s.created DESC");
         }
         GetSubsonicAlbumsListType::Recent => {
-            builder.push(" ORDER BY s.created DESC");
+            builder.push(" ORDER BY last_played DESC");
+        }
+        GetSubsonicAlbumsListType::Frequent => {
+            builder.push(" ORDER BY play_count DESC, last_played DESC");
         }
         GetSubsonicAlbumsListType::Starred => (),
+        GetSubsonicAlbumsListType::Highest => {
+            builder.push(" ORDER BY ra.average_rating DESC, albums.sort_key");
+        }
         GetSubsonicAlbumsListType::AlphabeticalByName => {
-            builder.push(" ORDER BY albums.title");
+            builder.push(" ORDER BY albums.sort_key");
         }
         GetSubsonicAlbumsListType::AlphabeticalByArtist => {
-            builder.push(" ORDER BY artist_name, albums.title");
+            builder.push(" ORDER BY ar.sort_key, albums.sort_key");
         }
         GetSubsonicAlbumsListType::ByYear { from_year, to_year } => {
             builder.push(" ORDER BY song_date");
             if from_year > to_year {
                 builder.push(" DESC");
             }
-            builder.push(", title");
+            builder.push(", albums.sort_key");
         }
         GetSubsonicAlbumsListType::ByGenre { .. } => (),
     };
@@ -235,12 +321,17 @@ pub async fn get_subsonic_albums_by_id3(
                 id,
                 name: row.get("title"),
                 title: row.get("title"),
+                sort_name: row.get("sort_name"),
                 song_count: row.get("song_count"),
                 duration: row.get("duration"),
                 artist: row.get("artist_name"),
                 artist_id: row.get("artist_id"),
                 cover_art: row.get("cover_art_id"),
                 starred: row.get("starred_date"),
+                user_rating: row.get("user_rating"),
+                average_rating: row.get("average_rating"),
+                play_count: row.get("play_count"),
+                played: row.get("last_played"),
                 ..Default::default()
             }
         })
@@ -272,7 +363,9 @@ where
                 }
                 (Some(ty), _, _, _) if ty == "random" => Ok(GetSubsonicAlbumsListType::Random),
                 (Some(ty), _, _, _) if ty == "recent" => Ok(GetSubsonicAlbumsListType::Recent),
+                (Some(ty), _, _, _) if ty == "frequent" => Ok(GetSubsonicAlbumsListType::Frequent),
                 (Some(ty), _, _, _) if ty == "starred" => Ok(GetSubsonicAlbumsListType::Starred),
+                (Some(ty), _, _, _) if ty == "highest" => Ok(GetSubsonicAlbumsListType::Highest),
                 (Some(ty), _, _, _) if ty == "newest" => Ok(GetSubsonicAlbumsListType::Newest),
                 (Some(ty), Some(from_year), Some(to_year), _) if ty == "byYear" => {
                     Ok(GetSubsonicAlbumsListType::ByYear { from_year, to_year })
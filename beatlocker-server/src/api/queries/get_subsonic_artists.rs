@@ -1,25 +1,33 @@
 use crate::api::model::SubsonicArtist;
-use crate::AppResult;
+use crate::api::queries::{push_music_folder_songs, push_rating_joins, push_search_join};
+use crate::{AppResult, DEFAULT_USERNAME};
 
 use sqlx::{QueryBuilder, Row, SqliteConnection};
 use uuid::Uuid;
 
 pub struct GetSubsonicArtistsQuery {
+    /// The user whose stars and ratings are returned.
+    pub username: String,
     pub artist_id: Option<Uuid>,
     pub folder_id: Option<Uuid>,
+    pub music_folder_id: Option<Uuid>,
     pub artist_offset: u32,
     pub artist_count: u32,
     pub starred: bool,
+    pub search: Option<String>,
 }
 
 impl Default for GetSubsonicArtistsQuery {
     fn default() -> Self {
         Self {
+            username: DEFAULT_USERNAME.to_string(),
             artist_id: None,
             folder_id: None,
+            music_folder_id: None,
             artist_count: 20,
             artist_offset: 0,
             starred: false,
+            search: None,
         }
     }
 }
@@ -29,13 +37,24 @@ pub async fn get_subsonic_artists(
     query: GetSubsonicArtistsQuery,
 ) -> AppResult<Vec<SubsonicArtist>> {
     let mut builder = QueryBuilder::new(
-        "SELECT artists.*, COUNT(aa.album_id) as album_count, st.created as starred_date
+        "SELECT artists.*, COUNT(aa.album_id) as album_count, st.created as starred_date, ur.rating AS user_rating, ra.average_rating
         FROM artists
         LEFT JOIN album_artists aa on artists.artist_id = aa.artist_id
-        LEFT JOIN starred st ON st.starred_id = artists.artist_id
-        WHERE 1=1
         ",
     );
+    builder
+        .push(" LEFT JOIN starred st ON st.starred_id = artists.artist_id AND st.username = ")
+        .push_bind(query.username.clone());
+    push_rating_joins(&mut builder, &query.username, "artists.artist_id");
+
+    let searching = push_search_join(
+        &mut builder,
+        query.search.as_deref(),
+        "artist",
+        "si",
+        "artists.artist_id",
+    );
+    builder.push(" WHERE 1=1");
 
     if let Some(id) = query.artist_id {
         builder.push(" AND artists.artist_id = ").push_bind(id);
@@ -43,11 +62,25 @@ pub async fn get_subsonic_artists(
     if let Some(id) = query.folder_id {
         builder.push(" AND folder_id = ").push_bind(id);
     }
+    // Artists are in a music folder through their own songs, or the songs on their albums
+    if let Some(id) = query.music_folder_id {
+        builder.push(" AND artists.artist_id IN (SELECT artist_id FROM songs WHERE song_id IN");
+        push_music_folder_songs(&mut builder, id);
+        builder.push(" UNION SELECT mf_aa.artist_id FROM album_artists mf_aa JOIN songs mf_s ON mf_s.album_id = mf_aa.album_id WHERE mf_s.song_id IN");
+        push_music_folder_songs(&mut builder, id);
+        builder.push(")");
+    }
     if query.starred {
         builder.push(" AND starred_date IS NOT NULL");
     }
+    builder.push(" GROUP BY 1");
+    if searching {
+        builder.push(" ORDER BY si.rank, artists.sort_key");
+    } else {
+        builder.push(" ORDER BY artists.sort_key");
+    }
     builder
-        .push(" GROUP BY 1 ORDER BY artist_id LIMIT ")
+        .push(" LIMIT ")
         .push_bind(query.artist_offset)
         .push(", ")
         .push_bind(query.artist_count);
@@ -58,10 +91,13 @@ pub async fn get_subsonic_artists(
             SubsonicArtist {
                 id,
                 name: row.get("name"),
+                sort_name: row.get("sort_name"),
                 cover_art: row.get("cover_art_id"),
                 album_count: row.get("album_count"),
                 album: vec![],
                 starred: row.get("starred_date"),
+                user_rating: row.get("user_rating"),
+                average_rating: row.get("average_rating"),
                 song: vec![],
             }
         })
@@ -1,37 +1,72 @@
 use crate::api::model::{SubsonicSong, UNKNOWN_GENRE};
-use crate::AppResult;
+use crate::api::queries::{push_music_folder_songs, push_rating_joins, push_search_join};
+use crate::db::search_match_query;
+use crate::{AppResult, DEFAULT_USERNAME};
 use chrono::{DateTime, Datelike, NaiveDateTime, Utc};
 
-use sqlx::{QueryBuilder, Row, SqliteConnection};
+use sqlx::{QueryBuilder, Row, Sqlite, SqliteConnection};
 
 use uuid::Uuid;
 
+/// Song count for lists that are returned whole instead of paged, like playlists and shares.
+pub const ALL_SONGS: u32 = u32::MAX;
+
 pub struct GetSubsonicSongsQuery {
+    /// The user whose stars and ratings are returned.
+    pub username: String,
+    pub folder_child_id: Option<Uuid>,
     pub folder_id: Option<Uuid>,
+    pub music_folder_id: Option<Uuid>,
     pub album_id: Option<Uuid>,
     pub artist_id: Option<Uuid>,
+    /// Songs by any of these artists.
+    pub artist_ids: Option<Vec<Uuid>>,
+    pub playlist_id: Option<Uuid>,
+    /// Songs in the saved play queue of this user, in the order of the queue.
+    pub play_queue_username: Option<String>,
+    /// Songs in this share, in the order they were shared.
+    pub share_id: Option<Uuid>,
     pub genre: Option<String>,
     pub song_offset: u32,
     pub song_count: u32,
     pub starred: bool,
     pub from_year: Option<u32>,
     pub to_year: Option<u32>,
+    /// Songs added after this time.
+    pub newer_than: Option<DateTime<Utc>>,
     pub random: bool,
+    pub search: Option<String>,
+    /// Songs whose title, artist or album matches.
+    pub any_search: Option<String>,
+    pub artist_search: Option<String>,
+    pub album_search: Option<String>,
 }
 
 impl Default for GetSubsonicSongsQuery {
     fn default() -> Self {
         Self {
+            username: DEFAULT_USERNAME.to_string(),
+            folder_child_id: None,
             folder_id: None,
+            music_folder_id: None,
             album_id: None,
             artist_id: None,
+            artist_ids: None,
+            playlist_id: None,
+            play_queue_username: None,
+            share_id: None,
             genre: None,
             song_count: 20,
             song_offset: 0,
             starred: false,
             from_year: None,
             to_year: None,
+            newer_than: None,
             random: false,
+            search: None,
+            any_search: None,
+            artist_search: None,
+            album_search: None,
         }
     }
 }
@@ -40,47 +75,19 @@ pub async fn get_subsonic_songs(
     conn: &mut SqliteConnection,
     query: GetSubsonicSongsQuery,
 ) -> AppResult<Vec<SubsonicSong>> {
-    let mut builder = QueryBuilder::new(
-        r#"SELECT fc.folder_child_id, fc.folder_id, s.*, ar.name as artist, al.title as album, st.created as starred_date
-        FROM folder_children fc
-        LEFT JOIN songs s ON s.song_id = fc.song_id
-        LEFT JOIN artists ar ON ar.artist_id = s.artist_id
-        LEFT JOIN albums al ON al.album_id = s.album_id
-        LEFT JOIN starred st ON st.starred_id = s.song_id OR st.starred_id = fc.folder_child_id
-        WHERE 1=1
-        "#,
-    );
-
-    if let Some(id) = query.folder_id {
-        builder.push(" AND folder_id = ").push_bind(id);
-    };
-    if let Some(id) = query.album_id {
-        builder.push(" AND al.album_id = ").push_bind(id);
-    };
-    if let Some(id) = query.artist_id {
-        builder.push(" AND s.artist_id = ").push_bind(id);
-    };
-    if let Some(id) = query.genre {
-        match id.as_str() {
-            id if id == UNKNOWN_GENRE => builder.push(" AND s.genre IS NULL"),
-            _ => builder.push(" AND s.genre = ").push_bind(id),
-        };
-    };
-    if query.starred {
-        builder.push(" AND starred_date IS NOT NULL");
-    }
-
-    if let Some(year) = query.from_year {
-        let year: DateTime<Utc> = DateTime::default().with_year(year as i32).unwrap();
-        builder.push(" AND s.date >= ").push_bind(year);
-    }
-    if let Some(year) = query.to_year {
-        let year: DateTime<Utc> = DateTime::default().with_year(year as i32).unwrap();
-        builder.push(" AND s.date <= ").push_bind(year);
-    }
+    let mut builder = QueryBuilder::new("");
+    let searching = push_songs_query(&mut builder, &query);
 
     if query.random {
         builder.push(" ORDER BY RANDOM()");
+    } else if query.playlist_id.is_some() {
+        builder.push(" ORDER BY pe.position");
+    } else if query.play_queue_username.is_some() {
+        builder.push(" ORDER BY pqe.position");
+    } else if query.share_id.is_some() {
+        builder.push(" ORDER BY se.position");
+    } else if searching {
+        builder.push(" ORDER BY si.rank, s.title");
     } else {
         builder.push(" ORDER BY s.title");
     }
@@ -119,6 +126,10 @@ pub async fn get_subsonic_songs(
                 year: date.map(|d| d.year() as u32),
                 genre: Some(genre.unwrap_or_else(|| "Unknown genre".to_string())),
                 starred: row.get("starred_date"),
+                user_rating: row.get("user_rating"),
+                average_rating: row.get("average_rating"),
+                play_count: row.get("play_count"),
+                played: row.get("last_played"),
                 ..Default::default()
             }
         })
@@ -128,3 +139,136 @@ pub async fn get_subsonic_songs(
 
     Ok(songs)
 }
+
+/// Counts all songs that `get_subsonic_songs` would find, ignoring the offset and count.
+pub async fn count_subsonic_songs(
+    conn: &mut SqliteConnection,
+    query: &GetSubsonicSongsQuery,
+) -> AppResult<u32> {
+    let mut builder = QueryBuilder::new("SELECT COUNT(*) AS count FROM (");
+    push_songs_query(&mut builder, query);
+    builder.push(")");
+
+    let count: u32 = builder
+        .build()
+        .map(|row| row.get("count"))
+        .fetch_one(conn)
+        .await?;
+    Ok(count)
+}
+
+/// Pushes the query of the songs, without an order or limit. Returns whether the songs are
+/// searched for, so they can be ordered by relevance.
+fn push_songs_query(builder: &mut QueryBuilder<Sqlite>, query: &GetSubsonicSongsQuery) -> bool {
+    builder.push(
+        r#"SELECT fc.folder_child_id, fc.folder_id, s.*, ar.name as artist, al.title as album, st.created as starred_date, ur.rating AS user_rating, ra.average_rating, pl.play_count, pl.last_played
+        FROM folder_children fc
+        LEFT JOIN songs s ON s.song_id = fc.song_id
+        LEFT JOIN artists ar ON ar.artist_id = s.artist_id
+        LEFT JOIN albums al ON al.album_id = s.album_id
+        LEFT JOIN (SELECT song_id, COUNT(*) AS play_count, MAX(played) AS last_played FROM plays GROUP BY song_id) pl ON pl.song_id = s.song_id
+        "#,
+    );
+    builder
+        .push(" LEFT JOIN starred st ON (st.starred_id = s.song_id OR st.starred_id = fc.folder_child_id) AND st.username = ")
+        .push_bind(query.username.clone());
+    push_rating_joins(builder, &query.username, "s.song_id");
+
+    let searching = push_search_join(builder, query.search.as_deref(), "song", "si", "s.song_id");
+    push_search_join(
+        builder,
+        query.artist_search.as_deref(),
+        "artist",
+        "si_ar",
+        "s.artist_id",
+    );
+    push_search_join(
+        builder,
+        query.album_search.as_deref(),
+        "album",
+        "si_al",
+        "s.album_id",
+    );
+    if let Some(id) = query.playlist_id {
+        builder
+            .push(" JOIN playlist_entries pe ON pe.folder_child_id = fc.folder_child_id AND pe.playlist_id = ")
+            .push_bind(id);
+    }
+    if let Some(username) = &query.play_queue_username {
+        builder
+            .push(" JOIN play_queue_entries pqe ON pqe.folder_child_id = fc.folder_child_id AND pqe.username = ")
+            .push_bind(username.clone());
+    }
+    if let Some(id) = query.share_id {
+        builder
+            .push(" JOIN share_entries se ON se.folder_child_id = fc.folder_child_id AND se.share_id = ")
+            .push_bind(id);
+    }
+    builder.push(" WHERE 1=1");
+
+    if let Some(id) = query.folder_child_id {
+        builder.push(" AND fc.folder_child_id = ").push_bind(id);
+    };
+    if let Some(id) = query.folder_id {
+        builder.push(" AND folder_id = ").push_bind(id);
+    };
+    if let Some(id) = query.music_folder_id {
+        builder.push(" AND s.song_id IN");
+        push_music_folder_songs(builder, id);
+    };
+    if let Some(id) = query.album_id {
+        builder.push(" AND al.album_id = ").push_bind(id);
+    };
+    if let Some(id) = query.artist_id {
+        builder.push(" AND s.artist_id = ").push_bind(id);
+    };
+    if let Some(ids) = &query.artist_ids {
+        builder.push(" AND s.artist_id IN (");
+        let mut separated = builder.separated(", ");
+        for id in ids.iter().copied() {
+            separated.push_bind(id);
+        }
+        separated.push_unseparated(")");
+    };
+    if let Some(id) = &query.genre {
+        match id.as_str() {
+            id if id == UNKNOWN_GENRE => builder.push(" AND s.genre IS NULL"),
+            _ => builder.push(" AND s.genre = ").push_bind(id.clone()),
+        };
+    };
+    if query.starred {
+        builder.push(" AND starred_date IS NOT NULL");
+    }
+    if let Some(search) = query.any_search.as_deref().and_then(search_match_query) {
+        builder.push(" AND (");
+        for (i, column) in ["s.song_id", "s.artist_id", "s.album_id"]
+            .iter()
+            .enumerate()
+        {
+            if i > 0 {
+                builder.push(" OR ");
+            }
+            builder
+                .push(format!(
+                    "{column} IN (SELECT item_id FROM search_index WHERE search_index MATCH "
+                ))
+                .push_bind(search.clone())
+                .push(")");
+        }
+        builder.push(")");
+    }
+
+    if let Some(year) = query.from_year {
+        let year: DateTime<Utc> = DateTime::default().with_year(year as i32).unwrap();
+        builder.push(" AND s.date >= ").push_bind(year);
+    }
+    if let Some(year) = query.to_year {
+        let year: DateTime<Utc> = DateTime::default().with_year(year as i32).unwrap();
+        builder.push(" AND s.date <= ").push_bind(year);
+    }
+    if let Some(time) = query.newer_than {
+        builder.push(" AND s.created > ").push_bind(time);
+    }
+
+    searching
+}
@@ -5,3 +5,71 @@ mod get_subsonic_songs;
 pub use get_subsonic_albums::*;
 pub use get_subsonic_artists::*;
 pub use get_subsonic_songs::*;
+
+use crate::db::search_match_query;
+use sqlx::{QueryBuilder, Sqlite};
+use uuid::Uuid;
+
+/// Joins the search results for `item_type` as `alias`, so only rows where `column` matches the
+/// search are kept. Returns whether anything was joined, as a search without words matches all.
+pub(crate) fn push_search_join(
+    builder: &mut QueryBuilder<Sqlite>,
+    search: Option<&str>,
+    item_type: &str,
+    alias: &str,
+    column: &str,
+) -> bool {
+    match search.and_then(search_match_query) {
+        Some(search) => {
+            builder
+                .push(" JOIN (SELECT item_id, rank FROM search_index WHERE search_index MATCH ")
+                .push_bind(search)
+                .push(format!(
+                    " AND item_type = '{item_type}') {alias} ON {alias}.item_id = {column}"
+                ));
+            true
+        }
+        None => false,
+    }
+}
+
+/// Joins the rating `username` gave to the item in `column` as `ur`, and the average rating of all
+/// users as `ra`.
+pub(crate) fn push_rating_joins(builder: &mut QueryBuilder<Sqlite>, username: &str, column: &str) {
+    builder
+        .push(format!(
+            " LEFT JOIN ratings ur ON ur.rated_id = {column} AND ur.username = "
+        ))
+        .push_bind(username.to_string())
+        .push(format!(
+            " LEFT JOIN (SELECT rated_id, AVG(rating) AS average_rating FROM ratings GROUP BY rated_id) ra ON ra.rated_id = {column}"
+        ));
+}
+
+/// Pushes a subquery of the ids of all songs in a music folder.
+fn push_music_folder_songs(builder: &mut QueryBuilder<Sqlite>, music_folder_id: Uuid) {
+    builder
+        .push(" (SELECT mf_fc.song_id FROM folder_children mf_fc JOIN folders mf_f ON mf_f.folder_id = mf_fc.folder_id WHERE mf_f.music_folder_id = ")
+        .push_bind(music_folder_id)
+        .push(")");
+}
+
+/// Joins the play count and last played time per song or album as `pl`, optionally only counting
+/// the plays of a single user. The plays are counted before joining, so joins that repeat a row,
+/// like those of an album with several artists, don't count them twice.
+fn push_plays_join(
+    builder: &mut QueryBuilder<Sqlite>,
+    played_by: Option<&str>,
+    key: &str,
+    column: &str,
+) {
+    builder.push(format!(
+        " LEFT JOIN (SELECT s.{key}, COUNT(*) AS play_count, MAX(p.played) AS last_played FROM plays p JOIN songs s ON s.song_id = p.song_id"
+    ));
+    if let Some(username) = played_by {
+        builder
+            .push(" WHERE p.username = ")
+            .push_bind(username.to_string());
+    }
+    builder.push(format!(" GROUP BY s.{key}) pl ON pl.{key} = {column}"));
+}
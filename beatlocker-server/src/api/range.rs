@@ -0,0 +1,204 @@
+use crate::AppResult;
+use axum::http::header::{ACCEPT_RANGES, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, RANGE};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum_extra::body::AsyncReadBody;
+use headers::{ETag, HeaderMapExt, IfRange, LastModified};
+use std::fs::Metadata;
+use std::io::SeekFrom;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum ByteRange {
+    Full,
+    Partial { start: u64, end: u64 },
+    Unsatisfiable,
+}
+
+/// Serves a file from disk, honouring `Range` and `If-Range` request headers.
+pub async fn file_response(
+    path: impl AsRef<Path>,
+    content_type: &str,
+    request_headers: &HeaderMap,
+) -> AppResult<Response> {
+    let mut file = tokio::fs::File::open(path).await?;
+    let metadata = file.metadata().await?;
+    let len = metadata.len();
+    let etag = file_etag(&metadata);
+    let last_modified = metadata.modified().ok().map(LastModified::from);
+
+    let range = match request_headers.get(RANGE).and_then(|v| v.to_str().ok()) {
+        Some(range) => {
+            let if_range_matches = match request_headers.typed_get::<IfRange>() {
+                Some(if_range) => !if_range.is_modified(etag.as_ref(), last_modified.as_ref()),
+                None => true,
+            };
+            if if_range_matches {
+                parse_range(range, len)
+            } else {
+                ByteRange::Full
+            }
+        }
+        None => ByteRange::Full,
+    };
+
+    let mut headers = HeaderMap::new();
+    headers.insert(ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    headers.insert(CONTENT_TYPE, HeaderValue::from_str(content_type)?);
+    if let Some(etag) = etag {
+        headers.typed_insert(etag);
+    }
+    if let Some(last_modified) = last_modified {
+        headers.typed_insert(last_modified);
+    }
+
+    match range {
+        ByteRange::Full => {
+            headers.insert(CONTENT_LENGTH, HeaderValue::from(len));
+            Ok((headers, AsyncReadBody::new(file)).into_response())
+        }
+        ByteRange::Partial { start, end } => {
+            let length = end - start + 1;
+            file.seek(SeekFrom::Start(start)).await?;
+
+            headers.insert(CONTENT_LENGTH, HeaderValue::from(length));
+            headers.insert(
+                CONTENT_RANGE,
+                HeaderValue::from_str(&format!("bytes {start}-{end}/{len}"))?,
+            );
+            Ok((
+                StatusCode::PARTIAL_CONTENT,
+                headers,
+                AsyncReadBody::new(file.take(length)),
+            )
+                .into_response())
+        }
+        ByteRange::Unsatisfiable => {
+            headers.remove(CONTENT_TYPE);
+            headers.insert(
+                CONTENT_RANGE,
+                HeaderValue::from_str(&format!("bytes */{len}"))?,
+            );
+            Ok((StatusCode::RANGE_NOT_SATISFIABLE, headers, ()).into_response())
+        }
+    }
+}
+
+/// Parses the value of a `Range` header for a resource of `len` bytes.
+///
+/// Only single `bytes` ranges are supported. Malformed ranges, multiple ranges and ranges in other
+/// units are ignored, in which case the full resource should be sent. Only a well-formed range
+/// that selects none of the resource is unsatisfiable.
+pub fn parse_range(range: &str, len: u64) -> ByteRange {
+    let spec = match range.trim().split_once('=') {
+        Some((unit, spec)) if unit.trim().eq_ignore_ascii_case("bytes") => spec.trim(),
+        _ => return ByteRange::Full,
+    };
+
+    if spec.contains(',') {
+        return ByteRange::Full;
+    }
+
+    let (start, end) = match spec.split_once('-') {
+        Some((start, end)) => (start.trim(), end.trim()),
+        None => return ByteRange::Full,
+    };
+
+    let (start, end) = match (start.parse::<u64>(), end.parse::<u64>()) {
+        // bytes=500-999
+        (Ok(start), Ok(end)) if start <= end => (start, end.min(len.saturating_sub(1))),
+        // bytes=500-
+        (Ok(start), Err(_)) if end.is_empty() => (start, len.saturating_sub(1)),
+        // bytes=-0, which asks for nothing at all
+        (Err(_), Ok(0)) if start.is_empty() => return ByteRange::Unsatisfiable,
+        // bytes=-500
+        (Err(_), Ok(suffix)) if start.is_empty() => {
+            (len.saturating_sub(suffix), len.saturating_sub(1))
+        }
+        _ => return ByteRange::Full,
+    };
+
+    if start >= len {
+        ByteRange::Unsatisfiable
+    } else {
+        ByteRange::Partial { start, end }
+    }
+}
+
+fn file_etag(metadata: &Metadata) -> Option<ETag> {
+    let modified = metadata
+        .modified()
+        .ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()?
+        .as_secs();
+    format!("\"{:x}-{:x}\"", metadata.len(), modified)
+        .parse()
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_ranges() {
+        assert_eq!(
+            parse_range("bytes=0-99", 1000),
+            ByteRange::Partial { start: 0, end: 99 }
+        );
+        assert_eq!(
+            parse_range("bytes=500-", 1000),
+            ByteRange::Partial {
+                start: 500,
+                end: 999
+            }
+        );
+        assert_eq!(
+            parse_range("bytes=-100", 1000),
+            ByteRange::Partial {
+                start: 900,
+                end: 999
+            }
+        );
+        assert_eq!(
+            parse_range("bytes=900-5000", 1000),
+            ByteRange::Partial {
+                start: 900,
+                end: 999
+            }
+        );
+        assert_eq!(
+            parse_range("bytes=-5000", 1000),
+            ByteRange::Partial { start: 0, end: 999 }
+        );
+    }
+
+    #[test]
+    fn rejects_unsatisfiable_ranges() {
+        assert_eq!(parse_range("bytes=1000-", 1000), ByteRange::Unsatisfiable);
+        assert_eq!(
+            parse_range("bytes=1000-1099", 1000),
+            ByteRange::Unsatisfiable
+        );
+        assert_eq!(parse_range("bytes=-0", 1000), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range("bytes=0-0", 0), ByteRange::Unsatisfiable);
+    }
+
+    #[test]
+    fn ignores_malformed_ranges() {
+        assert_eq!(parse_range("bytes=abc", 1000), ByteRange::Full);
+        assert_eq!(parse_range("bytes=99-0", 1000), ByteRange::Full);
+        assert_eq!(parse_range("bytes=a-b", 1000), ByteRange::Full);
+        assert_eq!(parse_range("bytes=-", 1000), ByteRange::Full);
+        assert_eq!(parse_range("bytes=0-10, 20-30", 1000), ByteRange::Full);
+    }
+
+    #[test]
+    fn ignores_unknown_units() {
+        assert_eq!(parse_range("items=0-10", 1000), ByteRange::Full);
+        assert_eq!(parse_range("0-10", 1000), ByteRange::Full);
+    }
+}
@@ -0,0 +1,31 @@
+use crate::api::format::SubsonicFormat;
+use crate::{refresh_podcast_feeds, AppResult, AuthenticatedUser, SharedState, TaskState};
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use std::sync::Arc;
+use tracing::error;
+
+/// Reads the feeds of all podcast channels in the background, instead of waiting for the next
+/// periodic refresh.
+pub async fn refresh_podcasts(
+    format: SubsonicFormat,
+    AuthenticatedUser(current_user): AuthenticatedUser,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    if !current_user.admin_role {
+        return Ok((StatusCode::FORBIDDEN, ()).into_response());
+    }
+
+    let task_state = Arc::new(TaskState {
+        options: state.options.clone(),
+        db: state.db.clone(),
+    });
+    tokio::spawn(async move {
+        refresh_podcast_feeds(task_state).await.unwrap_or_else(|e| {
+            error!(?e, "Error when refreshing podcasts");
+        });
+    });
+
+    Ok(format.render::<()>(None))
+}
@@ -0,0 +1,73 @@
+use crate::api::format::SubsonicFormat;
+use crate::api::update_playlist::resolve_song_ids;
+use crate::{AppResult, CurrentUser, Deserialize, SharedState};
+use axum::extract::State;
+use axum::response::Response;
+use sqlx::Connection;
+use std::ops::DerefMut;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SavePlayQueueParams {
+    #[serde(default = "Vec::new")]
+    id: Vec<String>,
+    current: Option<String>,
+    /// Position in the current song, in milliseconds.
+    position: Option<u64>,
+    #[serde(rename = "c")]
+    client: Option<String>,
+}
+
+/// Saves the play queue of the user, replacing the one saved before. Saving a queue without songs
+/// clears it.
+pub async fn save_play_queue(
+    format: SubsonicFormat,
+    CurrentUser(username): CurrentUser,
+    axum_extra::extract::Query(params): axum_extra::extract::Query<SavePlayQueueParams>,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    let mut conn = state.db.conn().await?;
+
+    let entries = resolve_song_ids(conn.deref_mut(), &params.id).await?;
+    let current = match params.current {
+        Some(current) => resolve_song_ids(conn.deref_mut(), &[current])
+            .await?
+            .into_iter()
+            .next(),
+        None => None,
+    };
+
+    let mut tx = conn.begin().await?;
+    // Entries are removed through the foreign key
+    sqlx::query("DELETE FROM play_queues WHERE username = ?")
+        .bind(&username)
+        .execute(&mut tx)
+        .await?;
+
+    if !entries.is_empty() {
+        sqlx::query(
+            "INSERT INTO play_queues (username, current, position, changed, changed_by) VALUES (?, ?, ?, ?, ?)",
+        )
+        .bind(&username)
+        .bind(current)
+        .bind(params.position.unwrap_or_default() as i64)
+        .bind((state.options.now_provider)())
+        .bind(params.client.as_deref().unwrap_or("unknown"))
+        .execute(&mut tx)
+        .await?;
+
+        for (position, folder_child_id) in entries.iter().enumerate() {
+            sqlx::query(
+                "INSERT INTO play_queue_entries (username, position, folder_child_id) VALUES (?, ?, ?)",
+            )
+            .bind(&username)
+            .bind(position as u32)
+            .bind(folder_child_id)
+            .execute(&mut tx)
+            .await?;
+        }
+    }
+    tx.commit().await?;
+
+    Ok(format.render::<()>(None))
+}
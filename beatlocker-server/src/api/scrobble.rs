@@ -0,0 +1,111 @@
+use crate::api::format::SubsonicFormat;
+use crate::{
+    queue_scrobble, submit_scrobbles, AppResult, CurrentUser, Deserialize, SharedState, TaskState,
+};
+use axum::extract::State;
+use axum::response::Response;
+use chrono::{TimeZone, Utc};
+use sqlx::sqlite::SqliteRow;
+use sqlx::Row;
+use std::ops::DerefMut;
+use std::str::FromStr;
+use std::sync::Arc;
+use tracing::error;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScrobbleParams {
+    #[serde(default = "Vec::new")]
+    id: Vec<String>,
+    /// When each song was played, in milliseconds since the epoch.
+    #[serde(default = "Vec::new")]
+    time: Vec<i64>,
+    submission: Option<bool>,
+    #[serde(rename = "c")]
+    client: Option<String>,
+}
+
+pub async fn scrobble(
+    format: SubsonicFormat,
+    CurrentUser(username): CurrentUser,
+    axum_extra::extract::Query(params): axum_extra::extract::Query<ScrobbleParams>,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    let mut conn = state.db.conn().await?;
+    let now = (state.options.now_provider)();
+    let client = params.client.as_deref().unwrap_or("unknown");
+    let mut submitted = false;
+
+    for (index, id) in params.id.iter().enumerate() {
+        let id = match Uuid::from_str(id) {
+            Ok(id) => id,
+            Err(_) => continue,
+        };
+        let song = sqlx::query(
+            "SELECT folder_child_id, song_id FROM folder_children WHERE (folder_child_id = ? OR song_id = ?) AND song_id IS NOT NULL",
+        )
+        .bind(id)
+        .bind(id)
+        .map(|row: SqliteRow| {
+            let folder_child_id: Uuid = row.get("folder_child_id");
+            let song_id: Uuid = row.get("song_id");
+            (folder_child_id, song_id)
+        })
+        .fetch_optional(conn.deref_mut())
+        .await?;
+        let (folder_child_id, song_id) = match song {
+            Some(song) => song,
+            None => continue,
+        };
+
+        let time = params
+            .time
+            .get(index)
+            .and_then(|time| Utc.timestamp_millis_opt(*time).single())
+            .unwrap_or(now);
+
+        if params.submission.unwrap_or(true) {
+            sqlx::query(
+                "INSERT INTO plays (song_id, username, client, played) VALUES (?, ?, ?, ?)",
+            )
+            .bind(song_id)
+            .bind(&username)
+            .bind(client)
+            .bind(time)
+            .execute(conn.deref_mut())
+            .await?;
+            queue_scrobble(conn.deref_mut(), &username, song_id, time, now).await?;
+            submitted = true;
+        } else {
+            sqlx::query(
+                r#"
+                INSERT INTO now_playing (username, client, folder_child_id, started)
+                VALUES (?, ?, ?, ?)
+                ON CONFLICT (username, client) DO UPDATE SET folder_child_id = excluded.folder_child_id, started = excluded.started
+                "#,
+            )
+            .bind(&username)
+            .bind(client)
+            .bind(folder_child_id)
+            .bind(time)
+            .execute(conn.deref_mut())
+            .await?;
+        }
+    }
+
+    // Forward the plays right away, instead of waiting for the next periodic submission
+    if submitted {
+        let task_state = Arc::new(TaskState {
+            options: state.options.clone(),
+            db: state.db.clone(),
+        });
+        tokio::spawn(async move {
+            submit_scrobbles(task_state).await.unwrap_or_else(|e| {
+                error!(?e, "Error when submitting scrobbles");
+            });
+        });
+    }
+
+    Ok(format.render::<()>(None))
+}
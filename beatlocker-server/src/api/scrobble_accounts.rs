@@ -0,0 +1,93 @@
+use crate::api::format::SubsonicFormat;
+use crate::{
+    post_lastfm, AppResult, CurrentUser, Deserialize, LastFmSessionResponse, ScrobbleService,
+    SharedState,
+};
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use std::ops::DerefMut;
+use tracing::warn;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LinkScrobbleAccountParams {
+    service: ScrobbleService,
+    /// A last.fm token authorized by the user, or a ListenBrainz user token.
+    token: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UnlinkScrobbleAccountParams {
+    service: ScrobbleService,
+}
+
+pub async fn link_scrobble_account(
+    format: SubsonicFormat,
+    CurrentUser(username): CurrentUser,
+    Query(params): Query<LinkScrobbleAccountParams>,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    let token = match params.service {
+        ScrobbleService::LastFm => {
+            let (api_key, api_secret) = match (
+                &state.options.lastfm_api_key,
+                &state.options.lastfm_api_secret,
+            ) {
+                (Some(api_key), Some(api_secret)) => (api_key, api_secret),
+                _ => return Ok((StatusCode::NOT_IMPLEMENTED, ()).into_response()),
+            };
+
+            // Exchange the token for a session key, which doesn't expire
+            let session = post_lastfm::<LastFmSessionResponse>(
+                &state.options.lastfm_url,
+                api_secret,
+                &[
+                    ("method", "auth.getSession"),
+                    ("api_key", api_key),
+                    ("token", &params.token),
+                ],
+            )
+            .await;
+            match session {
+                Ok(response) => response.session.key,
+                Err(e) => {
+                    warn!(?e, "Could not get a last.fm session");
+                    return Ok((StatusCode::BAD_REQUEST, ()).into_response());
+                }
+            }
+        }
+        ScrobbleService::ListenBrainz => params.token,
+    };
+
+    sqlx::query(
+        r#"
+        INSERT INTO scrobble_accounts (username, service, token) VALUES (?, ?, ?)
+        ON CONFLICT (username, service) DO UPDATE SET token = excluded.token
+        "#,
+    )
+    .bind(&username)
+    .bind(params.service.as_str())
+    .bind(token)
+    .execute(state.db.conn().await?.deref_mut())
+    .await?;
+
+    Ok(format.render::<()>(None))
+}
+
+pub async fn unlink_scrobble_account(
+    format: SubsonicFormat,
+    CurrentUser(username): CurrentUser,
+    Query(params): Query<UnlinkScrobbleAccountParams>,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    // Plays that weren't submitted yet are removed along with the account
+    sqlx::query("DELETE FROM scrobble_accounts WHERE username = ? AND service = ?")
+        .bind(&username)
+        .bind(params.service.as_str())
+        .execute(state.db.conn().await?.deref_mut())
+        .await?;
+
+    Ok(format.render::<()>(None))
+}
@@ -0,0 +1,97 @@
+use crate::api::format::{SubsonicFormat, ToXml};
+use crate::api::model::SubsonicSong;
+use crate::api::queries::{count_subsonic_songs, get_subsonic_songs, GetSubsonicSongsQuery};
+use crate::{AppResult, CurrentUser, SharedState};
+use axum::extract::{Query, State};
+use axum::response::Response;
+use chrono::{TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use std::ops::DerefMut;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchParams {
+    artist: Option<String>,
+    album: Option<String>,
+    title: Option<String>,
+    any: Option<String>,
+    count: Option<u32>,
+    offset: Option<u32>,
+    /// Milliseconds since the epoch.
+    newer_than: Option<i64>,
+}
+
+/// The deprecated search endpoint, which only returns songs. `any` matches songs by their title,
+/// artist or album, and `newerThan` only keeps songs added after it.
+pub async fn search(
+    format: SubsonicFormat,
+    CurrentUser(username): CurrentUser,
+    Query(params): Query<SearchParams>,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    let mut conn = state.db.conn().await?;
+
+    let query = GetSubsonicSongsQuery {
+        username,
+        song_offset: params.offset.unwrap_or_default(),
+        song_count: params.count.unwrap_or(20),
+        search: params.title,
+        any_search: params.any,
+        artist_search: params.artist,
+        album_search: params.album,
+        newer_than: params
+            .newer_than
+            .and_then(|millis| Utc.timestamp_millis_opt(millis).single()),
+        ..Default::default()
+    };
+    let offset = query.song_offset;
+    let total_hits = count_subsonic_songs(conn.deref_mut(), &query).await?;
+    let matches = get_subsonic_songs(conn.deref_mut(), query).await?;
+
+    Ok(format.render(SearchResultResponse {
+        search_result: SearchResult {
+            offset,
+            total_hits,
+            matches,
+        },
+    }))
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResultResponse {
+    search_result: SearchResult,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResult {
+    offset: u32,
+    total_hits: u32,
+    #[serde(rename = "match", skip_serializing_if = "Vec::is_empty")]
+    matches: Vec<SubsonicSong>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum XmlSearchResultResponse {
+    #[serde(rename_all = "camelCase")]
+    SearchResult {
+        offset: u32,
+        total_hits: u32,
+        #[serde(rename = "match")]
+        matches: Vec<SubsonicSong>,
+    },
+}
+
+impl ToXml for SearchResultResponse {
+    type Output = XmlSearchResultResponse;
+
+    fn into_xml(self) -> Self::Output {
+        XmlSearchResultResponse::SearchResult {
+            offset: self.search_result.offset,
+            total_hits: self.search_result.total_hits,
+            matches: self.search_result.matches,
+        }
+    }
+}
@@ -0,0 +1,188 @@
+use crate::api::format::{SubsonicFormat, ToXml};
+use crate::api::model::{SubsonicAlbum, SubsonicArtist, SubsonicSong};
+use crate::api::queries::{
+    get_subsonic_albums, get_subsonic_songs, push_rating_joins, push_search_join,
+    GetSubsonicAlbumsQuery, GetSubsonicSongsQuery,
+};
+use crate::api::search3::SubsonicItem;
+use crate::{AppResult, CurrentUser, SharedState};
+use axum::extract::{Query, State};
+use axum::response::Response;
+use itertools::Itertools;
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::SqliteRow;
+use sqlx::{QueryBuilder, Row, SqliteConnection};
+use std::ops::DerefMut;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Search2Params {
+    query: String,
+    artist_count: Option<u32>,
+    artist_offset: Option<u32>,
+    album_count: Option<u32>,
+    album_offset: Option<u32>,
+    song_count: Option<u32>,
+    song_offset: Option<u32>,
+    music_folder_id: Option<Uuid>,
+}
+
+pub async fn search2(
+    format: SubsonicFormat,
+    CurrentUser(username): CurrentUser,
+    Query(params): Query<Search2Params>,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    let mut conn = state.db.conn().await?;
+
+    let songs = get_subsonic_songs(
+        &mut conn,
+        GetSubsonicSongsQuery {
+            username: username.clone(),
+            song_offset: params.song_offset.unwrap_or_default(),
+            song_count: params.song_count.unwrap_or(20),
+            music_folder_id: params.music_folder_id,
+            search: Some(params.query.clone()),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    let artists = search_artist_folders(conn.deref_mut(), &username, &params).await?;
+
+    let albums = get_subsonic_albums(
+        conn.deref_mut(),
+        GetSubsonicAlbumsQuery {
+            username,
+            offset: params.album_offset.unwrap_or_default(),
+            size: params.album_count.unwrap_or(20),
+            music_folder_id: params.music_folder_id,
+            search: Some(params.query),
+            ..Default::default()
+        },
+    )
+    .await?;
+
+    Ok(format.render(SearchResult2Response {
+        search_result2: {
+            SearchResult2 {
+                album: albums,
+                artist: artists,
+                song: songs,
+            }
+        },
+    }))
+}
+
+/// Like getIndexes, search2 browses by folder, so its artists are the top level folders of the
+/// music folders rather than ID3 artists.
+async fn search_artist_folders(
+    conn: &mut SqliteConnection,
+    username: &str,
+    params: &Search2Params,
+) -> AppResult<Vec<SubsonicArtist>> {
+    let mut builder = QueryBuilder::new(
+        "SELECT folders.*, (SELECT COUNT(*) FROM folders c WHERE c.parent_id = folders.folder_id) AS album_count, ur.rating AS user_rating, ra.average_rating
+        FROM folders",
+    );
+    push_rating_joins(&mut builder, username, "folders.folder_id");
+    let searching = push_search_join(
+        &mut builder,
+        Some(params.query.as_str()),
+        "folder",
+        "si",
+        "folders.folder_id",
+    );
+    builder.push(
+        " WHERE folders.parent_id IN (SELECT folder_id FROM folders WHERE parent_id IS NULL)",
+    );
+    if let Some(id) = params.music_folder_id {
+        builder
+            .push(" AND folders.music_folder_id = ")
+            .push_bind(id);
+    }
+    if searching {
+        builder.push(" ORDER BY si.rank, folders.sort_key");
+    } else {
+        builder.push(" ORDER BY folders.sort_key");
+    }
+    builder
+        .push(" LIMIT ")
+        .push_bind(params.artist_offset.unwrap_or_default())
+        .push(", ")
+        .push_bind(params.artist_count.unwrap_or(20));
+
+    let artists = builder
+        .build()
+        .map(|row: SqliteRow| SubsonicArtist {
+            id: row.get("folder_id"),
+            name: row.get("name"),
+            sort_name: None,
+            cover_art: row.get("cover_art_id"),
+            album_count: row.get("album_count"),
+            album: vec![],
+            song: vec![],
+            starred: None,
+            user_rating: row.get("user_rating"),
+            average_rating: row.get("average_rating"),
+        })
+        .fetch_all(conn)
+        .await?;
+
+    Ok(artists)
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResult2Response {
+    search_result2: SearchResult2,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchResult2 {
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    album: Vec<SubsonicAlbum>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    artist: Vec<SubsonicArtist>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    song: Vec<SubsonicSong>,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+pub enum XmlSearchResult2 {
+    #[serde(rename = "searchResult2")]
+    SearchResult2(Vec<SubsonicItem>),
+}
+
+impl ToXml for SearchResult2Response {
+    type Output = XmlSearchResult2;
+
+    fn into_xml(self) -> Self::Output {
+        let mut items: Vec<SubsonicItem> = vec![];
+        items.extend(
+            self.search_result2
+                .album
+                .into_iter()
+                .map(SubsonicItem::Album)
+                .collect_vec(),
+        );
+        items.extend(
+            self.search_result2
+                .artist
+                .into_iter()
+                .map(SubsonicItem::Artist)
+                .collect_vec(),
+        );
+        items.extend(
+            self.search_result2
+                .song
+                .into_iter()
+                .map(SubsonicItem::Song)
+                .collect_vec(),
+        );
+
+        XmlSearchResult2::SearchResult2(items)
+    }
+}
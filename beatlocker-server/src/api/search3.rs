@@ -4,18 +4,17 @@ use crate::api::queries::{
     get_subsonic_albums_by_id3, get_subsonic_artists, get_subsonic_songs, GetSubsonicAlbumsQuery,
     GetSubsonicArtistsQuery, GetSubsonicSongsQuery,
 };
-use crate::{AppResult, SharedState};
+use crate::{AppResult, CurrentUser, SharedState};
 use axum::extract::{Query, State};
 use axum::response::Response;
 use itertools::Itertools;
 use serde::{Deserialize, Serialize};
 use std::ops::DerefMut;
+use uuid::Uuid;
 
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Search3Params {
-    // We don't use 'query', but it's required
-    #[allow(dead_code)]
     query: String,
     artist_count: Option<u32>,
     artist_offset: Option<u32>,
@@ -23,11 +22,12 @@ pub struct Search3Params {
     album_offset: Option<u32>,
     song_count: Option<u32>,
     song_offset: Option<u32>,
-    //music_folder_id: Option<Uuid>,
+    music_folder_id: Option<Uuid>,
 }
 
 pub async fn search3(
     format: SubsonicFormat,
+    CurrentUser(username): CurrentUser,
     Query(params): Query<Search3Params>,
     State(state): State<SharedState>,
 ) -> AppResult<Response> {
@@ -36,8 +36,11 @@ pub async fn search3(
     let songs = get_subsonic_songs(
         &mut conn,
         GetSubsonicSongsQuery {
+            username: username.clone(),
             song_offset: params.song_offset.unwrap_or_default(),
             song_count: params.song_count.unwrap_or(20),
+            music_folder_id: params.music_folder_id,
+            search: Some(params.query.clone()),
             ..Default::default()
         },
     )
@@ -46,8 +49,11 @@ pub async fn search3(
     let artists = get_subsonic_artists(
         conn.deref_mut(),
         GetSubsonicArtistsQuery {
+            username: username.clone(),
             artist_offset: params.artist_offset.unwrap_or_default(),
             artist_count: params.artist_count.unwrap_or(20),
+            music_folder_id: params.music_folder_id,
+            search: Some(params.query.clone()),
             ..Default::default()
         },
     )
@@ -56,8 +62,11 @@ pub async fn search3(
     let albums = get_subsonic_albums_by_id3(
         conn.deref_mut(),
         GetSubsonicAlbumsQuery {
+            username,
             offset: params.album_offset.unwrap_or_default(),
             size: params.album_count.unwrap_or(20),
+            music_folder_id: params.music_folder_id,
+            search: Some(params.query),
             ..Default::default()
         },
     )
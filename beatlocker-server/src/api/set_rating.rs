@@ -0,0 +1,65 @@
+use crate::api::format::SubsonicFormat;
+use crate::{AppResult, CurrentUser, Deserialize, SharedState};
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use sqlx::sqlite::SqliteRow;
+use sqlx::Row;
+use std::ops::DerefMut;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SetRatingParams {
+    id: Uuid,
+    rating: u32,
+}
+
+/// Rates a song, album or artist from 1 to 5. A rating of 0 removes the rating.
+pub async fn set_rating(
+    format: SubsonicFormat,
+    CurrentUser(username): CurrentUser,
+    Query(params): Query<SetRatingParams>,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    if params.rating > 5 {
+        return Ok((StatusCode::BAD_REQUEST, ()).into_response());
+    }
+
+    let mut conn = state.db.conn().await?;
+
+    // Songs can be rated through their folder child, but the rating belongs to the song
+    let song_id = sqlx::query(
+        "SELECT song_id FROM folder_children WHERE folder_child_id = ? AND song_id IS NOT NULL",
+    )
+    .bind(params.id)
+    .map(|row: SqliteRow| {
+        let song_id: Uuid = row.get("song_id");
+        song_id
+    })
+    .fetch_optional(conn.deref_mut())
+    .await?;
+    let rated_id = song_id.unwrap_or(params.id);
+
+    if params.rating == 0 {
+        sqlx::query("DELETE FROM ratings WHERE rated_id = ? AND username = ?")
+            .bind(rated_id)
+            .bind(&username)
+            .execute(conn.deref_mut())
+            .await?;
+    } else {
+        sqlx::query(
+            r#"
+            INSERT INTO ratings (rated_id, username, rating) VALUES (?, ?, ?)
+            ON CONFLICT (rated_id, username) DO UPDATE SET rating = excluded.rating
+            "#,
+        )
+        .bind(rated_id)
+        .bind(&username)
+        .bind(params.rating)
+        .execute(conn.deref_mut())
+        .await?;
+    }
+
+    Ok(format.render::<()>(None))
+}
@@ -8,7 +8,7 @@ use sqlx::Row;
 use uuid::Uuid;
 
 use crate::api::format::SubsonicFormat;
-use crate::{AppResult, Deserialize, SharedState};
+use crate::{AppResult, CurrentUser, Deserialize, SharedState};
 
 #[derive(Default, Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -33,16 +33,20 @@ impl StarParams {
 
 pub async fn star(
     format: SubsonicFormat,
+    CurrentUser(username): CurrentUser,
     params: axum_extra::extract::Query<StarParams>,
     State(state): State<SharedState>,
 ) -> AppResult<Response> {
     let ids = params.all_ids();
     for id in ids {
-        sqlx::query("INSERT OR IGNORE INTO starred (starred_id, created) VALUES (?, ?)")
-            .bind(id)
-            .bind((state.options.now_provider)())
-            .execute(state.db.conn().await?.deref_mut())
-            .await?;
+        sqlx::query(
+            "INSERT OR IGNORE INTO starred (starred_id, username, created) VALUES (?, ?, ?)",
+        )
+        .bind(id)
+        .bind(&username)
+        .bind((state.options.now_provider)())
+        .execute(state.db.conn().await?.deref_mut())
+        .await?;
     }
 
     Ok(format.render::<()>(None))
@@ -50,6 +54,7 @@ pub async fn star(
 
 pub async fn unstar(
     format: SubsonicFormat,
+    CurrentUser(username): CurrentUser,
     params: axum_extra::extract::Query<StarParams>,
     State(state): State<SharedState>,
 ) -> AppResult<Response> {
@@ -74,8 +79,9 @@ pub async fn unstar(
         .unwrap();
 
         for id in [Some(id), folder_child_id].iter().flatten() {
-            sqlx::query("DELETE FROM starred WHERE starred_id = ?")
+            sqlx::query("DELETE FROM starred WHERE starred_id = ? AND username = ?")
                 .bind(id)
+                .bind(&username)
                 .execute(state.db.conn().await?.deref_mut())
                 .await?
                 .rows_affected();
@@ -1,14 +1,19 @@
-use crate::{AppResult, SharedState};
+use crate::api::range::file_response;
+use crate::{
+    streaming_reqwest_client, AppResult, AuthenticatedUser, SharedState, TranscodeProfile,
+};
 use std::ops::DerefMut;
 
+use axum::body::StreamBody;
 use axum::extract::{Query, State};
-use axum::http::header::{CONTENT_LENGTH, CONTENT_TYPE};
-use axum::http::StatusCode;
+use axum::http::header::{ACCEPT_RANGES, CONTENT_TYPE};
+use axum::http::{HeaderMap, HeaderValue, StatusCode};
 use axum::response::{IntoResponse, Response};
 use axum_extra::body::AsyncReadBody;
 use serde::Deserialize;
 use sqlx::sqlite::SqliteRow;
-use sqlx::Row;
+use sqlx::{Row, SqliteConnection};
+use tracing::warn;
 
 use uuid::Uuid;
 
@@ -16,37 +21,152 @@ use uuid::Uuid;
 #[serde(rename_all = "camelCase")]
 pub struct StreamParams {
     id: Uuid,
+    max_bit_rate: Option<u32>,
+    format: Option<String>,
+    time_offset: Option<u32>,
+}
+
+struct StreamSource {
+    path: String,
+    content_type: String,
+    suffix: Option<String>,
+    bit_rate: Option<u32>,
 }
 
 pub async fn stream(
+    AuthenticatedUser(current_user): AuthenticatedUser,
     Query(params): Query<StreamParams>,
     State(state): State<SharedState>,
+    headers: HeaderMap,
 ) -> AppResult<Response> {
+    if !current_user.stream_role {
+        return Ok((StatusCode::FORBIDDEN, ()).into_response());
+    }
+
     let mut conn = state.db.conn().await?;
 
     let result = sqlx::query(
-        "SELECT path, s.content_type FROM folder_children fc LEFT JOIN songs s ON s.song_id = fc.song_id WHERE folder_child_id = ?",
+        "SELECT path, s.content_type, s.suffix, s.size, s.duration, s.bit_rate FROM folder_children fc LEFT JOIN songs s ON s.song_id = fc.song_id WHERE folder_child_id = ?",
     )
     .bind(params.id)
     .map(|row: SqliteRow| {
-        let path: String = row.get("path");
-        let content_type: String = row.get("content_type");
-        (path, content_type)
+        let size: Option<u32> = row.get("size");
+        let duration: Option<u32> = row.get("duration");
+        // Fall back to the average bitrate if the file didn't tell us
+        let bit_rate: Option<u32> = row.get::<Option<u32>, _>("bit_rate").or_else(|| {
+            match (size, duration) {
+                (Some(size), Some(duration)) if duration > 0 => {
+                    Some((size as u64 * 8 / duration as u64 / 1000) as u32)
+                }
+                _ => None,
+            }
+        });
+        StreamSource {
+            path: row.get("path"),
+            content_type: row.get("content_type"),
+            suffix: row.get("suffix"),
+            bit_rate,
+        }
     })
     .fetch_optional(conn.deref_mut())
     .await?;
+    let result = match result {
+        Some(source) => Some(source),
+        None => podcast_episode_source(conn.deref_mut(), params.id).await?,
+    };
 
-    match result {
-        Some((path, content_type)) => {
-            let file = tokio::fs::File::open(&path).await?;
+    let source = match result {
+        Some(source) => source,
+        None if state.options.relay_internet_radio => {
+            return relay_internet_radio_station(conn.deref_mut(), params.id).await
+        }
+        None => return Ok((StatusCode::NOT_FOUND, ()).into_response()),
+    };
+
+    match TranscodeProfile::select(
+        &state.options.transcode_profiles,
+        params.format.as_deref(),
+        params.max_bit_rate,
+        params.time_offset,
+        source.suffix.as_deref(),
+        source.bit_rate,
+    ) {
+        Some((profile, bit_rate)) => {
+            let stream = profile.transcode(
+                &source.path,
+                bit_rate,
+                params.time_offset.unwrap_or_default(),
+            )?;
 
-            let headers = [
-                (CONTENT_TYPE, &content_type),
-                (CONTENT_LENGTH, &file.metadata().await?.len().to_string()),
-            ];
-            let body = AsyncReadBody::new(file);
-            Ok((headers, body).into_response())
+            let mut headers = HeaderMap::new();
+            headers.insert(ACCEPT_RANGES, HeaderValue::from_static("none"));
+            headers.insert(CONTENT_TYPE, HeaderValue::from_str(&profile.content_type)?);
+            Ok((headers, AsyncReadBody::new(stream)).into_response())
         }
-        None => Ok((StatusCode::NOT_FOUND, ()).into_response()),
+        None => file_response(&source.path, &source.content_type, &headers).await,
     }
 }
+
+/// Downloaded podcast episodes are streamed by their episode id.
+async fn podcast_episode_source(
+    conn: &mut SqliteConnection,
+    episode_id: Uuid,
+) -> AppResult<Option<StreamSource>> {
+    Ok(sqlx::query(
+        "SELECT path, COALESCE(content_type, 'audio/mpeg') AS content_type, suffix, bit_rate FROM podcast_episodes WHERE episode_id = ? AND status = 'completed'",
+    )
+    .bind(episode_id)
+    .map(|row: SqliteRow| StreamSource {
+        path: row.get("path"),
+        content_type: row.get("content_type"),
+        suffix: row.get("suffix"),
+        bit_rate: row.get("bit_rate"),
+    })
+    .fetch_optional(conn)
+    .await?)
+}
+
+/// Streams an internet radio station through the server, for clients that can't reach the station
+/// themselves. The stream is passed on as-is for as long as the client keeps listening.
+async fn relay_internet_radio_station(
+    conn: &mut SqliteConnection,
+    station_id: Uuid,
+) -> AppResult<Response> {
+    let stream_url =
+        sqlx::query("SELECT stream_url FROM internet_radio_stations WHERE station_id = ?")
+            .bind(station_id)
+            .map(|row: SqliteRow| {
+                let stream_url: String = row.get("stream_url");
+                stream_url
+            })
+            .fetch_optional(conn)
+            .await?;
+    let stream_url = match stream_url {
+        Some(stream_url) => stream_url,
+        None => return Ok((StatusCode::NOT_FOUND, ()).into_response()),
+    };
+
+    let response = match streaming_reqwest_client().get(&stream_url).send().await {
+        Ok(response) if response.status().is_success() => response,
+        Ok(response) => {
+            warn!(%stream_url, status = %response.status(), "Station could not be relayed");
+            return Ok((StatusCode::BAD_GATEWAY, ()).into_response());
+        }
+        Err(e) => {
+            warn!(%stream_url, ?e, "Station could not be relayed");
+            return Ok((StatusCode::BAD_GATEWAY, ()).into_response());
+        }
+    };
+
+    let mut headers = HeaderMap::new();
+    headers.insert(ACCEPT_RANGES, HeaderValue::from_static("none"));
+    headers.insert(
+        CONTENT_TYPE,
+        response
+            .headers()
+            .get(CONTENT_TYPE)
+            .cloned()
+            .unwrap_or_else(|| HeaderValue::from_static("audio/mpeg")),
+    );
+    Ok((headers, StreamBody::new(response.bytes_stream())).into_response())
+}
@@ -0,0 +1,68 @@
+use crate::api::format::SubsonicFormat;
+use crate::{AppResult, AuthenticatedUser, Deserialize, SharedState};
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use sqlx::sqlite::SqliteRow;
+use sqlx::{Row, SqliteConnection};
+use std::ops::DerefMut;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateInternetRadioStationParams {
+    id: Uuid,
+    stream_url: String,
+    name: String,
+    homepage_url: Option<String>,
+}
+
+pub async fn update_internet_radio_station(
+    format: SubsonicFormat,
+    AuthenticatedUser(current_user): AuthenticatedUser,
+    Query(params): Query<UpdateInternetRadioStationParams>,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    if !current_user.admin_role {
+        return Ok((StatusCode::FORBIDDEN, ()).into_response());
+    }
+
+    let mut conn = state.db.conn().await?;
+    if let Some(status) = check_station_editable(conn.deref_mut(), params.id).await? {
+        return Ok((status, ()).into_response());
+    }
+
+    sqlx::query(
+        "UPDATE internet_radio_stations SET name = ?, stream_url = ?, home_page_url = ? WHERE station_id = ?",
+    )
+    .bind(&params.name)
+    .bind(&params.stream_url)
+    .bind(&params.homepage_url)
+    .bind(params.id)
+    .execute(conn.deref_mut())
+    .await?;
+
+    Ok(format.render::<()>(None))
+}
+
+/// Returns the status to respond with if the station can't be changed. Stations imported from a
+/// station list are changed by changing the list.
+pub(crate) async fn check_station_editable(
+    conn: &mut SqliteConnection,
+    station_id: Uuid,
+) -> AppResult<Option<StatusCode>> {
+    let path = sqlx::query("SELECT path FROM internet_radio_stations WHERE station_id = ?")
+        .bind(station_id)
+        .map(|row: SqliteRow| {
+            let path: Option<String> = row.get("path");
+            path
+        })
+        .fetch_optional(conn)
+        .await?;
+
+    Ok(match path {
+        Some(None) => None,
+        Some(Some(_)) => Some(StatusCode::FORBIDDEN),
+        None => Some(StatusCode::NOT_FOUND),
+    })
+}
@@ -0,0 +1,139 @@
+use crate::api::format::SubsonicFormat;
+use crate::db::write_playlist_entries;
+use crate::{AppResult, AuthenticatedUser, Deserialize, SharedState};
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use sqlx::sqlite::SqliteRow;
+use sqlx::{Connection, Row, SqliteConnection};
+use std::collections::HashSet;
+use std::str::FromStr;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdatePlaylistParams {
+    playlist_id: Uuid,
+    name: Option<String>,
+    comment: Option<String>,
+    public: Option<bool>,
+    #[serde(default = "Vec::new")]
+    song_id_to_add: Vec<String>,
+    #[serde(default = "Vec::new")]
+    song_index_to_remove: Vec<usize>,
+}
+
+pub async fn update_playlist(
+    format: SubsonicFormat,
+    AuthenticatedUser(current_user): AuthenticatedUser,
+    params: axum_extra::extract::Query<UpdatePlaylistParams>,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    if !current_user.playlist_role {
+        return Ok((StatusCode::FORBIDDEN, ()).into_response());
+    }
+    let username = current_user.username;
+
+    let mut conn = state.db.conn().await?;
+    // The entries are read and written in one transaction, so concurrent changes aren't lost
+    let mut tx = conn.begin().await?;
+
+    if let Some(status) = check_playlist_editable(&mut tx, params.playlist_id, &username).await? {
+        return Ok((status, ()).into_response());
+    }
+
+    sqlx::query(
+        r#"
+        UPDATE playlists
+        SET name = COALESCE(?, name), comment = COALESCE(?, comment), public = COALESCE(?, public), changed = ?
+        WHERE playlist_id = ?
+        "#,
+    )
+    .bind(&params.name)
+    .bind(&params.comment)
+    .bind(params.public)
+    .bind((state.options.now_provider)())
+    .bind(params.playlist_id)
+    .execute(&mut tx)
+    .await?;
+
+    if !params.song_index_to_remove.is_empty() || !params.song_id_to_add.is_empty() {
+        let to_remove: HashSet<usize> = params.song_index_to_remove.iter().copied().collect();
+        let mut entries: Vec<Uuid> = get_playlist_entries(&mut tx, params.playlist_id)
+            .await?
+            .into_iter()
+            .enumerate()
+            .filter(|(index, _)| !to_remove.contains(index))
+            .map(|(_, id)| id)
+            .collect();
+        entries.extend(resolve_song_ids(&mut tx, &params.song_id_to_add).await?);
+        write_playlist_entries(&mut tx, params.playlist_id, &entries).await?;
+    }
+    tx.commit().await?;
+
+    Ok(format.render::<()>(None))
+}
+
+/// Returns the status to respond with if the user can't change the playlist. Only the owner can
+/// change a playlist, and playlists imported from files can't be changed at all.
+pub(crate) async fn check_playlist_editable(
+    conn: &mut SqliteConnection,
+    playlist_id: Uuid,
+    username: &str,
+) -> AppResult<Option<StatusCode>> {
+    let playlist = sqlx::query("SELECT owner, path FROM playlists WHERE playlist_id = ?")
+        .bind(playlist_id)
+        .map(|row: SqliteRow| {
+            let owner: String = row.get("owner");
+            let path: Option<String> = row.get("path");
+            (owner, path)
+        })
+        .fetch_optional(conn)
+        .await?;
+
+    Ok(match playlist {
+        Some((owner, None)) if owner == username => None,
+        Some(_) => Some(StatusCode::FORBIDDEN),
+        None => Some(StatusCode::NOT_FOUND),
+    })
+}
+
+async fn get_playlist_entries(
+    conn: &mut SqliteConnection,
+    playlist_id: Uuid,
+) -> AppResult<Vec<Uuid>> {
+    Ok(sqlx::query(
+        "SELECT folder_child_id FROM playlist_entries WHERE playlist_id = ? ORDER BY position",
+    )
+    .bind(playlist_id)
+    .map(|row: SqliteRow| row.get("folder_child_id"))
+    .fetch_all(conn)
+    .await?)
+}
+
+/// Songs can be referred to by their folder child id or their song id. Unknown songs are skipped.
+pub(crate) async fn resolve_song_ids(
+    conn: &mut SqliteConnection,
+    ids: &[String],
+) -> AppResult<Vec<Uuid>> {
+    let mut folder_child_ids = vec![];
+    for id in ids.iter().filter_map(|s| Uuid::from_str(s).ok()) {
+        let folder_child_id = sqlx::query(
+            r#"
+                SELECT folder_child_id
+                FROM folder_children
+                WHERE folder_child_id = ? OR song_id = ?"#,
+        )
+        .bind(id)
+        .bind(id)
+        .map(|row: SqliteRow| {
+            let folder_child_id: Uuid = row.get("folder_child_id");
+            folder_child_id
+        })
+        .fetch_optional(&mut *conn)
+        .await?;
+        folder_child_ids.extend(folder_child_id);
+    }
+
+    Ok(folder_child_ids)
+}
@@ -0,0 +1,70 @@
+use crate::api::create_share::expiry_date;
+use crate::api::format::SubsonicFormat;
+use crate::db::DbUser;
+use crate::{AppResult, AuthenticatedUser, Deserialize, SharedState};
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use sqlx::sqlite::SqliteRow;
+use sqlx::{Row, SqliteConnection};
+use std::ops::DerefMut;
+use uuid::Uuid;
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UpdateShareParams {
+    id: Uuid,
+    description: Option<String>,
+    /// Milliseconds since the epoch, or zero to never expire.
+    expires: Option<i64>,
+}
+
+pub async fn update_share(
+    format: SubsonicFormat,
+    AuthenticatedUser(current_user): AuthenticatedUser,
+    Query(params): Query<UpdateShareParams>,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    let mut conn = state.db.conn().await?;
+    if let Some(status) = check_share_editable(conn.deref_mut(), params.id, &current_user).await? {
+        return Ok((status, ()).into_response());
+    }
+
+    sqlx::query("UPDATE shares SET description = COALESCE(?, description) WHERE share_id = ?")
+        .bind(&params.description)
+        .bind(params.id)
+        .execute(conn.deref_mut())
+        .await?;
+    if let Some(expires) = params.expires {
+        sqlx::query("UPDATE shares SET expires = ? WHERE share_id = ?")
+            .bind(expiry_date(expires))
+            .bind(params.id)
+            .execute(conn.deref_mut())
+            .await?;
+    }
+
+    Ok(format.render::<()>(None))
+}
+
+/// Returns the status to respond with if the user can't change the share. Shares can be changed by
+/// the user that created them, and by admins.
+pub(crate) async fn check_share_editable(
+    conn: &mut SqliteConnection,
+    share_id: Uuid,
+    user: &DbUser,
+) -> AppResult<Option<StatusCode>> {
+    let owner = sqlx::query("SELECT username FROM shares WHERE share_id = ?")
+        .bind(share_id)
+        .map(|row: SqliteRow| {
+            let username: String = row.get("username");
+            username
+        })
+        .fetch_optional(conn)
+        .await?;
+
+    Ok(match owner {
+        Some(owner) if owner == user.username || user.admin_role => None,
+        Some(_) => Some(StatusCode::FORBIDDEN),
+        None => Some(StatusCode::NOT_FOUND),
+    })
+}
@@ -0,0 +1,55 @@
+use crate::api::auth::decode_password;
+use crate::api::create_user::UserParams;
+use crate::api::format::SubsonicFormat;
+use crate::{AppResult, AuthenticatedUser, SharedState};
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use std::ops::DerefMut;
+
+pub async fn update_user(
+    format: SubsonicFormat,
+    AuthenticatedUser(current_user): AuthenticatedUser,
+    Query(params): Query<UserParams>,
+    State(state): State<SharedState>,
+) -> AppResult<Response> {
+    if !current_user.admin_role {
+        return Ok((StatusCode::FORBIDDEN, ()).into_response());
+    }
+    // Admins can't lock themselves out of user management
+    if current_user.username == params.username && params.admin_role == Some(false) {
+        return Ok((StatusCode::BAD_REQUEST, ()).into_response());
+    }
+
+    let updated = sqlx::query(
+        r#"
+        UPDATE users SET
+            password = COALESCE(?, password),
+            email = COALESCE(?, email),
+            admin_role = COALESCE(?, admin_role),
+            settings_role = COALESCE(?, settings_role),
+            stream_role = COALESCE(?, stream_role),
+            download_role = COALESCE(?, download_role),
+            playlist_role = COALESCE(?, playlist_role),
+            share_role = COALESCE(?, share_role)
+        WHERE username = ?
+        "#,
+    )
+    .bind(params.password.as_deref().map(decode_password))
+    .bind(&params.email)
+    .bind(params.admin_role)
+    .bind(params.settings_role)
+    .bind(params.stream_role)
+    .bind(params.download_role)
+    .bind(params.playlist_role)
+    .bind(params.share_role)
+    .bind(&params.username)
+    .execute(state.db.conn().await?.deref_mut())
+    .await?
+    .rows_affected();
+
+    if updated == 0 {
+        return Ok((StatusCode::NOT_FOUND, ()).into_response());
+    }
+    Ok(format.render::<()>(None))
+}
@@ -0,0 +1,444 @@
+use crate::AppResult;
+use anyhow::anyhow;
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use std::path::PathBuf;
+use tokio::io::{AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+const LOCAL_FILE_HEADER_SIGNATURE: u32 = 0x04034b50;
+const DATA_DESCRIPTOR_SIGNATURE: u32 = 0x08074b50;
+const CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x02014b50;
+const ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x06064b50;
+const ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR_SIGNATURE: u32 = 0x07064b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x06054b50;
+const ZIP64_EXTRA_FIELD: u16 = 0x0001;
+const VERSION: u16 = 20;
+const ZIP64_VERSION: u16 = 45;
+// Sizes and CRC follow the file data, and names are UTF-8
+const FLAGS: u16 = 0x0008 | 0x0800;
+// Sizes, offsets and counts from these on are stored in ZIP64 records instead
+const ZIP64_SIZE: u64 = u32::MAX as u64;
+const ZIP64_COUNT: u64 = u16::MAX as u64;
+
+pub struct ZipEntry {
+    pub name: String,
+    pub path: PathBuf,
+}
+
+struct CentralDirectoryEntry {
+    name: String,
+    crc: u32,
+    size: u64,
+    offset: u64,
+    time: u16,
+    date: u16,
+}
+
+/// Writes the files as an uncompressed zip archive, using ZIP64 records where an archive grows
+/// beyond 4GB or 65535 files.
+///
+/// Nothing is buffered besides the central directory, so the archive can be sent to a client
+/// while it is being written. Audio files don't compress well, so the files are stored as-is.
+pub async fn write_zip(
+    entries: Vec<ZipEntry>,
+    mut writer: impl AsyncWrite + Unpin,
+) -> AppResult<()> {
+    let mut offset: u64 = 0;
+    let mut central_directory = vec![];
+    let mut buf = vec![0u8; 64 * 1024];
+
+    for entry in entries {
+        let mut file = tokio::fs::File::open(&entry.path).await?;
+        let metadata = file.metadata().await?;
+        let modified = metadata
+            .modified()
+            .map(DateTime::<Utc>::from)
+            .unwrap_or_default();
+        let (time, date) = dos_date_time(modified);
+        // Sizes follow the data, so whether they need 64 bits has to be decided up front
+        let zip64 = metadata.len() >= ZIP64_SIZE;
+        let local_offset = offset;
+
+        let header = local_file_header(&entry.name, time, date, zip64);
+        writer.write_all(&header).await?;
+        offset += header.len() as u64;
+
+        let mut hasher = crc32fast::Hasher::new();
+        let mut size: u64 = 0;
+        loop {
+            let read = file.read(&mut buf).await?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buf[..read]);
+            writer.write_all(&buf[..read]).await?;
+            size += read as u64;
+        }
+        offset += size;
+        if size >= ZIP64_SIZE && !zip64 {
+            return Err(anyhow!("{:?} grew while it was being zipped", entry.path).into());
+        }
+
+        let crc = hasher.finalize();
+        let descriptor = data_descriptor(crc, size, zip64);
+        writer.write_all(&descriptor).await?;
+        offset += descriptor.len() as u64;
+
+        central_directory.push(CentralDirectoryEntry {
+            name: entry.name,
+            crc,
+            size,
+            offset: local_offset,
+            time,
+            date,
+        });
+    }
+
+    let mut directory = vec![];
+    for entry in &central_directory {
+        directory.extend(central_directory_header(entry));
+    }
+    let end = end_of_central_directory(
+        central_directory.len() as u64,
+        directory.len() as u64,
+        offset,
+    );
+    directory.extend(end);
+    writer.write_all(&directory).await?;
+    writer.shutdown().await?;
+
+    Ok(())
+}
+
+fn local_file_header(name: &str, time: u16, date: u16, zip64: bool) -> Vec<u8> {
+    let mut header = vec![];
+    put_u32(&mut header, LOCAL_FILE_HEADER_SIGNATURE);
+    put_u16(&mut header, if zip64 { ZIP64_VERSION } else { VERSION });
+    put_u16(&mut header, FLAGS);
+    put_u16(&mut header, 0); // stored
+    put_u16(&mut header, time);
+    put_u16(&mut header, date);
+    put_u32(&mut header, 0); // crc, in data descriptor
+    if zip64 {
+        // The sizes in the extra field are in the data descriptor as well
+        put_u32(&mut header, u32::MAX);
+        put_u32(&mut header, u32::MAX);
+        put_u16(&mut header, name.len() as u16);
+        put_u16(&mut header, 20); // extra field length
+        header.extend_from_slice(name.as_bytes());
+        put_u16(&mut header, ZIP64_EXTRA_FIELD);
+        put_u16(&mut header, 16);
+        put_u64(&mut header, 0);
+        put_u64(&mut header, 0);
+    } else {
+        put_u32(&mut header, 0); // compressed size, in data descriptor
+        put_u32(&mut header, 0); // uncompressed size, in data descriptor
+        put_u16(&mut header, name.len() as u16);
+        put_u16(&mut header, 0); // extra field length
+        header.extend_from_slice(name.as_bytes());
+    }
+    header
+}
+
+fn data_descriptor(crc: u32, size: u64, zip64: bool) -> Vec<u8> {
+    let mut descriptor = vec![];
+    put_u32(&mut descriptor, DATA_DESCRIPTOR_SIGNATURE);
+    put_u32(&mut descriptor, crc);
+    if zip64 {
+        put_u64(&mut descriptor, size);
+        put_u64(&mut descriptor, size);
+    } else {
+        put_u32(&mut descriptor, size as u32);
+        put_u32(&mut descriptor, size as u32);
+    }
+    descriptor
+}
+
+fn central_directory_header(entry: &CentralDirectoryEntry) -> Vec<u8> {
+    // Only the values that don't fit are in the extra field, in this order
+    let mut extra = vec![];
+    if entry.size >= ZIP64_SIZE {
+        put_u64(&mut extra, entry.size);
+        put_u64(&mut extra, entry.size);
+    }
+    if entry.offset >= ZIP64_SIZE {
+        put_u64(&mut extra, entry.offset);
+    }
+    let version = if extra.is_empty() {
+        VERSION
+    } else {
+        ZIP64_VERSION
+    };
+
+    let mut header = vec![];
+    put_u32(&mut header, CENTRAL_DIRECTORY_SIGNATURE);
+    put_u16(&mut header, version); // version made by
+    put_u16(&mut header, version); // version needed to extract
+    put_u16(&mut header, FLAGS);
+    put_u16(&mut header, 0); // stored
+    put_u16(&mut header, entry.time);
+    put_u16(&mut header, entry.date);
+    put_u32(&mut header, entry.crc);
+    put_u32(&mut header, entry.size.min(ZIP64_SIZE) as u32);
+    put_u32(&mut header, entry.size.min(ZIP64_SIZE) as u32);
+    put_u16(&mut header, entry.name.len() as u16);
+    put_u16(
+        &mut header,
+        if extra.is_empty() {
+            0
+        } else {
+            4 + extra.len() as u16
+        },
+    );
+    put_u16(&mut header, 0); // comment length
+    put_u16(&mut header, 0); // disk number
+    put_u16(&mut header, 0); // internal attributes
+    put_u32(&mut header, 0); // external attributes
+    put_u32(&mut header, entry.offset.min(ZIP64_SIZE) as u32);
+    header.extend_from_slice(entry.name.as_bytes());
+    if !extra.is_empty() {
+        put_u16(&mut header, ZIP64_EXTRA_FIELD);
+        put_u16(&mut header, extra.len() as u16);
+        header.extend(extra);
+    }
+    header
+}
+
+/// The end of central directory record, preceded by the ZIP64 record and its locator when the
+/// archive is too big for the former.
+fn end_of_central_directory(
+    entry_count: u64,
+    central_directory_size: u64,
+    central_directory_offset: u64,
+) -> Vec<u8> {
+    let mut end = vec![];
+    if entry_count >= ZIP64_COUNT
+        || central_directory_size >= ZIP64_SIZE
+        || central_directory_offset >= ZIP64_SIZE
+    {
+        put_u32(&mut end, ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+        put_u64(&mut end, 44); // size of the rest of the record
+        put_u16(&mut end, ZIP64_VERSION); // version made by
+        put_u16(&mut end, ZIP64_VERSION); // version needed to extract
+        put_u32(&mut end, 0); // disk number
+        put_u32(&mut end, 0); // disk with central directory
+        put_u64(&mut end, entry_count);
+        put_u64(&mut end, entry_count);
+        put_u64(&mut end, central_directory_size);
+        put_u64(&mut end, central_directory_offset);
+
+        put_u32(&mut end, ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR_SIGNATURE);
+        put_u32(&mut end, 0); // disk with ZIP64 end of central directory
+        put_u64(&mut end, central_directory_offset + central_directory_size);
+        put_u32(&mut end, 1); // number of disks
+    }
+
+    put_u32(&mut end, END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+    put_u16(&mut end, 0); // disk number
+    put_u16(&mut end, 0); // disk with central directory
+    put_u16(&mut end, entry_count.min(ZIP64_COUNT) as u16);
+    put_u16(&mut end, entry_count.min(ZIP64_COUNT) as u16);
+    put_u32(&mut end, central_directory_size.min(ZIP64_SIZE) as u32);
+    put_u32(&mut end, central_directory_offset.min(ZIP64_SIZE) as u32);
+    put_u16(&mut end, 0); // comment length
+    end
+}
+
+fn put_u16(buf: &mut Vec<u8>, value: u16) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(buf: &mut Vec<u8>, value: u32) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u64(buf: &mut Vec<u8>, value: u64) {
+    buf.extend_from_slice(&value.to_le_bytes());
+}
+
+/// MS-DOS time and date, which can't represent anything before 1980.
+fn dos_date_time(date_time: DateTime<Utc>) -> (u16, u16) {
+    if date_time.year() < 1980 {
+        return (0, (1 << 5) | 1);
+    }
+
+    let time = (date_time.hour() << 11) | (date_time.minute() << 5) | (date_time.second() / 2);
+    let date =
+        (((date_time.year() - 1980) as u32) << 9) | (date_time.month() << 5) | date_time.day();
+    (time as u16, date as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn u16_at(buf: &[u8], pos: usize) -> usize {
+        u16::from_le_bytes([buf[pos], buf[pos + 1]]) as usize
+    }
+
+    fn u32_at(buf: &[u8], pos: usize) -> usize {
+        u32::from_le_bytes(buf[pos..pos + 4].try_into().unwrap()) as usize
+    }
+
+    fn u64_at(buf: &[u8], pos: usize) -> usize {
+        u64::from_le_bytes(buf[pos..pos + 8].try_into().unwrap()) as usize
+    }
+
+    /// Reads the names and contents of a zip archive through its central directory.
+    fn read_zip(zip: &[u8]) -> Vec<(String, Vec<u8>)> {
+        let eocd = zip.len() - 22;
+        assert_eq!(
+            u32_at(zip, eocd),
+            END_OF_CENTRAL_DIRECTORY_SIGNATURE as usize
+        );
+        let (count, mut pos) = if u16_at(zip, eocd + 10) == u16::MAX as usize {
+            let locator = eocd - 20;
+            assert_eq!(
+                u32_at(zip, locator),
+                ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR_SIGNATURE as usize
+            );
+            let zip64_eocd = u64_at(zip, locator + 8);
+            assert_eq!(
+                u32_at(zip, zip64_eocd),
+                ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE as usize
+            );
+            (u64_at(zip, zip64_eocd + 32), u64_at(zip, zip64_eocd + 48))
+        } else {
+            (u16_at(zip, eocd + 10), u32_at(zip, eocd + 16))
+        };
+
+        (0..count)
+            .map(|_| {
+                assert_eq!(u32_at(zip, pos), CENTRAL_DIRECTORY_SIGNATURE as usize);
+                let crc = u32_at(zip, pos + 16) as u32;
+                let size = u32_at(zip, pos + 24);
+                let name_len = u16_at(zip, pos + 28);
+                let extra_len = u16_at(zip, pos + 30);
+                let local = u32_at(zip, pos + 42);
+                let name = String::from_utf8(zip[pos + 46..pos + 46 + name_len].to_vec()).unwrap();
+                pos += 46 + name_len + extra_len;
+
+                assert_eq!(u32_at(zip, local), LOCAL_FILE_HEADER_SIGNATURE as usize);
+                let data_start = local + 30 + u16_at(zip, local + 26);
+                let data = zip[data_start..data_start + size].to_vec();
+                assert_eq!(crc32fast::hash(&data), crc);
+                (name, data)
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn can_write_zip() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.txt"), b"hello").unwrap();
+        std::fs::write(dir.path().join("b.txt"), b"").unwrap();
+
+        let mut zip = vec![];
+        write_zip(
+            vec![
+                ZipEntry {
+                    name: "a.txt".to_string(),
+                    path: dir.path().join("a.txt"),
+                },
+                ZipEntry {
+                    name: "sub/b.txt".to_string(),
+                    path: dir.path().join("b.txt"),
+                },
+            ],
+            &mut zip,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            read_zip(&zip),
+            vec![
+                ("a.txt".to_string(), b"hello".to_vec()),
+                ("sub/b.txt".to_string(), vec![])
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn can_write_zip_with_many_files() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.txt"), b"a").unwrap();
+
+        let count = ZIP64_COUNT as usize + 1;
+        let mut zip = vec![];
+        write_zip(
+            (0..count)
+                .map(|i| ZipEntry {
+                    name: format!("{i}.txt"),
+                    path: dir.path().join("a.txt"),
+                })
+                .collect(),
+            &mut zip,
+        )
+        .await
+        .unwrap();
+
+        let files = read_zip(&zip);
+        assert_eq!(files.len(), count);
+        assert_eq!(
+            files[count - 1],
+            (format!("{}.txt", count - 1), b"a".to_vec())
+        );
+    }
+
+    #[test]
+    fn can_write_zip64_headers() {
+        let size = 5 * ZIP64_SIZE;
+        let offset = 6 * ZIP64_SIZE;
+
+        let header = local_file_header("a", 0, 0, true);
+        assert_eq!(u16_at(&header, 4), ZIP64_VERSION as usize);
+        assert_eq!(u32_at(&header, 22), u32::MAX as usize);
+        assert_eq!(u16_at(&header, 28), 20);
+        assert_eq!(u16_at(&header, 31), ZIP64_EXTRA_FIELD as usize);
+
+        let descriptor = data_descriptor(0, size, true);
+        assert_eq!(descriptor.len(), 24);
+        assert_eq!(u64_at(&descriptor, 16), size as usize);
+
+        let header = central_directory_header(&CentralDirectoryEntry {
+            name: "a".to_string(),
+            crc: 0,
+            size,
+            offset,
+            time: 0,
+            date: 0,
+        });
+        assert_eq!(u32_at(&header, 24), u32::MAX as usize);
+        assert_eq!(u32_at(&header, 42), u32::MAX as usize);
+        assert_eq!(u16_at(&header, 30), 28);
+        assert_eq!(u16_at(&header, 47), ZIP64_EXTRA_FIELD as usize);
+        assert_eq!(u64_at(&header, 51), size as usize);
+        assert_eq!(u64_at(&header, 59), size as usize);
+        assert_eq!(u64_at(&header, 67), offset as usize);
+
+        // Small archives only have the regular record
+        assert_eq!(end_of_central_directory(1, 100, 1000).len(), 22);
+
+        let end = end_of_central_directory(1, 100, offset);
+        assert_eq!(end.len(), 56 + 20 + 22);
+        assert_eq!(
+            u32_at(&end, 0),
+            ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE as usize
+        );
+        assert_eq!(u64_at(&end, 48), offset as usize);
+        assert_eq!(u64_at(&end, 64), (offset + 100) as usize);
+        assert_eq!(u32_at(&end, 92), u32::MAX as usize);
+    }
+
+    #[test]
+    fn can_convert_dos_date_time() {
+        let date_time = DateTime::parse_from_rfc3339("2020-02-03T04:05:06Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        assert_eq!(
+            dos_date_time(date_time),
+            ((4 << 11) | (5 << 5) | 3, (40 << 9) | (2 << 5) | 3)
+        );
+        assert_eq!(dos_date_time(DateTime::default()), (0, 0x21));
+    }
+}
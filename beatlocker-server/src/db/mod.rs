@@ -1,18 +1,20 @@
 mod db_pool;
 mod model;
+mod search;
 
 pub use model::*;
+pub use search::*;
 use std::fmt::{Debug, Formatter};
 use std::ops::DerefMut;
 use std::path::PathBuf;
 
-use crate::AppResult;
+use crate::{match_name, sort_key, AppResult};
 use chrono::Duration;
 use db_pool::DbPool;
 use deadpool::managed::{Object, Pool};
 use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqliteRow, SqliteSynchronous};
 use sqlx::types::Uuid;
-use sqlx::Row;
+use sqlx::{Connection, Row, SqliteConnection};
 use std::str::FromStr;
 use tracing::debug;
 
@@ -83,18 +85,24 @@ impl Db {
 
         let id = sqlx::query(
             r#"
-        INSERT INTO albums (album_id, title, cover_art_id)
-        VALUES (?, ?, ?)
-        ON CONFLICT (album_id) DO UPDATE set album_id = album_id
+        INSERT INTO albums (album_id, title, cover_art_id, sort_name, sort_key)
+        VALUES (?, ?, ?, ?, ?)
+        ON CONFLICT (album_id) DO UPDATE SET
+            sort_name = COALESCE(excluded.sort_name, sort_name),
+            sort_key = CASE WHEN excluded.sort_name IS NULL THEN sort_key ELSE excluded.sort_key END
         RETURNING album_id
         "#,
         )
         .bind(album.album_id)
         .bind(&album.title)
         .bind(album.cover_art_id)
+        .bind(&album.sort_name)
+        .bind(&album.sort_key)
         .map(|row| row.get("album_id"))
         .fetch_one(self.conn().await?.deref_mut())
         .await?;
+        self.insert_search_item_if_not_exists(id, "album", &album.title)
+            .await?;
 
         Ok(id)
     }
@@ -104,9 +112,11 @@ impl Db {
 
         let id = sqlx::query(
             r#"
-        INSERT INTO artists (artist_id, name, cover_art_id, musicbrainz_id)
-        VALUES (?, ?, ?, ?)
-        ON CONFLICT (artist_id) DO UPDATE set artist_id = artist_id
+        INSERT INTO artists (artist_id, name, cover_art_id, musicbrainz_id, sort_name, sort_key, match_name)
+        VALUES (?, ?, ?, ?, ?, ?, ?)
+        ON CONFLICT (artist_id) DO UPDATE SET
+            sort_name = COALESCE(excluded.sort_name, sort_name),
+            sort_key = CASE WHEN excluded.sort_name IS NULL THEN sort_key ELSE excluded.sort_key END
         RETURNING artist_id
         "#,
         )
@@ -114,9 +124,14 @@ impl Db {
         .bind(&artist.name)
         .bind(artist.cover_art_id)
         .bind(&artist.musicbrainz_id)
+        .bind(&artist.sort_name)
+        .bind(&artist.sort_key)
+        .bind(match_name(&artist.name))
         .map(|row| row.get("artist_id"))
         .fetch_one(self.conn().await?.deref_mut())
         .await?;
+        self.insert_search_item_if_not_exists(id, "artist", &artist.name)
+            .await?;
 
         Ok(id)
     }
@@ -160,6 +175,8 @@ impl Db {
                 name: row.get("name"),
                 cover_art_id: row.get("cover_art_id"),
                 musicbrainz_id: row.get("musicbrainz_id"),
+                sort_name: row.get("sort_name"),
+                sort_key: row.get("sort_key"),
             })
             .fetch_optional(self.conn().await?.deref_mut())
             .await?;
@@ -174,6 +191,8 @@ impl Db {
                 album_id: row.get("album_id"),
                 title: row.get("title"),
                 cover_art_id: row.get("cover_art_id"),
+                sort_name: row.get("sort_name"),
+                sort_key: row.get("sort_key"),
             })
             .fetch_optional(self.conn().await?.deref_mut())
             .await?;
@@ -217,21 +236,25 @@ impl Db {
 
         let id = sqlx::query(
             r#"
-        INSERT INTO folders (folder_id, parent_id, name, path, cover_art_id, created)
-        VALUES (?, ?, ?, ?, ?, ?)
+        INSERT INTO folders (folder_id, parent_id, music_folder_id, name, path, cover_art_id, created, sort_key)
+        VALUES (?, ?, ?, ?, ?, ?, ?, ?)
         ON CONFLICT (folder_id) DO UPDATE set folder_id = folder_id
         RETURNING folder_id
         "#,
         )
         .bind(folder.folder_id)
         .bind(folder.parent_id)
+        .bind(folder.music_folder_id)
         .bind(&folder.name)
         .bind(&folder.path)
         .bind(folder.cover_art_id)
         .bind(folder.created)
+        .bind(&folder.sort_key)
         .map(|row| row.get("folder_id"))
         .fetch_one(self.conn().await?.deref_mut())
         .await?;
+        self.insert_search_item_if_not_exists(id, "folder", &folder.name)
+            .await?;
 
         Ok(id)
     }
@@ -315,10 +338,223 @@ impl Db {
             .map(|row| row.get("song_id"))
         .fetch_one(self.conn().await?.deref_mut())
         .await?;
+        self.insert_search_item_if_not_exists(id, "song", &song.title)
+            .await?;
 
         Ok(id)
     }
 
+    /// Uses local (embedded or sidecar) cover art for an album, unless it already uses the art of
+    /// one of its songs. This replaces any art that was found online.
+    pub async fn update_album_local_cover_art(
+        &self,
+        album_id: Uuid,
+        cover_art_id: Uuid,
+    ) -> AppResult<()> {
+        sqlx::query(
+            r#"
+        UPDATE albums SET cover_art_id = ?
+        WHERE album_id = ?
+        AND (cover_art_id IS NULL OR cover_art_id NOT IN
+            (SELECT cover_art_id FROM songs WHERE album_id = ? AND cover_art_id IS NOT NULL))
+        "#,
+        )
+        .bind(cover_art_id)
+        .bind(album_id)
+        .bind(album_id)
+        .execute(self.conn().await?.deref_mut())
+        .await?;
+
+        Ok(())
+    }
+
+    /// Replaces the cover art of a folder, and of the songs and albums in it that were using the
+    /// folder's previous cover art or had none at all.
+    pub async fn update_folder_cover_art(
+        &self,
+        folder_id: Uuid,
+        cover_art_id: Option<Uuid>,
+    ) -> AppResult<()> {
+        let mut conn = self.conn().await?;
+        let previous: Option<Option<Uuid>> =
+            sqlx::query("SELECT cover_art_id FROM folders WHERE folder_id = ?")
+                .bind(folder_id)
+                .map(|row: SqliteRow| row.get("cover_art_id"))
+                .fetch_optional(conn.deref_mut())
+                .await?;
+        let previous = match previous {
+            Some(previous) if previous != cover_art_id => previous,
+            _ => return Ok(()),
+        };
+        debug!(
+            ?folder_id,
+            ?previous,
+            ?cover_art_id,
+            "Updating folder cover art"
+        );
+
+        sqlx::query(
+            r#"
+        UPDATE albums SET cover_art_id = ?
+        WHERE album_id IN
+            (SELECT s.album_id FROM songs s JOIN folder_children fc ON fc.song_id = s.song_id WHERE fc.folder_id = ?)
+        AND (cover_art_id IS NULL OR cover_art_id = ?);
+
+        UPDATE songs SET cover_art_id = ?
+        WHERE song_id IN (SELECT song_id FROM folder_children WHERE folder_id = ?)
+        AND (cover_art_id IS NULL OR cover_art_id = ?);
+
+        UPDATE folders SET cover_art_id = ? WHERE folder_id = ?;
+        "#,
+        )
+        .bind(cover_art_id)
+        .bind(folder_id)
+        .bind(previous)
+        .bind(cover_art_id)
+        .bind(folder_id)
+        .bind(previous)
+        .bind(cover_art_id)
+        .bind(folder_id)
+        .execute(conn.deref_mut())
+        .await?;
+
+        Ok(())
+    }
+
+    /// Recomputes the sort keys of all folders, artists and albums from their sort name or name.
+    pub async fn update_sort_keys(&self, ignored_articles: &[String]) -> AppResult<()> {
+        let mut conn = self.conn().await?;
+        for (table, id_column, name_column, sort_name_column) in [
+            ("folders", "folder_id", "name", "NULL"),
+            ("artists", "artist_id", "name", "sort_name"),
+            ("albums", "album_id", "title", "sort_name"),
+        ] {
+            let rows: Vec<(Uuid, String, Option<String>)> = sqlx::query(&format!(
+                "SELECT {id_column} AS id, COALESCE({sort_name_column}, {name_column}) AS name, sort_key FROM {table}"
+            ))
+            .map(|row: SqliteRow| (row.get("id"), row.get("name"), row.get("sort_key")))
+            .fetch_all(conn.deref_mut())
+            .await?;
+
+            for (id, name, previous) in rows {
+                let key = sort_key(&name, ignored_articles);
+                if previous.as_ref() != Some(&key) {
+                    sqlx::query(&format!(
+                        "UPDATE {table} SET sort_key = ? WHERE {id_column} = ?"
+                    ))
+                    .bind(key)
+                    .bind(id)
+                    .execute(conn.deref_mut())
+                    .await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Derives the match names of artists that were imported before artists had them.
+    pub async fn update_match_names(&self) -> AppResult<()> {
+        let mut conn = self.conn().await?;
+        let rows: Vec<(Uuid, String)> =
+            sqlx::query("SELECT artist_id, name FROM artists WHERE match_name IS NULL")
+                .map(|row: SqliteRow| (row.get("artist_id"), row.get("name")))
+                .fetch_all(conn.deref_mut())
+                .await?;
+
+        for (id, name) in rows {
+            sqlx::query("UPDATE artists SET match_name = ? WHERE artist_id = ?")
+                .bind(match_name(&name))
+                .bind(id)
+                .execute(conn.deref_mut())
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Adds a song, album, artist or folder to the search index. Items are removed from it again by
+    /// the removed deleted files task.
+    async fn insert_search_item_if_not_exists(
+        &self,
+        item_id: Uuid,
+        item_type: &str,
+        name: &str,
+    ) -> AppResult<()> {
+        sqlx::query(
+            r#"
+        INSERT OR IGNORE INTO search_items (item_id, item_type, name)
+        VALUES (?, ?, ?)
+        "#,
+        )
+        .bind(item_id)
+        .bind(item_type)
+        .bind(normalize_search_text(name))
+        .execute(self.conn().await?.deref_mut())
+        .await?;
+
+        Ok(())
+    }
+
+    /// Replaces all songs of a playlist, keeping them in the given order.
+    pub async fn set_playlist_entries(
+        &self,
+        playlist_id: Uuid,
+        folder_child_ids: &[Uuid],
+    ) -> AppResult<()> {
+        let mut conn = self.conn().await?;
+        let mut tx = conn.begin().await?;
+        write_playlist_entries(&mut tx, playlist_id, folder_child_ids).await?;
+        tx.commit().await?;
+
+        Ok(())
+    }
+
+    pub async fn find_user(&self, username: &str) -> AppResult<Option<DbUser>> {
+        Ok(sqlx::query("SELECT * FROM users WHERE username = ?")
+            .bind(username)
+            .map(map_row_to_db_user)
+            .fetch_optional(self.conn().await?.deref_mut())
+            .await?)
+    }
+
+    pub async fn find_users(&self) -> AppResult<Vec<DbUser>> {
+        Ok(sqlx::query("SELECT * FROM users ORDER BY username")
+            .map(map_row_to_db_user)
+            .fetch_all(self.conn().await?.deref_mut())
+            .await?)
+    }
+
+    /// Makes sure an admin with all roles exists. A given password replaces the current one, so the
+    /// configured password always works; without one the current password is kept.
+    pub async fn seed_admin(&self, username: &str, password: Option<&str>) -> AppResult<()> {
+        sqlx::query(
+            r#"
+        INSERT INTO users (username, password, admin_role, settings_role, stream_role, download_role, playlist_role, share_role)
+        VALUES (?, ?, true, true, true, true, true, true)
+        ON CONFLICT (username) DO UPDATE SET password = COALESCE(excluded.password, password), admin_role = true
+        "#,
+        )
+        .bind(username)
+        .bind(password)
+        .execute(self.conn().await?.deref_mut())
+        .await?;
+
+        // Stars from before there were users belong to nobody, until now
+        let mut conn = self.conn().await?;
+        sqlx::query(
+            "UPDATE OR IGNORE starred SET username = ? WHERE username NOT IN (SELECT username FROM users)",
+        )
+        .bind(username)
+        .execute(conn.deref_mut())
+        .await?;
+        sqlx::query("DELETE FROM starred WHERE username NOT IN (SELECT username FROM users)")
+            .execute(conn.deref_mut())
+            .await?;
+
+        Ok(())
+    }
+
     pub async fn insert_cover_art_if_not_exists(&self, cover_art: &DbCoverArt) -> AppResult<Uuid> {
         let cover_art_id = cover_art.cover_art_id.to_string();
         debug!(cover_art_id, "Inserting cover art");
@@ -341,6 +577,32 @@ impl Db {
     }
 }
 
+/// Replaces all songs of a playlist like [`Db::set_playlist_entries`], on a connection that may be
+/// in a transaction, so the entries can be read and written together.
+pub async fn write_playlist_entries(
+    conn: &mut SqliteConnection,
+    playlist_id: Uuid,
+    folder_child_ids: &[Uuid],
+) -> AppResult<()> {
+    sqlx::query("DELETE FROM playlist_entries WHERE playlist_id = ?")
+        .bind(playlist_id)
+        .execute(&mut *conn)
+        .await?;
+
+    for (position, folder_child_id) in folder_child_ids.iter().enumerate() {
+        sqlx::query(
+            "INSERT INTO playlist_entries (playlist_id, position, folder_child_id) VALUES (?, ?, ?)",
+        )
+        .bind(playlist_id)
+        .bind(position as u32)
+        .bind(folder_child_id)
+        .execute(&mut *conn)
+        .await?;
+    }
+
+    Ok(())
+}
+
 fn map_row_to_db_song(row: SqliteRow) -> DbSong {
     let duration: Option<u32> = row.get("duration");
     DbSong {
@@ -362,9 +624,24 @@ fn map_row_to_db_song(row: SqliteRow) -> DbSong {
     }
 }
 
+fn map_row_to_db_user(row: SqliteRow) -> DbUser {
+    DbUser {
+        username: row.get("username"),
+        password: row.get("password"),
+        email: row.get("email"),
+        admin_role: row.get("admin_role"),
+        settings_role: row.get("settings_role"),
+        stream_role: row.get("stream_role"),
+        download_role: row.get("download_role"),
+        playlist_role: row.get("playlist_role"),
+        share_role: row.get("share_role"),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use chrono::Utc;
 
     #[tokio::test]
     async fn can_migrate() -> AppResult<()> {
@@ -375,4 +652,56 @@ mod tests {
         db.migrate().await?;
         Ok(())
     }
+
+    #[tokio::test]
+    async fn can_seed_admin() -> AppResult<()> {
+        let db = Db::new(&DatabaseOptions {
+            path: None,
+            in_memory: true,
+        })?;
+        db.migrate().await?;
+
+        db.seed_admin("joe", None).await?;
+        let user = db.find_user("joe").await?.unwrap();
+        assert_eq!(user.password, None);
+        assert!(user.admin_role && user.download_role && user.share_role);
+
+        // The configured password replaces the current one, which is kept without one
+        db.seed_admin("joe", Some("sesame")).await?;
+        db.seed_admin("joe", Some("other")).await?;
+        db.seed_admin("joe", None).await?;
+        assert_eq!(
+            db.find_user("joe").await?.unwrap().password.as_deref(),
+            Some("other")
+        );
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn seeded_admin_gets_stars_without_user() -> AppResult<()> {
+        let db = Db::new(&DatabaseOptions {
+            path: None,
+            in_memory: true,
+        })?;
+        db.migrate().await?;
+
+        // Stars that were migrated, and one the admin already had
+        let shared_id = Uuid::new_v4();
+        for (starred_id, username) in [(shared_id, ""), (Uuid::new_v4(), ""), (shared_id, "joe")] {
+            sqlx::query("INSERT INTO starred (starred_id, username, created) VALUES (?, ?, ?)")
+                .bind(starred_id)
+                .bind(username)
+                .bind(Utc::now())
+                .execute(db.conn().await?.deref_mut())
+                .await?;
+        }
+
+        db.seed_admin("joe", None).await?;
+        let usernames: Vec<String> = sqlx::query("SELECT username FROM starred")
+            .map(|row: SqliteRow| row.get("username"))
+            .fetch_all(db.conn().await?.deref_mut())
+            .await?;
+        assert_eq!(usernames, vec!["joe", "joe"]);
+        Ok(())
+    }
 }
@@ -5,10 +5,13 @@ use sqlx::types::Uuid;
 pub struct DbFolder {
     pub folder_id: Uuid,
     pub parent_id: Option<Uuid>,
+    /// The root folder this folder is in, or its own id for a root folder.
+    pub music_folder_id: Uuid,
     pub name: String,
     pub path: String,
     pub cover_art_id: Option<Uuid>,
     pub created: DateTime<Utc>,
+    pub sort_key: String,
 }
 
 #[derive(Debug, Default, PartialEq, Eq)]
@@ -33,6 +36,8 @@ pub struct DbAlbum {
     pub album_id: Uuid,
     pub title: String,
     pub cover_art_id: Option<Uuid>,
+    pub sort_name: Option<String>,
+    pub sort_key: String,
 }
 
 #[derive(Debug, Default, PartialEq, Eq)]
@@ -41,6 +46,8 @@ pub struct DbArtist {
     pub name: String,
     pub cover_art_id: Option<Uuid>,
     pub musicbrainz_id: Option<String>,
+    pub sort_name: Option<String>,
+    pub sort_key: String,
 }
 
 #[derive(Debug, Default, PartialEq, Eq)]
@@ -67,3 +74,16 @@ pub struct DbCoverArt {
     pub cover_art_id: Uuid,
     pub data: Vec<u8>,
 }
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DbUser {
+    pub username: String,
+    pub password: Option<String>,
+    pub email: Option<String>,
+    pub admin_role: bool,
+    pub settings_role: bool,
+    pub stream_role: bool,
+    pub download_role: bool,
+    pub playlist_role: bool,
+    pub share_role: bool,
+}
@@ -0,0 +1,43 @@
+use unidecode::unidecode;
+
+/// Normalizes text for the search index, so accents can be left out and non-latin names can be
+/// found with a latin keyboard.
+pub fn normalize_search_text(text: &str) -> String {
+    unidecode(text).to_lowercase()
+}
+
+/// Turns a search query into an FTS5 query that matches items containing all of its words, as
+/// prefixes. Returns `None` if there are no words, which clients use to list everything.
+pub fn search_match_query(query: &str) -> Option<String> {
+    let query = normalize_search_text(query);
+    let words: Vec<String> = query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("\"{word}\"*"))
+        .collect();
+
+    if words.is_empty() {
+        None
+    } else {
+        Some(words.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_build_match_query() {
+        assert_eq!(search_match_query("\"\""), None);
+        assert_eq!(search_match_query("  "), None);
+        assert_eq!(
+            search_match_query("Sigur Rós"),
+            Some("\"sigur\"* \"ros\"*".to_string())
+        );
+        assert_eq!(
+            search_match_query("AC/DC \"live\" OR"),
+            Some("\"ac\"* \"dc\"* \"live\"* \"or\"*".to_string())
+        );
+    }
+}
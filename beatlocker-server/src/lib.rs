@@ -3,15 +3,20 @@ mod api;
 mod db;
 mod errors;
 mod tasks;
+mod thumbnails;
+mod transcoding;
 mod utils;
 
 pub use api::*;
 pub use db::DatabaseOptions;
 pub use tasks::*;
+pub use thumbnails::ThumbnailCacheOptions;
+pub use transcoding::TranscodeProfile;
 pub use utils::*;
 
 use crate::db::Db;
 use crate::errors::AppError;
+use crate::thumbnails::ThumbnailCache;
 use axum::http::{HeaderMap, HeaderValue, Method};
 
 use axum::{routing::get, Router};
@@ -23,6 +28,7 @@ use axum::middleware::from_extractor_with_state;
 use reqwest_retry::policies::ExponentialBackoff;
 use std::fmt::{Debug, Formatter};
 use std::path::PathBuf;
+use std::str::FromStr;
 use std::string::ToString;
 use std::sync::Arc;
 use tower_http::cors::CorsLayer;
@@ -30,21 +36,64 @@ use tower_http::trace::TraceLayer;
 use tracing::level_filters::LevelFilter;
 
 use tracing_subscriber::{EnvFilter, FmtSubscriber};
+use uuid::Uuid;
 
 pub const SERVER_VERSION: &str = git_version::git_version!();
 pub const USER_AGENT: &str = formatcp!("beatlocker/{}", SERVER_VERSION);
 pub type AppResult<T> = Result<T, AppError>;
 
+/// Images next to audio files that are used as cover art, in order of preference.
+pub const DEFAULT_COVER_ART_FILENAMES: &[&str] = &[
+    "cover.jpg",
+    "cover.jpeg",
+    "cover.png",
+    "folder.jpg",
+    "folder.jpeg",
+    "folder.png",
+    "front.jpg",
+    "front.jpeg",
+    "front.png",
+    "album.jpg",
+    "album.jpeg",
+    "album.png",
+];
+
+/// Leading articles that are ignored when sorting and indexing names, so "The Beatles" is listed under B.
+pub const DEFAULT_IGNORED_ARTICLES: &[&str] = &[
+    "The", "El", "La", "Los", "Las", "Le", "Les", "Os", "As", "O", "A",
+];
+
+pub const DEFAULT_LASTFM_URL: &str = "http://ws.audioscrobbler.com/2.0/";
+pub const DEFAULT_LISTENBRAINZ_URL: &str = "https://api.listenbrainz.org";
+
 #[derive(Clone)]
 pub struct ServerOptions {
-    pub path: PathBuf,
+    /// Library roots, listed as music folders. Must contain at least one.
+    pub music_folders: Vec<MusicFolderOptions>,
     pub database: DatabaseOptions,
     pub import_external_metadata: bool,
     pub server_version: String,
     pub discogs_token: Option<String>,
     pub lastfm_api_key: Option<String>,
+    /// Needed to forward plays to last.fm, together with the API key.
+    pub lastfm_api_secret: Option<String>,
+    pub lastfm_url: String,
+    pub listenbrainz_url: String,
     pub now_provider: Arc<Box<dyn Fn() -> DateTime<Utc> + Send + Sync>>,
     pub subsonic_auth: SubsonicAuth,
+    pub transcode_profiles: Vec<TranscodeProfile>,
+    pub thumbnail_cache: ThumbnailCacheOptions,
+    pub cover_art_filenames: Vec<String>,
+    pub ignored_articles: Vec<String>,
+    /// Allows streaming internet radio stations through the server, by passing the id of the
+    /// station to `stream`. Stations are then listed with that `stream` URL.
+    pub relay_internet_radio: bool,
+    /// Folder that podcast episodes are downloaded into.
+    pub podcast_path: PathBuf,
+    /// URL the server can be reached at from outside, e.g. `https://music.example.com`, which share
+    /// links and the URLs of relayed stations start with. Without it, they use the URL the client
+    /// used.
+    pub external_url: Option<String>,
 }
 
 impl Debug for ServerOptions {
@@ -56,7 +105,7 @@ impl Debug for ServerOptions {
 impl Default for ServerOptions {
     fn default() -> Self {
         Self {
-            path: PathBuf::from("."),
+            music_folders: vec![MusicFolderOptions::main(".")],
             database: DatabaseOptions {
                 path: None,
                 in_memory: true,
@@ -65,8 +114,24 @@ impl Default for ServerOptions {
             import_external_metadata: false,
             discogs_token: None,
             lastfm_api_key: None,
+            lastfm_api_secret: None,
+            lastfm_url: DEFAULT_LASTFM_URL.to_string(),
+            listenbrainz_url: DEFAULT_LISTENBRAINZ_URL.to_string(),
             now_provider: Arc::new(Box::new(Utc::now)),
             subsonic_auth: SubsonicAuth::None,
+            transcode_profiles: vec![],
+            thumbnail_cache: ThumbnailCacheOptions::default(),
+            cover_art_filenames: DEFAULT_COVER_ART_FILENAMES
+                .iter()
+                .map(|f| f.to_string())
+                .collect(),
+            ignored_articles: DEFAULT_IGNORED_ARTICLES
+                .iter()
+                .map(|a| a.to_string())
+                .collect(),
+            relay_internet_radio: false,
+            podcast_path: std::env::temp_dir().join("beatlocker-podcasts"),
+            external_url: None,
         }
     }
 }
@@ -78,6 +143,48 @@ pub struct App {
     pub task_manager: Arc<TaskManager>,
 }
 
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct MusicFolderOptions {
+    pub id: Uuid,
+    pub name: String,
+    pub path: PathBuf,
+}
+
+impl MusicFolderOptions {
+    /// A music folder identified by its name, so it keeps its id when it's moved elsewhere.
+    pub fn new(name: &str, path: impl Into<PathBuf>) -> Self {
+        Self {
+            id: str_to_uuid(name),
+            name: name.to_string(),
+            path: path.into(),
+        }
+    }
+
+    /// The music folder of the library path. It keeps the nil id from when it was the only music
+    /// folder, so existing databases and clients keep working.
+    pub fn main(path: impl Into<PathBuf>) -> Self {
+        Self {
+            id: Uuid::nil(),
+            name: "Music".to_string(),
+            path: path.into(),
+        }
+    }
+}
+
+impl FromStr for MusicFolderOptions {
+    type Err = String;
+
+    /// Parses a music folder in the form `name:path`, e.g. `Audiobooks:/mnt/audiobooks`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some((name, path)) if !name.trim().is_empty() && !path.trim().is_empty() => {
+                Ok(MusicFolderOptions::new(name.trim(), path.trim()))
+            }
+            _ => Err(format!("Invalid music folder '{s}', expected name:path")),
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum SubsonicAuth {
     None,
@@ -88,6 +195,7 @@ pub enum SubsonicAuth {
 pub struct AppState {
     pub options: ServerOptions,
     pub db: Arc<Db>,
+    pub thumbnails: Arc<ThumbnailCache>,
 }
 
 type SharedState = Arc<AppState>;
@@ -97,16 +205,79 @@ impl App {
         let state: SharedState = Arc::new(AppState {
             options: options.clone(),
             db: Arc::new(Db::new(&options.database)?),
+            thumbnails: Arc::new(ThumbnailCache::new(&options.thumbnail_cache)?),
         });
         state.db.migrate().await?;
+        match &options.subsonic_auth {
+            SubsonicAuth::UsernamePassword { username, password } => {
+                state.db.seed_admin(username, Some(password)).await?
+            }
+            SubsonicAuth::None => state.db.seed_admin(DEFAULT_USERNAME, None).await?,
+        }
+        // The ignored articles may have changed since the library was imported
+        state.db.update_sort_keys(&options.ignored_articles).await?;
+        state.db.update_match_names().await?;
 
         let task_manager = Arc::new(TaskManager::new(2)?);
 
         let rest_routes = Router::new()
             .route("/ping", get(ping))
             .route("/ping.view", get(ping))
+            .route("/download", get(download))
+            .route("/download.view", get(download))
+            .route("/changePassword", get(change_password))
+            .route("/changePassword.view", get(change_password))
+            .route("/createBookmark", get(create_bookmark))
+            .route("/createBookmark.view", get(create_bookmark))
+            .route(
+                "/createInternetRadioStation",
+                get(create_internet_radio_station),
+            )
+            .route(
+                "/createInternetRadioStation.view",
+                get(create_internet_radio_station),
+            )
+            .route("/createPlaylist", get(create_playlist))
+            .route("/createPlaylist.view", get(create_playlist))
+            .route("/createPodcastChannel", get(create_podcast_channel))
+            .route("/createPodcastChannel.view", get(create_podcast_channel))
+            .route("/createShare", get(create_share))
+            .route("/createShare.view", get(create_share))
+            .route("/createUser", get(create_user))
+            .route("/createUser.view", get(create_user))
+            .route("/deleteBookmark", get(delete_bookmark))
+            .route("/deleteBookmark.view", get(delete_bookmark))
+            .route(
+                "/deleteInternetRadioStation",
+                get(delete_internet_radio_station),
+            )
+            .route(
+                "/deleteInternetRadioStation.view",
+                get(delete_internet_radio_station),
+            )
+            .route("/deletePlaylist", get(delete_playlist))
+            .route("/deletePlaylist.view", get(delete_playlist))
+            .route("/deletePodcastChannel", get(delete_podcast_channel))
+            .route("/deletePodcastChannel.view", get(delete_podcast_channel))
+            .route("/deletePodcastEpisode", get(delete_podcast_episode))
+            .route("/deletePodcastEpisode.view", get(delete_podcast_episode))
+            .route("/deleteShare", get(delete_share))
+            .route("/deleteShare.view", get(delete_share))
+            .route("/deleteUser", get(delete_user))
+            .route("/deleteUser.view", get(delete_user))
+            .route("/downloadPodcastEpisode", get(download_podcast_episode))
+            .route(
+                "/downloadPodcastEpisode.view",
+                get(download_podcast_episode),
+            )
+            .route("/exportPlaylist", get(export_playlist))
+            .route("/exportPlaylist.view", get(export_playlist))
             .route("/getAlbum", get(get_album))
             .route("/getAlbum.view", get(get_album))
+            .route("/getAlbumInfo", get(get_album_info))
+            .route("/getAlbumInfo.view", get(get_album_info))
+            .route("/getAlbumInfo2", get(get_album_info2))
+            .route("/getAlbumInfo2.view", get(get_album_info2))
             .route("/getAlbumList", get(get_album_list))
             .route("/getAlbumList.view", get(get_album_list))
             .route("/getAlbumList2", get(get_album_list2))
@@ -119,50 +290,122 @@ impl App {
             .route("/getArtistInfo2.view", get(get_artist_info2))
             .route("/getArtists", get(get_artists))
             .route("/getArtists.view", get(get_artists))
+            .route("/getBookmarks", get(get_bookmarks))
+            .route("/getBookmarks.view", get(get_bookmarks))
             .route("/getCoverArt", get(get_cover_art))
             .route("/getCoverArt.view", get(get_cover_art))
             .route("/getGenres", get(get_genres))
             .route("/getGenres.view", get(get_genres))
             .route("/getIndexes", get(get_indexes))
             .route("/getIndexes.view", get(get_indexes))
-            .route("/getInternetRadioStations", get(ping))
-            .route("/getInternetRadioStations.view", get(ping))
+            .route(
+                "/getInternetRadioStations",
+                get(get_internet_radio_stations),
+            )
+            .route(
+                "/getInternetRadioStations.view",
+                get(get_internet_radio_stations),
+            )
             .route("/getLicense", get(get_license))
             .route("/getLicense.view", get(get_license))
+            .route("/getLyrics", get(get_lyrics))
+            .route("/getLyrics.view", get(get_lyrics))
+            .route("/getLyricsBySongId", get(get_lyrics_by_song_id))
+            .route("/getLyricsBySongId.view", get(get_lyrics_by_song_id))
             .route("/getMusicDirectory", get(get_music_directory))
             .route("/getMusicDirectory.view", get(get_music_directory))
             .route("/getMusicFolders", get(get_music_folders))
             .route("/getMusicFolders.view", get(get_music_folders))
+            .route("/getNewestPodcasts", get(get_newest_podcasts))
+            .route("/getNewestPodcasts.view", get(get_newest_podcasts))
+            .route("/getNowPlaying", get(get_now_playing))
+            .route("/getNowPlaying.view", get(get_now_playing))
+            .route(
+                "/getOpenSubsonicExtensions",
+                get(get_open_subsonic_extensions),
+            )
+            .route(
+                "/getOpenSubsonicExtensions.view",
+                get(get_open_subsonic_extensions),
+            )
+            .route("/getPlayQueue", get(get_play_queue))
+            .route("/getPlayQueue.view", get(get_play_queue))
             .route("/getPlaylist", get(get_playlist))
             .route("/getPlaylist.view", get(get_playlist))
             .route("/getPlaylists", get(get_playlists))
             .route("/getPlaylists.view", get(get_playlists))
-            .route("/getPodcasts", get(ping))
-            .route("/getPodcasts.view", get(ping))
+            .route("/getPodcasts", get(get_podcasts))
+            .route("/getPodcasts.view", get(get_podcasts))
             .route("/getRandomSongs", get(get_random_songs))
             .route("/getRandomSongs.view", get(get_random_songs))
+            .route("/getShares", get(get_shares))
+            .route("/getShares.view", get(get_shares))
+            .route("/getSimilarSongs", get(get_similar_songs))
+            .route("/getSimilarSongs.view", get(get_similar_songs))
+            .route("/getSimilarSongs2", get(get_similar_songs2))
+            .route("/getSimilarSongs2.view", get(get_similar_songs2))
             .route("/getSongsByGenre", get(get_songs_by_genre))
             .route("/getSongsByGenre.view", get(get_songs_by_genre))
             .route("/getStarred", get(get_starred))
             .route("/getStarred.view", get(get_starred))
             .route("/getStarred2", get(get_starred2))
             .route("/getStarred2.view", get(get_starred2))
-            .route("/scrobble", get(ping))
-            .route("/scrobble.view", get(ping))
+            .route("/getTopSongs", get(get_top_songs))
+            .route("/getTopSongs.view", get(get_top_songs))
+            .route("/getUser", get(get_user))
+            .route("/getUser.view", get(get_user))
+            .route("/getUsers", get(get_users))
+            .route("/getUsers.view", get(get_users))
+            .route("/linkScrobbleAccount", get(link_scrobble_account))
+            .route("/linkScrobbleAccount.view", get(link_scrobble_account))
+            .route("/refreshPodcasts", get(refresh_podcasts))
+            .route("/refreshPodcasts.view", get(refresh_podcasts))
+            .route("/savePlayQueue", get(save_play_queue))
+            .route("/savePlayQueue.view", get(save_play_queue))
+            .route("/scrobble", get(scrobble))
+            .route("/scrobble.view", get(scrobble))
+            .route("/search", get(search))
+            .route("/search.view", get(search))
+            .route("/search2", get(search2))
+            .route("/search2.view", get(search2))
             .route("/search3", get(search3))
             .route("/search3.view", get(search3))
+            .route("/setRating", get(set_rating))
+            .route("/setRating.view", get(set_rating))
             .route("/star", get(star))
             .route("/star.view", get(star))
             .route("/stream", get(stream))
             .route("/stream.view", get(stream))
+            .route("/unlinkScrobbleAccount", get(unlink_scrobble_account))
+            .route("/unlinkScrobbleAccount.view", get(unlink_scrobble_account))
             .route("/unstar", get(unstar))
             .route("/unstar.view", get(unstar))
-            .route_layer(from_extractor_with_state::<RequireAuth, SubsonicAuth>(
-                options.subsonic_auth.clone(),
+            .route(
+                "/updateInternetRadioStation",
+                get(update_internet_radio_station),
+            )
+            .route(
+                "/updateInternetRadioStation.view",
+                get(update_internet_radio_station),
+            )
+            .route("/updatePlaylist", get(update_playlist))
+            .route("/updatePlaylist.view", get(update_playlist))
+            .route("/updateShare", get(update_share))
+            .route("/updateShare.view", get(update_share))
+            .route("/updateUser", get(update_user))
+            .route("/updateUser.view", get(update_user))
+            .route_layer(from_extractor_with_state::<RequireAuth, SharedState>(
+                state.clone(),
             ));
 
+        // Shares are public, so anyone with the link can listen
+        let share_routes = Router::new()
+            .route("/:share_id", get(public_share))
+            .route("/:share_id/stream/:id", get(public_share_stream));
+
         let app = Router::new()
             .nest("/rest", rest_routes)
+            .nest("/share", share_routes)
             .layer(
                 CorsLayer::new()
                     .allow_origin("*".parse::<HeaderValue>().unwrap())
@@ -187,10 +430,8 @@ impl App {
     }
 
     pub async fn import_all_folders(&self) -> AppResult<TaskMessage> {
-        Ok(TaskMessage::ImportFolder {
+        Ok(TaskMessage::ImportFolders {
             state: self.task_state().await,
-            folder: self.options.path.clone(),
-            parent_folder_id: None,
         })
     }
 
@@ -211,6 +452,18 @@ impl App {
             state: self.task_state().await,
         })
     }
+
+    pub async fn submit_scrobbles(&self) -> AppResult<TaskMessage> {
+        Ok(TaskMessage::SubmitScrobbles {
+            state: self.task_state().await,
+        })
+    }
+
+    pub async fn refresh_podcasts(&self) -> AppResult<TaskMessage> {
+        Ok(TaskMessage::RefreshPodcasts {
+            state: self.task_state().await,
+        })
+    }
 }
 
 pub fn enable_default_tracing() {
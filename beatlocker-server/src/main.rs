@@ -1,6 +1,7 @@
 use beatlocker_server::{
-    enable_default_tracing, App, AppResult, DatabaseOptions, ServerOptions, SubsonicAuth,
-    SERVER_VERSION,
+    enable_default_tracing, App, AppResult, DatabaseOptions, MusicFolderOptions, ServerOptions,
+    SubsonicAuth, ThumbnailCacheOptions, TranscodeProfile, DEFAULT_COVER_ART_FILENAMES,
+    DEFAULT_IGNORED_ARTICLES, DEFAULT_LASTFM_URL, DEFAULT_LISTENBRAINZ_URL, SERVER_VERSION,
 };
 use clap::Parser;
 use futures::FutureExt;
@@ -23,6 +24,11 @@ struct Cli {
     #[arg(long, env = "BL_LIBRARY_PATH")]
     library_path: String,
 
+    /// Additional audio library in the form name:path, e.g. "Audiobooks:/mnt/audiobooks". Each is
+    /// listed as a separate music folder, next to the library path.
+    #[arg(long, env = "BL_MUSIC_FOLDERS", value_delimiter = ';')]
+    music_folder: Vec<MusicFolderOptions>,
+
     /// Path to a data folder Beatlocker may use
     #[arg(long, default_value = ".", env = "BL_DATA_PATH")]
     data_path: String,
@@ -35,17 +41,68 @@ struct Cli {
     #[arg(long, env = "BL_LASTFM_API_KEY")]
     lastfm_api_key: Option<String>,
 
+    /// last.fm shared secret, needed to forward plays to last.fm
+    #[arg(long, env = "BL_LASTFM_API_SECRET")]
+    lastfm_api_secret: Option<String>,
+
+    /// Base URL of the last.fm API
+    #[arg(long, default_value = DEFAULT_LASTFM_URL, env = "BL_LASTFM_URL")]
+    lastfm_url: String,
+
+    /// Base URL of the ListenBrainz API
+    #[arg(long, default_value = DEFAULT_LISTENBRAINZ_URL, env = "BL_LISTENBRAINZ_URL")]
+    listenbrainz_url: String,
+
     /// Run fully in-memory (no SQLite database will be created)
     #[arg(long)]
     run_in_memory: bool,
 
-    /// Username to use for authentication
+    /// Username of the admin account, which is created if it doesn't exist yet
     #[arg(long, requires = "auth_password", env = "BL_AUTH_USER")]
     auth_user: Option<String>,
 
-    /// Password to use for authentication
+    /// Password of the admin account, which replaces its current password on every start
     #[arg(long, requires = "auth_user", env = "BL_AUTH_PASSWORD")]
     auth_password: Option<String>,
+
+    /// Filenames of images to use as cover art for the folder they're in, in order of preference
+    #[arg(
+        long,
+        env = "BL_COVER_ART_FILENAMES",
+        value_delimiter = ',',
+        default_values = DEFAULT_COVER_ART_FILENAMES
+    )]
+    cover_art_filenames: Vec<String>,
+
+    /// Leading articles that are ignored when sorting and indexing artist and album names
+    #[arg(
+        long,
+        env = "BL_IGNORED_ARTICLES",
+        value_delimiter = ',',
+        default_values = DEFAULT_IGNORED_ARTICLES
+    )]
+    ignored_articles: Vec<String>,
+
+    /// Allow clients to stream internet radio stations through the server, for when they can't
+    /// reach a station themselves. Stations are then listed with a stream URL of the server
+    #[arg(long, env = "BL_RELAY_INTERNET_RADIO")]
+    relay_internet_radio: bool,
+
+    /// URL the server can be reached at from outside, e.g. "https://music.example.com", which
+    /// share links and relayed stations start with
+    #[arg(long, env = "BL_EXTERNAL_URL")]
+    external_url: Option<String>,
+
+    /// Maximum size of the cover art thumbnail cache, in megabytes
+    #[arg(long, default_value_t = 256, env = "BL_THUMBNAIL_CACHE_SIZE")]
+    thumbnail_cache_size: u64,
+
+    /// Transcoding profile in the form name:content-type:bitrate:command, e.g.
+    /// "mp3:audio/mpeg:128:lame --silent -b {bitrate} - -". The first profile is used when a
+    /// client limits the bitrate without requesting a specific format. No profiles are configured
+    /// by default, so without one songs are always sent as-is.
+    #[arg(long, env = "BL_TRANSCODE_PROFILES", value_delimiter = ';')]
+    transcode_profile: Vec<TranscodeProfile>,
 }
 
 #[tokio::main]
@@ -62,23 +119,45 @@ async fn main() -> AppResult<()> {
         _ => SubsonicAuth::None,
     };
 
+    let data_path = PathBuf::from(cli.data_path);
     let options = ServerOptions {
-        path: PathBuf::from(cli.library_path),
+        music_folders: std::iter::once(MusicFolderOptions::main(cli.library_path))
+            .chain(cli.music_folder)
+            .collect(),
         database: DatabaseOptions {
-            path: Some(PathBuf::from(cli.data_path)),
+            path: Some(data_path.clone()),
             in_memory: cli.run_in_memory,
         },
+        thumbnail_cache: ThumbnailCacheOptions {
+            path: (!cli.run_in_memory).then(|| data_path.join("thumbnails")),
+            max_size: cli.thumbnail_cache_size * 1024 * 1024,
+        },
         server_version: SERVER_VERSION.to_string(),
         import_external_metadata: true,
         discogs_token: cli.discogs_token,
         lastfm_api_key: cli.lastfm_api_key,
+        lastfm_api_secret: cli.lastfm_api_secret,
+        lastfm_url: cli.lastfm_url,
+        listenbrainz_url: cli.listenbrainz_url,
         subsonic_auth,
+        transcode_profiles: cli.transcode_profile,
+        cover_art_filenames: cli.cover_art_filenames,
+        ignored_articles: cli.ignored_articles,
+        relay_internet_radio: cli.relay_internet_radio,
+        podcast_path: data_path.join("podcasts"),
+        external_url: cli.external_url,
         ..Default::default()
     };
 
     if options.discogs_token.is_none() {
         info!("No Discogs API token was found. Discogs will not be queried.");
     }
+    if options.lastfm_api_key.is_none() || options.lastfm_api_secret.is_none() {
+        info!("No last.fm API key and secret were found. Plays can't be forwarded to last.fm.");
+    }
+    if options.transcode_profiles.is_empty() {
+        info!("No transcoding profiles were configured. Songs will always be streamed as-is, even when clients limit the bitrate.");
+    }
     if let SubsonicAuth::None = &options.subsonic_auth {
         warn!("No authorization has been set up. Make sure this server isn't public.");
     }
@@ -97,6 +176,8 @@ async fn main() -> AppResult<()> {
         app.import_external_metadata().await?,
         app.remove_deleted_files().await?,
         app.optimize_database().await?,
+        app.submit_scrobbles().await?,
+        app.refresh_podcasts().await?,
     ];
     let join = tokio::spawn(async move {
         let lim = RateLimiter::direct(Quota::per_hour(NonZeroU32::new(1u32).unwrap()));
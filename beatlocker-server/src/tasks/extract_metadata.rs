@@ -1,14 +1,16 @@
+use crate::tasks::lyrics::Lyrics;
 use crate::{AppError, AppResult};
 use anyhow::anyhow;
 use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, Utc};
 use heck::ToTitleCase;
+use id3::frame::TimestampFormat;
 use lewton::inside_ogg::OggStreamReader;
 use std::ffi::OsStr;
 use std::path::PathBuf;
 use symphonia::core::codecs::{CODEC_TYPE_FLAC, CODEC_TYPE_MP3, CODEC_TYPE_VORBIS};
 use symphonia::core::formats::FormatOptions;
 use symphonia::core::io::{MediaSource, MediaSourceStream};
-use symphonia::core::meta::{MetadataOptions, StandardTagKey};
+use symphonia::core::meta::{MetadataOptions, MetadataRevision, StandardTagKey, StandardVisualKey};
 use symphonia::core::probe::Hint;
 use symphonia_metadata::id3v1;
 
@@ -18,6 +20,9 @@ pub struct SongMetadata {
     pub artist: Option<String>,
     pub album: Option<String>,
     pub album_artist: Option<String>,
+    pub artist_sort: Option<String>,
+    pub album_sort: Option<String>,
+    pub album_artist_sort: Option<String>,
     pub date: Option<DateTime<Utc>>,
     pub track_number: Option<u32>,
     pub disc_number: Option<u32>,
@@ -26,6 +31,8 @@ pub struct SongMetadata {
     pub genre: Option<String>,
     pub content_type: Option<String>,
     pub suffix: Option<String>,
+    pub cover_art: Option<Vec<u8>>,
+    pub lyrics: Vec<Lyrics>,
 }
 
 impl SongMetadata {
@@ -40,10 +47,10 @@ impl SongMetadata {
 
 pub fn extract_metadata(
     filename: &OsStr,
-    reader: impl Fn() -> Box<dyn MediaSource>,
+    reader: impl Fn() -> std::io::Result<Box<dyn MediaSource>>,
 ) -> AppResult<Option<SongMetadata>> {
     let metadata: Option<SongMetadata> = {
-        let mss = MediaSourceStream::new(reader(), Default::default());
+        let mss = MediaSourceStream::new(reader()?, Default::default());
 
         let suffix = PathBuf::from(filename)
             .extension()
@@ -75,23 +82,32 @@ pub fn extract_metadata(
             _ => None,
         };
 
-        let bit_rate = match codec_params.bits_per_coded_sample {
-            Some(val) => Some(val),
-            None => match &codec_params.codec {
-                _ if codec_params.codec == CODEC_TYPE_VORBIS => OggStreamReader::new(reader())
-                    .ok()
-                    .map(|h| (h.ident_hdr.bitrate_nominal / 1000) as u32),
-                _ => None,
-            },
-        };
+        let time = codec_params
+            .time_base
+            .and_then(|tb| codec_params.n_frames.map(|nf| tb.calc_time(nf)));
+
+        // Bitrates are in kbps. Vorbis streams know their nominal bitrate, for other formats the
+        // average bitrate is calculated from the size and duration of the file.
+        let bit_rate = match &codec_params.codec {
+            _ if codec_params.codec == CODEC_TYPE_VORBIS => OggStreamReader::new(reader()?)
+                .ok()
+                .map(|h| (h.ident_hdr.bitrate_nominal / 1000) as u32)
+                .filter(|bit_rate| *bit_rate > 0),
+            _ => None,
+        }
+        .or_else(|| {
+            let seconds = time.map(|t| t.seconds as f64 + t.frac)?;
+            let bytes = reader().ok()?.byte_len()?;
+            if seconds > 0.0 {
+                Some((bytes as f64 * 8.0 / seconds / 1000.0).round() as u32)
+            } else {
+                None
+            }
+        });
 
         let metadata = SongMetadata {
             bit_rate,
-            duration: codec_params.time_base.and_then(|tb| {
-                codec_params
-                    .n_frames
-                    .map(|nf| Duration::seconds(tb.calc_time(nf).seconds as i64))
-            }),
+            duration: time.map(|t| Duration::seconds(t.seconds as i64)),
             content_type,
             suffix,
             ..Default::default()
@@ -102,6 +118,24 @@ pub fn extract_metadata(
             .get()
             .and_then(|mut m| m.skip_to_latest().cloned());
         let format_metadata = format.metadata().skip_to_latest().cloned();
+        let cover_art = format_metadata
+            .iter()
+            .chain(probed_metadata.iter())
+            .find_map(embedded_cover_art);
+        let mut lyrics = format_metadata
+            .iter()
+            .chain(probed_metadata.iter())
+            .map(embedded_lyrics)
+            .find(|lyrics| !lyrics.is_empty())
+            .unwrap_or_default();
+        if suffix.as_deref() == Some("mp3") {
+            lyrics.extend(synced_id3_lyrics(reader()?));
+        }
+        let metadata = SongMetadata {
+            cover_art,
+            lyrics,
+            ..metadata
+        };
 
         if let Some(rev) = format_metadata.or(probed_metadata) {
             let get_value = |wanted_key: StandardTagKey| {
@@ -116,6 +150,13 @@ pub fn extract_metadata(
                 album: get_value(StandardTagKey::Album),
                 album_artist: get_value(StandardTagKey::AlbumArtist)
                     .or_else(|| get_value(StandardTagKey::Artist)),
+                artist_sort: get_value(StandardTagKey::SortArtist),
+                album_sort: get_value(StandardTagKey::SortAlbum),
+                // Like the album artist, which falls back to the artist
+                album_artist_sort: match get_value(StandardTagKey::AlbumArtist) {
+                    Some(_) => get_value(StandardTagKey::SortAlbumArtist),
+                    None => get_value(StandardTagKey::SortArtist),
+                },
                 date: get_value(StandardTagKey::Date)
                     .or_else(|| get_value(StandardTagKey::ReleaseDate))
                     .and_then(|s| {
@@ -199,16 +240,59 @@ pub fn extract_metadata(
     }
 }
 
+/// Picks the front cover from the pictures embedded in the tags, or any picture if there is none.
+fn embedded_cover_art(rev: &MetadataRevision) -> Option<Vec<u8>> {
+    let visuals = rev.visuals();
+    visuals
+        .iter()
+        .find(|visual| visual.usage == Some(StandardVisualKey::FrontCover))
+        .or_else(|| visuals.first())
+        .filter(|visual| !visual.data.is_empty())
+        .map(|visual| visual.data.to_vec())
+}
+
+/// Unsynced lyrics from the tags. ID3 tags keep their language in the key, as in `USLT!eng`.
+fn embedded_lyrics(rev: &MetadataRevision) -> Vec<Lyrics> {
+    rev.tags()
+        .iter()
+        .filter(|tag| tag.std_key == Some(StandardTagKey::Lyrics))
+        .filter_map(|tag| {
+            Lyrics::unsynced(
+                &tag.value.to_string(),
+                tag.key.split_once('!').map(|(_, lang)| lang),
+            )
+        })
+        .collect()
+}
+
+/// Synced lyrics from ID3 `SYLT` frames, which Symphonia doesn't read.
+fn synced_id3_lyrics(reader: Box<dyn MediaSource>) -> Vec<Lyrics> {
+    let tag = match id3::Tag::read_from(reader) {
+        Ok(tag) => tag,
+        Err(_) => return vec![],
+    };
+
+    tag.synchronised_lyrics()
+        .filter(|lyrics| lyrics.timestamp_format == TimestampFormat::Ms)
+        .filter_map(|lyrics| Lyrics::synced(lyrics.content.clone(), Some(&lyrics.lang)))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::tasks::lyrics::LyricsLine;
+    use id3::frame::{
+        Lyrics as Id3Lyrics, Picture, PictureType, SynchronisedLyrics, SynchronisedLyricsType,
+    };
+    use id3::{Tag, TagLike};
     use std::io::Cursor;
 
     #[test]
     fn can_extract_ogg() {
         let bytes = include_bytes!("../../tests/data/Richard Bona/Richard Bona - Ba Senge.ogg");
         let metadata = extract_metadata(OsStr::new("Richard Bona - Ba Senge.ogg"), || {
-            Box::new(Cursor::new(bytes))
+            Ok(Box::new(Cursor::new(bytes)))
         })
         .unwrap()
         .unwrap();
@@ -235,7 +319,7 @@ mod tests {
         let bytes =
             include_bytes!("../../tests/data/Richard Bona/Richard Bona - Akwa Samba Yaya.mp3");
         let metadata = extract_metadata(OsStr::new("Richard Bona - Akwa Samba Yaya.mp3"), || {
-            Box::new(Cursor::new(bytes))
+            Ok(Box::new(Cursor::new(bytes)))
         })
         .unwrap()
         .unwrap();
@@ -254,7 +338,7 @@ mod tests {
         assert_eq!(metadata.disc_number, None);
         assert_eq!(metadata.content_type, Some("audio/mp3".to_string()));
         assert_eq!(metadata.suffix, Some("mp3".to_string()));
-        assert_eq!(metadata.bit_rate, None);
+        assert_eq!(metadata.bit_rate, Some(225));
         assert_eq!(metadata.duration, Some(Duration::seconds(27)));
     }
 
@@ -264,7 +348,7 @@ mod tests {
             "../../tests/data/Motorway OST/MotorwayNested/Alex Gopher - Radar Unit.flac"
         );
         let metadata = extract_metadata(OsStr::new("Alex Gopher - Radar Unit.flac"), || {
-            Box::new(Cursor::new(bytes))
+            Ok(Box::new(Cursor::new(bytes)))
         })
         .unwrap()
         .unwrap();
@@ -283,17 +367,140 @@ mod tests {
         assert_eq!(metadata.disc_number, None);
         assert_eq!(metadata.content_type, Some("audio/flac".to_string()));
         assert_eq!(metadata.suffix, Some("flac".to_string()));
-        assert_eq!(metadata.bit_rate, None);
+        assert_eq!(metadata.bit_rate, Some(294));
         assert_eq!(metadata.duration, Some(Duration::seconds(95)));
     }
 
+    #[test]
+    fn can_extract_embedded_cover_art() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), include_bytes!("../../tests/silent.mp3")).unwrap();
+        let mut tag = Tag::new();
+        tag.set_title("A");
+        tag.set_artist("Artist");
+        for (picture_type, data) in [
+            (PictureType::CoverBack, b"back".to_vec()),
+            (PictureType::CoverFront, b"front".to_vec()),
+        ] {
+            tag.add_frame(Picture {
+                mime_type: "image/jpeg".to_string(),
+                picture_type,
+                description: format!("{picture_type}"),
+                data,
+            });
+        }
+        tag.write_to_path(file.path(), id3::Version::Id3v24)
+            .unwrap();
+
+        let bytes = std::fs::read(file.path()).unwrap();
+        let metadata = extract_metadata(OsStr::new("a.mp3"), || {
+            Ok(Box::new(Cursor::new(bytes.clone())))
+        })
+        .unwrap()
+        .unwrap();
+        assert_eq!(metadata.cover_art, Some(b"front".to_vec()));
+
+        let bytes = include_bytes!("../../tests/data/Richard Bona/Richard Bona - Ba Senge.ogg");
+        let metadata = extract_metadata(OsStr::new("Richard Bona - Ba Senge.ogg"), || {
+            Ok(Box::new(Cursor::new(bytes)))
+        })
+        .unwrap()
+        .unwrap();
+        assert_eq!(metadata.cover_art, None);
+    }
+
+    #[test]
+    fn can_extract_sort_names() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), include_bytes!("../../tests/silent.mp3")).unwrap();
+        let mut tag = Tag::new();
+        tag.set_title("Help!");
+        tag.set_artist("The Beatles");
+        tag.set_album("Help!");
+        tag.set_text("TSOP", "Beatles, The");
+        tag.set_text("TSOA", "Help");
+        tag.write_to_path(file.path(), id3::Version::Id3v24)
+            .unwrap();
+
+        let bytes = std::fs::read(file.path()).unwrap();
+        let metadata = extract_metadata(OsStr::new("a.mp3"), || {
+            Ok(Box::new(Cursor::new(bytes.clone())))
+        })
+        .unwrap()
+        .unwrap();
+        assert_eq!(metadata.artist_sort, Some("Beatles, The".to_string()));
+        assert_eq!(metadata.album_artist_sort, Some("Beatles, The".to_string()));
+        assert_eq!(metadata.album_sort, Some("Help".to_string()));
+    }
+
+    #[test]
+    fn can_extract_lyrics() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        std::fs::write(file.path(), include_bytes!("../../tests/silent.mp3")).unwrap();
+        let mut tag = Tag::new();
+        tag.set_title("A");
+        tag.set_artist("Artist");
+        tag.add_frame(Id3Lyrics {
+            lang: "eng".to_string(),
+            description: "".to_string(),
+            text: "First line\nSecond line".to_string(),
+        });
+        tag.add_frame(SynchronisedLyrics {
+            lang: "eng".to_string(),
+            timestamp_format: TimestampFormat::Ms,
+            content_type: SynchronisedLyricsType::Lyrics,
+            description: "".to_string(),
+            content: vec![
+                (2000, "Second line".to_string()),
+                (0, "First line".to_string()),
+            ],
+        });
+        tag.write_to_path(file.path(), id3::Version::Id3v24)
+            .unwrap();
+
+        let bytes = std::fs::read(file.path()).unwrap();
+        let metadata = extract_metadata(OsStr::new("a.mp3"), || {
+            Ok(Box::new(Cursor::new(bytes.clone())))
+        })
+        .unwrap()
+        .unwrap();
+        let line = |start: Option<u32>, value: &str| LyricsLine {
+            start,
+            value: value.to_string(),
+        };
+        assert_eq!(
+            metadata.lyrics,
+            vec![
+                Lyrics {
+                    lang: "eng".to_string(),
+                    synced: false,
+                    lines: vec![line(None, "First line"), line(None, "Second line")],
+                },
+                Lyrics {
+                    lang: "eng".to_string(),
+                    synced: true,
+                    lines: vec![line(Some(0), "First line"), line(Some(2000), "Second line")],
+                },
+            ]
+        );
+
+        let bytes = include_bytes!("../../tests/data/Richard Bona/Richard Bona - Ba Senge.ogg");
+        let metadata = extract_metadata(OsStr::new("Richard Bona - Ba Senge.ogg"), || {
+            Ok(Box::new(Cursor::new(bytes)))
+        })
+        .unwrap()
+        .unwrap();
+        assert!(metadata.lyrics.is_empty());
+    }
+
     #[test]
     fn can_extract_unknown_metadata() {
         let bytes = include_bytes!("../../tests/data/Unknown/Unknown Artist - Unknown Song.ogg");
-        let metadata =
-            extract_metadata(OsStr::new("Foo - Bar.ogg"), || Box::new(Cursor::new(bytes)))
-                .unwrap()
-                .unwrap();
+        let metadata = extract_metadata(OsStr::new("Foo - Bar.ogg"), || {
+            Ok(Box::new(Cursor::new(bytes)))
+        })
+        .unwrap()
+        .unwrap();
         assert!(metadata.is_valid());
         assert_eq!(metadata.title, Some("Bar".to_string()));
         assert_eq!(metadata.album, None);
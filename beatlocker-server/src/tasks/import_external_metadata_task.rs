@@ -48,7 +48,8 @@ pub async fn import_external_metadata(state: Arc<TaskState>) -> AppResult<()> {
             WHERE (songs.cover_art_id is null
             OR artists.cover_art_id is null
             OR albums.cover_art_id is null
-            OR songs.genre is null)
+            OR songs.genre is null
+            OR NOT EXISTS (SELECT 1 FROM artist_tags t WHERE t.artist_id = songs.artist_id))
             AND (fc.last_updated is null OR fc.last_updated < ?)
         "#,
     )
@@ -91,6 +92,11 @@ pub async fn import_external_metadata(state: Arc<TaskState>) -> AppResult<()> {
                     || (),
                 )
                 .await;
+                wrap_err(
+                    update_artist_tags(&ctx, get_db_song_info(&state, &info).await?),
+                    || (),
+                )
+                .await;
                 wrap_err(
                     update_genre(&ctx, get_db_song_info(&state, &info).await?),
                     || (),
@@ -143,6 +149,40 @@ async fn update_artist(ctx: &UpdateContext<'_>, db: DbSongInfo) -> AppResult<()>
     Ok(())
 }
 
+async fn update_artist_tags(ctx: &UpdateContext<'_>, db: DbSongInfo) -> AppResult<()> {
+    let has_tags = sqlx::query("SELECT 1 FROM artist_tags WHERE artist_id = ?")
+        .bind(db.artist.artist_id)
+        .fetch_optional(ctx.state.db.conn().await?.deref_mut())
+        .await?
+        .is_some();
+    if has_tags {
+        return Ok(());
+    }
+
+    let mb_arid = match db.artist.musicbrainz_id {
+        Some(mb_arid) => Some(mb_arid),
+        None => musicbrainz_find_song(ctx.info)
+            .await?
+            .and_then(|mut mb_song| mb_song.artist_credit.pop())
+            .map(|c| c.artist.id),
+    };
+
+    if let Some(mb_arid) = mb_arid {
+        if let Some(artist) = musicbrainz_find_artist(mb_arid).await? {
+            debug!(ctx.info.artist_name, "Updating artist tags");
+            for tag in artist.tags {
+                sqlx::query("INSERT OR IGNORE INTO artist_tags (artist_id, tag) VALUES (?, ?)")
+                    .bind(db.artist.artist_id)
+                    .bind(tag.name.to_lowercase())
+                    .execute(ctx.state.db.conn().await?.deref_mut())
+                    .await?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
 async fn update_genre(ctx: &UpdateContext<'_>, db: DbSongInfo) -> AppResult<()> {
     if db.song.genre.is_some() {
         return Ok(());
@@ -1,58 +1,76 @@
 use super::*;
-use crate::db::{DbAlbum, DbArtist, DbFailedFolderChild, DbFolder, DbFolderChild, DbSong};
-use crate::str_to_uuid;
+use crate::db::{
+    DbAlbum, DbArtist, DbCoverArt, DbFailedFolderChild, DbFolder, DbFolderChild, DbSong,
+};
 use crate::tasks::extract_metadata::extract_metadata;
+use crate::tasks::lyrics::{
+    import_lyrics_file, is_lyrics_file, read_lrc_file, remove_lyrics, sidecar_lyrics_path,
+    store_lyrics,
+};
+use crate::tasks::playlist_files::{
+    import_playlist_files, is_playlist_file, register_playlist_file,
+};
+use crate::{bytes_to_uuid, sort_key, str_to_uuid, MusicFolderOptions};
 use async_recursion::async_recursion;
-use std::path::Path;
+use std::io::Seek;
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 use tokio::task::JoinSet;
 use tokio_stream::wrappers::ReadDirStream;
 use tokio_stream::StreamExt;
 use tracing::{debug, warn};
 
+/// Imports every music folder in turn.
+pub async fn import_music_folders(state: Arc<TaskState>) -> AppResult<()> {
+    for music_folder in &state.options.music_folders {
+        import_folder(
+            state.clone(),
+            music_folder.clone(),
+            &music_folder.path,
+            None,
+        )
+        .await?;
+    }
+
+    // Playlist files can refer to songs anywhere in the library, so they are only imported
+    // once everything else has been
+    import_playlist_files(&state).await?;
+
+    Ok(())
+}
+
 #[async_recursion]
 pub async fn import_folder(
     state: Arc<TaskState>,
+    music_folder: MusicFolderOptions,
     folder: &Path,
     parent_folder_id: Option<Uuid>,
 ) -> AppResult<()> {
     debug!(?folder, "Processing folder");
 
-    let parent_folder_id = match parent_folder_id {
-        Some(p) => p,
-        None => {
-            state
-                .db
-                .insert_folder_if_not_exists(&DbFolder {
-                    folder_id: Uuid::nil(),
-                    parent_id: None,
-                    name: "root".to_owned(),
-                    path: folder.to_string_lossy().to_string(),
-                    cover_art_id: None,
-                    created: (state.options.now_provider)(),
-                })
-                .await?
-        }
+    // Music folders are the roots of the folder tree, and share their id
+    let (folder_id, folder_name) = match parent_folder_id {
+        Some(_) => (
+            str_to_uuid(folder.to_str().unwrap()),
+            folder.file_name().unwrap().to_string_lossy().to_string(),
+        ),
+        None => (music_folder.id, music_folder.name.clone()),
     };
+    let folder_id = state
+        .db
+        .insert_folder_if_not_exists(&DbFolder {
+            folder_id,
+            parent_id: parent_folder_id,
+            music_folder_id: music_folder.id,
+            name: folder_name.clone(),
+            path: folder.to_string_lossy().to_string(),
+            cover_art_id: None,
+            created: (state.options.now_provider)(),
+            sort_key: sort_key(&folder_name, &state.options.ignored_articles),
+        })
+        .await?;
 
-    // Insert folder in DB
-    let folder_id = if folder == state.options.path {
-        Uuid::nil()
-    } else {
-        let folder_name = folder.file_name().unwrap();
-
-        state
-            .db
-            .insert_folder_if_not_exists(&DbFolder {
-                folder_id: str_to_uuid(folder.to_str().unwrap()),
-                parent_id: Some(parent_folder_id),
-                name: folder_name.to_string_lossy().to_string(),
-                path: folder.to_string_lossy().to_string(),
-                cover_art_id: None,
-                created: (state.options.now_provider)(),
-            })
-            .await?
-    };
+    let folder_cover_art_id = import_sidecar_cover_art(&state, folder, folder_id).await?;
 
     let read_dir_chunks = ReadDirStream::new(tokio::fs::read_dir(folder).await?)
         .chunks_timeout(64, Duration::from_secs(10));
@@ -64,10 +82,12 @@ pub async fn import_folder(
             let file_type = entry.file_type().await?;
             if file_type.is_dir() {
                 let state = state.clone();
+                let music_folder = music_folder.clone();
                 let entry = entry.path().clone();
                 let folder_id = folder_id;
                 set.spawn(async move {
-                    let _ = import_folder(state, entry.as_path(), Some(folder_id)).await;
+                    let _ =
+                        import_folder(state, music_folder, entry.as_path(), Some(folder_id)).await;
                     Ok(())
                 });
             }
@@ -75,7 +95,9 @@ pub async fn import_folder(
                 let folder_id = folder_id;
                 let state = state.clone();
                 let entry = entry.path().clone();
-                set.spawn(async move { import_file(state, entry.as_path(), folder_id).await });
+                set.spawn(async move {
+                    import_file(state, entry.as_path(), folder_id, folder_cover_art_id).await
+                });
             }
         }
 
@@ -86,9 +108,86 @@ pub async fn import_folder(
     Ok(())
 }
 
-async fn import_file(state: Arc<TaskState>, path: &Path, folder_id: Uuid) -> AppResult<()> {
+/// Finds the image in a folder that should be used as its cover art, if any.
+/// The configured filenames are matched case-insensitively, in order of priority.
+async fn find_sidecar_cover_art(folder: &Path, filenames: &[String]) -> AppResult<Option<PathBuf>> {
+    let mut candidates = vec![];
+
+    let mut read_dir = tokio::fs::read_dir(folder).await?;
+    while let Some(entry) = read_dir.next_entry().await? {
+        if !entry.file_type().await?.is_file() {
+            continue;
+        }
+        if let Some(priority) = sidecar_priority(&entry.path(), filenames) {
+            candidates.push((priority, entry.path()));
+        }
+    }
+
+    Ok(candidates
+        .into_iter()
+        .min_by_key(|(priority, _)| *priority)
+        .map(|(_, path)| path))
+}
+
+/// Attaches a folder's sidecar image to the folder, and to the songs and albums inside it.
+///
+/// Sidecar images are identified by their contents, so when an image changes or is removed
+/// everything that used the previous one is updated on the next scan.
+async fn import_sidecar_cover_art(
+    state: &TaskState,
+    folder: &Path,
+    folder_id: Uuid,
+) -> AppResult<Option<Uuid>> {
+    let cover_art_id =
+        match find_sidecar_cover_art(folder, &state.options.cover_art_filenames).await? {
+            Some(path) => {
+                debug!(?path, "Found sidecar cover art");
+                let data = tokio::fs::read(&path).await?;
+                Some(
+                    state
+                        .db
+                        .insert_cover_art_if_not_exists(&DbCoverArt {
+                            cover_art_id: bytes_to_uuid(&data),
+                            data,
+                        })
+                        .await?,
+                )
+            }
+            None => None,
+        };
+
+    state
+        .db
+        .update_folder_cover_art(folder_id, cover_art_id)
+        .await?;
+    Ok(cover_art_id)
+}
+
+fn sidecar_priority(path: &Path, filenames: &[String]) -> Option<usize> {
+    let name = path.file_name()?.to_string_lossy().to_lowercase();
+    filenames.iter().position(|f| f.to_lowercase() == name)
+}
+
+async fn import_file(
+    state: Arc<TaskState>,
+    path: &Path,
+    folder_id: Uuid,
+    folder_cover_art_id: Option<Uuid>,
+) -> AppResult<()> {
     let folder_child_path = path.to_str().unwrap().to_string();
 
+    if sidecar_priority(path, &state.options.cover_art_filenames).is_some() {
+        return Ok(());
+    }
+
+    if is_playlist_file(path) {
+        return register_playlist_file(&state, path).await;
+    }
+
+    if is_lyrics_file(path) {
+        return import_lyrics_file(&state, path).await;
+    }
+
     if state
         .db
         .find_failed_folder_child_by_path(&folder_child_path)
@@ -122,7 +221,12 @@ async fn import_file(state: Arc<TaskState>, path: &Path, folder_id: Uuid) -> App
         let file = std::fs::File::open(path)?;
         let len = file.metadata()?.len() as u32;
         (
-            match extract_metadata(filename, || Box::new(file.try_clone().unwrap())) {
+            match extract_metadata(filename, || {
+                // Clones share the position of the file, so each reader starts over at the start
+                let mut file = file.try_clone()?;
+                file.rewind()?;
+                Ok(Box::new(file))
+            }) {
                 Ok(m) => m,
                 Err(e) => {
                     warn!(?path, ?e, "Could not extract metadata");
@@ -151,7 +255,22 @@ async fn import_file(state: Arc<TaskState>, path: &Path, folder_id: Uuid) -> App
 
         return Ok(());
     }
-    let metadata = metadata.unwrap();
+    let mut metadata = metadata.unwrap();
+
+    // Embedded cover art is identified by its contents, since it's often shared by a whole album.
+    // Songs without any use the folder's sidecar image instead.
+    let cover_art_id = match metadata.cover_art.take() {
+        Some(data) => Some(
+            state
+                .db
+                .insert_cover_art_if_not_exists(&DbCoverArt {
+                    cover_art_id: bytes_to_uuid(&data),
+                    data,
+                })
+                .await?,
+        ),
+        None => folder_cover_art_id,
+    };
 
     let album_id = if let Some(album_title) = &metadata.album {
         let artist = metadata
@@ -159,17 +278,26 @@ async fn import_file(state: Arc<TaskState>, path: &Path, folder_id: Uuid) -> App
             .clone()
             .unwrap_or_else(|| metadata.artist().to_string());
 
-        let album_id = str_to_uuid(&format!("{}{}", album_title, artist));
-        Some(
+        let album_id = state
+            .db
+            .insert_album_if_not_exists(&DbAlbum {
+                album_id: str_to_uuid(&format!("{}{}", album_title, artist)),
+                title: album_title.clone(),
+                cover_art_id,
+                sort_key: sort_key(
+                    metadata.album_sort.as_deref().unwrap_or(album_title),
+                    &state.options.ignored_articles,
+                ),
+                sort_name: metadata.album_sort.clone(),
+            })
+            .await?;
+        if let Some(cover_art_id) = cover_art_id {
             state
                 .db
-                .insert_album_if_not_exists(&DbAlbum {
-                    album_id,
-                    title: album_title.clone(),
-                    cover_art_id: None,
-                })
-                .await?,
-        )
+                .update_album_local_cover_art(album_id, cover_art_id)
+                .await?;
+        }
+        Some(album_id)
     } else {
         None
     };
@@ -182,6 +310,11 @@ async fn import_file(state: Arc<TaskState>, path: &Path, folder_id: Uuid) -> App
                 name: metadata.artist().to_string(),
                 cover_art_id: None,
                 musicbrainz_id: None,
+                sort_key: sort_key(
+                    metadata.artist_sort.as_deref().unwrap_or(metadata.artist()),
+                    &state.options.ignored_articles,
+                ),
+                sort_name: metadata.artist_sort.clone(),
             })
             .await?,
     );
@@ -195,6 +328,11 @@ async fn import_file(state: Arc<TaskState>, path: &Path, folder_id: Uuid) -> App
                     name: artist_name.clone(),
                     cover_art_id: None,
                     musicbrainz_id: None,
+                    sort_key: sort_key(
+                        metadata.album_artist_sort.as_deref().unwrap_or(artist_name),
+                        &state.options.ignored_articles,
+                    ),
+                    sort_name: metadata.album_artist_sort.clone(),
                 })
                 .await?,
         )
@@ -219,28 +357,38 @@ async fn import_file(state: Arc<TaskState>, path: &Path, folder_id: Uuid) -> App
         artist_id.unwrap_or_default(),
         album_id.unwrap_or_default()
     ));
-    let song_id = Some(
-        state
-            .db
-            .insert_song_if_not_exists(&DbSong {
-                song_id,
-                title: song_title.clone(),
-                created: (state.options.now_provider)(),
-                date: metadata.date,
-                cover_art_id: None,
-                artist_id,
-                album_id,
-                content_type: metadata.content_type,
-                suffix: metadata.suffix,
-                size: Some(file_size),
-                track_number: metadata.track_number,
-                disc_number: metadata.disc_number,
-                duration: metadata.duration,
-                bit_rate: metadata.bit_rate,
-                genre: metadata.genre,
-            })
-            .await?,
-    );
+    let song_id = state
+        .db
+        .insert_song_if_not_exists(&DbSong {
+            song_id,
+            title: song_title.clone(),
+            created: (state.options.now_provider)(),
+            date: metadata.date,
+            cover_art_id,
+            artist_id,
+            album_id,
+            content_type: metadata.content_type,
+            suffix: metadata.suffix,
+            size: Some(file_size),
+            track_number: metadata.track_number,
+            disc_number: metadata.disc_number,
+            duration: metadata.duration,
+            bit_rate: metadata.bit_rate,
+            genre: metadata.genre,
+        })
+        .await?;
+
+    // Files are only imported once, so changes to embedded lyrics aren't picked up until the file
+    // is removed and found again. Songs are identified by their tags rather than their path though,
+    // so a moved or copied file may bring lyrics the song had before, which are replaced here.
+    // Lyrics next to the file take precedence over those in its tags, and `.lrc` files are read on
+    // every import.
+    remove_lyrics(&state, song_id).await?;
+    store_lyrics(&state, song_id, &metadata.lyrics, None).await?;
+    let lyrics_path = sidecar_lyrics_path(path);
+    if let Some(lyrics) = read_lrc_file(&lyrics_path).await {
+        store_lyrics(&state, song_id, &[lyrics], Some(&lyrics_path)).await?;
+    }
 
     state
         .db
@@ -249,10 +397,216 @@ async fn import_file(state: Arc<TaskState>, path: &Path, folder_id: Uuid) -> App
             folder_id,
             path: folder_child_path,
             name: song_title.clone(),
-            song_id,
+            song_id: Some(song_id),
             last_updated: None,
         })
         .await?;
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::db::DbCoverArt;
+    use crate::{bytes_to_uuid, TestState, MOCK_COVER_ART, MOCK_SIDECAR_COVER_ART};
+    use sqlx::sqlite::SqliteRow;
+    use sqlx::Row;
+    use std::ops::DerefMut;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn can_import_embedded_cover_art() {
+        let state = TestState::new().await.unwrap();
+        let db = state.db().await;
+        let mut conn = db.conn().await.unwrap();
+        let cover_art_id = bytes_to_uuid(MOCK_COVER_ART);
+
+        let song_cover_art = |title: &'static str| {
+            sqlx::query("SELECT cover_art_id FROM songs WHERE title = ?")
+                .bind(title)
+                .map(|row: SqliteRow| row.get::<Option<Uuid>, _>("cover_art_id"))
+        };
+        assert_eq!(
+            song_cover_art("G")
+                .fetch_one(conn.deref_mut())
+                .await
+                .unwrap(),
+            Some(cover_art_id)
+        );
+        assert_eq!(
+            song_cover_art("H")
+                .fetch_one(conn.deref_mut())
+                .await
+                .unwrap(),
+            None
+        );
+
+        let album_cover_art: Option<Uuid> =
+            sqlx::query("SELECT cover_art_id FROM albums WHERE title = 'SharedAlbum'")
+                .map(|row: SqliteRow| row.get("cover_art_id"))
+                .fetch_one(conn.deref_mut())
+                .await
+                .unwrap();
+        assert_eq!(album_cover_art, Some(cover_art_id));
+
+        let data: Vec<u8> = sqlx::query("SELECT data FROM cover_art WHERE cover_art_id = ?")
+            .bind(cover_art_id)
+            .map(|row: SqliteRow| row.get("data"))
+            .fetch_one(conn.deref_mut())
+            .await
+            .unwrap();
+        assert_eq!(data, MOCK_COVER_ART);
+    }
+
+    #[tokio::test]
+    async fn prefers_embedded_cover_art() {
+        let state = TestState::new().await.unwrap();
+        let db = state.db().await;
+        let cover_art_id = bytes_to_uuid(MOCK_COVER_ART);
+        let remote_cover_art_id = db
+            .insert_cover_art_if_not_exists(&DbCoverArt {
+                cover_art_id: Uuid::new_v4(),
+                data: vec![],
+            })
+            .await
+            .unwrap();
+
+        // Pretend art was found online before the embedded art was imported
+        let album_id: Uuid = {
+            let mut conn = db.conn().await.unwrap();
+            sqlx::query("UPDATE songs SET cover_art_id = NULL")
+                .execute(conn.deref_mut())
+                .await
+                .unwrap();
+            sqlx::query(
+                "UPDATE albums SET cover_art_id = ? WHERE title = 'SharedAlbum' RETURNING album_id",
+            )
+            .bind(remote_cover_art_id)
+            .map(|row: SqliteRow| row.get("album_id"))
+            .fetch_one(conn.deref_mut())
+            .await
+            .unwrap()
+        };
+
+        db.update_album_local_cover_art(album_id, cover_art_id)
+            .await
+            .unwrap();
+        let mut conn = db.conn().await.unwrap();
+        let album_cover_art: Option<Uuid> =
+            sqlx::query("SELECT cover_art_id FROM albums WHERE album_id = ?")
+                .bind(album_id)
+                .map(|row: SqliteRow| row.get("cover_art_id"))
+                .fetch_one(conn.deref_mut())
+                .await
+                .unwrap();
+        assert_eq!(album_cover_art, Some(cover_art_id));
+    }
+
+    async fn cover_art_ids(state: &TestState, table: &str, column: &str) -> Vec<Option<Uuid>> {
+        let db = state.db().await;
+        let mut conn = db.conn().await.unwrap();
+        sqlx::query(&format!(
+            "SELECT cover_art_id FROM {table} ORDER BY {column}"
+        ))
+        .map(|row: SqliteRow| row.get("cover_art_id"))
+        .fetch_all(conn.deref_mut())
+        .await
+        .unwrap()
+    }
+
+    async fn rescan(state: &TestState) {
+        state
+            .app
+            .task_manager
+            .send(state.app.import_all_folders().await.unwrap())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn can_import_sidecar_cover_art() {
+        let state = TestState::new().await.unwrap();
+        let sidecar_id = Some(bytes_to_uuid(MOCK_SIDECAR_COVER_ART));
+        let embedded_id = Some(bytes_to_uuid(MOCK_COVER_ART));
+
+        assert_eq!(
+            cover_art_ids(&state, "songs", "title").await,
+            &[
+                sidecar_id,
+                sidecar_id,
+                sidecar_id,
+                None,
+                None,
+                None,
+                embedded_id,
+                None
+            ]
+        );
+        assert_eq!(
+            cover_art_ids(&state, "albums", "title").await,
+            &[sidecar_id, sidecar_id, embedded_id]
+        );
+        assert_eq!(
+            cover_art_ids(&state, "folders", "path").await,
+            &[None, sidecar_id, None, None]
+        );
+
+        // The sidecar image is not treated as a failed song
+        let db = state.db().await;
+        assert_eq!(
+            db.find_failed_folder_child_by_path(
+                state
+                    .tempdir
+                    .as_ref()
+                    .unwrap()
+                    .path()
+                    .join("folder1/Cover.JPG")
+                    .to_str()
+                    .unwrap()
+            )
+            .await
+            .unwrap(),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn can_update_sidecar_cover_art() {
+        let state = TestState::new().await.unwrap();
+        let sidecar_path = state
+            .tempdir
+            .as_ref()
+            .unwrap()
+            .path()
+            .join("folder1/Cover.JPG");
+
+        // cover.jpg is preferred over folder.png
+        std::fs::remove_file(&sidecar_path).unwrap();
+        std::fs::write(sidecar_path.with_file_name("folder.png"), b"folder").unwrap();
+        std::fs::write(sidecar_path.with_file_name("cover.jpg"), b"changed").unwrap();
+        rescan(&state).await;
+
+        let changed_id = Some(bytes_to_uuid(b"changed"));
+        assert_eq!(
+            cover_art_ids(&state, "songs", "title").await[0..3],
+            [changed_id, changed_id, changed_id]
+        );
+        assert_eq!(
+            cover_art_ids(&state, "albums", "title").await[0..2],
+            [changed_id, changed_id]
+        );
+
+        // Removing the sidecar images removes the cover art
+        std::fs::remove_file(sidecar_path.with_file_name("folder.png")).unwrap();
+        std::fs::remove_file(sidecar_path.with_file_name("cover.jpg")).unwrap();
+        rescan(&state).await;
+        assert_eq!(
+            cover_art_ids(&state, "songs", "title").await[0..3],
+            [None, None, None]
+        );
+        assert_eq!(
+            cover_art_ids(&state, "folders", "path").await,
+            &[None, None, None, None]
+        );
+    }
+}
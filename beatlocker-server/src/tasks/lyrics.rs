@@ -0,0 +1,320 @@
+use crate::{AppResult, TaskState};
+use sqlx::sqlite::SqliteRow;
+use sqlx::Row;
+use std::ops::DerefMut;
+use std::path::{Path, PathBuf};
+use tracing::debug;
+use uuid::Uuid;
+
+/// Language of lyrics that don't tell which language they're in.
+pub const UNKNOWN_LANGUAGE: &str = "xxx";
+
+/// Lyrics of a song. Synced lyrics have a start time for every line.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Lyrics {
+    pub lang: String,
+    pub synced: bool,
+    pub lines: Vec<LyricsLine>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LyricsLine {
+    /// Start of the line in milliseconds.
+    pub start: Option<u32>,
+    pub value: String,
+}
+
+impl Lyrics {
+    /// Lyrics without timing, with a line per line of text. Returns nothing if there is no text.
+    pub fn unsynced(text: &str, lang: Option<&str>) -> Option<Self> {
+        if text.trim().is_empty() {
+            return None;
+        }
+
+        Some(Self {
+            lang: language(lang),
+            synced: false,
+            lines: text
+                .trim()
+                .lines()
+                .map(|line| LyricsLine {
+                    start: None,
+                    value: line.trim_end().to_string(),
+                })
+                .collect(),
+        })
+    }
+
+    /// Lyrics with the start of every line in milliseconds. Returns nothing if there are no lines.
+    pub fn synced(mut lines: Vec<(u32, String)>, lang: Option<&str>) -> Option<Self> {
+        if lines.is_empty() {
+            return None;
+        }
+
+        lines.sort_by_key(|(start, _)| *start);
+        Some(Self {
+            lang: language(lang),
+            synced: true,
+            lines: lines
+                .into_iter()
+                .map(|(start, value)| LyricsLine {
+                    start: Some(start),
+                    value: value.trim().to_string(),
+                })
+                .collect(),
+        })
+    }
+}
+
+fn language(lang: Option<&str>) -> String {
+    lang.map(|lang| lang.trim().to_lowercase())
+        .filter(|lang| !lang.is_empty())
+        .unwrap_or_else(|| UNKNOWN_LANGUAGE.to_string())
+}
+
+pub fn is_lyrics_file(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext.eq_ignore_ascii_case("lrc"))
+        .unwrap_or_default()
+}
+
+/// The `.lrc` file next to an audio file, with the same name.
+pub fn sidecar_lyrics_path(path: &Path) -> PathBuf {
+    path.with_extension("lrc")
+}
+
+/// Reads an `.lrc` file, if it exists.
+pub async fn read_lrc_file(path: &Path) -> Option<Lyrics> {
+    let bytes = tokio::fs::read(path).await.ok()?;
+    parse_lrc(&String::from_utf8_lossy(&bytes))
+}
+
+/// Stores the lyrics of an `.lrc` file with the songs next to it that have the same name. Songs
+/// that are imported later pick up the file themselves.
+pub async fn import_lyrics_file(state: &TaskState, path: &Path) -> AppResult<()> {
+    let lyrics = match read_lrc_file(path).await {
+        Some(lyrics) => lyrics,
+        None => return Ok(()),
+    };
+
+    let folder = path.parent().unwrap_or(path).to_string_lossy().to_string();
+    let song_ids = sqlx::query(
+        "SELECT path, song_id FROM folder_children WHERE song_id IS NOT NULL AND substr(path, 1, length(?)) = ?",
+    )
+    .bind(&folder)
+    .bind(&folder)
+    .map(|row: SqliteRow| {
+        let path: String = row.get("path");
+        let song_id: Uuid = row.get("song_id");
+        (path, song_id)
+    })
+    .fetch_all(state.db.conn().await?.deref_mut())
+    .await?
+    .into_iter()
+    .filter(|(song_path, _)| sidecar_lyrics_path(Path::new(song_path)) == path)
+    .map(|(_, song_id)| song_id);
+
+    for song_id in song_ids {
+        debug!(?path, %song_id, "Importing lyrics");
+        store_lyrics(state, song_id, &[lyrics.clone()], Some(path)).await?;
+    }
+
+    Ok(())
+}
+
+/// Removes all lyrics of a song, before those of a newly imported file are stored.
+pub async fn remove_lyrics(state: &TaskState, song_id: Uuid) -> AppResult<()> {
+    // Lines are removed through the foreign key
+    sqlx::query("DELETE FROM lyrics WHERE song_id = ?")
+        .bind(song_id)
+        .execute(state.db.conn().await?.deref_mut())
+        .await?;
+
+    Ok(())
+}
+
+/// Stores the lyrics of a song, from the tags of the song or the `.lrc` file at `path`. Lyrics
+/// replace those the song already had with the same timing.
+pub async fn store_lyrics(
+    state: &TaskState,
+    song_id: Uuid,
+    lyrics: &[Lyrics],
+    path: Option<&Path>,
+) -> AppResult<()> {
+    let mut conn = state.db.conn().await?;
+    let path = path.map(|path| path.to_string_lossy().to_string());
+
+    for lyrics in lyrics {
+        // Lines are removed through the foreign key
+        sqlx::query("DELETE FROM lyrics WHERE song_id = ? AND synced = ?")
+            .bind(song_id)
+            .bind(lyrics.synced)
+            .execute(conn.deref_mut())
+            .await?;
+        sqlx::query("INSERT INTO lyrics (song_id, synced, lang, path) VALUES (?, ?, ?, ?)")
+            .bind(song_id)
+            .bind(lyrics.synced)
+            .bind(&lyrics.lang)
+            .bind(&path)
+            .execute(conn.deref_mut())
+            .await?;
+
+        for (position, line) in lyrics.lines.iter().enumerate() {
+            sqlx::query(
+                "INSERT INTO lyrics_lines (song_id, synced, position, start, value) VALUES (?, ?, ?, ?, ?)",
+            )
+            .bind(song_id)
+            .bind(lyrics.synced)
+            .bind(position as u32)
+            .bind(line.start)
+            .bind(&line.value)
+            .execute(conn.deref_mut())
+            .await?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Parses the contents of an `.lrc` file. Files without any timestamps are read as unsynced
+/// lyrics.
+pub fn parse_lrc(text: &str) -> Option<Lyrics> {
+    let mut synced = vec![];
+    let mut unsynced = vec![];
+    let mut lang = None;
+    // Positive offsets make the lyrics appear sooner
+    let mut offset: i64 = 0;
+
+    for line in text.lines() {
+        let mut rest = line.trim();
+        let mut starts = vec![];
+        let mut is_tag = false;
+
+        while let Some(stripped) = rest.strip_prefix('[') {
+            let (tag, after) = match stripped.split_once(']') {
+                Some(tag) => tag,
+                None => break,
+            };
+            match parse_timestamp(tag) {
+                Some(start) => starts.push(start),
+                None => {
+                    is_tag = true;
+                    match tag.split_once(':') {
+                        Some(("offset", value)) => offset = value.trim().parse().unwrap_or(0),
+                        Some(("la" | "lang", value)) => lang = Some(value.trim().to_string()),
+                        _ => {}
+                    }
+                }
+            }
+            rest = after.trim_start();
+        }
+
+        if !starts.is_empty() {
+            for start in starts {
+                synced.push(((start as i64 - offset).max(0) as u32, rest.to_string()));
+            }
+        } else if !is_tag {
+            unsynced.push(rest);
+        }
+    }
+
+    if synced.is_empty() {
+        Lyrics::unsynced(&unsynced.join("\n"), lang.as_deref())
+    } else {
+        Lyrics::synced(synced, lang.as_deref())
+    }
+}
+
+/// Parses an `.lrc` timestamp like `01:02.50` into milliseconds.
+fn parse_timestamp(tag: &str) -> Option<u32> {
+    let (minutes, seconds) = tag.split_once(':')?;
+    let minutes: u32 = minutes.trim().parse().ok()?;
+    let (seconds, fraction) = match seconds.split_once(['.', ':']) {
+        Some((seconds, fraction)) => (seconds, fraction),
+        None => (seconds, "0"),
+    };
+    let seconds: u32 = seconds.trim().parse().ok()?;
+    // Hundredths are most common, but some files use milliseconds
+    let millis = match fraction.len() {
+        1 => fraction.parse::<u32>().ok()? * 100,
+        2 => fraction.parse::<u32>().ok()? * 10,
+        _ => fraction.get(0..3)?.parse::<u32>().ok()?,
+    };
+
+    Some(minutes * 60_000 + seconds * 1000 + millis)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_synced_lrc() {
+        let lrc = "[ar:Richard Bona]\n[ti:Ba Senge]\n[la:ENG]\n\n[00:01.50]First line\n[00:03.00][00:10.250]Chorus\n[00:05]Second line\n";
+
+        assert_eq!(
+            parse_lrc(lrc),
+            Some(Lyrics {
+                lang: "eng".to_string(),
+                synced: true,
+                lines: vec![
+                    LyricsLine {
+                        start: Some(1500),
+                        value: "First line".to_string()
+                    },
+                    LyricsLine {
+                        start: Some(3000),
+                        value: "Chorus".to_string()
+                    },
+                    LyricsLine {
+                        start: Some(5000),
+                        value: "Second line".to_string()
+                    },
+                    LyricsLine {
+                        start: Some(10250),
+                        value: "Chorus".to_string()
+                    },
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn can_parse_lrc_offset() {
+        let lyrics = parse_lrc("[offset:+500]\n[00:01.00]Sooner\n[00:00.20]Start").unwrap();
+        assert_eq!(
+            lyrics
+                .lines
+                .iter()
+                .map(|line| line.start)
+                .collect::<Vec<_>>(),
+            vec![Some(0), Some(500)]
+        );
+    }
+
+    #[test]
+    fn can_parse_unsynced_lrc() {
+        assert_eq!(
+            parse_lrc("[ti:Ba Senge]\nFirst line\n\nSecond line\n"),
+            Some(Lyrics {
+                lang: UNKNOWN_LANGUAGE.to_string(),
+                synced: false,
+                lines: vec![
+                    LyricsLine {
+                        start: None,
+                        value: "First line".to_string()
+                    },
+                    LyricsLine {
+                        start: None,
+                        value: "".to_string()
+                    },
+                    LyricsLine {
+                        start: None,
+                        value: "Second line".to_string()
+                    },
+                ]
+            })
+        );
+        assert_eq!(parse_lrc("[ti:Nothing]\n"), None);
+    }
+}
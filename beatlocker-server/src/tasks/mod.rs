@@ -1,17 +1,21 @@
 mod extract_metadata;
 mod import_external_metadata_task;
 mod import_folder_task;
+mod lyrics;
 mod optimize_database_task;
+mod playlist_files;
+mod podcast_feed;
+mod refresh_podcasts_task;
 mod removed_deleted_files_task;
+mod submit_scrobbles_task;
 
 use crate::db::DbCoverArt;
 use crate::tasks::import_external_metadata_task::import_external_metadata;
-use crate::tasks::import_folder_task::import_folder;
+use crate::tasks::import_folder_task::import_music_folders;
 use crate::tasks::optimize_database_task::optimize_database;
 use crate::tasks::removed_deleted_files_task::remove_deleted_files;
 use crate::{reqwest_client, str_to_uuid, AppResult, Db, ServerOptions};
 use std::fmt::{Debug, Formatter};
-use std::path::PathBuf;
 use std::sync::Arc;
 use std::thread;
 use std::thread::JoinHandle;
@@ -21,6 +25,11 @@ use tokio::{runtime, task};
 use tracing::{debug, error, info};
 use uuid::Uuid;
 
+pub use refresh_podcasts_task::{
+    fetch_podcast_episode, refresh_podcast_feed, refresh_podcast_feeds,
+};
+pub use submit_scrobbles_task::{queue_scrobble, submit_scrobbles, ScrobbleService};
+
 pub struct TaskManager {
     #[allow(dead_code)]
     thread: JoinHandle<()>,
@@ -43,29 +52,23 @@ impl Debug for TaskEnvelope {
 #[derive(Clone, Debug)]
 pub enum TaskMessage {
     Ping,
-    ImportFolder {
-        state: Arc<TaskState>,
-        folder: PathBuf,
-        parent_folder_id: Option<Uuid>,
-    },
-    ImportExternalMetadata {
-        state: Arc<TaskState>,
-    },
-    OptimizeDatabase {
-        state: Arc<TaskState>,
-    },
-    RemoveDeletedFiles {
-        state: Arc<TaskState>,
-    },
+    ImportFolders { state: Arc<TaskState> },
+    ImportExternalMetadata { state: Arc<TaskState> },
+    OptimizeDatabase { state: Arc<TaskState> },
+    RemoveDeletedFiles { state: Arc<TaskState> },
+    SubmitScrobbles { state: Arc<TaskState> },
+    RefreshPodcasts { state: Arc<TaskState> },
 }
 
 #[derive(Debug, PartialEq)]
 pub enum TaskReply {
     Pong,
-    ImportFolder(PathBuf),
+    ImportFolders,
     ImportExternalMetadata,
     OptimizeDatabase,
     RemoveDeletedFiles,
+    SubmitScrobbles,
+    RefreshPodcasts,
 }
 
 pub struct TaskState {
@@ -107,12 +110,12 @@ impl TaskManager {
                                 TaskMessage::Ping => {
                                     envelope.reply_tx.send(TaskReply::Pong).unwrap();
                                 },
-                                TaskMessage::ImportFolder { state, folder, parent_folder_id } => {
+                                TaskMessage::ImportFolders { state } => {
                                     task::spawn(async move {
-                                        import_folder(state, folder.as_path(), parent_folder_id).await.unwrap_or_else(|e| {
+                                        import_music_folders(state).await.unwrap_or_else(|e| {
                                             error!(?e, "Error when importing folders");
                                         });
-                                        let _ = envelope.reply_tx.send(TaskReply::ImportFolder(folder));
+                                        let _ = envelope.reply_tx.send(TaskReply::ImportFolders);
                                     });
                                 }
                                 TaskMessage::ImportExternalMetadata { state } => {
@@ -139,6 +142,22 @@ impl TaskManager {
                                         let _ = envelope.reply_tx.send(TaskReply::RemoveDeletedFiles);
                                     });
                                 }
+                                TaskMessage::SubmitScrobbles { state } => {
+                                    task::spawn(async move {
+                                        submit_scrobbles(state).await.unwrap_or_else(|e| {
+                                            error!(?e, "Error when submitting scrobbles");
+                                        });
+                                        let _ = envelope.reply_tx.send(TaskReply::SubmitScrobbles);
+                                    });
+                                }
+                                TaskMessage::RefreshPodcasts { state } => {
+                                    task::spawn(async move {
+                                        refresh_podcast_feeds(state).await.unwrap_or_else(|e| {
+                                            error!(?e, "Error when refreshing podcasts");
+                                        });
+                                        let _ = envelope.reply_tx.send(TaskReply::RefreshPodcasts);
+                                    });
+                                }
                             }
                         },
                         Some(_) = shutdown_rx.recv() => {
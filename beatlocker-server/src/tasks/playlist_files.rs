@@ -0,0 +1,565 @@
+use super::*;
+use crate::DEFAULT_USERNAME;
+use sqlx::sqlite::SqliteRow;
+use sqlx::Row;
+use std::collections::HashMap;
+use std::ops::DerefMut;
+use std::path::{Component, Path, PathBuf};
+use tracing::warn;
+
+const PLAYLIST_EXTENSIONS: &[&str] = &["m3u", "m3u8", "pls"];
+
+pub fn is_playlist_file(path: &Path) -> bool {
+    match path.extension() {
+        Some(ext) => PLAYLIST_EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str()),
+        None => false,
+    }
+}
+
+/// Records a playlist file found in the library. Its songs are only resolved by
+/// [`import_playlist_files`], once all songs have been imported.
+///
+/// Playlist files that only list streams are station lists, whose streams are imported as
+/// internet radio stations instead.
+pub async fn register_playlist_file(state: &TaskState, path: &Path) -> AppResult<()> {
+    let entries = read_playlist_file(path).await?;
+    let path = path.to_string_lossy().to_string();
+    if !entries.is_empty() && entries.iter().all(|entry| is_stream_url(&entry.location)) {
+        return import_station_list(state, &path, entries).await;
+    }
+
+    let name = Path::new(&path)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.clone());
+    let now = (state.options.now_provider)();
+
+    let mut conn = state.db.conn().await?;
+    sqlx::query("DELETE FROM internet_radio_stations WHERE path = ?")
+        .bind(&path)
+        .execute(conn.deref_mut())
+        .await?;
+    sqlx::query(
+        r#"
+        INSERT OR IGNORE INTO playlists (playlist_id, name, owner, public, created, changed, path)
+        VALUES (?, ?, ?, true, ?, ?, ?)
+        "#,
+    )
+    .bind(str_to_uuid(&path))
+    .bind(name)
+    .bind(DEFAULT_USERNAME)
+    .bind(now)
+    .bind(now)
+    .bind(&path)
+    .execute(conn.deref_mut())
+    .await?;
+
+    Ok(())
+}
+
+/// Imports the stations of a station list, replacing the ones that were imported from it before.
+/// Stations are named after their title in the list, or otherwise after their stream.
+async fn import_station_list(
+    state: &TaskState,
+    path: &str,
+    entries: Vec<PlaylistEntry>,
+) -> AppResult<()> {
+    let mut conn = state.db.conn().await?;
+    for query in [
+        "DELETE FROM playlists WHERE path = ?",
+        "DELETE FROM internet_radio_stations WHERE path = ?",
+    ] {
+        sqlx::query(query)
+            .bind(path)
+            .execute(conn.deref_mut())
+            .await?;
+    }
+
+    for entry in entries {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO internet_radio_stations (station_id, name, stream_url, path)
+            VALUES (?, ?, ?, ?)
+            "#,
+        )
+        .bind(str_to_uuid(&format!("{path}:{}", entry.location)))
+        .bind(entry.title.as_ref().unwrap_or(&entry.location))
+        .bind(&entry.location)
+        .bind(path)
+        .execute(conn.deref_mut())
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// (Re)imports the songs of every playlist file that is new or has changed since it was last
+/// imported. Entries that aren't in the library are skipped, and counted as missing. Playlists
+/// with missing entries are resolved again after every import, as their songs may have been added
+/// since.
+pub async fn import_playlist_files(state: &TaskState) -> AppResult<()> {
+    let playlists = sqlx::query(
+        "SELECT playlist_id, path, file_modified, missing_entries FROM playlists WHERE path IS NOT NULL",
+    )
+    .map(|row: SqliteRow| {
+        let id: Uuid = row.get("playlist_id");
+        let path: String = row.get("path");
+        let file_modified: Option<i64> = row.get("file_modified");
+        let missing_entries: u32 = row.get("missing_entries");
+        (id, PathBuf::from(path), file_modified, missing_entries)
+    })
+    .fetch_all(state.db.conn().await?.deref_mut())
+    .await?;
+
+    for (playlist_id, path, file_modified, previously_missing) in playlists {
+        // Deleted playlist files are cleaned up by the removed deleted files task
+        let modified = match tokio::fs::metadata(&path).await.and_then(|m| m.modified()) {
+            Ok(modified) => chrono::DateTime::<chrono::Utc>::from(modified).timestamp_millis(),
+            Err(_) => continue,
+        };
+        let unchanged = file_modified == Some(modified);
+        if unchanged && previously_missing == 0 {
+            continue;
+        }
+
+        if unchanged {
+            debug!(?path, "Resolving missing entries of playlist");
+        } else {
+            info!(?path, "Importing playlist");
+        }
+        let entries = read_playlist_file(&path).await?;
+
+        let playlist_dir = path.parent().unwrap_or_else(|| Path::new(""));
+        let mut folder_child_ids = vec![];
+        let mut missing_entries = 0;
+        for entry in entries {
+            match resolve_entry(state, playlist_dir, &entry.location).await? {
+                Some(id) => folder_child_ids.push(id),
+                None => {
+                    // Only warned about once, not every time the entry is looked for again
+                    if !unchanged {
+                        warn!(
+                            ?path,
+                            entry = entry.location.as_str(),
+                            "Could not find song in playlist"
+                        );
+                    }
+                    missing_entries += 1;
+                }
+            }
+        }
+        // None of the missing entries turned up
+        if unchanged && missing_entries == previously_missing {
+            continue;
+        }
+
+        state
+            .db
+            .set_playlist_entries(playlist_id, &folder_child_ids)
+            .await?;
+        sqlx::query(
+            "UPDATE playlists SET changed = ?, file_modified = ?, missing_entries = ? WHERE playlist_id = ?",
+        )
+        .bind((state.options.now_provider)())
+        .bind(modified)
+        .bind(missing_entries)
+        .bind(playlist_id)
+        .execute(state.db.conn().await?.deref_mut())
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// An entry of a playlist file, with the title the file gives it.
+#[derive(Debug, PartialEq, Eq)]
+struct PlaylistEntry {
+    location: String,
+    title: Option<String>,
+}
+
+async fn read_playlist_file(path: &Path) -> AppResult<Vec<PlaylistEntry>> {
+    let contents = tokio::fs::read(path).await?;
+    let contents = String::from_utf8_lossy(&contents);
+    Ok(match path.extension() {
+        Some(ext) if ext.eq_ignore_ascii_case("pls") => parse_pls(&contents),
+        _ => parse_m3u(&contents),
+    })
+}
+
+/// Entries of an (extended) M3U playlist, which are all lines that aren't comments or directives.
+/// Titles come from the `#EXTINF` directive before an entry.
+fn parse_m3u(contents: &str) -> Vec<PlaylistEntry> {
+    let mut entries = vec![];
+    let mut title = None;
+    for line in contents
+        .lines()
+        .map(|line| line.trim_start_matches('\u{feff}').trim())
+    {
+        if let Some(info) = line.strip_prefix("#EXTINF:") {
+            title = info
+                .split_once(',')
+                .map(|(_, title)| title.trim().to_string())
+                .filter(|title| !title.is_empty());
+        } else if !line.is_empty() && !line.starts_with('#') {
+            entries.push(PlaylistEntry {
+                location: line.to_string(),
+                title: title.take(),
+            });
+        }
+    }
+    entries
+}
+
+/// Entries of a PLS playlist, ordered by their number.
+fn parse_pls(contents: &str) -> Vec<PlaylistEntry> {
+    let mut files: Vec<(u32, String)> = vec![];
+    let mut titles: HashMap<u32, String> = HashMap::new();
+    for line in contents.lines() {
+        let (key, value) = match line.trim().split_once('=') {
+            Some(pair) => pair,
+            None => continue,
+        };
+        let key = key.trim();
+        let value = value.trim().to_string();
+        if let Some(number) = key.strip_prefix("File").and_then(|n| n.parse().ok()) {
+            files.push((number, value));
+        } else if let Some(number) = key.strip_prefix("Title").and_then(|n| n.parse().ok()) {
+            titles.insert(number, value);
+        }
+    }
+    files.sort_by_key(|(number, _)| *number);
+    files
+        .into_iter()
+        .map(|(number, location)| PlaylistEntry {
+            location,
+            title: titles.remove(&number).filter(|title| !title.is_empty()),
+        })
+        .collect()
+}
+
+fn is_stream_url(location: &str) -> bool {
+    let location = location.to_ascii_lowercase();
+    location.starts_with("http://") || location.starts_with("https://")
+}
+
+async fn resolve_entry(
+    state: &TaskState,
+    playlist_dir: &Path,
+    entry: &str,
+) -> AppResult<Option<Uuid>> {
+    // Entries are looked up in the music folder of the playlist first
+    let mut roots: Vec<&Path> = state
+        .options
+        .music_folders
+        .iter()
+        .map(|music_folder| music_folder.path.as_path())
+        .collect();
+    roots.sort_by_key(|root| !playlist_dir.starts_with(root));
+
+    for root in roots {
+        for path in candidate_paths(root, playlist_dir, entry) {
+            if let Some(id) = state
+                .db
+                .find_folder_child_by_path(&path.to_string_lossy())
+                .await?
+            {
+                return Ok(Some(id));
+            }
+        }
+    }
+
+    Ok(None)
+}
+
+/// Paths in the library an entry could refer to, in order of preference.
+///
+/// Relative paths are relative to the playlist, or otherwise to the library root. Absolute paths
+/// are often from another machine, so the library root is tried with every part of the path
+/// that is left after removing leading folders.
+///
+/// Candidates are always built by joining onto the library root, so they are spelled the same
+/// way as the paths of imported files.
+fn candidate_paths(root: &Path, playlist_dir: &Path, entry: &str) -> Vec<PathBuf> {
+    let entry = entry.strip_prefix("file://").unwrap_or(entry);
+    if entry.contains("://") {
+        return vec![];
+    }
+
+    // Playlists made on Windows use backslashes, and possibly a drive letter
+    let entry = entry.replace('\\', "/");
+    let entry = match entry.as_bytes() {
+        [drive, b':', b'/', ..] if drive.is_ascii_alphabetic() => &entry[2..],
+        _ => &entry,
+    };
+
+    let entry = Path::new(entry);
+    if entry.has_root() {
+        let mut candidates = vec![];
+        if let Ok(relative) = entry.strip_prefix(root) {
+            candidates.push(root.join(normalize_path(relative)));
+        }
+        let entry = normalize_path(entry);
+        let parts: Vec<Component> = entry.components().collect();
+        candidates.extend(
+            (0..parts.len()).map(|skip| root.join(parts[skip..].iter().collect::<PathBuf>())),
+        );
+        candidates
+    } else {
+        let mut candidates = vec![];
+        if let Ok(relative) = playlist_dir.strip_prefix(root) {
+            candidates.push(root.join(normalize_path(&relative.join(entry))));
+        }
+        candidates.push(root.join(normalize_path(entry)));
+        candidates.dedup();
+        candidates
+    }
+}
+
+/// Removes the root, `.` and `..` from a path without touching the filesystem.
+fn normalize_path(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            Component::Normal(part) => normalized.push(part),
+            _ => {}
+        }
+    }
+    normalized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::get_playlist_impl;
+    use crate::utils::TestState;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn can_parse_m3u() {
+        let m3u =
+            "\u{feff}#EXTM3U\r\n#EXTINF:123,Artist - Title\r\nfolder1/a.mp3\r\n\r\n  b.mp3  \n";
+        assert_eq!(
+            parse_m3u(m3u),
+            vec![
+                PlaylistEntry {
+                    location: "folder1/a.mp3".to_string(),
+                    title: Some("Artist - Title".to_string())
+                },
+                PlaylistEntry {
+                    location: "b.mp3".to_string(),
+                    title: None
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn can_parse_pls() {
+        let pls = "[playlist]\nFile2=b.mp3\nTitle2=B\nFile1=a.mp3\nNumberOfEntries=2\nVersion=2\n";
+        assert_eq!(
+            parse_pls(pls),
+            vec![
+                PlaylistEntry {
+                    location: "a.mp3".to_string(),
+                    title: None
+                },
+                PlaylistEntry {
+                    location: "b.mp3".to_string(),
+                    title: Some("B".to_string())
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn can_find_candidate_paths() {
+        let root = Path::new("/music/./library");
+        let dir = Path::new("/music/./library/lists");
+        assert_eq!(
+            candidate_paths(root, dir, "../folder1/a.mp3"),
+            vec![PathBuf::from("/music/./library/folder1/a.mp3")]
+        );
+        assert_eq!(
+            candidate_paths(root, dir, "a.mp3"),
+            vec![
+                PathBuf::from("/music/./library/lists/a.mp3"),
+                PathBuf::from("/music/./library/a.mp3"),
+            ]
+        );
+        assert_eq!(
+            candidate_paths(root, dir, "C:\\Music\\folder1\\a.mp3"),
+            vec![
+                PathBuf::from("/music/./library/Music/folder1/a.mp3"),
+                PathBuf::from("/music/./library/folder1/a.mp3"),
+                PathBuf::from("/music/./library/a.mp3"),
+            ]
+        );
+        assert_eq!(
+            candidate_paths(root, dir, "/music/library/folder1/a.mp3")[0],
+            PathBuf::from("/music/./library/folder1/a.mp3")
+        );
+        assert!(candidate_paths(root, dir, "http://example.com/stream").is_empty());
+    }
+
+    fn write_playlist(state: &TestState, name: &str, contents: &str) -> PathBuf {
+        let path = state.tempdir.as_ref().unwrap().path().join(name);
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    async fn playlist_titles(state: &TestState, path: &Path) -> Vec<String> {
+        let mut conn = state.db().await.conn().await.unwrap();
+        sqlx::query(
+            r#"
+            SELECT s.title
+            FROM playlist_entries pe
+            JOIN folder_children fc ON fc.folder_child_id = pe.folder_child_id
+            JOIN songs s ON s.song_id = fc.song_id
+            WHERE pe.playlist_id = ?
+            ORDER BY pe.position
+            "#,
+        )
+        .bind(str_to_uuid(&path.to_string_lossy()))
+        .map(|row: SqliteRow| row.get("title"))
+        .fetch_all(conn.deref_mut())
+        .await
+        .unwrap()
+    }
+
+    async fn rescan(state: &TestState) {
+        state
+            .app
+            .task_manager
+            .send(state.app.import_all_folders().await.unwrap())
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn can_import_playlist_files() {
+        let state = TestState::new().await.unwrap();
+        let root = state.tempdir.as_ref().unwrap().path().to_path_buf();
+        std::fs::create_dir_all(root.join("lists")).unwrap();
+        let m3u = write_playlist(
+            &state,
+            "lists/mix.m3u8",
+            &format!(
+                "#EXTM3U\n../folder2/artist1-d.mp3\n{}\nfolder3/missing.mp3\n",
+                std::fs::canonicalize(root.join("folder1/artist1-a.mp3"))
+                    .unwrap()
+                    .to_string_lossy()
+            ),
+        );
+        let pls = write_playlist(
+            &state,
+            "mix.pls",
+            "[playlist]\nFile1=folder1\\artist2-c.mp3\nFile2=/elsewhere/folder2/artist2-f.mp3\n",
+        );
+        rescan(&state).await;
+
+        assert_eq!(playlist_titles(&state, &m3u).await, vec!["D", "A"]);
+        assert_eq!(playlist_titles(&state, &pls).await, vec!["C", "F"]);
+
+        // Entries that weren't found are counted, and only shown when there are any
+        let mut conn = state.db().await.conn().await.unwrap();
+        for (path, expected) in [(&m3u, json!(1)), (&pls, Value::Null)] {
+            let playlist = get_playlist_impl(
+                conn.deref_mut(),
+                str_to_uuid(&path.to_string_lossy()),
+                DEFAULT_USERNAME,
+            )
+            .await
+            .unwrap()
+            .unwrap();
+            assert_eq!(
+                serde_json::to_value(playlist).unwrap()["playlist"]["missingEntryCount"],
+                expected
+            );
+        }
+        drop(conn);
+
+        // Missing entries are found once they're added to the library
+        std::fs::create_dir_all(root.join("lists/folder3")).unwrap();
+        std::fs::copy(
+            root.join("folder1/artist1-a.mp3"),
+            root.join("lists/folder3/missing.mp3"),
+        )
+        .unwrap();
+        rescan(&state).await;
+        assert_eq!(playlist_titles(&state, &m3u).await, vec!["D", "A", "A"]);
+
+        // Changed files are imported again
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        std::fs::write(&m3u, "folder1/artist1-b.mp3\n").unwrap();
+        rescan(&state).await;
+        assert_eq!(playlist_titles(&state, &m3u).await, vec!["B"]);
+        let mut conn = state.db().await.conn().await.unwrap();
+        let missing: u32 = sqlx::query("SELECT missing_entries FROM playlists WHERE path = ?")
+            .bind(m3u.to_string_lossy().to_string())
+            .map(|row: SqliteRow| row.get("missing_entries"))
+            .fetch_one(conn.deref_mut())
+            .await
+            .unwrap();
+        assert_eq!(missing, 0);
+        drop(conn);
+
+        // Removed files are removed as playlists
+        std::fs::remove_file(&pls).unwrap();
+        state
+            .app
+            .task_manager
+            .send(state.app.remove_deleted_files().await.unwrap())
+            .await
+            .unwrap();
+        let mut conn = state.db().await.conn().await.unwrap();
+        let remaining: Vec<String> = sqlx::query("SELECT name FROM playlists ORDER BY name")
+            .map(|row: SqliteRow| row.get("name"))
+            .fetch_all(conn.deref_mut())
+            .await
+            .unwrap();
+        assert_eq!(remaining, vec!["mix"]);
+    }
+
+    #[tokio::test]
+    async fn can_import_station_lists() {
+        let state = TestState::new().await.unwrap();
+        let pls = write_playlist(
+            &state,
+            "radio.pls",
+            "[playlist]\nFile1=http://example.com/jazz\nTitle1=Jazz\nFile2=https://example.com/news\n",
+        );
+        rescan(&state).await;
+
+        let mut conn = state.db().await.conn().await.unwrap();
+        let stations: Vec<(String, String)> = sqlx::query(
+            "SELECT name, stream_url FROM internet_radio_stations WHERE path = ? ORDER BY name",
+        )
+        .bind(pls.to_string_lossy().to_string())
+        .map(|row: SqliteRow| (row.get("name"), row.get("stream_url")))
+        .fetch_all(conn.deref_mut())
+        .await
+        .unwrap();
+        assert_eq!(
+            stations,
+            vec![
+                ("Jazz".to_string(), "http://example.com/jazz".to_string()),
+                (
+                    "https://example.com/news".to_string(),
+                    "https://example.com/news".to_string()
+                ),
+            ]
+        );
+
+        // Station lists aren't playlists
+        let playlists: Vec<String> = sqlx::query("SELECT name FROM playlists")
+            .map(|row: SqliteRow| row.get("name"))
+            .fetch_all(conn.deref_mut())
+            .await
+            .unwrap();
+        assert!(playlists.is_empty());
+    }
+}
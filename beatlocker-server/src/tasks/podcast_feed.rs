@@ -0,0 +1,280 @@
+use crate::AppResult;
+use chrono::{DateTime, Utc};
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct PodcastFeed {
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub image_url: Option<String>,
+    pub episodes: Vec<PodcastFeedEpisode>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct PodcastFeedEpisode {
+    pub guid: Option<String>,
+    pub title: Option<String>,
+    pub description: Option<String>,
+    pub publish_date: Option<DateTime<Utc>>,
+    pub url: Option<String>,
+    pub content_type: Option<String>,
+    pub size: Option<u32>,
+    /// Duration in seconds.
+    pub duration: Option<u32>,
+}
+
+/// Parses an RSS 2.0 or Atom feed. Namespaces are ignored, so e.g. `itunes:image` is read the same
+/// way as `image`.
+pub fn parse_podcast_feed(xml: &str) -> AppResult<PodcastFeed> {
+    let mut reader = Reader::from_str(xml);
+    reader.trim_text(true);
+
+    let mut feed = PodcastFeed::default();
+    let mut episode: Option<PodcastFeedEpisode> = None;
+    // Local names of the elements the reader is in
+    let mut elements: Vec<String> = vec![];
+    let mut buf = Vec::new();
+
+    loop {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(e) => {
+                let name = local_name(&e);
+                if name == "item" || name == "entry" {
+                    episode = Some(PodcastFeedEpisode::default());
+                }
+                read_attributes(&mut feed, episode.as_mut(), &name, &e)?;
+                elements.push(name);
+            }
+            Event::Empty(e) => {
+                read_attributes(&mut feed, episode.as_mut(), &local_name(&e), &e)?;
+            }
+            Event::End(_) => {
+                if let Some(name) = elements.pop() {
+                    if name == "item" || name == "entry" {
+                        feed.episodes.extend(episode.take());
+                    }
+                }
+            }
+            Event::Text(e) => {
+                let text = e.unescape()?.to_string();
+                read_text(&mut feed, episode.as_mut(), &elements, text);
+            }
+            Event::CData(e) => {
+                let text = String::from_utf8_lossy(&e.into_inner()).trim().to_string();
+                read_text(&mut feed, episode.as_mut(), &elements, text);
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    Ok(feed)
+}
+
+fn local_name(e: &BytesStart) -> String {
+    String::from_utf8_lossy(e.local_name().as_ref()).to_string()
+}
+
+fn read_attributes(
+    feed: &mut PodcastFeed,
+    episode: Option<&mut PodcastFeedEpisode>,
+    name: &str,
+    e: &BytesStart,
+) -> AppResult<()> {
+    let mut attributes = vec![];
+    for attribute in e.attributes() {
+        let attribute = attribute?;
+        attributes.push((
+            String::from_utf8_lossy(attribute.key.local_name().as_ref()).to_string(),
+            attribute.unescape_value()?.to_string(),
+        ));
+    }
+    let attribute = |key: &str| {
+        attributes
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.clone())
+    };
+
+    match (episode, name) {
+        // RSS enclosures, and Atom links to them
+        (Some(episode), "enclosure") => {
+            episode.url = attribute("url");
+            episode.content_type = attribute("type");
+            episode.size = attribute("length").and_then(|length| length.parse().ok());
+        }
+        (Some(episode), "link") if attribute("rel").as_deref() == Some("enclosure") => {
+            episode.url = attribute("href");
+            episode.content_type = attribute("type");
+            episode.size = attribute("length").and_then(|length| length.parse().ok());
+        }
+        (None, "image") if feed.image_url.is_none() => feed.image_url = attribute("href"),
+        _ => {}
+    }
+
+    Ok(())
+}
+
+fn read_text(
+    feed: &mut PodcastFeed,
+    episode: Option<&mut PodcastFeedEpisode>,
+    elements: &[String],
+    text: String,
+) {
+    let (parent, name) = match elements {
+        [.., parent, name] => (parent.as_str(), name.as_str()),
+        _ => return,
+    };
+
+    match episode {
+        Some(episode) => match name {
+            "title" => episode.title = Some(text),
+            "description" | "summary" if episode.description.is_none() => {
+                episode.description = Some(text)
+            }
+            "guid" | "id" => episode.guid = Some(text),
+            "pubDate" => episode.publish_date = parse_rfc2822(&text),
+            "published" => episode.publish_date = parse_rfc3339(&text),
+            "updated" if episode.publish_date.is_none() => {
+                episode.publish_date = parse_rfc3339(&text)
+            }
+            "duration" => episode.duration = parse_duration(&text),
+            _ => {}
+        },
+        None => match (parent, name) {
+            ("channel" | "feed", "title") => feed.title = Some(text),
+            ("channel" | "feed", "description" | "subtitle") if feed.description.is_none() => {
+                feed.description = Some(text)
+            }
+            ("image", "url") | ("feed", "logo" | "icon") if feed.image_url.is_none() => {
+                feed.image_url = Some(text)
+            }
+            _ => {}
+        },
+    }
+}
+
+fn parse_rfc2822(text: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc2822(text)
+        .ok()
+        .map(|date| date.with_timezone(&Utc))
+}
+
+fn parse_rfc3339(text: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(text)
+        .ok()
+        .map(|date| date.with_timezone(&Utc))
+}
+
+/// Durations are given in seconds, or as `[[HH:]MM:]SS`.
+fn parse_duration(text: &str) -> Option<u32> {
+    text.split(':').try_fold(0u32, |duration, part| {
+        Some(duration * 60 + part.trim().parse::<f64>().ok()? as u32)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_parse_rss() {
+        let rss = r#"<?xml version="1.0" encoding="UTF-8"?>
+            <rss version="2.0" xmlns:itunes="http://www.itunes.com/dtds/podcast-1.0.dtd">
+              <channel>
+                <title>Tea &amp; Talk</title>
+                <description><![CDATA[All about <b>tea</b>]]></description>
+                <image><url>http://example.com/image.jpg</url><title>Ignored</title></image>
+                <itunes:image href="http://example.com/itunes.jpg"/>
+                <item>
+                  <title>Episode 2</title>
+                  <guid isPermaLink="false">ep-2</guid>
+                  <pubDate>Tue, 04 Feb 2020 10:00:00 +0100</pubDate>
+                  <enclosure url="http://example.com/2.mp3" type="audio/mpeg" length="1234"/>
+                  <itunes:duration>01:02:03</itunes:duration>
+                </item>
+                <item>
+                  <title>Episode 1</title>
+                  <description>The first one</description>
+                  <enclosure url="http://example.com/1.mp3" type="audio/mpeg" length="0"/>
+                  <itunes:duration>95</itunes:duration>
+                </item>
+              </channel>
+            </rss>"#;
+
+        let feed = parse_podcast_feed(rss).unwrap();
+        assert_eq!(feed.title.as_deref(), Some("Tea & Talk"));
+        assert_eq!(feed.description.as_deref(), Some("All about <b>tea</b>"));
+        assert_eq!(
+            feed.image_url.as_deref(),
+            Some("http://example.com/image.jpg")
+        );
+        assert_eq!(
+            feed.episodes,
+            vec![
+                PodcastFeedEpisode {
+                    guid: Some("ep-2".to_string()),
+                    title: Some("Episode 2".to_string()),
+                    description: None,
+                    publish_date: Some("2020-02-04T09:00:00Z".parse().unwrap()),
+                    url: Some("http://example.com/2.mp3".to_string()),
+                    content_type: Some("audio/mpeg".to_string()),
+                    size: Some(1234),
+                    duration: Some(3723),
+                },
+                PodcastFeedEpisode {
+                    guid: None,
+                    title: Some("Episode 1".to_string()),
+                    description: Some("The first one".to_string()),
+                    publish_date: None,
+                    url: Some("http://example.com/1.mp3".to_string()),
+                    content_type: Some("audio/mpeg".to_string()),
+                    size: Some(0),
+                    duration: Some(95),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn can_parse_atom() {
+        let atom = r#"<?xml version="1.0" encoding="utf-8"?>
+            <feed xmlns="http://www.w3.org/2005/Atom">
+              <title>Atom Cast</title>
+              <subtitle>Short and sweet</subtitle>
+              <logo>http://example.com/logo.png</logo>
+              <entry>
+                <title>Pilot</title>
+                <id>urn:uuid:1225c695</id>
+                <updated>2020-02-03T00:00:00Z</updated>
+                <published>2020-02-02T12:00:00Z</published>
+                <summary>Where it all began</summary>
+                <link rel="alternate" href="http://example.com/pilot"/>
+                <link rel="enclosure" href="http://example.com/pilot.ogg" type="audio/ogg" length="42"/>
+              </entry>
+            </feed>"#;
+
+        let feed = parse_podcast_feed(atom).unwrap();
+        assert_eq!(feed.title.as_deref(), Some("Atom Cast"));
+        assert_eq!(feed.description.as_deref(), Some("Short and sweet"));
+        assert_eq!(
+            feed.image_url.as_deref(),
+            Some("http://example.com/logo.png")
+        );
+        assert_eq!(
+            feed.episodes,
+            vec![PodcastFeedEpisode {
+                guid: Some("urn:uuid:1225c695".to_string()),
+                title: Some("Pilot".to_string()),
+                description: Some("Where it all began".to_string()),
+                publish_date: Some("2020-02-02T12:00:00Z".parse().unwrap()),
+                url: Some("http://example.com/pilot.ogg".to_string()),
+                content_type: Some("audio/ogg".to_string()),
+                size: Some(42),
+                duration: None,
+            }]
+        );
+    }
+}
@@ -0,0 +1,307 @@
+use crate::tasks::extract_metadata::extract_metadata;
+use crate::tasks::insert_cover_art;
+use crate::tasks::podcast_feed::{parse_podcast_feed, PodcastFeed};
+use crate::{
+    reqwest_client, str_to_uuid, streaming_reqwest_client, wrap_err, AppResult, TaskState,
+};
+use futures::StreamExt;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::{Response, Url};
+use sqlx::sqlite::SqliteRow;
+use sqlx::Row;
+use std::io::Seek;
+use std::ops::DerefMut;
+use std::path::Path;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Reads the feeds of all podcast channels, adding any new episodes.
+pub async fn refresh_podcast_feeds(state: Arc<TaskState>) -> AppResult<()> {
+    let channel_ids = sqlx::query("SELECT channel_id FROM podcast_channels ORDER BY created")
+        .map(|row: SqliteRow| {
+            let channel_id: Uuid = row.get("channel_id");
+            channel_id
+        })
+        .fetch_all(state.db.conn().await?.deref_mut())
+        .await?;
+
+    for channel_id in channel_ids {
+        refresh_podcast_feed(state.clone(), channel_id).await?;
+    }
+
+    Ok(())
+}
+
+/// Reads the feed of a podcast channel. If the feed can't be read, the error is stored with the
+/// channel so it can be shown to the user.
+pub async fn refresh_podcast_feed(state: Arc<TaskState>, channel_id: Uuid) -> AppResult<()> {
+    let url = sqlx::query("SELECT url FROM podcast_channels WHERE channel_id = ?")
+        .bind(channel_id)
+        .map(|row: SqliteRow| {
+            let url: String = row.get("url");
+            url
+        })
+        .fetch_optional(state.db.conn().await?.deref_mut())
+        .await?;
+    let url = match url {
+        Some(url) => url,
+        None => return Ok(()),
+    };
+
+    info!(%url, "Refreshing podcast");
+    match read_feed(&url).await {
+        Ok(feed) => store_feed(&state, channel_id, feed).await,
+        Err(e) => {
+            warn!(%url, ?e, "Could not refresh podcast");
+            sqlx::query(
+                "UPDATE podcast_channels SET status = 'error', error_message = ? WHERE channel_id = ?",
+            )
+            .bind(e.0.to_string())
+            .bind(channel_id)
+            .execute(state.db.conn().await?.deref_mut())
+            .await?;
+            Ok(())
+        }
+    }
+}
+
+async fn read_feed(url: &str) -> AppResult<PodcastFeed> {
+    let response = reqwest_client().get(url).send().await?.error_for_status()?;
+    parse_podcast_feed(&response.text().await?)
+}
+
+async fn store_feed(state: &TaskState, channel_id: Uuid, feed: PodcastFeed) -> AppResult<()> {
+    let mut conn = state.db.conn().await?;
+
+    // Cover art is only fetched again when the channel changes its image
+    let (image_url, cover_art_id) =
+        sqlx::query("SELECT image_url, cover_art_id FROM podcast_channels WHERE channel_id = ?")
+            .bind(channel_id)
+            .map(|row: SqliteRow| {
+                let image_url: Option<String> = row.get("image_url");
+                let cover_art_id: Option<Uuid> = row.get("cover_art_id");
+                (image_url, cover_art_id)
+            })
+            .fetch_one(conn.deref_mut())
+            .await?;
+    let cover_art_id = match &feed.image_url {
+        Some(url) if image_url.as_ref() != Some(url) || cover_art_id.is_none() => {
+            wrap_err(
+                async { Ok(Some(insert_cover_art(&state.db, url).await?)) },
+                || None,
+            )
+            .await
+        }
+        Some(_) => cover_art_id,
+        None => None,
+    };
+
+    sqlx::query(
+        r#"
+        UPDATE podcast_channels
+        SET title = ?, description = ?, image_url = ?, cover_art_id = ?, status = 'completed', error_message = NULL
+        WHERE channel_id = ?
+        "#,
+    )
+    .bind(&feed.title)
+    .bind(&feed.description)
+    .bind(&feed.image_url)
+    .bind(cover_art_id)
+    .bind(channel_id)
+    .execute(conn.deref_mut())
+    .await?;
+
+    for episode in feed.episodes {
+        // Episodes without an enclosure have nothing to listen to
+        let url = match episode.url {
+            Some(url) => url,
+            None => continue,
+        };
+        let episode_id = str_to_uuid(&format!(
+            "{channel_id}:{}",
+            episode.guid.as_ref().unwrap_or(&url)
+        ));
+
+        sqlx::query(
+            r#"
+            INSERT INTO podcast_episodes (episode_id, channel_id, title, description, publish_date, url, status, content_type, size, duration)
+            VALUES (?, ?, ?, ?, ?, ?, 'skipped', ?, ?, ?)
+            ON CONFLICT (episode_id) DO UPDATE SET title = excluded.title, description = excluded.description, publish_date = excluded.publish_date
+            "#,
+        )
+        .bind(episode_id)
+        .bind(channel_id)
+        .bind(episode.title.as_ref().unwrap_or(&url))
+        .bind(&episode.description)
+        .bind(episode.publish_date)
+        .bind(&url)
+        .bind(&episode.content_type)
+        .bind(episode.size.filter(|size| *size > 0))
+        .bind(episode.duration)
+        .execute(conn.deref_mut())
+        .await?;
+    }
+
+    Ok(())
+}
+
+/// Downloads an episode into the podcast path, and reads its metadata like that of any song.
+pub async fn fetch_podcast_episode(state: Arc<TaskState>, episode_id: Uuid) -> AppResult<()> {
+    let episode = sqlx::query("SELECT channel_id, url FROM podcast_episodes WHERE episode_id = ?")
+        .bind(episode_id)
+        .map(|row: SqliteRow| {
+            let channel_id: Uuid = row.get("channel_id");
+            let url: String = row.get("url");
+            (channel_id, url)
+        })
+        .fetch_optional(state.db.conn().await?.deref_mut())
+        .await?;
+    let (channel_id, url) = match episode {
+        Some(episode) => episode,
+        None => return Ok(()),
+    };
+
+    sqlx::query("UPDATE podcast_episodes SET status = 'downloading' WHERE episode_id = ?")
+        .bind(episode_id)
+        .execute(state.db.conn().await?.deref_mut())
+        .await?;
+
+    info!(%url, "Downloading podcast episode");
+    if let Err(e) = download_episode_file(&state, channel_id, episode_id, &url).await {
+        warn!(%url, ?e, "Could not download podcast episode");
+        sqlx::query("UPDATE podcast_episodes SET status = 'error' WHERE episode_id = ?")
+            .bind(episode_id)
+            .execute(state.db.conn().await?.deref_mut())
+            .await?;
+    }
+
+    Ok(())
+}
+
+async fn download_episode_file(
+    state: &TaskState,
+    channel_id: Uuid,
+    episode_id: Uuid,
+    url: &str,
+) -> AppResult<()> {
+    let response = streaming_reqwest_client()
+        .get(url)
+        .send()
+        .await?
+        .error_for_status()?;
+    let content_type = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .map(|content_type| content_type.to_string());
+
+    let folder = state.options.podcast_path.join(channel_id.to_string());
+    tokio::fs::create_dir_all(&folder).await?;
+    let path = folder.join(format!(
+        "{episode_id}.{}",
+        episode_suffix(url, content_type.as_deref())
+    ));
+
+    // Episodes are downloaded next to where they go, so a failed download never leaves a partial
+    // file where a complete one is expected
+    let download_path = path.with_extension("download");
+    if let Err(e) = download_to(response, &download_path).await {
+        let _ = tokio::fs::remove_file(&download_path).await;
+        return Err(e);
+    }
+    tokio::fs::rename(&download_path, &path).await?;
+
+    let (metadata, size) = {
+        let file = std::fs::File::open(&path)?;
+        let size = file.metadata()?.len() as u32;
+        (
+            match extract_metadata(path.as_os_str(), || {
+                // Clones share the position of the file, so each reader starts over at the start
+                let mut file = file.try_clone()?;
+                file.rewind()?;
+                Ok(Box::new(file))
+            }) {
+                Ok(m) => m,
+                Err(e) => {
+                    warn!(?path, ?e, "Could not extract metadata");
+                    None
+                }
+            },
+            size,
+        )
+    };
+    let metadata = metadata.unwrap_or_default();
+
+    sqlx::query(
+        r#"
+        UPDATE podcast_episodes
+        SET status = 'completed', path = ?, content_type = COALESCE(?, content_type), suffix = ?, size = ?, bit_rate = ?, duration = COALESCE(?, duration)
+        WHERE episode_id = ?
+        "#,
+    )
+    .bind(path.to_string_lossy().to_string())
+    .bind(metadata.content_type.or(content_type))
+    .bind(path.extension().map(|ext| ext.to_string_lossy().to_string()))
+    .bind(size)
+    .bind(metadata.bit_rate)
+    .bind(metadata.duration.map(|d| d.num_seconds() as u32))
+    .bind(episode_id)
+    .execute(state.db.conn().await?.deref_mut())
+    .await?;
+
+    Ok(())
+}
+
+async fn download_to(response: Response, path: &Path) -> AppResult<()> {
+    let mut file = tokio::fs::File::create(path).await?;
+    let mut stream = response.bytes_stream();
+    while let Some(chunk) = stream.next().await {
+        file.write_all(&chunk?).await?;
+    }
+    file.flush().await?;
+
+    Ok(())
+}
+
+/// Suffix to store an episode with, from its URL or otherwise its content type. Most podcasts are
+/// MP3s, so that's assumed when neither tells.
+fn episode_suffix(url: &str, content_type: Option<&str>) -> String {
+    let from_url = Url::parse(url).ok().and_then(|url| {
+        Path::new(url.path())
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+    });
+    let from_content_type = content_type.and_then(|content_type| {
+        match content_type.split(';').next().unwrap_or_default().trim() {
+            "audio/mpeg" => Some("mp3"),
+            "audio/mp4" | "audio/x-m4a" | "audio/aac" => Some("m4a"),
+            "audio/ogg" => Some("ogg"),
+            "audio/flac" => Some("flac"),
+            _ => None,
+        }
+    });
+
+    from_url
+        .or_else(|| from_content_type.map(|suffix| suffix.to_string()))
+        .unwrap_or_else(|| "mp3".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_find_episode_suffix() {
+        assert_eq!(
+            episode_suffix("http://example.com/episode.MP3?id=1", None),
+            "mp3"
+        );
+        assert_eq!(
+            episode_suffix("http://example.com/episode", Some("audio/ogg; codecs=opus")),
+            "ogg"
+        );
+        assert_eq!(episode_suffix("http://example.com/episode", None), "mp3");
+    }
+}
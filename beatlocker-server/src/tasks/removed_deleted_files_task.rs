@@ -79,7 +79,70 @@ pub async fn remove_deleted_files(state: Arc<TaskState>) -> AppResult<()> {
         }
     }
 
-    // Cleanup albums and artists without songs
+    let playlists = sqlx::query("SELECT playlist_id, path FROM playlists WHERE path IS NOT NULL")
+        .map(|row: SqliteRow| {
+            let id: Uuid = row.get("playlist_id");
+            let path: String = row.get("path");
+            (id, PathBuf::from_str(&path).unwrap())
+        })
+        .fetch_all(conn.deref_mut())
+        .await?;
+
+    for (playlist_id, playlist_path) in playlists {
+        if tokio::fs::metadata(&playlist_path).await.ok().is_none() {
+            info!("Playlist was removed: {:?}", playlist_path.as_os_str());
+
+            sqlx::query("DELETE FROM playlists WHERE playlist_id = ?")
+                .bind(playlist_id)
+                .execute(conn.deref_mut())
+                .await?;
+        }
+    }
+
+    let station_lists =
+        sqlx::query("SELECT DISTINCT path FROM internet_radio_stations WHERE path IS NOT NULL")
+            .map(|row: SqliteRow| {
+                let path: String = row.get("path");
+                PathBuf::from_str(&path).unwrap()
+            })
+            .fetch_all(conn.deref_mut())
+            .await?;
+
+    for station_list_path in station_lists {
+        if tokio::fs::metadata(&station_list_path).await.ok().is_none() {
+            info!(
+                "Station list was removed: {:?}",
+                station_list_path.as_os_str()
+            );
+
+            sqlx::query("DELETE FROM internet_radio_stations WHERE path = ?")
+                .bind(station_list_path.to_string_lossy().to_string())
+                .execute(conn.deref_mut())
+                .await?;
+        }
+    }
+
+    let lyrics_files = sqlx::query("SELECT DISTINCT path FROM lyrics WHERE path IS NOT NULL")
+        .map(|row: SqliteRow| {
+            let path: String = row.get("path");
+            PathBuf::from_str(&path).unwrap()
+        })
+        .fetch_all(conn.deref_mut())
+        .await?;
+
+    for lyrics_path in lyrics_files {
+        if tokio::fs::metadata(&lyrics_path).await.ok().is_none() {
+            info!("Lyrics file was removed: {:?}", lyrics_path.as_os_str());
+
+            // Lines are removed through the foreign key
+            sqlx::query("DELETE FROM lyrics WHERE path = ?")
+                .bind(lyrics_path.to_string_lossy().to_string())
+                .execute(conn.deref_mut())
+                .await?;
+        }
+    }
+
+    // Cleanup albums and artists without songs, and anything removed from the search index
     sqlx::query(
         r#"
         DELETE FROM album_artists
@@ -93,19 +156,31 @@ pub async fn remove_deleted_files(state: Arc<TaskState>) -> AppResult<()> {
         DELETE FROM artists
         WHERE artist_id IN
         (SELECT a.artist_id FROM artists a LEFT JOIN songs s on a.artist_id = s.artist_id WHERE s.artist_id IS NULL);
+
+        DELETE FROM search_items
+        WHERE item_id NOT IN (select song_id from songs UNION ALL select album_id from albums UNION ALL select artist_id from artists UNION ALL select folder_id from folders);
     "#,
     )
     .execute(conn.deref_mut())
     .await?;
 
-    // Cleanup cover art and favorites
+    // Cleanup cover art, favorites and ratings
     sqlx::query(
         r#"
         DELETE FROM cover_art
-        WHERE cover_art_id NOT IN (select song_id from songs UNION ALL select artist_id from songs UNION ALL select album_id from songs);
+        WHERE cover_art_id NOT IN (
+            select cover_art_id from songs where cover_art_id is not null
+            UNION ALL select cover_art_id from albums where cover_art_id is not null
+            UNION ALL select cover_art_id from artists where cover_art_id is not null
+            UNION ALL select cover_art_id from folders where cover_art_id is not null
+            UNION ALL select cover_art_id from podcast_channels where cover_art_id is not null
+        );
 
         DELETE FROM starred
         WHERE starred_id NOT IN (select song_id from songs UNION ALL select artist_id from songs UNION ALL select album_id from songs);
+
+        DELETE FROM ratings
+        WHERE rated_id NOT IN (select song_id from songs UNION ALL select artist_id from songs UNION ALL select album_id from songs UNION ALL select folder_id from folders);
     "#,
     )
     .execute(conn.deref_mut())
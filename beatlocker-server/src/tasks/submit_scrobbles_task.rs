@@ -0,0 +1,290 @@
+use crate::{post_lastfm, post_listenbrainz, AppResult, TaskState};
+use anyhow::anyhow;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use sqlx::sqlite::SqliteRow;
+use sqlx::{Row, SqliteConnection};
+use std::ops::DerefMut;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// Both services accept at most this many plays in a single request.
+const MAX_BATCH_SIZE: usize = 50;
+/// Plays that still can't be submitted after this many attempts are given up on.
+const MAX_ATTEMPTS: u32 = 20;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScrobbleService {
+    LastFm,
+    ListenBrainz,
+}
+
+impl ScrobbleService {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ScrobbleService::LastFm => "lastfm",
+            ScrobbleService::ListenBrainz => "listenbrainz",
+        }
+    }
+
+    fn from_str(service: &str) -> Option<Self> {
+        match service {
+            "lastfm" => Some(ScrobbleService::LastFm),
+            "listenbrainz" => Some(ScrobbleService::ListenBrainz),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct OutboxEntry {
+    id: i64,
+    artist: String,
+    title: String,
+    album: Option<String>,
+    duration: Option<u32>,
+    played: DateTime<Utc>,
+    attempts: u32,
+}
+
+static SUBMIT_MUTEX: once_cell::sync::OnceCell<Mutex<()>> = once_cell::sync::OnceCell::new();
+
+/// Adds a play to the outbox of every account the user has linked, to be submitted from `now` on.
+pub async fn queue_scrobble(
+    conn: &mut SqliteConnection,
+    username: &str,
+    song_id: Uuid,
+    played: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> AppResult<()> {
+    sqlx::query(
+        r#"
+        INSERT INTO scrobble_outbox (username, service, artist, title, album, duration, played, next_attempt)
+        SELECT a.username, a.service, ar.name, s.title, al.title, s.duration, ?, ?
+        FROM scrobble_accounts a, songs s
+        JOIN artists ar ON ar.artist_id = s.artist_id
+        LEFT JOIN albums al ON al.album_id = s.album_id
+        WHERE a.username = ? AND s.song_id = ?
+        "#,
+    )
+    .bind(played)
+    .bind(now)
+    .bind(username)
+    .bind(song_id)
+    .execute(conn)
+    .await?;
+
+    Ok(())
+}
+
+/// Submits every play in the outbox that is due. Plays that can't be submitted are retried later,
+/// waiting longer after every attempt.
+pub async fn submit_scrobbles(state: Arc<TaskState>) -> AppResult<()> {
+    // Never submit the same play twice because two submissions overlap
+    let _guard = SUBMIT_MUTEX.get_or_init(|| Mutex::new(())).lock().await;
+
+    let mut conn = state.db.conn().await?;
+    let now = (state.options.now_provider)();
+
+    let accounts = sqlx::query(
+        r#"
+        SELECT DISTINCT a.username, a.service, a.token
+        FROM scrobble_accounts a
+        JOIN scrobble_outbox o ON o.username = a.username AND o.service = a.service
+        WHERE o.next_attempt <= ?
+        "#,
+    )
+    .bind(now)
+    .map(|row: SqliteRow| {
+        let username: String = row.get("username");
+        let service: String = row.get("service");
+        let token: String = row.get("token");
+        (username, service, token)
+    })
+    .fetch_all(conn.deref_mut())
+    .await?;
+
+    for (username, service, token) in accounts {
+        let entries = sqlx::query(
+            r#"
+            SELECT * FROM scrobble_outbox
+            WHERE username = ? AND service = ? AND next_attempt <= ?
+            ORDER BY played
+            "#,
+        )
+        .bind(&username)
+        .bind(&service)
+        .bind(now)
+        .map(|row: SqliteRow| OutboxEntry {
+            id: row.get("outbox_id"),
+            artist: row.get("artist"),
+            title: row.get("title"),
+            album: row.get("album"),
+            duration: row.get("duration"),
+            played: row.get("played"),
+            attempts: row.get("attempts"),
+        })
+        .fetch_all(conn.deref_mut())
+        .await?;
+
+        for batch in entries.chunks(MAX_BATCH_SIZE) {
+            let result = match ScrobbleService::from_str(&service) {
+                Some(ScrobbleService::LastFm) => submit_lastfm(&state, &token, batch).await,
+                Some(ScrobbleService::ListenBrainz) => {
+                    submit_listenbrainz(&state, &token, batch).await
+                }
+                None => Err(anyhow!("Unknown scrobble service").into()),
+            };
+
+            match result {
+                Ok(()) => {
+                    info!(username, service, count = batch.len(), "Submitted plays");
+                    for entry in batch {
+                        sqlx::query("DELETE FROM scrobble_outbox WHERE outbox_id = ?")
+                            .bind(entry.id)
+                            .execute(conn.deref_mut())
+                            .await?;
+                    }
+                }
+                Err(e) => {
+                    warn!(
+                        username,
+                        service,
+                        ?e,
+                        "Could not submit plays, will retry later"
+                    );
+                    for entry in batch {
+                        postpone(conn.deref_mut(), entry, now).await?;
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+async fn postpone(
+    conn: &mut SqliteConnection,
+    entry: &OutboxEntry,
+    now: DateTime<Utc>,
+) -> AppResult<()> {
+    let attempts = entry.attempts + 1;
+    if attempts >= MAX_ATTEMPTS {
+        warn!(?entry, "Giving up on submitting play");
+        sqlx::query("DELETE FROM scrobble_outbox WHERE outbox_id = ?")
+            .bind(entry.id)
+            .execute(conn)
+            .await?;
+        return Ok(());
+    }
+
+    sqlx::query("UPDATE scrobble_outbox SET attempts = ?, next_attempt = ? WHERE outbox_id = ?")
+        .bind(attempts)
+        .bind(now + retry_delay(attempts))
+        .bind(entry.id)
+        .execute(conn)
+        .await?;
+    Ok(())
+}
+
+/// Waits twice as long after every failed attempt, up to six hours.
+fn retry_delay(attempts: u32) -> Duration {
+    Duration::minutes(1 << attempts.min(9)).min(Duration::hours(6))
+}
+
+async fn submit_lastfm(
+    state: &TaskState,
+    session_key: &str,
+    batch: &[OutboxEntry],
+) -> AppResult<()> {
+    let (api_key, api_secret) = match (
+        &state.options.lastfm_api_key,
+        &state.options.lastfm_api_secret,
+    ) {
+        (Some(api_key), Some(api_secret)) => (api_key, api_secret),
+        _ => return Err(anyhow!("No last.fm API key and secret were configured").into()),
+    };
+
+    let mut params = vec![
+        ("method".to_string(), "track.scrobble".to_string()),
+        ("api_key".to_string(), api_key.clone()),
+        ("sk".to_string(), session_key.to_string()),
+    ];
+    for (index, entry) in batch.iter().enumerate() {
+        params.push((format!("artist[{index}]"), entry.artist.clone()));
+        params.push((format!("track[{index}]"), entry.title.clone()));
+        params.push((
+            format!("timestamp[{index}]"),
+            entry.played.timestamp().to_string(),
+        ));
+        if let Some(album) = &entry.album {
+            params.push((format!("album[{index}]"), album.clone()));
+        }
+        if let Some(duration) = entry.duration {
+            params.push((format!("duration[{index}]"), duration.to_string()));
+        }
+    }
+
+    let params: Vec<(&str, &str)> = params
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.as_str()))
+        .collect();
+    post_lastfm::<Value>(&state.options.lastfm_url, api_secret, &params).await?;
+    Ok(())
+}
+
+async fn submit_listenbrainz(
+    state: &TaskState,
+    token: &str,
+    batch: &[OutboxEntry],
+) -> AppResult<()> {
+    let payload: Vec<Value> = batch
+        .iter()
+        .map(|entry| {
+            let mut additional_info = json!({ "submission_client": "beatlocker" });
+            if let Some(duration) = entry.duration {
+                additional_info["duration_ms"] = json!(duration as u64 * 1000);
+            }
+            let mut track_metadata = json!({
+                "artist_name": entry.artist,
+                "track_name": entry.title,
+                "additional_info": additional_info,
+            });
+            if let Some(album) = &entry.album {
+                track_metadata["release_name"] = json!(album);
+            }
+            json!({
+                "listened_at": entry.played.timestamp(),
+                "track_metadata": track_metadata,
+            })
+        })
+        .collect();
+
+    let listen_type = if batch.len() == 1 { "single" } else { "import" };
+    post_listenbrainz(
+        &state.options.listenbrainz_url,
+        token,
+        "submit-listens",
+        &json!({ "listen_type": listen_type, "payload": payload }),
+    )
+    .await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backs_off_exponentially() {
+        assert_eq!(retry_delay(1), Duration::minutes(2));
+        assert_eq!(retry_delay(2), Duration::minutes(4));
+        assert_eq!(retry_delay(8), Duration::minutes(256));
+        assert_eq!(retry_delay(9), Duration::hours(6));
+        assert_eq!(retry_delay(MAX_ATTEMPTS), Duration::hours(6));
+    }
+}
@@ -0,0 +1,242 @@
+use crate::AppResult;
+use std::collections::HashMap;
+use std::ffi::OsStr;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::SystemTime;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+#[derive(Clone, Debug)]
+pub struct ThumbnailCacheOptions {
+    /// Folder to store thumbnails in. If there is none, thumbnails are not cached.
+    pub path: Option<PathBuf>,
+    /// Maximum total size of all cached thumbnails, in bytes.
+    pub max_size: u64,
+}
+
+impl Default for ThumbnailCacheOptions {
+    fn default() -> Self {
+        Self {
+            path: None,
+            max_size: 256 * 1024 * 1024,
+        }
+    }
+}
+
+/// On-disk cache of generated thumbnails.
+///
+/// When the cache grows beyond its maximum size, the least recently used thumbnails are removed.
+/// Usage is only tracked in memory, so after a restart the oldest files are removed first.
+pub struct ThumbnailCache {
+    path: Option<PathBuf>,
+    max_size: u64,
+    index: Mutex<CacheIndex>,
+}
+
+#[derive(Default)]
+struct CacheIndex {
+    entries: HashMap<String, CacheEntry>,
+    total_size: u64,
+    clock: u64,
+}
+
+struct CacheEntry {
+    size: u64,
+    last_used: u64,
+}
+
+impl CacheIndex {
+    fn touch(&mut self, key: &str) -> bool {
+        self.clock += 1;
+        match self.entries.get_mut(key) {
+            Some(entry) => {
+                entry.last_used = self.clock;
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn insert(&mut self, key: String, size: u64) {
+        self.clock += 1;
+        self.remove(&key);
+        self.total_size += size;
+        self.entries.insert(
+            key,
+            CacheEntry {
+                size,
+                last_used: self.clock,
+            },
+        );
+    }
+
+    fn remove(&mut self, key: &str) {
+        if let Some(entry) = self.entries.remove(key) {
+            self.total_size -= entry.size;
+        }
+    }
+
+    /// Removes the least recently used entries until the cache fits, returning their keys.
+    fn evict(&mut self, max_size: u64) -> Vec<String> {
+        let mut evicted = vec![];
+        while self.total_size > max_size {
+            let key = match self.entries.iter().min_by_key(|(_, e)| e.last_used) {
+                Some((key, _)) => key.clone(),
+                None => break,
+            };
+            self.remove(&key);
+            evicted.push(key);
+        }
+        evicted
+    }
+}
+
+impl ThumbnailCache {
+    pub fn new(options: &ThumbnailCacheOptions) -> AppResult<Self> {
+        let mut index = CacheIndex::default();
+
+        if let Some(path) = &options.path {
+            std::fs::create_dir_all(path)?;
+
+            let mut files = vec![];
+            for entry in std::fs::read_dir(path)? {
+                let entry = entry?;
+                let metadata = entry.metadata()?;
+                if !metadata.is_file() {
+                    continue;
+                }
+                // Left behind by an interrupted write
+                if entry.path().extension() == Some(OsStr::new("tmp")) {
+                    std::fs::remove_file(entry.path())?;
+                    continue;
+                }
+                let modified = metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH);
+                files.push((
+                    modified,
+                    entry.file_name().to_string_lossy().to_string(),
+                    metadata.len(),
+                ));
+            }
+
+            files.sort();
+            for (_, key, size) in files {
+                index.insert(key, size);
+            }
+        }
+
+        let cache = Self {
+            path: options.path.clone(),
+            max_size: options.max_size,
+            index: Mutex::new(index),
+        };
+        cache.evict();
+        Ok(cache)
+    }
+
+    pub async fn get(&self, key: &str) -> Option<Vec<u8>> {
+        let path = self.path.as_ref()?.join(key);
+        if !self.index.lock().unwrap().touch(key) {
+            return None;
+        }
+
+        match tokio::fs::read(&path).await {
+            Ok(data) => Some(data),
+            Err(e) => {
+                warn!(?path, ?e, "Could not read cached thumbnail");
+                self.index.lock().unwrap().remove(key);
+                None
+            }
+        }
+    }
+
+    pub async fn insert(&self, key: &str, data: &[u8]) -> AppResult<()> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+
+        // Write to a temporary file first, so nobody ever reads a partial thumbnail. Each write gets
+        // its own, as the same thumbnail may be requested several times at once.
+        let temp_path = path.join(format!("{key}.{}.tmp", Uuid::new_v4()));
+        tokio::fs::write(&temp_path, data).await?;
+        tokio::fs::rename(&temp_path, path.join(key)).await?;
+
+        self.index
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), data.len() as u64);
+        self.evict();
+        Ok(())
+    }
+
+    fn evict(&self) {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return,
+        };
+
+        let evicted = self.index.lock().unwrap().evict(self.max_size);
+        for key in evicted {
+            debug!(key, "Removing cached thumbnail");
+            if let Err(e) = std::fs::remove_file(path.join(&key)) {
+                warn!(key, ?e, "Could not remove cached thumbnail");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn cache(dir: &TempDir, max_size: u64) -> ThumbnailCache {
+        ThumbnailCache::new(&ThumbnailCacheOptions {
+            path: Some(dir.path().to_path_buf()),
+            max_size,
+        })
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn can_cache() {
+        let dir = TempDir::new().unwrap();
+        let cache = cache(&dir, 100);
+
+        assert_eq!(cache.get("a.jpg").await, None);
+        cache.insert("a.jpg", b"aaa").await.unwrap();
+        assert_eq!(cache.get("a.jpg").await, Some(b"aaa".to_vec()));
+
+        // Thumbnails survive a restart
+        let cache = self::cache(&dir, 100);
+        assert_eq!(cache.get("a.jpg").await, Some(b"aaa".to_vec()));
+    }
+
+    #[tokio::test]
+    async fn evicts_least_recently_used() {
+        let dir = TempDir::new().unwrap();
+        let cache = cache(&dir, 10);
+
+        cache.insert("a.jpg", &[0; 4]).await.unwrap();
+        cache.insert("b.jpg", &[0; 4]).await.unwrap();
+        assert!(cache.get("a.jpg").await.is_some());
+        cache.insert("c.jpg", &[0; 4]).await.unwrap();
+
+        assert!(cache.get("a.jpg").await.is_some());
+        assert!(cache.get("b.jpg").await.is_none());
+        assert!(cache.get("c.jpg").await.is_some());
+        assert!(!dir.path().join("b.jpg").exists());
+
+        // Shrinking the cache on startup removes files as well
+        let _ = self::cache(&dir, 4);
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[tokio::test]
+    async fn does_nothing_without_path() {
+        let cache = ThumbnailCache::new(&ThumbnailCacheOptions::default()).unwrap();
+        cache.insert("a.jpg", b"aaa").await.unwrap();
+        assert_eq!(cache.get("a.jpg").await, None);
+    }
+}
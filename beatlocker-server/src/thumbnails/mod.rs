@@ -0,0 +1,104 @@
+mod cache;
+
+pub use cache::{ThumbnailCache, ThumbnailCacheOptions};
+
+use crate::AppResult;
+use image::codecs::jpeg::JpegEncoder;
+use image::imageops::FilterType;
+use image::io::Reader;
+use image::ImageFormat;
+use std::io::Cursor;
+
+/// Thumbnails are never made larger than this, no matter what size is requested.
+pub const MAX_THUMBNAIL_SIZE: u32 = 1200;
+const JPEG_QUALITY: u8 = 85;
+
+/// Resizes an image so that it fits within `size` pixels and encodes it as a JPEG.
+///
+/// Returns `None` if the original can be sent as-is: it is already a JPEG and is no larger than
+/// requested. Images are never upscaled.
+pub fn make_thumbnail(data: &[u8], size: Option<u32>) -> AppResult<Option<Vec<u8>>> {
+    let format = image::guess_format(data)?;
+    let is_jpeg = format == ImageFormat::Jpeg;
+    let size = size.map(|s| s.clamp(1, MAX_THUMBNAIL_SIZE));
+
+    let (width, height) = Reader::with_format(Cursor::new(data), format).into_dimensions()?;
+    let needs_resize = match size {
+        Some(size) => width > size || height > size,
+        None => false,
+    };
+    if !needs_resize && is_jpeg {
+        return Ok(None);
+    }
+
+    let image = image::load_from_memory_with_format(data, format)?;
+    let image = match size {
+        Some(size) if needs_resize => image.resize(size, size, FilterType::Lanczos3),
+        _ => image,
+    };
+
+    let mut thumbnail = vec![];
+    JpegEncoder::new_with_quality(&mut thumbnail, JPEG_QUALITY).encode_image(&image.to_rgb8())?;
+    Ok(Some(thumbnail))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{GenericImageView, ImageOutputFormat, RgbaImage};
+
+    fn image(width: u32, height: u32, format: ImageOutputFormat) -> Vec<u8> {
+        let mut data = Cursor::new(vec![]);
+        image::DynamicImage::ImageRgba8(RgbaImage::new(width, height))
+            .write_to(&mut data, format)
+            .unwrap();
+        data.into_inner()
+    }
+
+    #[test]
+    fn can_resize() {
+        let thumbnail = make_thumbnail(&image(400, 200, ImageOutputFormat::Png), Some(100))
+            .unwrap()
+            .unwrap();
+        assert_eq!(image::guess_format(&thumbnail).unwrap(), ImageFormat::Jpeg);
+        assert_eq!(
+            image::load_from_memory(&thumbnail).unwrap().dimensions(),
+            (100, 50)
+        );
+    }
+
+    #[test]
+    fn does_not_upscale() {
+        let original = image(50, 50, ImageOutputFormat::Jpeg(80));
+        assert_eq!(make_thumbnail(&original, Some(100)).unwrap(), None);
+        assert_eq!(make_thumbnail(&original, None).unwrap(), None);
+    }
+
+    #[test]
+    fn converts_to_jpeg() {
+        for format in [ImageOutputFormat::Png, ImageOutputFormat::Gif] {
+            let original = image(50, 50, format);
+            let thumbnail = make_thumbnail(&original, None).unwrap().unwrap();
+            assert_eq!(image::guess_format(&thumbnail).unwrap(), ImageFormat::Jpeg);
+            assert_eq!(
+                image::load_from_memory(&thumbnail).unwrap().dimensions(),
+                (50, 50)
+            );
+        }
+    }
+
+    #[test]
+    fn caps_size() {
+        let original = image(2000, 1000, ImageOutputFormat::Jpeg(80));
+        let thumbnail = make_thumbnail(&original, Some(5000)).unwrap().unwrap();
+        assert_eq!(
+            image::load_from_memory(&thumbnail).unwrap().dimensions(),
+            (MAX_THUMBNAIL_SIZE, MAX_THUMBNAIL_SIZE / 2)
+        );
+    }
+
+    #[test]
+    fn rejects_non_images() {
+        assert!(make_thumbnail(b"not an image", Some(100)).is_err());
+    }
+}
@@ -0,0 +1,169 @@
+use crate::{AppError, AppResult};
+use anyhow::anyhow;
+use std::path::Path;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::DecoderOptions;
+use symphonia::core::errors::Error;
+use symphonia::core::formats::{FormatOptions, SeekMode, SeekTo};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use symphonia::core::units::Time;
+
+/// Decodes an audio file into a 16-bit PCM WAV stream, starting at `time_offset` seconds.
+///
+/// The WAV data is handed to `write` in chunks. When `write` returns false decoding stops, which
+/// happens when whoever is consuming the stream has gone away.
+pub fn decode_to_wav(
+    path: &Path,
+    time_offset: u32,
+    mut write: impl FnMut(Vec<u8>) -> bool,
+) -> AppResult<()> {
+    let file = std::fs::File::open(path)?;
+    let mss = MediaSourceStream::new(Box::new(file), Default::default());
+
+    let mut hint = Hint::new();
+    if let Some(extension) = path.extension().and_then(|s| s.to_str()) {
+        hint.with_extension(extension);
+    }
+
+    let probed = symphonia::default::get_probe()
+        .format(
+            &hint,
+            mss,
+            &FormatOptions::default(),
+            &MetadataOptions::default(),
+        )
+        .map_err(|_| anyhow!("Unsupported format"))?;
+
+    let mut format = probed.format;
+    let track = format
+        .default_track()
+        .ok_or_else(|| AppError(anyhow!("No supported audio tracks")))?;
+    let track_id = track.id;
+    let mut decoder =
+        symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+
+    if time_offset > 0 {
+        format.seek(
+            SeekMode::Coarse,
+            SeekTo::Time {
+                time: Time::from(time_offset),
+                track_id: Some(track_id),
+            },
+        )?;
+        decoder.reset();
+    }
+
+    let mut header_written = false;
+    let mut sample_buffer: Option<SampleBuffer<i16>> = None;
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(Error::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => return Err(e.into()),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // Corrupt packets can just be skipped
+            Err(Error::DecodeError(_)) => continue,
+            Err(e) => return Err(e.into()),
+        };
+
+        let spec = *decoded.spec();
+        if !header_written {
+            if !write(wav_header(spec.rate, spec.channels.count() as u16)) {
+                return Ok(());
+            }
+            header_written = true;
+        }
+
+        let required_capacity = decoded.capacity() * spec.channels.count();
+        let buffer = match &mut sample_buffer {
+            Some(buffer) if buffer.capacity() >= required_capacity => buffer,
+            _ => sample_buffer.insert(SampleBuffer::new(decoded.capacity() as u64, spec)),
+        };
+        buffer.copy_interleaved_ref(decoded);
+
+        let bytes = buffer
+            .samples()
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect();
+        if !write(bytes) {
+            return Ok(());
+        }
+    }
+
+    Ok(())
+}
+
+/// Header of a 16-bit PCM WAV stream of unknown length.
+fn wav_header(sample_rate: u32, channels: u16) -> Vec<u8> {
+    const BITS_PER_SAMPLE: u16 = 16;
+    let block_align = channels * BITS_PER_SAMPLE / 8;
+    let byte_rate = sample_rate * block_align as u32;
+
+    let mut header = Vec::with_capacity(44);
+    header.extend_from_slice(b"RIFF");
+    header.extend_from_slice(&u32::MAX.to_le_bytes());
+    header.extend_from_slice(b"WAVE");
+    header.extend_from_slice(b"fmt ");
+    header.extend_from_slice(&16u32.to_le_bytes());
+    header.extend_from_slice(&1u16.to_le_bytes());
+    header.extend_from_slice(&channels.to_le_bytes());
+    header.extend_from_slice(&sample_rate.to_le_bytes());
+    header.extend_from_slice(&byte_rate.to_le_bytes());
+    header.extend_from_slice(&block_align.to_le_bytes());
+    header.extend_from_slice(&BITS_PER_SAMPLE.to_le_bytes());
+    header.extend_from_slice(b"data");
+    header.extend_from_slice(&u32::MAX.to_le_bytes());
+    header
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_decode_to_wav() {
+        let mut wav = vec![];
+        decode_to_wav(
+            Path::new("tests/data/Richard Bona/Richard Bona - Ba Senge.ogg"),
+            0,
+            |chunk| {
+                wav.extend(chunk);
+                true
+            },
+        )
+        .unwrap();
+
+        assert_eq!(&wav[0..4], b"RIFF");
+        assert_eq!(&wav[8..12], b"WAVE");
+        // 6 seconds of 16-bit stereo audio at 44.1kHz
+        assert!(wav.len() > 44 + 5 * 44100 * 2 * 2);
+    }
+
+    #[test]
+    fn can_decode_from_offset() {
+        let decode = |offset| {
+            let mut len = 0;
+            decode_to_wav(
+                Path::new("tests/data/Richard Bona/Richard Bona - Ba Senge.ogg"),
+                offset,
+                |chunk| {
+                    len += chunk.len();
+                    true
+                },
+            )
+            .unwrap();
+            len
+        };
+
+        assert!(decode(3) < decode(0));
+    }
+}
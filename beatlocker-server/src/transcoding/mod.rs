@@ -0,0 +1,304 @@
+mod decoder;
+
+use crate::AppResult;
+use anyhow::anyhow;
+use decoder::decode_to_wav;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::process::Stdio;
+use std::str::FromStr;
+use std::task::{Context, Poll};
+use tokio::io::{AsyncRead, AsyncWriteExt, ReadBuf};
+use tokio::process::{Child, ChildStdout, Command};
+use tokio::sync::mpsc;
+use tracing::{debug, warn};
+
+/// A named way of transcoding songs, selected through the `format` parameter of `stream`.
+///
+/// The command may contain the placeholders `{path}`, `{bitrate}` (in kbps) and `{offset}`
+/// (in seconds). If `{path}` is used the command is expected to read the file itself, otherwise
+/// the song is decoded by Beatlocker and fed to the command's stdin as a WAV stream. Either way
+/// the encoded result is read from the command's stdout.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TranscodeProfile {
+    pub name: String,
+    pub content_type: String,
+    pub bit_rate: u32,
+    pub command: Vec<String>,
+}
+
+impl FromStr for TranscodeProfile {
+    type Err = String;
+
+    /// Parses a profile in the form `name:content-type:bitrate:command`,
+    /// e.g. `mp3:audio/mpeg:128:lame --silent -b {bitrate} - -`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<&str> = s.splitn(4, ':').collect();
+        match parts[..] {
+            [name, content_type, bit_rate, command] => {
+                let bit_rate = bit_rate
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("Invalid bitrate in transcode profile '{s}'"))?;
+                let command: Vec<String> =
+                    command.split_whitespace().map(|s| s.to_string()).collect();
+                if command.is_empty() {
+                    return Err(format!("Missing command in transcode profile '{s}'"));
+                }
+
+                Ok(TranscodeProfile {
+                    name: name.trim().to_string(),
+                    content_type: content_type.trim().to_string(),
+                    bit_rate,
+                    command,
+                })
+            }
+            _ => Err(format!(
+                "Transcode profile '{s}' should be in the form name:content-type:bitrate:command"
+            )),
+        }
+    }
+}
+
+impl TranscodeProfile {
+    /// Decides whether a song should be transcoded for a `stream` request, and if so with which
+    /// profile and at which bitrate.
+    ///
+    /// An explicitly requested `format` is used when a profile with that name exists, unless the
+    /// song is already in that format and within the bitrate limit. Otherwise the first profile
+    /// is used whenever the song exceeds `max_bit_rate` or has to start at a time offset.
+    /// `format=raw` always disables transcoding.
+    pub fn select<'a>(
+        profiles: &'a [TranscodeProfile],
+        format: Option<&str>,
+        max_bit_rate: Option<u32>,
+        time_offset: Option<u32>,
+        song_suffix: Option<&str>,
+        song_bit_rate: Option<u32>,
+    ) -> Option<(&'a TranscodeProfile, u32)> {
+        if format
+            .map(|f| f.eq_ignore_ascii_case("raw"))
+            .unwrap_or_default()
+        {
+            return None;
+        }
+
+        let max_bit_rate = max_bit_rate.filter(|b| *b > 0);
+        let has_offset = time_offset.unwrap_or_default() > 0;
+        let exceeds_bit_rate = match (max_bit_rate, song_bit_rate) {
+            (Some(max), Some(song)) => song > max,
+            (Some(_), None) => true,
+            (None, _) => false,
+        };
+
+        let requested =
+            format.and_then(|f| profiles.iter().find(|p| p.name.eq_ignore_ascii_case(f)));
+        let profile = match requested {
+            Some(profile) => {
+                let same_format = song_suffix
+                    .map(|s| s.eq_ignore_ascii_case(&profile.name))
+                    .unwrap_or_default();
+                if same_format && !exceeds_bit_rate && !has_offset {
+                    return None;
+                }
+                profile
+            }
+            None if exceeds_bit_rate || has_offset => profiles.first()?,
+            None => return None,
+        };
+
+        let bit_rate = max_bit_rate
+            .map(|max| max.min(profile.bit_rate))
+            .unwrap_or(profile.bit_rate);
+        Some((profile, bit_rate))
+    }
+
+    fn args(&self, path: &Path, bit_rate: u32, time_offset: u32) -> Vec<String> {
+        self.command
+            .iter()
+            .map(|arg| {
+                arg.replace("{path}", &path.to_string_lossy())
+                    .replace("{bitrate}", &bit_rate.to_string())
+                    .replace("{offset}", &time_offset.to_string())
+            })
+            .collect()
+    }
+
+    fn reads_file(&self) -> bool {
+        self.command.iter().any(|arg| arg.contains("{path}"))
+    }
+
+    /// Starts transcoding a file, returning the encoded output as it is produced.
+    pub fn transcode(
+        &self,
+        path: impl Into<PathBuf>,
+        bit_rate: u32,
+        time_offset: u32,
+    ) -> AppResult<TranscodeStream> {
+        let path = path.into();
+        let args = self.args(&path, bit_rate, time_offset);
+        debug!(?args, "Transcoding");
+
+        let reads_file = self.reads_file();
+        let mut child = Command::new(&args[0])
+            .args(&args[1..])
+            .stdin(if reads_file {
+                Stdio::null()
+            } else {
+                Stdio::piped()
+            })
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()?;
+
+        if !reads_file {
+            let mut stdin = child
+                .stdin
+                .take()
+                .ok_or_else(|| anyhow!("Could not open transcoder stdin"))?;
+            let (tx, mut rx) = mpsc::channel::<Vec<u8>>(16);
+
+            tokio::task::spawn_blocking(move || {
+                if let Err(e) =
+                    decode_to_wav(&path, time_offset, |chunk| tx.blocking_send(chunk).is_ok())
+                {
+                    warn!(?path, ?e, "Could not decode file for transcoding");
+                }
+            });
+            tokio::spawn(async move {
+                while let Some(chunk) = rx.recv().await {
+                    if stdin.write_all(&chunk).await.is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+
+        let stdout = child
+            .stdout
+            .take()
+            .ok_or_else(|| anyhow!("Could not open transcoder stdout"))?;
+        Ok(TranscodeStream {
+            _child: child,
+            stdout,
+        })
+    }
+}
+
+/// Output of a running transcoder. The transcoder is killed when this is dropped.
+pub struct TranscodeStream {
+    _child: Child,
+    stdout: ChildStdout,
+}
+
+impl AsyncRead for TranscodeStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.stdout).poll_read(cx, buf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profiles() -> Vec<TranscodeProfile> {
+        vec![
+            "mp3:audio/mpeg:128:lame -b {bitrate} - -".parse().unwrap(),
+            "opus:audio/ogg:96:ffmpeg -i {path} -b:a {bitrate}k -f opus -"
+                .parse()
+                .unwrap(),
+        ]
+    }
+
+    fn select(
+        format: Option<&str>,
+        max_bit_rate: Option<u32>,
+        time_offset: Option<u32>,
+        song_suffix: &str,
+        song_bit_rate: u32,
+    ) -> Option<(String, u32)> {
+        TranscodeProfile::select(
+            &profiles(),
+            format,
+            max_bit_rate,
+            time_offset,
+            Some(song_suffix),
+            Some(song_bit_rate),
+        )
+        .map(|(p, b)| (p.name.clone(), b))
+    }
+
+    #[test]
+    fn can_parse_profile() {
+        let profile: TranscodeProfile = "mp3:audio/mpeg:128:lame --silent -b {bitrate} - -"
+            .parse()
+            .unwrap();
+        assert_eq!(profile.name, "mp3");
+        assert_eq!(profile.content_type, "audio/mpeg");
+        assert_eq!(profile.bit_rate, 128);
+        assert_eq!(
+            profile.command,
+            &["lame", "--silent", "-b", "{bitrate}", "-", "-"]
+        );
+        assert!(!profile.reads_file());
+        assert_eq!(
+            profile.args(Path::new("a.flac"), 64, 0),
+            &["lame", "--silent", "-b", "64", "-", "-"]
+        );
+
+        assert!("mp3:audio/mpeg:lame".parse::<TranscodeProfile>().is_err());
+        assert!("mp3:audio/mpeg:abc:lame"
+            .parse::<TranscodeProfile>()
+            .is_err());
+        assert!("mp3:audio/mpeg:128: ".parse::<TranscodeProfile>().is_err());
+    }
+
+    #[test]
+    fn selects_requested_format() {
+        assert_eq!(
+            select(Some("opus"), None, None, "flac", 900),
+            Some(("opus".to_string(), 96))
+        );
+        assert_eq!(
+            select(Some("mp3"), Some(64), None, "flac", 900),
+            Some(("mp3".to_string(), 64))
+        );
+        assert_eq!(
+            select(Some("mp3"), Some(320), None, "flac", 900),
+            Some(("mp3".to_string(), 128))
+        );
+        // Already in the right format
+        assert_eq!(select(Some("mp3"), None, None, "mp3", 320), None);
+        assert_eq!(
+            select(Some("mp3"), Some(128), None, "mp3", 320),
+            Some(("mp3".to_string(), 128))
+        );
+    }
+
+    #[test]
+    fn selects_default_profile_when_limited() {
+        assert_eq!(select(None, None, None, "flac", 900), None);
+        assert_eq!(select(None, Some(0), None, "flac", 900), None);
+        assert_eq!(select(None, Some(320), None, "mp3", 256), None);
+        assert_eq!(
+            select(None, Some(96), None, "flac", 900),
+            Some(("mp3".to_string(), 96))
+        );
+        assert_eq!(
+            select(None, None, Some(30), "mp3", 256),
+            Some(("mp3".to_string(), 128))
+        );
+        // Unknown formats are treated as if no format was requested
+        assert_eq!(select(Some("aac"), None, None, "flac", 900), None);
+    }
+
+    #[test]
+    fn raw_disables_transcoding() {
+        assert_eq!(select(Some("raw"), Some(96), Some(30), "flac", 900), None);
+    }
+}
@@ -1,7 +1,8 @@
 use crate::{reqwest_client_builder, AppResult, RateLimiterMiddleware};
+use anyhow::anyhow;
 use governor::Quota;
 use http_cache_reqwest::{Cache, CacheMode, HttpCache, MokaManager};
-use reqwest::header::CONTENT_TYPE;
+use reqwest::header::{AUTHORIZATION, CONTENT_TYPE};
 use reqwest::{Method, StatusCode};
 use reqwest_middleware::ClientWithMiddleware;
 use reqwest_retry::policies::ExponentialBackoff;
@@ -66,6 +67,9 @@ pub struct MusicbrainzRecording {
 #[derive(Debug, Deserialize)]
 pub struct MusicbrainzRelease {
     pub id: String,
+    /// How well the release matches a search, from 0 to 100. Only search results have one.
+    #[serde(default)]
+    pub score: Option<u32>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -85,6 +89,12 @@ pub struct MusicbrainzTag {
     pub name: String,
 }
 
+#[derive(Debug, Deserialize)]
+pub struct MusicbrainzReleasesResponse {
+    #[serde(default)]
+    pub releases: Vec<MusicbrainzRelease>,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct MusicbrainzArtistsResponse {
     pub artists: Vec<MusicbrainzArtist>,
@@ -122,6 +132,34 @@ impl LastFmArtist {
     }
 }
 
+#[derive(Debug, Deserialize)]
+pub struct LastFmAlbumResponse {
+    pub album: Option<LastFmAlbum>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LastFmAlbum {
+    pub url: Option<String>,
+    pub mbid: Option<String>,
+    #[serde(default)]
+    pub image: Vec<LastFmImage>,
+    pub wiki: Option<LastFmWiki>,
+}
+
+impl LastFmAlbum {
+    pub fn image(&self, size: &str) -> Option<String> {
+        self.image
+            .iter()
+            .find(|i| i.size == size && !i.text.is_empty())
+            .map(|i| i.text.clone())
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LastFmWiki {
+    pub summary: String,
+}
+
 #[derive(Debug, Deserialize)]
 pub struct LastFmImage {
     #[serde(rename = "#text")]
@@ -134,6 +172,58 @@ pub struct LastFmBio {
     pub summary: String,
 }
 
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LastFmSimilarArtistsResponse {
+    #[serde(rename = "similarartists")]
+    pub similar_artists: LastFmSimilarArtists,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LastFmSimilarArtists {
+    #[serde(default)]
+    pub artist: Vec<LastFmSimilarArtist>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LastFmSimilarArtist {
+    pub name: String,
+    pub mbid: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LastFmTopTracksResponse {
+    #[serde(rename = "toptracks")]
+    pub top_tracks: LastFmTopTracks,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LastFmTopTracks {
+    #[serde(default)]
+    pub track: Vec<LastFmTrack>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct LastFmTrack {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LastFmSessionResponse {
+    pub session: LastFmSession,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LastFmSession {
+    pub name: String,
+    pub key: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct LastFmErrorResponse {
+    error: u32,
+    message: String,
+}
+
 static DISCOGS_CLIENT: once_cell::sync::OnceCell<ClientWithMiddleware> =
     once_cell::sync::OnceCell::new();
 
@@ -312,12 +402,13 @@ pub async fn get_cover_art_archive<T: for<'a> Deserialize<'a>>(
 }
 
 pub async fn get_lastfm<T: for<'a> Deserialize<'a>, Q: Serialize + Debug + ?Sized>(
+    base_url: &str,
     query: &Q,
 ) -> AppResult<Option<T>> {
     debug!(?query, "Sending last.fm query");
 
     let response = lastfm_client()
-        .request(Method::GET, "http://ws.audioscrobbler.com/2.0/")
+        .request(Method::GET, base_url)
         .header(CONTENT_TYPE, "application/json")
         .query(query)
         .send()
@@ -343,6 +434,83 @@ pub async fn get_lastfm<T: for<'a> Deserialize<'a>, Q: Serialize + Debug + ?Size
     }
 }
 
+static SCROBBLE_CLIENT: once_cell::sync::OnceCell<ClientWithMiddleware> =
+    once_cell::sync::OnceCell::new();
+
+fn scrobble_client() -> &'static ClientWithMiddleware {
+    // Failed submissions are retried through the scrobble outbox instead
+    SCROBBLE_CLIENT.get_or_init(|| reqwest_client_builder().build())
+}
+
+/// Sends a signed request to last.fm, which is needed for anything done on behalf of a user.
+pub async fn post_lastfm<T: for<'a> Deserialize<'a>>(
+    base_url: &str,
+    api_secret: &str,
+    params: &[(&str, &str)],
+) -> AppResult<T> {
+    debug!(?params, "Sending signed last.fm request");
+
+    let mut form = params.to_vec();
+    let signature = lastfm_signature(params, api_secret);
+    form.push(("api_sig", &signature));
+    form.push(("format", "json"));
+
+    let response = scrobble_client()
+        .request(Method::POST, base_url)
+        .form(&form)
+        .send()
+        .await?;
+
+    let status_code = response.status();
+    let json = response.text().await?;
+    if let Ok(error) = serde_json::from_str::<LastFmErrorResponse>(&json) {
+        return Err(anyhow!("last.fm error {}: {}", error.error, error.message).into());
+    }
+    if !status_code.is_success() {
+        return Err(anyhow!("last.fm responded with {}", status_code).into());
+    }
+    Ok(serde_json::from_str::<T>(&json)?)
+}
+
+/// See <https://www.last.fm/api/authspec#_8-signing-calls>.
+fn lastfm_signature(params: &[(&str, &str)], api_secret: &str) -> String {
+    let mut params = params.to_vec();
+    params.sort();
+    let mut signature = String::new();
+    for (name, value) in params {
+        signature.push_str(name);
+        signature.push_str(value);
+    }
+    signature.push_str(api_secret);
+    format!("{:x}", md5::compute(signature))
+}
+
+pub async fn post_listenbrainz<B: Serialize + Debug + ?Sized>(
+    base_url: &str,
+    token: &str,
+    endpoint: &str,
+    body: &B,
+) -> AppResult<()> {
+    debug!(?endpoint, ?body, "Sending ListenBrainz request");
+
+    let response = scrobble_client()
+        .request(
+            Method::POST,
+            format!("{}/1/{}", base_url.trim_end_matches('/'), endpoint),
+        )
+        .header(AUTHORIZATION, format!("Token {token}"))
+        .json(body)
+        .send()
+        .await?;
+
+    let status_code = response.status();
+    if !status_code.is_success() {
+        let text = response.text().await.unwrap_or_default();
+        return Err(anyhow!("ListenBrainz responded with {}: {}", status_code, text).into());
+    }
+    Ok(())
+}
+
 fn default_cache_middleware() -> Cache<MokaManager> {
     Cache(HttpCache {
         mode: CacheMode::ForceCache,
@@ -350,3 +518,23 @@ fn default_cache_middleware() -> Cache<MokaManager> {
         options: None,
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn can_sign_lastfm_requests() {
+        // Parameters are sorted by name before signing
+        assert_eq!(
+            lastfm_signature(
+                &[("method", "auth.getSession"), ("api_key", "key")],
+                "secret"
+            ),
+            format!(
+                "{:x}",
+                md5::compute("api_keykeymethodauth.getSessionsecret")
+            )
+        );
+    }
+}
@@ -3,16 +3,18 @@ use reqwest_middleware::ClientWithMiddleware;
 use reqwest_retry::RetryTransientMiddleware;
 use siphasher::sip128::{Hasher128, SipHasher};
 use std::future::Future;
-use std::hash::Hash;
+use std::hash::{Hash, Hasher};
 use std::time::Duration;
 use tracing::warn;
 use uuid::Uuid;
 
 mod api_clients;
 mod rate_limiter;
+mod sort_names;
 
 pub use api_clients::*;
 pub use rate_limiter::RateLimiterMiddleware;
+pub use sort_names::*;
 
 #[cfg(test)]
 mod test_utils;
@@ -26,6 +28,14 @@ pub fn str_to_uuid(str: &str) -> Uuid {
     Uuid::from_u64_pair(result.h1, result.h2)
 }
 
+/// Id derived from the contents of some data, so identical data always gets the same id.
+pub fn bytes_to_uuid(data: &[u8]) -> Uuid {
+    let mut h = SipHasher::new();
+    h.write(data);
+    let result = h.finish128();
+    Uuid::from_u64_pair(result.h1, result.h2)
+}
+
 static REQWEST_CLIENT: once_cell::sync::OnceCell<ClientWithMiddleware> =
     once_cell::sync::OnceCell::new();
 
@@ -38,6 +48,24 @@ pub fn reqwest_client() -> &'static ClientWithMiddleware {
     })
 }
 
+static STREAMING_REQWEST_CLIENT: once_cell::sync::OnceCell<reqwest::Client> =
+    once_cell::sync::OnceCell::new();
+
+/// Client for responses that are streamed for as long as someone listens, so unlike
+/// [`reqwest_client`] it only times out while connecting and doesn't retry.
+pub fn streaming_reqwest_client() -> &'static reqwest::Client {
+    STREAMING_REQWEST_CLIENT.get_or_init(|| {
+        let mut headers = HeaderMap::new();
+        headers.insert("User-Agent", USER_AGENT.parse().unwrap());
+
+        reqwest::Client::builder()
+            .connect_timeout(Duration::from_secs(5))
+            .default_headers(headers)
+            .build()
+            .unwrap()
+    })
+}
+
 pub fn reqwest_client_builder() -> reqwest_middleware::ClientBuilder {
     let mut headers = HeaderMap::new();
     headers.insert("User-Agent", USER_AGENT.parse().unwrap());
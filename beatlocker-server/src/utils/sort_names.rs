@@ -0,0 +1,81 @@
+use unidecode::unidecode;
+
+/// Key that names are ordered by: transliterated to ASCII, lowercased, and without a leading
+/// ignored article, so "The Beatles" sorts as "beatles" and "Émilie" as "emilie".
+pub fn sort_key(name: &str, ignored_articles: &[String]) -> String {
+    let name = name.trim();
+    let without_article = ignored_articles
+        .iter()
+        .find_map(|article| {
+            let prefix = name.get(..article.len())?;
+            let rest = name.get(article.len()..)?;
+            (prefix.eq_ignore_ascii_case(article) && rest.starts_with(' '))
+                .then(|| rest.trim_start())
+                .filter(|rest| !rest.is_empty())
+        })
+        .unwrap_or(name);
+
+    unidecode(without_article).to_lowercase()
+}
+
+/// Name that artists and songs are matched by: transliterated to ASCII, lowercased, and with words
+/// separated by a single space, so "AC/DC" and "ac-dc" match.
+pub fn match_name(name: &str) -> String {
+    unidecode(name)
+        .to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Name of the index a sort key is listed under: its first letter, or `#` for anything else.
+pub fn index_name(sort_key: &str) -> String {
+    match sort_key.chars().next() {
+        Some(c) if c.is_ascii_alphabetic() => c.to_ascii_uppercase().to_string(),
+        _ => "#".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DEFAULT_IGNORED_ARTICLES;
+
+    fn articles() -> Vec<String> {
+        DEFAULT_IGNORED_ARTICLES
+            .iter()
+            .map(|a| a.to_string())
+            .collect()
+    }
+
+    #[test]
+    fn ignores_articles() {
+        assert_eq!(sort_key("The Beatles", &articles()), "beatles");
+        assert_eq!(sort_key("the  beatles", &articles()), "beatles");
+        assert_eq!(
+            sort_key("Theatre of Tragedy", &articles()),
+            "theatre of tragedy"
+        );
+        assert_eq!(sort_key("The", &articles()), "the");
+        assert_eq!(
+            sort_key("A Tribe Called Quest", &[]),
+            "a tribe called quest"
+        );
+    }
+
+    #[test]
+    fn transliterates() {
+        assert_eq!(sort_key("Émilie Simon", &articles()), "emilie simon");
+        assert_eq!(index_name(&sort_key("Ólafur Arnalds", &articles())), "O");
+        assert_eq!(index_name(&sort_key("2Pac", &articles())), "#");
+        assert_eq!(index_name(""), "#");
+    }
+
+    #[test]
+    fn can_find_match_names() {
+        assert_eq!(match_name("AC/DC"), "ac dc");
+        assert_eq!(match_name("  Beyoncé -- Live "), "beyonce live");
+        assert_eq!(match_name("!!!"), "");
+    }
+}
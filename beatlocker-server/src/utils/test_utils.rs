@@ -1,5 +1,6 @@
-use crate::{App, AppResult, DatabaseOptions, Db, ServerOptions};
+use crate::{App, AppResult, DatabaseOptions, Db, MusicFolderOptions, ServerOptions};
 use chrono::{DateTime, Utc};
+use id3::frame::{Picture, PictureType};
 use id3::{Tag, TagLike, Timestamp};
 use std::fs;
 use std::path::{Path, PathBuf};
@@ -7,6 +8,12 @@ use std::str::FromStr;
 use std::sync::Arc;
 use tempfile::TempDir;
 
+/// Embedded in the first song of the 'SharedAlbum' album
+pub const MOCK_COVER_ART: &[u8] = include_bytes!("../api/fallback_cover.jpg");
+
+/// Sidecar image in folder 1
+pub const MOCK_SIDECAR_COVER_ART: &[u8] = b"folder1 cover";
+
 pub struct TestState {
     pub app: App,
     pub tempdir: Option<TempDir>,
@@ -18,7 +25,7 @@ impl TestState {
         add_mock_data(tempdir.as_ref().unwrap().path()).await?;
 
         let options = ServerOptions {
-            path: tempdir.as_ref().unwrap().path().into(),
+            music_folders: vec![MusicFolderOptions::main(tempdir.as_ref().unwrap().path())],
             database: DatabaseOptions {
                 path: Some(PathBuf::from_str(".")?),
                 in_memory: true,
@@ -50,12 +57,14 @@ pub async fn add_mock_data(path: &Path) -> AppResult<()> {
     // Album 1 made by artist 1, released in 2025
     // Album 2 made by artist 2, released in 2020
     // Album 3 made by both artists, released in 2014, under "Various Artists"
-    // Folder 1: 2 songs by artist 1 (album 1), 1 song by artist 2 (album 2)
+    // Folder 1: 2 songs by artist 1 (album 1), 1 song by artist 2 (album 2), and a sidecar image
     // Folder 2: 2 songs by artist 1 (album 1), 1 song by artist 2 (album 2)
     // Folder 3: 1 song by artist 1, 1 song by artist 2, both part of album 3
+    // Song G has embedded cover art
     fs::create_dir_all(path.join("folder1"))?;
     fs::create_dir_all(path.join("folder2"))?;
     fs::create_dir_all(path.join("folder3"))?;
+    fs::write(path.join("folder1/Cover.JPG"), MOCK_SIDECAR_COVER_ART)?;
     write_mp3(&path.join("folder1/artist1-a.mp3"), |tag| {
         tag.set_title("A");
         tag.set_album("Artist1_Album1");
@@ -119,6 +128,12 @@ pub async fn add_mock_data(path: &Path) -> AppResult<()> {
         tag.set_disc(1);
         tag.set_genre("Genre7");
         tag.set_date_recorded(Timestamp::from_str("2014").unwrap());
+        tag.add_frame(Picture {
+            mime_type: "image/jpeg".to_string(),
+            picture_type: PictureType::CoverFront,
+            description: "Cover".to_string(),
+            data: MOCK_COVER_ART.to_vec(),
+        });
     })?;
     write_mp3(&path.join("folder3/artist2-h.mp3"), |tag| {
         tag.set_title("H");
@@ -0,0 +1,121 @@
+use crate::test_utils::TestClient;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::{Json, Router};
+use beatlocker_server::*;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+#[path = "test_utils/mod.rs"]
+mod test_utils;
+
+use test_utils::*;
+
+/// Stands in for last.fm, and counts how often it was asked something.
+async fn lastfm(
+    State(requests): State<Arc<AtomicUsize>>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    requests.fetch_add(1, Ordering::SeqCst);
+    assert_eq!(params["api_key"], "key");
+    assert_eq!(params["method"], "album.getinfo");
+    match params["album"].as_str() {
+        "Motorway (Original Motion Picture Soundtrack)" => Json(json!({
+            "album": {
+                "name": "Motorway (Original Motion Picture Soundtrack)",
+                "mbid": "0e4bc8b4-5b35-4b2b-a7a1-1d0b0e8c5b4e",
+                "url": "https://www.last.fm/music/Alex+Gopher/Motorway",
+                "image": [
+                    { "#text": "https://lastfm.example/small.png", "size": "small" },
+                    { "#text": "https://lastfm.example/medium.png", "size": "medium" },
+                    { "#text": "https://lastfm.example/large.png", "size": "large" },
+                    { "#text": "", "size": "extralarge" }
+                ],
+                "wiki": { "summary": "Music for a film about cars." }
+            }
+        })),
+        _ => Json(json!({ "error": 6, "message": "Album not found" })),
+    }
+}
+
+async fn setup() -> AppResult<(App, TestClient, Arc<AtomicUsize>)> {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let router = Router::new()
+        .route("/lastfm/", get(lastfm))
+        .with_state(requests.clone());
+    let addr = spawn_stand_in(router);
+
+    let (app, client) = start_and_import(ServerOptions {
+        lastfm_api_key: Some("key".to_string()),
+        lastfm_url: format!("http://{addr}/lastfm/"),
+        ..test_options()
+    })
+    .await?;
+
+    Ok((app, client, requests))
+}
+
+async fn get_album_info(client: &TestClient, url: &str) -> Value {
+    get_json(client, url).await["albumInfo"].clone()
+}
+
+#[tokio::test]
+async fn album_info_test() -> AppResult<()> {
+    let (_app, client, requests) = setup().await?;
+
+    let expected = json!({
+        "notes": "Music for a film about cars.",
+        "musicBrainzId": "0e4bc8b4-5b35-4b2b-a7a1-1d0b0e8c5b4e",
+        "lastFmUrl": "https://www.last.fm/music/Alex+Gopher/Motorway",
+        "smallImageUrl": "https://lastfm.example/small.png",
+        "mediumImageUrl": "https://lastfm.example/medium.png",
+        "largeImageUrl": "https://lastfm.example/large.png"
+    });
+    assert_eq!(
+        get_album_info(
+            &client,
+            &format!("/rest/getAlbumInfo2?f=json&id={MOTORWAY_OST_ALBUM_UUID}")
+        )
+        .await,
+        expected
+    );
+
+    // The info was stored, so last.fm isn't asked again, also not when going through a song
+    assert_eq!(
+        get_album_info(
+            &client,
+            &format!("/rest/getAlbumInfo?f=json&id={MOTORWAY_OST_RADAR_UNIT_FOLDER_CHILD_UUID}")
+        )
+        .await,
+        expected
+    );
+    assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+    // Albums last.fm doesn't know are only asked about once as well
+    for _ in 0..2 {
+        let info = get_album_info(
+            &client,
+            &format!("/rest/getAlbumInfo?f=json&id={RICHARD_BONA_BA_SENGE_FOLDER_CHILD_UUID}"),
+        )
+        .await;
+        assert!(info.get("lastFmUrl").is_none());
+        assert!(info.get("notes").is_none());
+        assert!(info.get("smallImageUrl").is_none());
+    }
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+    // Only albums are accepted by getAlbumInfo2
+    let res = client
+        .get(&format!(
+            "/rest/getAlbumInfo2?f=json&id={MOTORWAY_OST_RADAR_UNIT_FOLDER_CHILD_UUID}"
+        ))
+        .send()
+        .await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    Ok(())
+}
@@ -0,0 +1,120 @@
+use crate::test_utils::TestClient;
+use beatlocker_server::*;
+use serde_json::Value;
+
+#[path = "test_utils/mod.rs"]
+mod test_utils;
+
+use test_utils::*;
+
+async fn setup() -> AppResult<(App, TestClient)> {
+    start_and_import(ServerOptions {
+        subsonic_auth: root_auth(),
+        ..test_options()
+    })
+    .await
+}
+
+#[tokio::test]
+async fn bookmarks_test() -> AppResult<()> {
+    let (_app, client) = setup().await?;
+    get_json(
+        &client,
+        &format!("/rest/createUser?{ROOT}&username=joe&password=secret"),
+    )
+    .await;
+
+    // Bookmarks can be created by song id and are listed with their folder child
+    get_json(
+        &client,
+        &format!("/rest/createBookmark?{ROOT}&id={MOTORWAY_OST_RADAR_UNIT_SONG_UUID}&position=1500&comment=Chapter%202"),
+    )
+    .await;
+    get_json(
+        &client,
+        &format!("/rest/createBookmark?{ROOT}&id={MOTORWAY_OST_RADAR_UNIT_FOLDER_CHILD_UUID}&position=2500"),
+    )
+    .await;
+    let res = get_json(&client, &format!("/rest/getBookmarks?{ROOT}")).await;
+    let bookmarks = res["bookmarks"]["bookmark"].as_array().unwrap();
+    assert_eq!(bookmarks.len(), 1);
+    assert_eq!(bookmarks[0]["position"], 2500);
+    assert_eq!(bookmarks[0]["username"], "root");
+    assert_eq!(bookmarks[0]["comment"], Value::Null);
+    assert_eq!(bookmarks[0]["changed"], "2020-02-02T00:00:00Z");
+    assert_eq!(
+        bookmarks[0]["entry"]["id"],
+        MOTORWAY_OST_RADAR_UNIT_FOLDER_CHILD_UUID
+    );
+    assert_eq!(bookmarks[0]["entry"]["title"], "Radar Unit");
+
+    // Bookmarks are per user
+    let res = get_json(&client, &format!("/rest/getBookmarks?{JOE}")).await;
+    assert_eq!(res["bookmarks"]["bookmark"], serde_json::json!([]));
+
+    let res = client
+        .get("/rest/getBookmarks?u=root&p=sesame")
+        .send()
+        .await;
+    let xml = res.xml_string().await;
+    assert!(xml.contains(r#"<bookmark position="2500" username="root""#));
+    assert!(xml.contains(r#"<entry id="9fe0fb24-dabd-4464-258b-1ab72a28aa94""#));
+
+    get_json(
+        &client,
+        &format!("/rest/deleteBookmark?{ROOT}&id={MOTORWAY_OST_RADAR_UNIT_SONG_UUID}"),
+    )
+    .await;
+    let res = get_json(&client, &format!("/rest/getBookmarks?{ROOT}")).await;
+    assert_eq!(res["bookmarks"]["bookmark"], serde_json::json!([]));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn play_queue_test() -> AppResult<()> {
+    let (_app, client) = setup().await?;
+
+    // Nothing is returned until a queue is saved
+    let res = get_json(&client, &format!("/rest/getPlayQueue?{ROOT}")).await;
+    assert_eq!(res["playQueue"], Value::Null);
+
+    get_json(
+        &client,
+        &format!(
+            "/rest/savePlayQueue?{ROOT}&id={MOTORWAY_OST_RADAR_UNIT_FOLDER_CHILD_UUID}&id={RICHARD_BONA_BA_SENGE_FOLDER_CHILD_UUID}&current={RICHARD_BONA_BA_SENGE_FOLDER_CHILD_UUID}&position=3000&c=phone"
+        ),
+    )
+    .await;
+    let res = get_json(&client, &format!("/rest/getPlayQueue?{ROOT}")).await;
+    let play_queue = &res["playQueue"];
+    assert_eq!(
+        play_queue["current"],
+        RICHARD_BONA_BA_SENGE_FOLDER_CHILD_UUID
+    );
+    assert_eq!(play_queue["position"], 3000);
+    assert_eq!(play_queue["username"], "root");
+    assert_eq!(play_queue["changedBy"], "phone");
+    let titles: Vec<_> = play_queue["entry"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["title"].as_str().unwrap())
+        .collect();
+    assert_eq!(titles, vec!["Radar Unit", "Ba Senge"]);
+
+    let res = client
+        .get("/rest/getPlayQueue?u=root&p=sesame")
+        .send()
+        .await;
+    let xml = res.xml_string().await;
+    assert!(xml.contains(r#"changedBy="phone""#));
+    assert_eq!(xml.matches("<entry ").count(), 2);
+
+    // Saving a queue without songs clears it
+    get_json(&client, &format!("/rest/savePlayQueue?{ROOT}&c=desktop")).await;
+    let res = get_json(&client, &format!("/rest/getPlayQueue?{ROOT}")).await;
+    assert_eq!(res["playQueue"], Value::Null);
+
+    Ok(())
+}
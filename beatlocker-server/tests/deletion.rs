@@ -28,7 +28,7 @@ async fn deletion_test() -> AppResult<()> {
 
     enable_default_tracing();
     let options = ServerOptions {
-        path: temp_path.clone(),
+        music_folders: vec![MusicFolderOptions::main(temp_path.clone())],
         database: DatabaseOptions {
             path: Some(PathBuf::from(".")),
             in_memory: true,
@@ -62,6 +62,13 @@ async fn deletion_test() -> AppResult<()> {
         "getArtistAfterDeletingBaSenge.json",
         res.json::<serde_json::Value>().await
     );
+    let res = client.get("/rest/search3?f=json&query=senge").send().await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let res = res.json::<serde_json::Value>().await;
+    assert_eq!(
+        res["subsonic-response"]["searchResult3"],
+        serde_json::json!({})
+    );
 
     remove_dir_all(temp_path.join("Richard Bona"))?;
     app.task_manager
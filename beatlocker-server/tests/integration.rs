@@ -15,7 +15,7 @@ use test_utils::*;
 async fn integration_test() -> AppResult<()> {
     enable_default_tracing();
     let options = ServerOptions {
-        path: PathBuf::from("tests/data"),
+        music_folders: vec![MusicFolderOptions::main("tests/data")],
         database: DatabaseOptions {
             path: Some(PathBuf::from(".")),
             in_memory: true,
@@ -187,6 +187,86 @@ async fn integration_test() -> AppResult<()> {
         .await;
     assert_eq!(res.status(), StatusCode::OK);
     insta::assert_snapshot!("search3.xml", res.xml_string().await);
+    let res = client.get("/rest/search3?f=json&query=bon").send().await;
+    assert_eq!(res.status(), StatusCode::OK);
+    insta::assert_json_snapshot!(
+        "search3WithQuery.json",
+        res.json::<serde_json::Value>().await
+    );
+    let res = client
+        .get("/rest/search3?f=json&query=RADAR%20un")
+        .send()
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let res = res.json::<serde_json::Value>().await;
+    let songs = res["subsonic-response"]["searchResult3"]["song"]
+        .as_array()
+        .unwrap();
+    assert_eq!(songs.len(), 1);
+    assert_eq!(songs[0]["title"], "Radar Unit");
+
+    let res = client.get("/rest/search2?f=json&query=tiki").send().await;
+    assert_eq!(res.status(), StatusCode::OK);
+    insta::assert_json_snapshot!("search2.json", res.json::<serde_json::Value>().await);
+    let res = client.get("/rest/search2?query=tiki").send().await;
+    assert_eq!(res.status(), StatusCode::OK);
+    insta::assert_snapshot!("search2.xml", res.xml_string().await);
+
+    // Artists of search2 are the folders of getIndexes
+    let res = get_json(&client, "/rest/search2?f=json&query=richard").await;
+    let artists = res["searchResult2"]["artist"].as_array().unwrap();
+    assert_eq!(artists.len(), 1);
+    assert_eq!(artists[0]["name"], "Richard Bona");
+    let res = get_json(&client, "/rest/getIndexes?f=json").await;
+    let index_ids = res["indexes"]["index"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .flat_map(|index| index["artist"].as_array().unwrap())
+        .map(|artist| artist["id"].clone())
+        .collect::<Vec<_>>();
+    assert!(index_ids.contains(&artists[0]["id"]));
+
+    let res = client
+        .get("/rest/search?f=json&artist=bona&count=1")
+        .send()
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    insta::assert_json_snapshot!("search.json", res.json::<serde_json::Value>().await);
+    let res = client.get("/rest/search?artist=bona&count=1").send().await;
+    assert_eq!(res.status(), StatusCode::OK);
+    insta::assert_snapshot!("search.xml", res.xml_string().await);
+
+    // Any matches the title, artist or album of a song, and is paged in the database
+    for (query, titles) in [
+        ("senge", vec!["Ba Senge"]),
+        ("bona", vec!["Akwa Samba Yaya", "Ba Senge"]),
+        ("motorway", vec!["Diamond Dealers", "Radar Unit"]),
+    ] {
+        let res = get_json(&client, &format!("/rest/search?f=json&any={query}")).await;
+        assert_eq!(res["searchResult"]["totalHits"], titles.len());
+        let matches: Vec<_> = res["searchResult"]["match"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|song| song["title"].as_str().unwrap())
+            .collect();
+        assert_eq!(matches, titles);
+    }
+    let res = get_json(&client, "/rest/search?f=json&any=bona&offset=1&count=1").await;
+    assert_eq!(res["searchResult"]["totalHits"], 2);
+    assert_eq!(res["searchResult"]["offset"], 1);
+    assert_eq!(res["searchResult"]["match"][0]["title"], "Ba Senge");
+
+    // Only songs added after newerThan are matched
+    let res = get_json(&client, "/rest/search?f=json&any=bona&newerThan=0").await;
+    assert_eq!(res["searchResult"]["totalHits"], 2);
+    let res = get_json(
+        &client,
+        "/rest/search?f=json&any=bona&newerThan=4102444800000",
+    )
+    .await;
+    assert_eq!(res["searchResult"]["totalHits"], 0);
 
     let res = client.get("/rest/getGenres?f=json").send().await;
     assert_eq!(res.status(), StatusCode::OK);
@@ -218,20 +298,12 @@ async fn integration_test() -> AppResult<()> {
     assert_eq!(res.status(), StatusCode::OK);
     insta::assert_snapshot!("getPlaylists.xml", res.xml_string().await);
 
-    let res = client
-        .get(&format!(
-            "/rest/getPlaylist?f=json&id={MOTORWAY_OST_FOLDER_UUID}"
-        ))
-        .send()
-        .await;
-    assert_eq!(res.status(), StatusCode::OK);
-    insta::assert_json_snapshot!("getPlaylist.json", res.json::<serde_json::Value>().await);
+    // Folders are no longer exposed as playlists
     let res = client
         .get(&format!("/rest/getPlaylist?id={MOTORWAY_OST_FOLDER_UUID}"))
         .send()
         .await;
-    assert_eq!(res.status(), StatusCode::OK);
-    insta::assert_snapshot!("getPlaylist.xml", res.xml_string().await);
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
 
     // Try streaming
     let res = client
@@ -263,6 +335,18 @@ async fn integration_test() -> AppResult<()> {
         include_bytes!("../src/api/fallback_cover.jpg")
     );
 
+    // Resized coverart
+    let res = client
+        .get("/rest/getCoverArt?id=1568a84c-22cd-2176-ab86-c69194a9de16&size=64")
+        .send()
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        res.headers().get(CONTENT_TYPE).unwrap().to_str().unwrap(),
+        "image/jpeg"
+    );
+    assert!(res.bytes().await.len() < include_bytes!("../src/api/fallback_cover.jpg").len());
+
     let res = client
         .get(&format!("/rest/star?id={RICHARD_BONA_UUID}&id={MOTORWAY_OST_FOLDER_UUID}&id={MOTORWAY_OST_RADAR_UNIT_FOLDER_CHILD_UUID}"))
         .send()
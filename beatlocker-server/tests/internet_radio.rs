@@ -0,0 +1,167 @@
+use crate::test_utils::TestClient;
+use axum::http::{header, StatusCode};
+use axum::routing::get;
+use axum::Router;
+use beatlocker_server::*;
+use serde_json::Value;
+use std::net::SocketAddr;
+
+#[path = "test_utils/mod.rs"]
+mod test_utils;
+
+use test_utils::*;
+
+/// Stands in for a radio station, which streams a few bytes of "audio".
+fn spawn_station() -> SocketAddr {
+    spawn_stand_in(Router::new().route(
+        "/stream",
+        get(|| async { ([(header::CONTENT_TYPE, "audio/aac")], "radio") }),
+    ))
+}
+
+async fn setup(relay_internet_radio: bool) -> AppResult<(App, TestClient)> {
+    start(ServerOptions {
+        subsonic_auth: root_auth(),
+        relay_internet_radio,
+        external_url: Some("http://music.example.com".to_string()),
+        ..test_options()
+    })
+    .await
+}
+
+async fn get_stations(client: &TestClient) -> Vec<Value> {
+    let res = get_json(client, &format!("/rest/getInternetRadioStations?{ROOT}")).await;
+    res["internetRadioStations"]["internetRadioStation"]
+        .as_array()
+        .unwrap()
+        .clone()
+}
+
+#[tokio::test]
+async fn internet_radio_station_test() -> AppResult<()> {
+    let (_app, client) = setup(false).await?;
+
+    get_json(
+        &client,
+        &format!("/rest/createInternetRadioStation?{ROOT}&name=Jazz&streamUrl=http://example.com/jazz&homepageUrl=http://example.com"),
+    )
+    .await;
+    let stations = get_stations(&client).await;
+    assert_eq!(stations.len(), 1);
+    assert_eq!(stations[0]["name"], "Jazz");
+    assert_eq!(stations[0]["streamUrl"], "http://example.com/jazz");
+    assert_eq!(stations[0]["homePageUrl"], "http://example.com");
+    let id = stations[0]["id"].as_str().unwrap().to_string();
+
+    let res = client
+        .get("/rest/getInternetRadioStations?u=root&p=sesame")
+        .send()
+        .await;
+    let xml = res.xml_string().await;
+    assert!(xml.contains(&format!(
+        r#"<internetRadioStation id="{id}" name="Jazz" streamUrl="http://example.com/jazz" homePageUrl="http://example.com""#
+    )));
+
+    get_json(
+        &client,
+        &format!("/rest/updateInternetRadioStation?{ROOT}&id={id}&name=Smooth%20Jazz&streamUrl=http://example.com/smooth"),
+    )
+    .await;
+    let stations = get_stations(&client).await;
+    assert_eq!(stations[0]["name"], "Smooth Jazz");
+    assert_eq!(stations[0]["streamUrl"], "http://example.com/smooth");
+    assert_eq!(stations[0]["homePageUrl"], Value::Null);
+
+    // Only admins can manage stations
+    get_json(
+        &client,
+        &format!("/rest/createUser?{ROOT}&username=joe&password=secret"),
+    )
+    .await;
+    for url in [
+        format!(
+            "/rest/createInternetRadioStation?{JOE}&name=Mine&streamUrl=http://example.com/mine"
+        ),
+        format!("/rest/deleteInternetRadioStation?{JOE}&id={id}"),
+    ] {
+        assert_eq!(
+            client.get(&url).send().await.status(),
+            StatusCode::FORBIDDEN
+        );
+    }
+
+    get_json(
+        &client,
+        &format!("/rest/deleteInternetRadioStation?{ROOT}&id={id}"),
+    )
+    .await;
+    assert!(get_stations(&client).await.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn relay_test() -> AppResult<()> {
+    let addr = spawn_station();
+    let (_app, client) = setup(true).await?;
+
+    get_json(
+        &client,
+        &format!(
+            "/rest/createInternetRadioStation?{ROOT}&name=Local&streamUrl=http://{addr}/stream"
+        ),
+    )
+    .await;
+    let station = get_stations(&client).await[0].clone();
+    let id = station["id"].as_str().unwrap().to_string();
+
+    // The listed stream URL is that of this server, and can be played as it is
+    let stream_url = station["streamUrl"].as_str().unwrap();
+    let path = stream_url
+        .strip_prefix("http://music.example.com/rest/stream?")
+        .unwrap();
+    let res = client.get(&format!("/rest/stream?{path}")).send().await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers()["content-type"], "audio/aac");
+    assert_eq!(res.text().await, "radio");
+
+    // Stations that can't be reached
+    get_json(
+        &client,
+        &format!("/rest/updateInternetRadioStation?{ROOT}&id={id}&name=Local&streamUrl=http://{addr}/missing"),
+    )
+    .await;
+    let res = client
+        .get(&format!("/rest/stream?{ROOT}&id={id}"))
+        .send()
+        .await;
+    assert_eq!(res.status(), StatusCode::BAD_GATEWAY);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn relay_is_disabled_by_default() -> AppResult<()> {
+    let addr = spawn_station();
+    let (_app, client) = setup(false).await?;
+
+    get_json(
+        &client,
+        &format!(
+            "/rest/createInternetRadioStation?{ROOT}&name=Local&streamUrl=http://{addr}/stream"
+        ),
+    )
+    .await;
+    let id = get_stations(&client).await[0]["id"]
+        .as_str()
+        .unwrap()
+        .to_string();
+
+    let res = client
+        .get(&format!("/rest/stream?{ROOT}&id={id}"))
+        .send()
+        .await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    Ok(())
+}
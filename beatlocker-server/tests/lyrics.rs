@@ -0,0 +1,144 @@
+use crate::test_utils::{copy_recursively, TestClient};
+use beatlocker_server::*;
+use id3::frame::Lyrics;
+use id3::{Tag, TagLike};
+use serde_json::json;
+use std::fs::{create_dir_all, remove_dir_all};
+use std::path::PathBuf;
+
+#[path = "test_utils/mod.rs"]
+mod test_utils;
+
+use test_utils::*;
+
+struct DirDeleter(PathBuf);
+
+impl Drop for DirDeleter {
+    fn drop(&mut self) {
+        remove_dir_all(&self.0).unwrap();
+    }
+}
+
+async fn find_song_id(client: &TestClient, query: &str) -> String {
+    let res = get_json(client, &format!("/rest/search3?f=json&query={query}")).await;
+    res["searchResult3"]["song"][0]["id"]
+        .as_str()
+        .unwrap()
+        .to_string()
+}
+
+#[tokio::test]
+async fn lyrics_test() -> AppResult<()> {
+    let temp_path = PathBuf::from("./lyrics_test_data");
+    create_dir_all(&temp_path)?;
+    let _deleter = DirDeleter(temp_path.clone());
+    copy_recursively("tests/data", &temp_path)?;
+
+    std::fs::write(
+        temp_path.join("Richard Bona/Richard Bona - Ba Senge.lrc"),
+        "[ar:Richard Bona]\n[ti:Ba Senge]\n[la:eng]\n[00:01.00]First line\n[00:02.50]Second line\n",
+    )?;
+    let mp3_path = temp_path.join("Richard Bona/Richard Bona - Akwa Samba Yaya.mp3");
+    let mut tag = Tag::read_from_path(&mp3_path).unwrap();
+    tag.add_frame(Lyrics {
+        lang: "fra".to_string(),
+        description: "".to_string(),
+        text: "Premier\nDeuxième".to_string(),
+    });
+    tag.write_to_path(&mp3_path, id3::Version::Id3v23).unwrap();
+
+    enable_default_tracing();
+    let (app, client) = start_and_import(ServerOptions {
+        music_folders: vec![MusicFolderOptions::main(temp_path.clone())],
+        ..test_options()
+    })
+    .await?;
+
+    // Clients only ask for structured lyrics when the extension is advertised
+    let res = get_json(&client, "/rest/getOpenSubsonicExtensions?f=json").await;
+    assert_eq!(res["openSubsonic"], json!(true));
+    assert_eq!(
+        res["openSubsonicExtensions"],
+        json!([{ "name": "songLyrics", "versions": [1] }])
+    );
+
+    // Lyrics from the .lrc file next to the song
+    let res = get_json(
+        &client,
+        "/rest/getLyrics?f=json&artist=richard%20bona&title=ba%20senge",
+    )
+    .await;
+    assert_eq!(
+        res["lyrics"],
+        json!({
+            "artist": "Richard Bona",
+            "title": "Ba Senge",
+            "value": "First line\nSecond line"
+        })
+    );
+
+    let ba_senge_id = find_song_id(&client, "senge").await;
+    let res = get_json(
+        &client,
+        &format!("/rest/getLyricsBySongId?f=json&id={ba_senge_id}"),
+    )
+    .await;
+    assert_eq!(
+        res["lyricsList"],
+        json!({
+            "structuredLyrics": [{
+                "displayArtist": "Richard Bona",
+                "displayTitle": "Ba Senge",
+                "lang": "eng",
+                "offset": 0,
+                "synced": true,
+                "line": [
+                    { "start": 1000, "value": "First line" },
+                    { "start": 2500, "value": "Second line" }
+                ]
+            }]
+        })
+    );
+
+    // Lyrics from the tags of the song
+    let akwa_id = find_song_id(&client, "akwa").await;
+    let res = get_json(
+        &client,
+        &format!("/rest/getLyricsBySongId?f=json&id={akwa_id}"),
+    )
+    .await;
+    assert_eq!(
+        res["lyricsList"]["structuredLyrics"][0]["lang"],
+        json!("fra")
+    );
+    assert_eq!(
+        res["lyricsList"]["structuredLyrics"][0]["synced"],
+        json!(false)
+    );
+    assert_eq!(
+        res["lyricsList"]["structuredLyrics"][0]["line"],
+        json!([{ "value": "Premier" }, { "value": "Deuxième" }])
+    );
+
+    // Nothing is found for songs without lyrics
+    let res = get_json(&client, "/rest/getLyrics?f=json&title=Unknown%20Song").await;
+    assert_eq!(res["lyrics"], json!({}));
+
+    // Nor without an artist or title
+    let res = get_json(&client, "/rest/getLyrics?f=json").await;
+    assert_eq!(res["lyrics"], json!({}));
+
+    // Lyrics of removed .lrc files are removed too
+    std::fs::remove_file(temp_path.join("Richard Bona/Richard Bona - Ba Senge.lrc"))?;
+    app.task_manager
+        .send(app.remove_deleted_files().await?)
+        .await?;
+    let res = get_json(
+        &client,
+        &format!("/rest/getLyricsBySongId?f=json&id={ba_senge_id}"),
+    )
+    .await;
+    assert_eq!(res["lyricsList"]["structuredLyrics"], json!([]));
+
+    Ok(())
+}
@@ -0,0 +1,109 @@
+use crate::test_utils::TestClient;
+use beatlocker_server::*;
+use serde_json::Value;
+
+#[path = "test_utils/mod.rs"]
+mod test_utils;
+
+use test_utils::*;
+
+const SOUNDTRACKS: &str = "Soundtracks";
+
+async fn setup() -> AppResult<(App, TestClient)> {
+    start_and_import(ServerOptions {
+        music_folders: vec![
+            MusicFolderOptions::main("tests/data/Richard Bona"),
+            MusicFolderOptions::new(SOUNDTRACKS, "tests/data/Motorway OST"),
+        ],
+        ..test_options()
+    })
+    .await
+}
+
+fn names(items: &Value, key: &str) -> Vec<String> {
+    items
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| item[key].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn music_folders_test() -> AppResult<()> {
+    let (_app, client) = setup().await?;
+    let soundtracks = str_to_uuid(SOUNDTRACKS);
+
+    let res = get_json(&client, "/rest/getMusicFolders?f=json").await;
+    let folders = &res["musicFolders"]["musicFolder"];
+    assert_eq!(names(folders, "name"), vec!["Music", SOUNDTRACKS]);
+    assert_eq!(folders[0]["id"], "00000000-0000-0000-0000-000000000000");
+    assert_eq!(folders[1]["id"], soundtracks.to_string());
+
+    let res = get_json(
+        &client,
+        "/rest/getAlbumList2?f=json&type=alphabeticalByName",
+    )
+    .await;
+    assert_eq!(
+        names(&res["albumList2"]["album"], "name"),
+        vec!["Motorway (Original Motion Picture Soundtrack)", "Tiki"]
+    );
+    let res = get_json(
+        &client,
+        &format!("/rest/getAlbumList2?f=json&type=alphabeticalByName&musicFolderId={soundtracks}"),
+    )
+    .await;
+    assert_eq!(
+        names(&res["albumList2"]["album"], "name"),
+        vec!["Motorway (Original Motion Picture Soundtrack)"]
+    );
+
+    let res = get_json(
+        &client,
+        &format!("/rest/getArtists?f=json&musicFolderId={soundtracks}"),
+    )
+    .await;
+    assert_eq!(
+        names(&res["artists"]["index"][0]["artist"], "name"),
+        vec!["Alex Gopher"]
+    );
+
+    // Folders are listed under the music folder they're in
+    let res = get_json(
+        &client,
+        &format!("/rest/getIndexes?f=json&musicFolderId={soundtracks}"),
+    )
+    .await;
+    assert_eq!(
+        names(&res["indexes"]["index"][0]["artist"], "name"),
+        vec!["MotorwayNested"]
+    );
+
+    let res = get_json(
+        &client,
+        "/rest/getRandomSongs?f=json&musicFolderId=00000000-0000-0000-0000-000000000000",
+    )
+    .await;
+    let mut titles = names(&res["randomSongs"]["song"], "title");
+    titles.sort();
+    assert_eq!(titles, vec!["Akwa Samba Yaya", "Ba Senge"]);
+
+    let res = get_json(
+        &client,
+        &format!("/rest/search3?f=json&query=Radar&musicFolderId={soundtracks}"),
+    )
+    .await;
+    assert_eq!(
+        names(&res["searchResult3"]["song"], "title"),
+        vec!["Radar Unit"]
+    );
+    let res = get_json(
+        &client,
+        "/rest/search3?f=json&query=Radar&musicFolderId=00000000-0000-0000-0000-000000000000",
+    )
+    .await;
+    assert!(res["searchResult3"]["song"].is_null());
+
+    Ok(())
+}
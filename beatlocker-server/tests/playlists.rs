@@ -0,0 +1,208 @@
+use axum::http::StatusCode;
+use beatlocker_server::*;
+use serde_json::Value;
+
+#[path = "test_utils/mod.rs"]
+mod test_utils;
+
+use test_utils::*;
+
+fn entry_titles(playlist: &Value) -> Vec<&str> {
+    playlist["entry"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["title"].as_str().unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn playlist_test() -> AppResult<()> {
+    let (_app, client) = setup().await?;
+
+    // Songs can be added by folder child id or song id
+    let res = get_json(
+        &client,
+        &format!(
+            "/rest/createPlaylist?f=json&name=Mix&songId={RICHARD_BONA_BA_SENGE_FOLDER_CHILD_UUID}&songId={MOTORWAY_OST_RADAR_UNIT_SONG_UUID}"
+        ),
+    )
+    .await;
+    let playlist = &res["playlist"];
+    assert_eq!(playlist["name"], "Mix");
+    assert_eq!(playlist["owner"], DEFAULT_USERNAME);
+    assert_eq!(playlist["public"], false);
+    assert_eq!(playlist["songCount"], 2);
+    assert_eq!(entry_titles(playlist), vec!["Ba Senge", "Radar Unit"]);
+    let id = playlist["id"].as_str().unwrap().to_string();
+
+    let res = get_json(&client, "/rest/getPlaylists?f=json").await;
+    let playlists = res["playlists"]["playlist"].as_array().unwrap();
+    assert_eq!(playlists.len(), 1);
+    assert_eq!(playlists[0]["id"], id.as_str());
+    assert_eq!(playlists[0]["duration"], 6 + 95);
+
+    get_json(
+        &client,
+        &format!(
+            "/rest/updatePlaylist?f=json&playlistId={id}&name=Renamed&comment=Nice&public=true&songIndexToRemove=0&songIdToAdd={RICHARD_BONA_AKWA_SAMBA_YAYA_FOLDER_CHILD_UUID}&songIdToAdd={RICHARD_BONA_BA_SENGE_FOLDER_CHILD_UUID}"
+        ),
+    )
+    .await;
+    let res = get_json(&client, &format!("/rest/getPlaylist?f=json&id={id}")).await;
+    let playlist = &res["playlist"];
+    assert_eq!(playlist["name"], "Renamed");
+    assert_eq!(playlist["comment"], "Nice");
+    assert_eq!(playlist["public"], true);
+    assert_eq!(
+        entry_titles(playlist),
+        vec!["Radar Unit", "Akwa Samba Yaya", "Ba Senge"]
+    );
+
+    let res = client
+        .get(&format!("/rest/getPlaylist?id={id}"))
+        .send()
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let xml = res.xml_string().await;
+    assert!(
+        xml.contains(r#"name="Renamed" comment="Nice" owner="admin" public="true" songCount="3""#)
+    );
+    assert_eq!(xml.matches("<entry ").count(), 3);
+
+    // Replacing all songs
+    let res = get_json(
+        &client,
+        &format!(
+            "/rest/createPlaylist?f=json&playlistId={id}&songId={RICHARD_BONA_BA_SENGE_FOLDER_CHILD_UUID}"
+        ),
+    )
+    .await;
+    assert_eq!(res["playlist"]["name"], "Renamed");
+    assert_eq!(entry_titles(&res["playlist"]), vec!["Ba Senge"]);
+
+    get_json(&client, &format!("/rest/deletePlaylist?f=json&id={id}")).await;
+    let res = get_json(&client, "/rest/getPlaylists?f=json").await;
+    assert_eq!(res["playlists"]["playlist"], serde_json::json!([]));
+    let res = client
+        .get(&format!("/rest/getPlaylist?id={id}"))
+        .send()
+        .await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    let res = client
+        .get(&format!("/rest/updatePlaylist?playlistId={id}&name=Gone"))
+        .send()
+        .await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn export_test() -> AppResult<()> {
+    let (_app, client) = setup().await?;
+
+    let res = get_json(
+        &client,
+        &format!(
+            "/rest/createPlaylist?f=json&name=Mix&songId={MOTORWAY_OST_RADAR_UNIT_FOLDER_CHILD_UUID}&songId={RICHARD_BONA_BA_SENGE_FOLDER_CHILD_UUID}"
+        ),
+    )
+    .await;
+    let id = res["playlist"]["id"].as_str().unwrap().to_string();
+
+    let res = client
+        .get(&format!("/rest/exportPlaylist?id={id}&entries=path"))
+        .send()
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers()["content-type"], "audio/x-mpegurl");
+    assert_eq!(
+        res.text().await,
+        "#EXTM3U\n#PLAYLIST:Mix\n#EXTINF:95,Alex Gopher - Radar Unit\ntests/data/Motorway OST/MotorwayNested/Alex Gopher - Radar Unit.flac\n#EXTINF:6,Richard Bona - Ba Senge\ntests/data/Richard Bona/Richard Bona - Ba Senge.ogg\n"
+    );
+
+    // Stream URLs carry the credentials of the request
+    let res = client
+        .get(&format!(
+            "/rest/exportPlaylist?id={id}&format=xspf&u=admin&t=token&s=salt&c=test"
+        ))
+        .send()
+        .await;
+    assert_eq!(res.headers()["content-type"], "application/xspf+xml");
+    let xspf = res.text().await;
+    assert!(xspf.contains(&format!(
+        "/rest/stream?u=admin&amp;t=token&amp;s=salt&amp;v=1.16.1&amp;c=test&amp;id={MOTORWAY_OST_RADAR_UNIT_FOLDER_CHILD_UUID}</location>"
+    )));
+    assert_eq!(xspf.matches("<track>").count(), 2);
+
+    // Folders include their subfolders
+    let res = client
+        .get(&format!(
+            "/rest/exportPlaylist?id={MOTORWAY_OST_FOLDER_UUID}&entries=path"
+        ))
+        .send()
+        .await;
+    let m3u8 = res.text().await;
+    assert!(m3u8.starts_with("#EXTM3U\n#PLAYLIST:Motorway OST\n"));
+    assert_eq!(m3u8.matches("#EXTINF").count(), 2);
+
+    // Starred songs are exported without an id
+    client
+        .get(&format!(
+            "/rest/star?id={RICHARD_BONA_BA_SENGE_FOLDER_CHILD_UUID}"
+        ))
+        .send()
+        .await;
+    let res = client.get("/rest/exportPlaylist?entries=path").send().await;
+    assert_eq!(
+        res.text().await,
+        "#EXTM3U\n#PLAYLIST:Starred\n#EXTINF:6,Richard Bona - Ba Senge\ntests/data/Richard Bona/Richard Bona - Ba Senge.ogg\n"
+    );
+
+    let res = client
+        .get(&format!("/rest/exportPlaylist?id={RICHARD_BONA_UUID}"))
+        .send()
+        .await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn playlist_role_test() -> AppResult<()> {
+    let (_app, client) = start_and_import(ServerOptions {
+        subsonic_auth: root_auth(),
+        ..test_options()
+    })
+    .await?;
+    get_json(
+        &client,
+        &format!("/rest/createUser?{ROOT}&username=joe&password=secret"),
+    )
+    .await;
+    let res = get_json(&client, &format!("/rest/createPlaylist?{ROOT}&name=Mine")).await;
+    let id = res["playlist"]["id"].as_str().unwrap().to_string();
+
+    // Without the role, playlists can't be created, changed or deleted
+    for url in [
+        format!("/rest/createPlaylist?{JOE}&name=Theirs"),
+        format!("/rest/createPlaylist?{JOE}&playlistId={id}"),
+        format!("/rest/updatePlaylist?{JOE}&playlistId={id}&name=Theirs"),
+        format!("/rest/deletePlaylist?{JOE}&id={id}"),
+    ] {
+        assert_eq!(
+            client.get(&url).send().await.status(),
+            StatusCode::FORBIDDEN
+        );
+    }
+
+    get_json(
+        &client,
+        &format!("/rest/updateUser?{ROOT}&username=joe&playlistRole=true"),
+    )
+    .await;
+    get_json(&client, &format!("/rest/createPlaylist?{JOE}&name=Theirs")).await;
+
+    Ok(())
+}
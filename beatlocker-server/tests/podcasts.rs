@@ -0,0 +1,187 @@
+use crate::test_utils::TestClient;
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::routing::get;
+use axum::Router;
+use beatlocker_server::*;
+use serde_json::Value;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::Duration;
+
+#[path = "test_utils/mod.rs"]
+mod test_utils;
+
+use test_utils::*;
+
+const EPISODE: &[u8] = include_bytes!("silent.mp3");
+
+/// Stands in for a podcast host, serving a feed with a single episode. The episode is linked
+/// through the host the feed was requested from.
+fn spawn_podcast_host() -> SocketAddr {
+    let feed = |headers: HeaderMap| async move {
+        let host = headers[header::HOST].to_str().unwrap().to_string();
+        let feed = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+            <rss version="2.0">
+              <channel>
+                <title>Silence</title>
+                <description>Nothing to hear here</description>
+                <item>
+                  <title>Episode 1</title>
+                  <guid>episode-1</guid>
+                  <pubDate>Sat, 01 Feb 2020 10:00:00 +0000</pubDate>
+                  <enclosure url="http://{host}/episode.mp3" type="audio/mpeg" length="{}"/>
+                </item>
+              </channel>
+            </rss>"#,
+            EPISODE.len()
+        );
+        ([(header::CONTENT_TYPE, "application/rss+xml")], feed)
+    };
+
+    spawn_stand_in(Router::new().route("/feed.xml", get(feed)).route(
+        "/episode.mp3",
+        get(|| async { ([(header::CONTENT_TYPE, "audio/mpeg")], EPISODE) }),
+    ))
+}
+
+async fn setup(podcast_path: &Path) -> AppResult<(App, TestClient)> {
+    start(ServerOptions {
+        subsonic_auth: root_auth(),
+        podcast_path: podcast_path.to_path_buf(),
+        ..test_options()
+    })
+    .await
+}
+
+async fn get_channels(client: &TestClient) -> Vec<Value> {
+    let res = get_json(client, &format!("/rest/getPodcasts?{ROOT}")).await;
+    res["podcasts"]["channel"].as_array().unwrap().clone()
+}
+
+#[tokio::test]
+async fn podcast_test() -> AppResult<()> {
+    let addr = spawn_podcast_host();
+    let podcast_path = tempfile::tempdir()?;
+    let (app, client) = setup(podcast_path.path()).await?;
+
+    get_json(
+        &client,
+        &format!("/rest/createPodcastChannel?{ROOT}&url=http://{addr}/feed.xml"),
+    )
+    .await;
+    app.task_manager.send(app.refresh_podcasts().await?).await?;
+
+    let channels = get_channels(&client).await;
+    assert_eq!(channels.len(), 1);
+    let channel = &channels[0];
+    assert_eq!(channel["title"], "Silence");
+    assert_eq!(channel["description"], "Nothing to hear here");
+    assert_eq!(channel["status"], "completed");
+    let channel_id = channel["id"].as_str().unwrap().to_string();
+    let episodes = channel["episode"].as_array().unwrap();
+    assert_eq!(episodes.len(), 1);
+    assert_eq!(episodes[0]["title"], "Episode 1");
+    assert_eq!(episodes[0]["channelId"], channel_id);
+    assert_eq!(episodes[0]["status"], "skipped");
+    assert_eq!(episodes[0]["streamId"], Value::Null);
+    let episode_id = episodes[0]["id"].as_str().unwrap().to_string();
+
+    // Episodes are downloaded in the background
+    get_json(
+        &client,
+        &format!("/rest/downloadPodcastEpisode?{ROOT}&id={episode_id}"),
+    )
+    .await;
+    let mut episode = Value::Null;
+    for _ in 0..100 {
+        episode = get_channels(&client).await[0]["episode"][0].clone();
+        if episode["status"] == "completed" {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    assert_eq!(episode["status"], "completed");
+    assert_eq!(episode["suffix"], "mp3");
+    assert_eq!(episode["size"], EPISODE.len());
+    assert_eq!(episode["streamId"], episode_id);
+
+    let res = client
+        .get(&format!("/rest/stream?{ROOT}&id={episode_id}"))
+        .send()
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(&res.bytes().await.to_vec(), EPISODE);
+
+    let res = get_json(&client, &format!("/rest/getNewestPodcasts?{ROOT}&count=5")).await;
+    let newest = res["newestPodcasts"]["episode"].as_array().unwrap();
+    assert_eq!(newest.len(), 1);
+    assert_eq!(newest[0]["id"], episode_id);
+
+    // Only admins can manage podcasts
+    get_json(
+        &client,
+        &format!("/rest/createUser?{ROOT}&username=joe&password=secret"),
+    )
+    .await;
+    for url in [
+        format!("/rest/createPodcastChannel?{JOE}&url=http://{addr}/feed.xml"),
+        format!("/rest/refreshPodcasts?{JOE}"),
+        format!("/rest/downloadPodcastEpisode?{JOE}&id={episode_id}"),
+        format!("/rest/deletePodcastEpisode?{JOE}&id={episode_id}"),
+        format!("/rest/deletePodcastChannel?{JOE}&id={channel_id}"),
+    ] {
+        assert_eq!(
+            client.get(&url).send().await.status(),
+            StatusCode::FORBIDDEN
+        );
+    }
+
+    get_json(
+        &client,
+        &format!("/rest/deletePodcastEpisode?{ROOT}&id={episode_id}"),
+    )
+    .await;
+    let episode = get_channels(&client).await[0]["episode"][0].clone();
+    assert_eq!(episode["status"], "deleted");
+    let res = client
+        .get(&format!("/rest/stream?{ROOT}&id={episode_id}"))
+        .send()
+        .await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    // Deleted episodes aren't added again
+    app.task_manager.send(app.refresh_podcasts().await?).await?;
+    let episode = get_channels(&client).await[0]["episode"][0].clone();
+    assert_eq!(episode["status"], "deleted");
+
+    get_json(
+        &client,
+        &format!("/rest/deletePodcastChannel?{ROOT}&id={channel_id}"),
+    )
+    .await;
+    assert!(get_channels(&client).await.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn broken_feeds_are_reported() -> AppResult<()> {
+    let addr = spawn_podcast_host();
+    let podcast_path = tempfile::tempdir()?;
+    let (app, client) = setup(podcast_path.path()).await?;
+
+    get_json(
+        &client,
+        &format!("/rest/createPodcastChannel?{ROOT}&url=http://{addr}/missing.xml"),
+    )
+    .await;
+    app.task_manager.send(app.refresh_podcasts().await?).await?;
+
+    let channels = get_channels(&client).await;
+    assert_eq!(channels[0]["status"], "error");
+    assert!(channels[0]["errorMessage"].as_str().is_some());
+    assert!(channels[0]["episode"].as_array().unwrap().is_empty());
+
+    Ok(())
+}
@@ -0,0 +1,129 @@
+use crate::test_utils::TestClient;
+use axum::http::StatusCode;
+use beatlocker_server::*;
+use serde_json::Value;
+
+#[path = "test_utils/mod.rs"]
+mod test_utils;
+
+use test_utils::*;
+
+async fn setup() -> AppResult<(App, TestClient)> {
+    start_and_import(ServerOptions {
+        subsonic_auth: root_auth(),
+        ..test_options()
+    })
+    .await
+}
+
+#[tokio::test]
+async fn stars_are_per_user() -> AppResult<()> {
+    let (_app, client) = setup().await?;
+    get_json(
+        &client,
+        &format!("/rest/createUser?{ROOT}&username=joe&password=secret"),
+    )
+    .await;
+
+    get_json(
+        &client,
+        &format!("/rest/star?{ROOT}&id={RICHARD_BONA_UUID}&id={MOTORWAY_OST_ALBUM_UUID}"),
+    )
+    .await;
+    let res = get_json(&client, &format!("/rest/getStarred2?{ROOT}")).await;
+    assert_eq!(res["starred2"]["artist"].as_array().unwrap().len(), 1);
+    assert_eq!(res["starred2"]["album"].as_array().unwrap().len(), 1);
+
+    let res = get_json(&client, &format!("/rest/getStarred2?{JOE}")).await;
+    assert_eq!(res["starred2"]["artist"], serde_json::json!([]));
+    assert_eq!(res["starred2"]["album"], serde_json::json!([]));
+
+    // Unstarring only affects the user's own stars
+    get_json(&client, &format!("/rest/star?{JOE}&id={RICHARD_BONA_UUID}")).await;
+    get_json(
+        &client,
+        &format!("/rest/unstar?{ROOT}&id={RICHARD_BONA_UUID}"),
+    )
+    .await;
+    let res = get_json(&client, &format!("/rest/getStarred2?{JOE}")).await;
+    assert_eq!(res["starred2"]["artist"].as_array().unwrap().len(), 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn ratings_test() -> AppResult<()> {
+    let (_app, client) = setup().await?;
+    get_json(
+        &client,
+        &format!("/rest/createUser?{ROOT}&username=joe&password=secret"),
+    )
+    .await;
+
+    get_json(
+        &client,
+        &format!("/rest/setRating?{ROOT}&id={MOTORWAY_OST_ALBUM_UUID}&rating=5"),
+    )
+    .await;
+    get_json(
+        &client,
+        &format!("/rest/setRating?{JOE}&id={MOTORWAY_OST_ALBUM_UUID}&rating=2"),
+    )
+    .await;
+    let res = get_json(
+        &client,
+        &format!("/rest/getAlbum?{JOE}&id={MOTORWAY_OST_ALBUM_UUID}"),
+    )
+    .await;
+    assert_eq!(res["album"]["userRating"], 2);
+    assert_eq!(res["album"]["averageRating"], 3.5);
+
+    let res = get_json(&client, &format!("/rest/getAlbumList2?{ROOT}&type=highest")).await;
+    let albums = res["albumList2"]["album"].as_array().unwrap();
+    assert_eq!(albums.len(), 1);
+    assert_eq!(albums[0]["id"], MOTORWAY_OST_ALBUM_UUID);
+    assert_eq!(albums[0]["userRating"], 5);
+
+    // Songs rated through their folder child can be found by their song
+    get_json(
+        &client,
+        &format!("/rest/setRating?{ROOT}&id={RICHARD_BONA_BA_SENGE_FOLDER_CHILD_UUID}&rating=4"),
+    )
+    .await;
+    let res = get_json(&client, &format!("/rest/search3?{ROOT}&query=Senge")).await;
+    assert_eq!(res["searchResult3"]["song"][0]["userRating"], 4);
+
+    get_json(
+        &client,
+        &format!("/rest/setRating?{ROOT}&id={RICHARD_BONA_UUID}&rating=3"),
+    )
+    .await;
+    let res = get_json(
+        &client,
+        &format!("/rest/getArtist?{ROOT}&id={RICHARD_BONA_UUID}"),
+    )
+    .await;
+    assert_eq!(res["artist"]["userRating"], 3);
+
+    // A rating of 0 removes it, and ratings must be at most 5
+    get_json(
+        &client,
+        &format!("/rest/setRating?{ROOT}&id={RICHARD_BONA_UUID}&rating=0"),
+    )
+    .await;
+    let res = get_json(
+        &client,
+        &format!("/rest/getArtist?{ROOT}&id={RICHARD_BONA_UUID}"),
+    )
+    .await;
+    assert_eq!(res["artist"]["userRating"], Value::Null);
+    let res = client
+        .get(&format!(
+            "/rest/setRating?{ROOT}&id={RICHARD_BONA_UUID}&rating=6"
+        ))
+        .send()
+        .await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    Ok(())
+}
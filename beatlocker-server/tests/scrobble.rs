@@ -0,0 +1,105 @@
+use axum::http::StatusCode;
+use beatlocker_server::*;
+
+#[path = "test_utils/mod.rs"]
+mod test_utils;
+
+use test_utils::*;
+
+#[tokio::test]
+async fn scrobble_test() -> AppResult<()> {
+    let (_app, client) = setup().await?;
+
+    let res = get_json(&client, "/rest/getNowPlaying?f=json").await;
+    assert_eq!(res["nowPlaying"]["entry"], serde_json::json!([]));
+
+    // Without submission, the song is only playing
+    get_json(
+        &client,
+        &format!(
+            "/rest/scrobble?f=json&c=player&submission=false&id={RICHARD_BONA_BA_SENGE_FOLDER_CHILD_UUID}"
+        ),
+    )
+    .await;
+    let res = get_json(&client, "/rest/getNowPlaying?f=json").await;
+    let entries = res["nowPlaying"]["entry"].as_array().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0]["title"], "Ba Senge");
+    assert_eq!(entries[0]["username"], DEFAULT_USERNAME);
+    assert_eq!(entries[0]["minutesAgo"], 0);
+    assert_eq!(entries[0]["playerName"], "player");
+
+    let res = get_json(&client, "/rest/search3?f=json&query=senge").await;
+    assert_eq!(res["searchResult3"]["song"][0].get("playCount"), None);
+
+    // Several songs can be submitted at once, each with their own time
+    get_json(
+        &client,
+        &format!(
+            "/rest/scrobble?f=json&c=player&id={RICHARD_BONA_BA_SENGE_FOLDER_CHILD_UUID}&time=1580515200000&id={RICHARD_BONA_BA_SENGE_FOLDER_CHILD_UUID}&time=1580601600000&id={RICHARD_BONA_AKWA_SAMBA_YAYA_FOLDER_CHILD_UUID}"
+        ),
+    )
+    .await;
+    let res = get_json(&client, "/rest/search3?f=json&query=senge").await;
+    let song = &res["searchResult3"]["song"][0];
+    assert_eq!(song["playCount"], 2);
+    assert_eq!(song["played"], "2020-02-02T00:00:00Z");
+
+    let res = get_json(&client, "/rest/search3?f=json&query=akwa").await;
+    assert_eq!(res["searchResult3"]["song"][0]["playCount"], 1);
+
+    let res = get_json(&client, "/rest/search3?f=json&query=tiki").await;
+    let album = &res["searchResult3"]["album"][0];
+    assert_eq!(album["playCount"], 3);
+    assert_eq!(album["played"], "2020-02-02T00:00:00Z");
+
+    let res = client.get("/rest/getNowPlaying").send().await;
+    let xml = res.xml_string().await;
+    assert!(xml.contains(r#"username="admin" minutesAgo="0" playerName="player""#));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn played_by_test() -> AppResult<()> {
+    let (_app, client) = start_and_import(ServerOptions {
+        subsonic_auth: root_auth(),
+        ..test_options()
+    })
+    .await?;
+    get_json(
+        &client,
+        &format!("/rest/createUser?{ROOT}&username=joe&password=secret"),
+    )
+    .await;
+    get_json(
+        &client,
+        &format!("/rest/scrobble?{ROOT}&id={RICHARD_BONA_BA_SENGE_FOLDER_CHILD_UUID}"),
+    )
+    .await;
+
+    // Users can only ask about their own plays, admins about anyone's
+    let res = get_json(
+        &client,
+        &format!("/rest/getAlbumList2?{JOE}&type=recent&playedBy=joe"),
+    )
+    .await;
+    assert_eq!(res["albumList2"]["album"], serde_json::json!([]));
+    let res = get_json(
+        &client,
+        &format!("/rest/getAlbumList2?{ROOT}&type=recent&playedBy=root"),
+    )
+    .await;
+    assert_eq!(res["albumList2"]["album"][0]["playCount"], 1);
+    for url in [
+        format!("/rest/getAlbumList?{JOE}&type=recent&playedBy=root"),
+        format!("/rest/getAlbumList2?{JOE}&type=recent&playedBy=root"),
+    ] {
+        assert_eq!(
+            client.get(&url).send().await.status(),
+            StatusCode::FORBIDDEN
+        );
+    }
+
+    Ok(())
+}
@@ -0,0 +1,171 @@
+use crate::test_utils::TestClient;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use axum::routing::post;
+use axum::{Form, Json, Router};
+use beatlocker_server::*;
+use chrono::{TimeZone, Utc};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+
+#[path = "test_utils/mod.rs"]
+mod test_utils;
+
+use test_utils::*;
+
+/// The current time of the server, in seconds since the epoch.
+static NOW: AtomicI64 = AtomicI64::new(1580601600);
+
+/// Stands in for last.fm and ListenBrainz, and records what was submitted to them.
+#[derive(Default)]
+struct StandIn {
+    lastfm_down: AtomicBool,
+    lastfm_scrobbles: Mutex<Vec<HashMap<String, String>>>,
+    listens: Mutex<Vec<(String, Value)>>,
+}
+
+async fn lastfm(
+    State(stand_in): State<Arc<StandIn>>,
+    Form(params): Form<HashMap<String, String>>,
+) -> impl IntoResponse {
+    assert!(params.contains_key("api_sig"));
+    assert_eq!(params["api_key"], "key");
+    match params["method"].as_str() {
+        "auth.getSession" if params["token"] == "authorized" => (
+            StatusCode::OK,
+            Json(json!({ "session": { "name": "joe", "key": "session-key" } })),
+        ),
+        "auth.getSession" => (
+            StatusCode::FORBIDDEN,
+            Json(json!({ "error": 4, "message": "Invalid authentication token" })),
+        ),
+        "track.scrobble" if stand_in.lastfm_down.load(Ordering::SeqCst) => (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(json!({ "error": 11, "message": "Service Offline" })),
+        ),
+        _ => {
+            stand_in.lastfm_scrobbles.lock().unwrap().push(params);
+            (StatusCode::OK, Json(json!({ "scrobbles": {} })))
+        }
+    }
+}
+
+async fn listenbrainz(
+    State(stand_in): State<Arc<StandIn>>,
+    headers: HeaderMap,
+    Json(body): Json<Value>,
+) -> impl IntoResponse {
+    let authorization = headers["authorization"].to_str().unwrap().to_string();
+    stand_in.listens.lock().unwrap().push((authorization, body));
+    Json(json!({ "status": "ok" }))
+}
+
+async fn setup() -> AppResult<(App, TestClient, Arc<StandIn>)> {
+    let stand_in = Arc::new(StandIn::default());
+    let router = Router::new()
+        .route("/lastfm/", post(lastfm))
+        .route("/listenbrainz/1/submit-listens", post(listenbrainz))
+        .with_state(stand_in.clone());
+    let addr = spawn_stand_in(router);
+
+    let (app, client) = start_and_import(ServerOptions {
+        lastfm_api_key: Some("key".to_string()),
+        lastfm_api_secret: Some("secret".to_string()),
+        lastfm_url: format!("http://{addr}/lastfm/"),
+        listenbrainz_url: format!("http://{addr}/listenbrainz"),
+        now_provider: Arc::new(Box::new(|| {
+            Utc.timestamp_opt(NOW.load(Ordering::SeqCst), 0).unwrap()
+        })),
+        ..test_options()
+    })
+    .await?;
+
+    Ok((app, client, stand_in))
+}
+
+async fn get_ok(client: &TestClient, url: &str) {
+    let res = client.get(url).send().await;
+    assert_eq!(res.status(), StatusCode::OK);
+}
+
+#[tokio::test]
+async fn scrobble_forwarding_test() -> AppResult<()> {
+    let (app, client, stand_in) = setup().await?;
+
+    let res = client
+        .get("/rest/linkScrobbleAccount?service=lastfm&token=unauthorized")
+        .send()
+        .await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    get_ok(
+        &client,
+        "/rest/linkScrobbleAccount?service=lastfm&token=authorized",
+    )
+    .await;
+    get_ok(
+        &client,
+        "/rest/linkScrobbleAccount?service=listenbrainz&token=lb-token",
+    )
+    .await;
+
+    // last.fm is offline, so its play stays in the outbox
+    stand_in.lastfm_down.store(true, Ordering::SeqCst);
+    get_ok(
+        &client,
+        &format!("/rest/scrobble?id={RICHARD_BONA_BA_SENGE_FOLDER_CHILD_UUID}&time=1580600000000"),
+    )
+    .await;
+    app.task_manager.send(app.submit_scrobbles().await?).await?;
+    assert!(stand_in.lastfm_scrobbles.lock().unwrap().is_empty());
+    {
+        let listens = stand_in.listens.lock().unwrap();
+        assert_eq!(listens.len(), 1);
+        let (authorization, body) = &listens[0];
+        assert_eq!(authorization, "Token lb-token");
+        assert_eq!(body["listen_type"], "single");
+        assert_eq!(body["payload"][0]["listened_at"], 1580600000);
+        let metadata = &body["payload"][0]["track_metadata"];
+        assert_eq!(metadata["artist_name"], "Richard Bona");
+        assert_eq!(metadata["track_name"], "Ba Senge");
+        assert_eq!(metadata["release_name"], "Tiki");
+        assert_eq!(metadata["additional_info"]["duration_ms"], 6000);
+    }
+
+    // The play isn't retried until it's due again
+    stand_in.lastfm_down.store(false, Ordering::SeqCst);
+    app.task_manager.send(app.submit_scrobbles().await?).await?;
+    assert!(stand_in.lastfm_scrobbles.lock().unwrap().is_empty());
+
+    NOW.fetch_add(60 * 60, Ordering::SeqCst);
+    app.task_manager.send(app.submit_scrobbles().await?).await?;
+    {
+        let scrobbles = stand_in.lastfm_scrobbles.lock().unwrap();
+        assert_eq!(scrobbles.len(), 1);
+        assert_eq!(scrobbles[0]["sk"], "session-key");
+        assert_eq!(scrobbles[0]["artist[0]"], "Richard Bona");
+        assert_eq!(scrobbles[0]["track[0]"], "Ba Senge");
+        assert_eq!(scrobbles[0]["album[0]"], "Tiki");
+        assert_eq!(scrobbles[0]["timestamp[0]"], "1580600000");
+    }
+
+    // Submitted plays are removed from the outbox
+    app.task_manager.send(app.submit_scrobbles().await?).await?;
+    assert_eq!(stand_in.lastfm_scrobbles.lock().unwrap().len(), 1);
+    assert_eq!(stand_in.listens.lock().unwrap().len(), 1);
+
+    // Plays are no longer forwarded to unlinked accounts
+    get_ok(&client, "/rest/unlinkScrobbleAccount?service=listenbrainz").await;
+    get_ok(
+        &client,
+        &format!("/rest/scrobble?id={MOTORWAY_OST_RADAR_UNIT_FOLDER_CHILD_UUID}"),
+    )
+    .await;
+    app.task_manager.send(app.submit_scrobbles().await?).await?;
+    assert_eq!(stand_in.lastfm_scrobbles.lock().unwrap().len(), 2);
+    assert_eq!(stand_in.listens.lock().unwrap().len(), 1);
+
+    Ok(())
+}
@@ -0,0 +1,204 @@
+use crate::test_utils::TestClient;
+use axum::http::StatusCode;
+use beatlocker_server::*;
+use serde_json::Value;
+
+#[path = "test_utils/mod.rs"]
+mod test_utils;
+
+use test_utils::*;
+
+async fn setup(external_url: Option<&str>) -> AppResult<(App, TestClient)> {
+    start_and_import(ServerOptions {
+        subsonic_auth: root_auth(),
+        external_url: external_url.map(|url| url.to_string()),
+        ..test_options()
+    })
+    .await
+}
+
+async fn get_shares(client: &TestClient, auth: &str) -> Vec<Value> {
+    let res = get_json(client, &format!("/rest/getShares?{auth}")).await;
+    res["shares"]["share"].as_array().unwrap().clone()
+}
+
+/// 2020-03-01T00:00:00Z
+const MARCH_2020: i64 = 1583020800000;
+/// 2020-01-01T00:00:00Z
+const JANUARY_2020: i64 = 1577836800000;
+
+#[tokio::test]
+async fn share_test() -> AppResult<()> {
+    let (_app, client) = setup(Some("https://music.example.com/")).await?;
+
+    // Albums share all of their songs
+    let res = get_json(
+        &client,
+        &format!("/rest/createShare?{ROOT}&id={RICHARD_BONA_BA_SENGE_FOLDER_CHILD_UUID}&id={MOTORWAY_OST_ALBUM_UUID}&description=For%20you&expires={MARCH_2020}"),
+    )
+    .await;
+    let share = &res["shares"]["share"][0];
+    let id = share["id"].as_str().unwrap().to_string();
+    assert_eq!(
+        share["url"],
+        format!("https://music.example.com/share/{id}")
+    );
+    assert_eq!(share["description"], "For you");
+    assert_eq!(share["username"], "root");
+    assert_eq!(share["created"], "2020-02-02T00:00:00Z");
+    assert_eq!(share["expires"], "2020-03-01T00:00:00Z");
+    assert_eq!(share["visitCount"], 0);
+    let titles: Vec<_> = share["entry"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["title"].as_str().unwrap())
+        .collect();
+    assert_eq!(titles[0], "Ba Senge");
+    assert!(titles.contains(&"Radar Unit"));
+    assert!(titles.contains(&"Diamond Dealers"));
+
+    let res = client.get("/rest/getShares?u=root&p=sesame").send().await;
+    let xml = res.xml_string().await;
+    assert!(xml.contains(&format!(
+        r#"<share id="{id}" url="https://music.example.com/share/{id}" description="For you" username="root""#
+    )));
+
+    // The share can be visited without logging in
+    let res = client.get(&format!("/share/{id}")).send().await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert!(res.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("text/html"));
+    let html = res.text().await;
+    assert!(html.contains("<h1>For you</h1>"));
+    assert!(html.contains(&format!(
+        "/share/{id}/stream/{RICHARD_BONA_BA_SENGE_FOLDER_CHILD_UUID}"
+    )));
+
+    let res = client
+        .get(&format!(
+            "/share/{id}/stream/{RICHARD_BONA_BA_SENGE_FOLDER_CHILD_UUID}"
+        ))
+        .send()
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(
+        &res.bytes().await.to_vec(),
+        include_bytes!("data/Richard Bona/Richard Bona - Ba Senge.ogg")
+    );
+
+    // Only shared songs can be streamed
+    let res = client
+        .get(&format!(
+            "/share/{id}/stream/{RICHARD_BONA_AKWA_SAMBA_YAYA_FOLDER_CHILD_UUID}"
+        ))
+        .send()
+        .await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    let share = &get_shares(&client, ROOT).await[0];
+    assert_eq!(share["visitCount"], 1);
+    assert_eq!(share["lastVisited"], "2020-02-02T00:00:00Z");
+
+    // Expired shares can't be visited anymore
+    get_json(
+        &client,
+        &format!("/rest/updateShare?{ROOT}&id={id}&description=Old&expires={JANUARY_2020}"),
+    )
+    .await;
+    let share = &get_shares(&client, ROOT).await[0];
+    assert_eq!(share["description"], "Old");
+    assert_eq!(share["expires"], "2020-01-01T00:00:00Z");
+    for url in [
+        format!("/share/{id}"),
+        format!("/share/{id}/stream/{RICHARD_BONA_BA_SENGE_FOLDER_CHILD_UUID}"),
+    ] {
+        assert_eq!(client.get(&url).send().await.status(), StatusCode::GONE);
+    }
+
+    // Until they no longer expire
+    get_json(
+        &client,
+        &format!("/rest/updateShare?{ROOT}&id={id}&expires=0"),
+    )
+    .await;
+    assert_eq!(get_shares(&client, ROOT).await[0]["expires"], Value::Null);
+    let res = client.get(&format!("/share/{id}")).send().await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    get_json(&client, &format!("/rest/deleteShare?{ROOT}&id={id}")).await;
+    assert!(get_shares(&client, ROOT).await.is_empty());
+    let res = client.get(&format!("/share/{id}")).send().await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn share_permissions() -> AppResult<()> {
+    let (_app, client) = setup(None).await?;
+    get_json(
+        &client,
+        &format!("/rest/createUser?{ROOT}&username=joe&password=secret"),
+    )
+    .await;
+
+    // Without an external URL, links use the URL of the request
+    let res = get_json(
+        &client,
+        &format!("/rest/createShare?{ROOT}&id={RICHARD_BONA_BA_SENGE_FOLDER_CHILD_UUID}"),
+    )
+    .await;
+    let share = &res["shares"]["share"][0];
+    let id = share["id"].as_str().unwrap().to_string();
+    assert!(share["url"]
+        .as_str()
+        .unwrap()
+        .ends_with(&format!("/share/{id}")));
+    assert!(share["url"].as_str().unwrap().starts_with("http://"));
+
+    // Users need the share role, and can't change the shares of others
+    let res = client
+        .get(&format!(
+            "/rest/createShare?{JOE}&id={RICHARD_BONA_BA_SENGE_FOLDER_CHILD_UUID}"
+        ))
+        .send()
+        .await;
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    assert!(get_shares(&client, JOE).await.is_empty());
+    for url in [
+        format!("/rest/updateShare?{JOE}&id={id}&description=Mine"),
+        format!("/rest/deleteShare?{JOE}&id={id}"),
+    ] {
+        assert_eq!(
+            client.get(&url).send().await.status(),
+            StatusCode::FORBIDDEN
+        );
+    }
+
+    get_json(
+        &client,
+        &format!("/rest/updateUser?{ROOT}&username=joe&shareRole=true"),
+    )
+    .await;
+    let res = get_json(
+        &client,
+        &format!("/rest/createShare?{JOE}&id={RICHARD_BONA_BA_SENGE_FOLDER_CHILD_UUID}"),
+    )
+    .await;
+    assert_eq!(res["shares"]["share"][0]["username"], "joe");
+    assert_eq!(get_shares(&client, JOE).await.len(), 1);
+
+    // Sharing nothing
+    let res = client
+        .get(&format!(
+            "/rest/createShare?{ROOT}&id=00000000-0000-0000-0000-000000000000"
+        ))
+        .send()
+        .await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    Ok(())
+}
@@ -0,0 +1,259 @@
+use crate::test_utils::TestClient;
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::routing::get;
+use axum::{Json, Router};
+use beatlocker_server::*;
+use serde_json::{json, Value};
+use std::collections::{BTreeSet, HashMap};
+use std::ops::DerefMut;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use uuid::Uuid;
+
+#[path = "test_utils/mod.rs"]
+mod test_utils;
+
+use test_utils::*;
+
+const ALEX_GOPHER_UUID: &str = "c2042f2f-fbda-64e4-ff33-62bad6853d99";
+const UNKNOWN_ARTIST_UUID: &str = "a597d760-ecda-330c-8e48-b8a92ba19a25";
+
+/// Stands in for last.fm, and counts how often it was asked something.
+async fn lastfm(
+    State(requests): State<Arc<AtomicUsize>>,
+    Query(params): Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    requests.fetch_add(1, Ordering::SeqCst);
+    assert_eq!(params["api_key"], "key");
+    match (params["method"].as_str(), params["artist"].as_str()) {
+        ("artist.getsimilar", "Alex Gopher") => Json(json!({
+            "similarartists": {
+                "artist": [
+                    { "name": "Someone Else", "mbid": "" },
+                    { "name": "richard bona", "mbid": "" }
+                ]
+            }
+        })),
+        ("artist.getsimilar", "Richard Bona") => Json(json!({
+            "similarartists": {
+                "artist": [{ "name": "Someone Else", "mbid": "" }]
+            }
+        })),
+        ("artist.gettoptracks", "Alex Gopher") => Json(json!({
+            "toptracks": {
+                "track": [
+                    { "name": "Radar unit" },
+                    { "name": "Not In The Library" },
+                    { "name": "Diamond Dealer" }
+                ]
+            }
+        })),
+        ("artist.gettoptracks", "Richard Bona") => Json(json!({
+            "toptracks": {
+                "track": [{ "name": "Not In The Library" }]
+            }
+        })),
+        _ => Json(json!({ "error": 6, "message": "The artist you supplied could not be found" })),
+    }
+}
+
+async fn setup(lastfm_api_key: Option<&str>) -> AppResult<(App, TestClient, Arc<AtomicUsize>)> {
+    let requests = Arc::new(AtomicUsize::new(0));
+    let router = Router::new()
+        .route("/lastfm/", get(lastfm))
+        .with_state(requests.clone());
+    let addr = spawn_stand_in(router);
+
+    let (app, client) = start_and_import(ServerOptions {
+        lastfm_api_key: lastfm_api_key.map(|key| key.to_string()),
+        lastfm_url: format!("http://{addr}/lastfm/"),
+        ..test_options()
+    })
+    .await?;
+
+    Ok((app, client, requests))
+}
+
+async fn get_songs(client: &TestClient, url: &str, key: &str) -> Vec<Value> {
+    get_json(client, url).await[key]["song"]
+        .as_array()
+        .cloned()
+        .unwrap_or_default()
+}
+
+fn artists(songs: &[Value]) -> BTreeSet<String> {
+    songs
+        .iter()
+        .map(|song| song["artist"].as_str().unwrap().to_string())
+        .collect()
+}
+
+fn titles(songs: &[Value]) -> Vec<String> {
+    songs
+        .iter()
+        .map(|song| song["title"].as_str().unwrap().to_string())
+        .collect()
+}
+
+#[tokio::test]
+async fn similar_songs_without_lastfm_test() -> AppResult<()> {
+    let (app, client, _) = setup(None).await?;
+
+    // Richard Bona and Unknown Artist share a tag, Alex Gopher has nothing in common with them
+    for (artist_id, tag) in [
+        (RICHARD_BONA_UUID, "world"),
+        (RICHARD_BONA_UUID, "jazz"),
+        (UNKNOWN_ARTIST_UUID, "world"),
+        (ALEX_GOPHER_UUID, "electronic"),
+    ] {
+        sqlx::query("INSERT INTO artist_tags (artist_id, tag) VALUES (?, ?)")
+            .bind(Uuid::parse_str(artist_id).unwrap())
+            .bind(tag)
+            .execute(app.state.db.conn().await?.deref_mut())
+            .await?;
+    }
+
+    let songs = get_songs(
+        &client,
+        &format!("/rest/getSimilarSongs2?f=json&id={RICHARD_BONA_UUID}&count=10"),
+        "similarSongs2",
+    )
+    .await;
+    assert_eq!(songs.len(), 3);
+    assert_eq!(
+        artists(&songs),
+        BTreeSet::from(["Richard Bona".to_string(), "Unknown Artist".to_string()])
+    );
+
+    // Songs are found through the artist of a song too
+    let songs = get_songs(
+        &client,
+        &format!(
+            "/rest/getSimilarSongs?f=json&id={RICHARD_BONA_BA_SENGE_FOLDER_CHILD_UUID}&count=2"
+        ),
+        "similarSongs",
+    )
+    .await;
+    assert_eq!(songs.len(), 2);
+    let res = client
+        .get(&format!(
+            "/rest/getSimilarSongs2?f=json&id={RICHARD_BONA_BA_SENGE_FOLDER_CHILD_UUID}"
+        ))
+        .send()
+        .await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    // Top songs are the most played ones
+    let res = client
+        .get(&format!(
+            "/rest/scrobble?f=json&id={RICHARD_BONA_AKWA_SAMBA_YAYA_FOLDER_CHILD_UUID}"
+        ))
+        .send()
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let songs = get_songs(
+        &client,
+        "/rest/getTopSongs?f=json&artist=richard%20bona",
+        "topSongs",
+    )
+    .await;
+    assert_eq!(titles(&songs), vec!["Akwa Samba Yaya", "Ba Senge"]);
+
+    let songs = get_songs(
+        &client,
+        "/rest/getTopSongs?f=json&artist=Nobody",
+        "topSongs",
+    )
+    .await;
+    assert!(songs.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn similar_songs_with_lastfm_test() -> AppResult<()> {
+    let (app, client, requests) = setup(Some("key")).await?;
+
+    for _ in 0..2 {
+        let songs = get_songs(
+            &client,
+            &format!("/rest/getSimilarSongs2?f=json&id={ALEX_GOPHER_UUID}&count=10"),
+            "similarSongs2",
+        )
+        .await;
+        assert_eq!(songs.len(), 4);
+        assert_eq!(
+            artists(&songs),
+            BTreeSet::from(["Alex Gopher".to_string(), "Richard Bona".to_string()])
+        );
+
+        let songs = get_songs(
+            &client,
+            "/rest/getTopSongs?f=json&artist=Alex%20Gopher",
+            "topSongs",
+        )
+        .await;
+        assert_eq!(titles(&songs), vec!["Radar Unit", "Diamond Dealers"]);
+    }
+
+    // The second time around the responses came from the cache
+    assert_eq!(requests.load(Ordering::SeqCst), 2);
+
+    let songs = get_songs(
+        &client,
+        "/rest/getTopSongs?f=json&artist=Alex%20Gopher&count=1",
+        "topSongs",
+    )
+    .await;
+    assert_eq!(titles(&songs), vec!["Radar Unit"]);
+
+    // Without any of its top tracks in the library, the most played songs are the top songs
+    let res = client
+        .get(&format!(
+            "/rest/scrobble?f=json&id={RICHARD_BONA_BA_SENGE_FOLDER_CHILD_UUID}"
+        ))
+        .send()
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    let songs = get_songs(
+        &client,
+        "/rest/getTopSongs?f=json&artist=RICHARD%20BONA",
+        "topSongs",
+    )
+    .await;
+    assert_eq!(titles(&songs), vec!["Ba Senge", "Akwa Samba Yaya"]);
+
+    // Artists last.fm doesn't know fall back to shared genres and tags
+    let songs = get_songs(
+        &client,
+        &format!("/rest/getSimilarSongs2?f=json&id={UNKNOWN_ARTIST_UUID}"),
+        "similarSongs2",
+    )
+    .await;
+    assert_eq!(
+        artists(&songs),
+        BTreeSet::from(["Unknown Artist".to_string()])
+    );
+
+    // And so do artists of which last.fm only knows similar artists outside the library
+    for artist_id in [RICHARD_BONA_UUID, UNKNOWN_ARTIST_UUID] {
+        sqlx::query("INSERT INTO artist_tags (artist_id, tag) VALUES (?, 'world')")
+            .bind(Uuid::parse_str(artist_id).unwrap())
+            .execute(app.state.db.conn().await?.deref_mut())
+            .await?;
+    }
+    let songs = get_songs(
+        &client,
+        &format!("/rest/getSimilarSongs2?f=json&id={RICHARD_BONA_UUID}"),
+        "similarSongs2",
+    )
+    .await;
+    assert_eq!(
+        artists(&songs),
+        BTreeSet::from(["Richard Bona".to_string(), "Unknown Artist".to_string()])
+    );
+
+    Ok(())
+}
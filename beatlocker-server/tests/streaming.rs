@@ -0,0 +1,273 @@
+use crate::test_utils::TestClient;
+use axum::http::header::{
+    ACCEPT_RANGES, CONTENT_DISPOSITION, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, ETAG,
+    IF_RANGE, RANGE,
+};
+use axum::http::StatusCode;
+use beatlocker_server::*;
+
+#[path = "test_utils/mod.rs"]
+mod test_utils;
+
+use test_utils::*;
+
+const BA_SENGE: &[u8] = include_bytes!("data/Richard Bona/Richard Bona - Ba Senge.ogg");
+
+fn stream_url() -> String {
+    format!("/rest/stream?id={RICHARD_BONA_BA_SENGE_FOLDER_CHILD_UUID}")
+}
+
+#[tokio::test]
+async fn stream_range_test() -> AppResult<()> {
+    let (_app, client) = setup().await?;
+    let len = BA_SENGE.len();
+
+    // Without a range the whole file is sent, but ranges are advertised
+    let res = client.get(&stream_url()).send().await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers().get(ACCEPT_RANGES).unwrap(), "bytes");
+    assert_eq!(res.headers().get(CONTENT_TYPE).unwrap(), "audio/ogg");
+    assert_eq!(res.headers().get(CONTENT_LENGTH).unwrap(), &len.to_string());
+    assert_eq!(&res.bytes().await.to_vec(), BA_SENGE);
+
+    // Bounded range
+    let res = client
+        .get(&stream_url())
+        .header(RANGE, "bytes=0-99")
+        .send()
+        .await;
+    assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(
+        res.headers().get(CONTENT_RANGE).unwrap(),
+        &format!("bytes 0-99/{len}")
+    );
+    assert_eq!(res.headers().get(CONTENT_LENGTH).unwrap(), "100");
+    assert_eq!(&res.bytes().await.to_vec(), &BA_SENGE[0..100]);
+
+    // Open-ended range
+    let res = client
+        .get(&stream_url())
+        .header(RANGE, "bytes=1000-")
+        .send()
+        .await;
+    assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(
+        res.headers().get(CONTENT_RANGE).unwrap(),
+        &format!("bytes 1000-{}/{len}", len - 1)
+    );
+    assert_eq!(&res.bytes().await.to_vec(), &BA_SENGE[1000..]);
+
+    // Suffix range
+    let res = client
+        .get(&stream_url())
+        .header(RANGE, "bytes=-500")
+        .send()
+        .await;
+    assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(&res.bytes().await.to_vec(), &BA_SENGE[len - 500..]);
+
+    // Range past the end of the file
+    let res = client
+        .get(&stream_url())
+        .header(RANGE, format!("bytes={len}-"))
+        .send()
+        .await;
+    assert_eq!(res.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+    assert_eq!(
+        res.headers().get(CONTENT_RANGE).unwrap(),
+        &format!("bytes */{len}")
+    );
+
+    // Malformed ranges, ranges in other units and multiple ranges are ignored
+    for range in ["bytes=100-50", "bytes=abc", "items=0-1", "bytes=0-10,20-30"] {
+        let res = client.get(&stream_url()).header(RANGE, range).send().await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers().get(CONTENT_LENGTH).unwrap(), &len.to_string());
+        assert_eq!(&res.bytes().await.to_vec(), BA_SENGE);
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn stream_if_range_test() -> AppResult<()> {
+    let (_app, client) = setup().await?;
+
+    let res = client.get(&stream_url()).send().await;
+    let etag = res
+        .headers()
+        .get(ETAG)
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .to_owned();
+
+    // Matching entity tag, so the range is honoured
+    let res = client
+        .get(&stream_url())
+        .header(RANGE, "bytes=0-99")
+        .header(IF_RANGE, &etag)
+        .send()
+        .await;
+    assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(&res.bytes().await.to_vec(), &BA_SENGE[0..100]);
+
+    // Stale entity tag, so the full file is sent
+    let res = client
+        .get(&stream_url())
+        .header(RANGE, "bytes=0-99")
+        .header(IF_RANGE, "\"stale\"")
+        .send()
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(&res.bytes().await.to_vec(), BA_SENGE);
+
+    // Date long before the file was last modified, so the full file is sent
+    let res = client
+        .get(&stream_url())
+        .header(RANGE, "bytes=0-99")
+        .header(IF_RANGE, "Thu, 01 Jan 1970 00:00:00 GMT")
+        .send()
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(&res.bytes().await.to_vec(), BA_SENGE);
+
+    Ok(())
+}
+
+async fn setup_with_profiles(profiles: &[&str]) -> AppResult<(App, TestClient)> {
+    start_and_import(ServerOptions {
+        transcode_profiles: profiles.iter().map(|p| p.parse().unwrap()).collect(),
+        ..test_options()
+    })
+    .await
+}
+
+#[tokio::test]
+async fn stream_transcode_test() -> AppResult<()> {
+    // `cat` just passes on the decoded WAV stream it is fed
+    let (_app, client) = setup_with_profiles(&["wav:audio/wav:1411:cat"]).await?;
+
+    let res = client
+        .get(&format!("{}&format=wav", stream_url()))
+        .send()
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers().get(CONTENT_TYPE).unwrap(), "audio/wav");
+    assert_eq!(res.headers().get(ACCEPT_RANGES).unwrap(), "none");
+    assert!(res.headers().get(CONTENT_LENGTH).is_none());
+    let wav = res.bytes().await.to_vec();
+    assert_eq!(&wav[0..4], b"RIFF");
+    assert_eq!(&wav[8..12], b"WAVE");
+
+    // Limiting the bitrate uses the first profile
+    let res = client
+        .get(&format!("{}&maxBitRate=64", stream_url()))
+        .send()
+        .await;
+    assert_eq!(res.headers().get(CONTENT_TYPE).unwrap(), "audio/wav");
+
+    // Starting at an offset results in less audio
+    let res = client
+        .get(&format!("{}&format=wav&timeOffset=3", stream_url()))
+        .send()
+        .await;
+    assert!(res.bytes().await.len() < wav.len());
+
+    // Raw always sends the original file
+    let res = client
+        .get(&format!("{}&format=raw&maxBitRate=64", stream_url()))
+        .send()
+        .await;
+    assert_eq!(res.headers().get(CONTENT_TYPE).unwrap(), "audio/ogg");
+    assert_eq!(&res.bytes().await.to_vec(), BA_SENGE);
+
+    // Without any limits the original file is sent as well
+    let res = client.get(&stream_url()).send().await;
+    assert_eq!(res.headers().get(CONTENT_TYPE).unwrap(), "audio/ogg");
+    assert_eq!(&res.bytes().await.to_vec(), BA_SENGE);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn stream_transcode_lossless_test() -> AppResult<()> {
+    let (_app, client) = setup_with_profiles(&["wav:audio/wav:1411:cat"]).await?;
+    let url = format!("/rest/stream?id={MOTORWAY_OST_RADAR_UNIT_FOLDER_CHILD_UUID}");
+
+    // The FLAC file averages 294 kbps, so it is only transcoded when that is too much
+    let res = client.get(&format!("{url}&maxBitRate=256")).send().await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers().get(CONTENT_TYPE).unwrap(), "audio/wav");
+
+    let res = client.get(&format!("{url}&maxBitRate=320")).send().await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers().get(CONTENT_TYPE).unwrap(), "audio/flac");
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn stream_transcode_from_path_test() -> AppResult<()> {
+    let (_app, client) = setup_with_profiles(&["copy:audio/ogg:128:cat {path}"]).await?;
+
+    let res = client
+        .get(&format!("{}&format=copy", stream_url()))
+        .send()
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(&res.bytes().await.to_vec(), BA_SENGE);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn download_test() -> AppResult<()> {
+    let (_app, client) = setup().await?;
+
+    // Songs are sent as-is
+    let res = client
+        .get(&format!(
+            "/rest/download?id={RICHARD_BONA_BA_SENGE_FOLDER_CHILD_UUID}"
+        ))
+        .send()
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(res.headers().get(CONTENT_TYPE).unwrap(), "audio/ogg");
+    assert_eq!(
+        res.headers().get(CONTENT_DISPOSITION).unwrap(),
+        "attachment; filename=\"Richard Bona - Ba Senge.ogg\"; filename*=UTF-8''Richard%20Bona%20-%20Ba%20Senge.ogg"
+    );
+    assert_eq!(&res.bytes().await.to_vec(), BA_SENGE);
+
+    // Folders and albums are zipped
+    for (id, name) in [
+        (MOTORWAY_OST_FOLDER_UUID, "Motorway OST"),
+        (
+            MOTORWAY_OST_ALBUM_UUID,
+            "Motorway (Original Motion Picture Soundtrack)",
+        ),
+    ] {
+        let res = client.get(&format!("/rest/download?id={id}")).send().await;
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers().get(CONTENT_TYPE).unwrap(), "application/zip");
+        assert!(res
+            .headers()
+            .get(CONTENT_DISPOSITION)
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .starts_with(&format!("attachment; filename=\"{name}.zip\"")));
+        let zip = res.bytes().await.to_vec();
+        assert_eq!(&zip[0..4], b"PK\x03\x04");
+        assert_eq!(&zip[zip.len() - 22..zip.len() - 18], b"PK\x05\x06");
+    }
+
+    let res = client
+        .get("/rest/download?id=00000000-0000-0000-0000-000000000001")
+        .send()
+        .await;
+    assert_eq!(res.status(), StatusCode::NOT_FOUND);
+
+    Ok(())
+}
@@ -2,11 +2,17 @@
 
 mod test_client;
 
-use beatlocker_server::AppResult;
+use axum::http::StatusCode;
+use axum::Router;
+use beatlocker_server::*;
+use chrono::{DateTime, Utc};
 use quick_xml::events::Event;
 use quick_xml::{Reader, Writer};
+use serde_json::Value;
 use std::fs;
-use std::path::Path;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 pub use test_client::*;
 
 pub const MOTORWAY_OST_FOLDER_UUID: &str = "68f8b71b-d9b4-c77e-c7f1-e4af263bcd93";
@@ -14,6 +20,77 @@ pub const MOTORWAY_OST_ALBUM_UUID: &str = "20d02390-2687-c407-2d28-d74f9fc6d5a1"
 pub const MOTORWAY_OST_RADAR_UNIT_FOLDER_CHILD_UUID: &str = "9fe0fb24-dabd-4464-258b-1ab72a28aa94";
 pub const MOTORWAY_OST_RADAR_UNIT_SONG_UUID: &str = "f417c310-98e2-e42f-ed0d-f9208c48419b";
 pub const RICHARD_BONA_UUID: &str = "d094e9f8-a8e2-1737-0cfc-c4b24ab0aedf";
+pub const RICHARD_BONA_BA_SENGE_FOLDER_CHILD_UUID: &str = "1568a84c-22cd-2176-ab86-c69194a9de16";
+pub const RICHARD_BONA_AKWA_SAMBA_YAYA_FOLDER_CHILD_UUID: &str =
+    "72315dd4-d365-8f1e-9cb7-c0c11f680af1";
+
+/// Credentials of the admin set up by `root_auth`, and of a user it can create.
+pub const ROOT: &str = "u=root&p=sesame&f=json";
+pub const JOE: &str = "u=joe&p=secret&f=json";
+
+/// Serves the test data from an in-memory database, at a fixed point in time.
+pub fn test_options() -> ServerOptions {
+    ServerOptions {
+        music_folders: vec![MusicFolderOptions::main("tests/data")],
+        database: DatabaseOptions {
+            path: Some(PathBuf::from(".")),
+            in_memory: true,
+        },
+        now_provider: Arc::new(Box::new(|| {
+            DateTime::parse_from_rfc3339("2020-02-02T00:00:00Z")
+                .unwrap()
+                .with_timezone(&Utc)
+        })),
+        ..Default::default()
+    }
+}
+
+/// Makes "root" the admin, so other users can be created.
+pub fn root_auth() -> SubsonicAuth {
+    SubsonicAuth::UsernamePassword {
+        username: "root".to_string(),
+        password: "sesame".to_string(),
+    }
+}
+
+/// Serves a stand-in for an outside service, like last.fm, on a free port.
+pub fn spawn_stand_in(router: Router) -> SocketAddr {
+    let server =
+        axum::Server::bind(&"127.0.0.1:0".parse().unwrap()).serve(router.into_make_service());
+    let addr = server.local_addr();
+    tokio::spawn(server);
+    addr
+}
+
+/// Starts the server without importing anything.
+pub async fn start(options: ServerOptions) -> AppResult<(App, TestClient)> {
+    let app = App::new(options).await?;
+    let client = TestClient::new(app.app.clone());
+
+    Ok((app, client))
+}
+
+/// Starts the server and waits for its music folders to be imported.
+pub async fn start_and_import(options: ServerOptions) -> AppResult<(App, TestClient)> {
+    let (app, client) = start(options).await?;
+    app.task_manager
+        .send(app.import_all_folders().await?)
+        .await?;
+
+    Ok((app, client))
+}
+
+/// Starts the server with the test data imported.
+pub async fn setup() -> AppResult<(App, TestClient)> {
+    start_and_import(test_options()).await
+}
+
+/// The `subsonic-response` of a request that should succeed.
+pub async fn get_json(client: &TestClient, url: &str) -> Value {
+    let res = client.get(url).send().await;
+    assert_eq!(res.status(), StatusCode::OK);
+    res.json::<Value>().await["subsonic-response"].clone()
+}
 
 pub fn prettify_xml(xml: &str) -> String {
     let mut buf = Vec::new();
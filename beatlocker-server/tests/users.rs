@@ -0,0 +1,108 @@
+use crate::test_utils::TestClient;
+use axum::http::StatusCode;
+use beatlocker_server::*;
+
+#[path = "test_utils/mod.rs"]
+mod test_utils;
+
+use test_utils::*;
+
+async fn setup() -> AppResult<(App, TestClient)> {
+    start_and_import(ServerOptions {
+        subsonic_auth: root_auth(),
+        ..test_options()
+    })
+    .await
+}
+
+#[tokio::test]
+async fn user_management_test() -> AppResult<()> {
+    let (_app, client) = setup().await?;
+    let root = "u=root&p=sesame&f=json";
+
+    // The CLI user is seeded as an admin
+    let res = get_json(&client, &format!("/rest/getUser?{root}&username=root")).await;
+    assert_eq!(res["user"]["adminRole"], true);
+    assert_eq!(res["user"]["podcastRole"], true);
+    assert_eq!(res["user"]["downloadRole"], true);
+
+    get_json(
+        &client,
+        &format!("/rest/createUser?{root}&username=joe&password=enc:736563726574&email=joe@example.com&streamRole=true"),
+    )
+    .await;
+    let res = client
+        .get(&format!(
+            "/rest/createUser?{root}&username=joe&password=other"
+        ))
+        .send()
+        .await;
+    assert_eq!(res.status(), StatusCode::CONFLICT);
+
+    let joe = "u=joe&p=secret&f=json";
+    let res = get_json(&client, &format!("/rest/getUser?{joe}&username=joe")).await;
+    let user = &res["user"];
+    assert_eq!(user["email"], "joe@example.com");
+    assert_eq!(user["adminRole"], false);
+    assert_eq!(user["podcastRole"], false);
+    assert_eq!(user["streamRole"], true);
+    assert_eq!(user["downloadRole"], false);
+
+    // Non-admins can't see or manage other users, or download without the role
+    for url in [
+        format!("/rest/getUser?{joe}&username=root"),
+        format!("/rest/getUsers?{joe}"),
+        format!("/rest/createUser?{joe}&username=eve&password=x"),
+        format!("/rest/download?{joe}&id={RICHARD_BONA_BA_SENGE_FOLDER_CHILD_UUID}"),
+    ] {
+        assert_eq!(
+            client.get(&url).send().await.status(),
+            StatusCode::FORBIDDEN
+        );
+    }
+
+    get_json(
+        &client,
+        &format!("/rest/updateUser?{root}&username=joe&downloadRole=true"),
+    )
+    .await;
+    let res = client
+        .get(&format!(
+            "/rest/download?{joe}&id={RICHARD_BONA_BA_SENGE_FOLDER_CHILD_UUID}"
+        ))
+        .send()
+        .await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    // Users can change their own password
+    get_json(
+        &client,
+        &format!("/rest/changePassword?{joe}&username=joe&password=hunter2"),
+    )
+    .await;
+    let res = client.get(&format!("/rest/ping?{joe}")).send().await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    get_json(&client, "/rest/ping?u=joe&p=hunter2&f=json").await;
+
+    let res = get_json(&client, &format!("/rest/getUsers?{root}")).await;
+    let usernames: Vec<_> = res["users"]["user"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|user| user["username"].as_str().unwrap())
+        .collect();
+    assert_eq!(usernames, vec!["joe", "root"]);
+
+    // Admins can't delete themselves
+    let res = client
+        .get(&format!("/rest/deleteUser?{root}&username=root"))
+        .send()
+        .await;
+    assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+
+    get_json(&client, &format!("/rest/deleteUser?{root}&username=joe")).await;
+    let res = client.get("/rest/ping?u=joe&p=hunter2&f=json").send().await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+
+    Ok(())
+}